@@ -0,0 +1,181 @@
+//! An interface's IP address, or the address the outside world sees, from asking a web server on a
+//! thread of its own.
+
+use anyhow::{bail, Context, Result};
+use nix::ifaddrs::getifaddrs;
+use nix::sys::socket::SockAddr;
+use std::io::{Read, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// How long to wait to connect to the web server, and for each read and write after that.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The most of a response to read. An address is only a few dozen bytes.
+const MAX_RESPONSE: u64 = 64 * 1024;
+
+/// An interface's first IPv4 address, or None if it has none, e.g. because it's down.
+pub fn ipv4(iface: &str) -> Option<Ipv4Addr> {
+    getifaddrs().ok()?
+        .filter(|ifaddr| ifaddr.interface_name == iface)
+        .find_map(|ifaddr| match ifaddr.address? {
+            SockAddr::Inet(inet) => match inet.to_std() {
+                SocketAddr::V4(addr) => Some(*addr.ip()),
+                SocketAddr::V6(_) => None,
+            },
+            _ => None,
+        })
+}
+
+/// A plain `http://` URL. There's no TLS support, but the usual "what's my IP" services answer
+/// over plain HTTP too.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Url {
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl Url {
+    pub fn parse(s: &str) -> Result<Self> {
+        let rest = match s.strip_prefix("http://") {
+            Some(rest) => rest,
+            None => bail!("{:?} should be an http:// URL", s),
+        };
+        let (host, path) = match rest.find('/') {
+            Some(slash) => (&rest[.. slash], &rest[slash ..]),
+            None => (rest, "/"),
+        };
+        let (host, port) = match host.split_once(':') {
+            Some((host, port)) => {
+                (host, port.parse().with_context(|| format!("invalid port {:?}", port))?)
+            }
+            None => (host, 80),
+        };
+        if host.is_empty() {
+            bail!("{:?} has no host name", s);
+        }
+        Ok(Self { host: host.to_owned(), port, path: path.to_owned() })
+    }
+}
+
+/// Ask a web server what address the request came from. It should answer with nothing but the
+/// address, like `http://api.ipify.org/` does.
+pub fn fetch(url: &Url) -> Result<IpAddr> {
+    let addr = (url.host.as_str(), url.port).to_socket_addrs()
+        .with_context(|| format!("failed to look up {}", url.host))?
+        .next()
+        .with_context(|| format!("{} has no addresses", url.host))?;
+    let mut stream = TcpStream::connect_timeout(&addr, TIMEOUT)
+        .with_context(|| format!("failed to connect to {}", url.host))?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let host = match url.port {
+        80 => url.host.clone(),
+        port => format!("{}:{}", url.host, port),
+    };
+    write!(stream, "GET {} HTTP/1.0\r\nHost: {}\r\nUser-Agent: h2-net-lcd\r\n\r\n",
+        url.path, host)?;
+    let mut response = vec![];
+    stream.take(MAX_RESPONSE).read_to_end(&mut response)
+        .with_context(|| format!("failed to read the response from {}", url.host))?;
+    parse_response(&String::from_utf8_lossy(&response))
+}
+
+/// The address in an HTTP response's body.
+fn parse_response(response: &str) -> Result<IpAddr> {
+    let (head, body) = match response.split_once("\r\n\r\n") {
+        Some(parts) => parts,
+        None => bail!("incomplete response"),
+    };
+    let status = head.lines().next().unwrap_or_default();
+    if status.split_whitespace().nth(1) != Some("200") {
+        bail!("the server said {:?}", status);
+    }
+    let body = body.trim();
+    body.parse().with_context(|| format!("{:?} isn't an IP address", body))
+}
+
+/// Looks up the external address every so often on a background thread, since it can take a
+/// while. The thread stops once this is dropped.
+pub struct External {
+    latest: Arc<Mutex<Option<IpAddr>>>,
+}
+
+impl External {
+    /// Start asking `url` for the address every `interval`, or after `retry` if it doesn't answer.
+    pub fn spawn(url: Url, interval: Duration, retry: Duration) -> Self {
+        let latest = Arc::new(Mutex::new(None));
+        let shared = Arc::downgrade(&latest);
+        thread::Builder::new()
+            .name("address".to_owned())
+            .spawn(move || {
+                let mut failing = false;
+                loop {
+                    let result = fetch(&url);
+                    let latest = match shared.upgrade() {
+                        Some(latest) => latest,
+                        None => return,
+                    };
+                    match result {
+                        Ok(addr) => {
+                            *latest.lock().unwrap() = Some(addr);
+                            failing = false;
+                        }
+                        Err(e) => {
+                            // keep showing the last one; it's probably still right
+                            if !failing {
+                                eprintln!("failed to get the external address: {:#}", e);
+                                failing = true;
+                            }
+                        }
+                    }
+                    drop(latest);
+                    thread::sleep(if failing { retry } else { interval });
+                }
+            })
+            .expect("failed to spawn address thread");
+        Self { latest }
+    }
+
+    /// The last address, or None if there hasn't been one yet.
+    pub fn latest(&self) -> Option<IpAddr> {
+        *self.latest.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_url() {
+    let url = |host: &str, port, path: &str| {
+        Url { host: host.to_owned(), port, path: path.to_owned() }
+    };
+    assert_eq!(url("api.ipify.org", 80, "/"), Url::parse("http://api.ipify.org").unwrap());
+    assert_eq!(url("192.168.1.10", 8080, "/ip?plain"),
+        Url::parse("http://192.168.1.10:8080/ip?plain").unwrap());
+    let err = |s| Url::parse(s).unwrap_err().to_string();
+    assert_eq!("\"https://api.ipify.org\" should be an http:// URL", err("https://api.ipify.org"));
+    assert_eq!("invalid port \"http\"", err("http://example.com:http/"));
+    assert_eq!("\"http:///ip\" has no host name", err("http:///ip"));
+}
+
+#[cfg(test)]
+#[test]
+fn test_parse_response() {
+    assert_eq!(IpAddr::from([203, 0, 113, 42]), parse_response(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n\r\n203.0.113.42\n").unwrap());
+    let err = |s| parse_response(s).unwrap_err().to_string();
+    assert_eq!("the server said \"HTTP/1.1 404 Not Found\"",
+        err("HTTP/1.1 404 Not Found\r\n\r\nnope"));
+    assert_eq!("\"<html>\" isn't an IP address", err("HTTP/1.0 200 OK\r\n\r\n<html>"));
+    assert_eq!("incomplete response", err("HTTP/1.0 200 OK\r\n"));
+}
+
+#[cfg(test)]
+#[test]
+fn test_ipv4() {
+    assert_eq!(Some(Ipv4Addr::LOCALHOST), ipv4("lo"));
+    assert_eq!(None, ipv4("nonexistent0"));
+}
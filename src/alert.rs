@@ -0,0 +1,303 @@
+//! Getting attention when something's wrong, by flashing the backlight while a value is over its
+//! threshold.
+
+use std::time::{Duration, Instant};
+
+/// When to raise an alert.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Thresholds {
+    /// CPU temperature, in °C.
+    pub temp: Option<f64>,
+    /// Memory in use, in percent.
+    pub mem: Option<f64>,
+    /// Speed of the busiest interface in either direction, in Mbps.
+    pub mbps: Option<f64>,
+    /// Pages swapped in or out, per second. Swap that's in use but sitting idle is no problem, but
+    /// a lot of it moving means the system is short of memory.
+    pub swapping: Option<f64>,
+    /// Ping round trip time, in milliseconds. No reply at all counts as over it.
+    pub ping: Option<f64>,
+    /// Temperature in °C, over which a fan that's stopped is a problem. Some fans stop when
+    /// things are cool, which is fine.
+    pub fan_stopped: Option<f64>,
+    /// Login sessions. Set to how many there usually are to hear about anyone else logging in.
+    pub sessions: Option<f64>,
+    /// Minutes the clock's been unsynchronized.
+    pub unsynced: Option<f64>,
+    /// Minutes the UPS has been on battery. 0 goes off as soon as the power does, after
+    /// `sustain`.
+    pub on_battery: Option<f64>,
+    /// Minutes any interface's link has been down. 0 goes off as soon as one goes down, after
+    /// `sustain`.
+    pub link_down: Option<f64>,
+    /// How long a value has to stay over its threshold before the alert goes off.
+    pub sustain: Duration,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            temp: None,
+            mem: None,
+            mbps: None,
+            swapping: None,
+            ping: None,
+            fan_stopped: None,
+            sessions: None,
+            unsynced: None,
+            on_battery: None,
+            link_down: None,
+            sustain: Duration::from_secs(30),
+        }
+    }
+}
+
+/// The latest values to check against the thresholds, in the same units.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Readings {
+    /// NaN if either of these can't be read.
+    pub temp: f64,
+    pub mem: f64,
+    pub mbps: f64,
+    pub swapping: f64,
+    pub ping: f64,
+    /// The temperature while any fan reads 0 RPM, or negative infinity while they're all
+    /// turning.
+    pub fan_stopped: f64,
+    /// NaN if they can't be counted.
+    pub sessions: f64,
+    /// 0 while it's synchronized, and NaN if it's not known.
+    pub unsynced: f64,
+    /// Negative infinity while the UPS is on mains power, and NaN if it's not known.
+    pub on_battery: f64,
+    /// Negative infinity while every link is up.
+    pub link_down: f64,
+}
+
+/// Where one value stands against its threshold.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum State {
+    Clear,
+    /// Over the threshold since the given time, but not for long enough to go off yet.
+    Over(Instant),
+    Firing,
+}
+
+/// The next state of an alert, given the latest value. It goes off once the value has been over
+/// the threshold for `sustain`, and only clears once the value drops `hysteresis` (a fraction of
+/// the threshold) below it, so a value hovering around the threshold doesn't set it off over and
+/// over.
+pub fn evaluate(state: State, now: Instant, value: f64, threshold: f64, sustain: Duration,
+    hysteresis: f64) -> State
+{
+    let since = match state {
+        State::Firing if value < threshold * (1. - hysteresis) => return State::Clear,
+        State::Firing => return State::Firing,
+        _ if value.is_nan() || value <= threshold => return State::Clear,
+        State::Over(since) => since,
+        State::Clear => now,
+    };
+    if now - since >= sustain {
+        State::Firing
+    } else {
+        State::Over(since)
+    }
+}
+
+pub struct Alerts {
+    hysteresis: f64,
+    /// How long the backlight stays off, then on, for each flash.
+    flash_period: Duration,
+    flashes: u32,
+    /// How often to flash again while an alert is still going.
+    repeat: Duration,
+    /// Temperature, memory, network, swapping, ping, stopped fans, sessions, clock sync, the UPS,
+    /// and links down.
+    states: [State; 10],
+    /// When the backlight last started flashing.
+    flashed: Option<Instant>,
+}
+
+impl Alerts {
+    pub fn new(hysteresis: f64, flash_period: Duration, flashes: u32, repeat: Duration) -> Self {
+        Self {
+            hysteresis,
+            flash_period,
+            flashes,
+            repeat,
+            states: [State::Clear; 10],
+            flashed: None,
+        }
+    }
+
+    /// Check the latest values, and return whether any alert is going off.
+    pub fn update(&mut self, now: Instant, thresholds: &Thresholds, readings: &Readings) -> bool {
+        let h = self.hysteresis;
+        let values = [
+            (thresholds.temp, readings.temp, h),
+            (thresholds.mem, readings.mem, h),
+            (thresholds.mbps, readings.mbps, h),
+            (thresholds.swapping, readings.swapping, h),
+            (thresholds.ping, readings.ping, h),
+            (thresholds.fan_stopped, readings.fan_stopped, h),
+            // a count can't hover around the threshold, so it's halfway to the next one instead,
+            // to go off at one more and clear once it's back down
+            (thresholds.sessions.map(|sessions| sessions + 0.5), readings.sessions, 0.),
+            (thresholds.unsynced, readings.unsynced, h),
+            (thresholds.on_battery, readings.on_battery, h),
+            (thresholds.link_down, readings.link_down, h),
+        ];
+        for (state, (threshold, value, hysteresis)) in self.states.iter_mut().zip(values) {
+            *state = match threshold {
+                Some(threshold) => {
+                    evaluate(*state, now, value, threshold, thresholds.sustain, hysteresis)
+                }
+                None => State::Clear,
+            };
+        }
+        let firing = self.states.contains(&State::Firing);
+        if !firing {
+            self.flashed = None;
+        } else if self.flashed.is_none_or(|flashed| now - flashed >= self.repeat) {
+            self.flashed = Some(now);
+        }
+        firing
+    }
+
+    /// Whether the backlight should be the opposite of usual right now, for a flash.
+    pub fn flash(&self, now: Instant) -> bool {
+        match self.flashed {
+            Some(flashed) => {
+                let half = (now - flashed).as_millis() / self.flash_period.as_millis().max(1);
+                half < self.flashes as u128 * 2 && half % 2 == 0
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_evaluate() {
+    let start = Instant::now();
+    let secs = |n| start + Duration::from_secs(n);
+    let sustain = Duration::from_secs(30);
+    let step = |state, t, value| evaluate(state, secs(t), value, 80., sustain, 0.05);
+
+    // has to stay over the threshold for 30s
+    assert_eq!(State::Clear, step(State::Clear, 0, 80.));
+    let over = step(State::Clear, 0, 81.);
+    assert_eq!(State::Over(start), over);
+    assert_eq!(State::Over(start), step(over, 29, 85.));
+    assert_eq!(State::Clear, step(over, 20, 79.));
+    assert_eq!(State::Firing, step(over, 30, 81.));
+
+    // once going off, it takes dropping below 76 to clear
+    assert_eq!(State::Firing, step(State::Firing, 40, 79.));
+    assert_eq!(State::Firing, step(State::Firing, 40, 76.));
+    assert_eq!(State::Clear, step(State::Firing, 40, 75.9));
+    assert_eq!(State::Clear, step(State::Clear, 0, f64::NAN));
+
+    // with no sustain, it goes off straight away
+    assert_eq!(State::Firing, evaluate(State::Clear, start, 81., 80., Duration::ZERO, 0.05));
+}
+
+#[cfg(test)]
+#[test]
+fn test_alerts() {
+    let start = Instant::now();
+    let ms = |n| start + Duration::from_millis(n);
+    let thresholds =
+        Thresholds { mbps: Some(100.), sustain: Duration::ZERO, ..Thresholds::default() };
+    let mut alerts = Alerts::new(0.05, Duration::from_millis(500), 2, Duration::from_secs(30));
+    let mbps = |mbps| Readings { mbps, ..Readings::default() };
+
+    let quiet = Readings {
+        temp: 99.,
+        mem: 100.,
+        mbps: 50.,
+        swapping: 1000.,
+        ping: 5000.,
+        fan_stopped: 99.,
+        sessions: 5.,
+        unsynced: 60.,
+        on_battery: 60.,
+        link_down: 60.,
+    };
+    assert!(!alerts.update(start, &thresholds, &quiet));
+    assert!(!alerts.flash(start));
+
+    // flashes off and on twice
+    assert!(alerts.update(start, &thresholds, &mbps(150.)));
+    let flashes: Vec<bool> = (0 .. 6).map(|i| alerts.flash(ms(i * 500))).collect();
+    assert_eq!(vec![true, false, true, false, false, false], flashes);
+
+    // and again 30s later, if it's still going
+    assert!(alerts.update(ms(29_000), &thresholds, &mbps(150.)));
+    assert!(!alerts.flash(ms(29_000)));
+    assert!(alerts.update(ms(30_000), &thresholds, &mbps(150.)));
+    assert!(alerts.flash(ms(30_000)));
+
+    // clearing stops the flashing
+    assert!(!alerts.update(ms(30_100), &thresholds, &mbps(10.)));
+    assert!(!alerts.flash(ms(30_100)));
+
+    // swapping hard
+    let thresholds = Thresholds { swapping: Some(100.), ..thresholds };
+    let swapping = Readings { swapping: 500., ..mbps(10.) };
+    assert!(alerts.update(ms(40_000), &thresholds, &swapping));
+
+    // no reply to a ping
+    let thresholds = Thresholds { ping: Some(100.), ..Thresholds::default() };
+    let timeout = Readings { ping: f64::INFINITY, ..Readings::default() };
+    let mut alerts = Alerts::new(0.05, Duration::from_millis(500), 2, Duration::from_secs(30));
+    assert!(!alerts.update(start, &thresholds, &timeout));
+    assert!(alerts.update(ms(30_000), &thresholds, &timeout));
+
+    // a fan stopped while it's hot, and clearing once it starts again
+    let thresholds = Thresholds { fan_stopped: Some(60.), ..Thresholds::default() };
+    let stopped = |temp| Readings { fan_stopped: temp, ..Readings::default() };
+    let mut alerts = Alerts::new(0.05, Duration::from_millis(500), 2, Duration::from_secs(30));
+    assert!(!alerts.update(start, &thresholds, &stopped(50.)));
+    assert!(!alerts.update(ms(30_000), &thresholds, &stopped(50.)));
+    assert!(!alerts.update(ms(31_000), &thresholds, &stopped(70.)));
+    assert!(alerts.update(ms(61_000), &thresholds, &stopped(70.)));
+    assert!(!alerts.update(ms(62_000), &thresholds, &stopped(f64::NEG_INFINITY)));
+
+    // someone else logging in, with no sustain, and not when they can't be counted
+    let thresholds =
+        Thresholds { sessions: Some(1.), sustain: Duration::ZERO, ..Thresholds::default() };
+    let sessions = |sessions| Readings { sessions, ..Readings::default() };
+    let mut alerts = Alerts::new(0.05, Duration::from_millis(500), 2, Duration::from_secs(30));
+    assert!(!alerts.update(start, &thresholds, &sessions(1.)));
+    assert!(alerts.update(ms(1_000), &thresholds, &sessions(2.)));
+    assert!(!alerts.update(ms(2_000), &thresholds, &sessions(1.)));
+    assert!(!alerts.update(ms(3_000), &thresholds, &sessions(f64::NAN)));
+
+    // the clock unsynchronized for longer than 10 minutes
+    let thresholds =
+        Thresholds { unsynced: Some(10.), sustain: Duration::ZERO, ..Thresholds::default() };
+    let unsynced = |unsynced| Readings { unsynced, ..Readings::default() };
+    let mut alerts = Alerts::new(0.05, Duration::from_millis(500), 2, Duration::from_secs(30));
+    assert!(!alerts.update(start, &thresholds, &unsynced(10.)));
+    assert!(alerts.update(ms(60_000), &thresholds, &unsynced(11.)));
+    assert!(!alerts.update(ms(90_000), &thresholds, &unsynced(0.)));
+
+    // the power going out, and coming back
+    let thresholds =
+        Thresholds { on_battery: Some(0.), sustain: Duration::ZERO, ..Thresholds::default() };
+    let on_battery = |on_battery| Readings { on_battery, ..Readings::default() };
+    let mut alerts = Alerts::new(0.05, Duration::from_millis(500), 2, Duration::from_secs(30));
+    assert!(!alerts.update(start, &thresholds, &on_battery(f64::NEG_INFINITY)));
+    assert!(alerts.update(ms(15_000), &thresholds, &on_battery(0.25)));
+    assert!(!alerts.update(ms(30_000), &thresholds, &on_battery(f64::NEG_INFINITY)));
+
+    // a cable unplugged for more than 2 minutes
+    let thresholds =
+        Thresholds { link_down: Some(2.), sustain: Duration::ZERO, ..Thresholds::default() };
+    let link_down = |link_down| Readings { link_down, ..Readings::default() };
+    let mut alerts = Alerts::new(0.05, Duration::from_millis(500), 2, Duration::from_secs(30));
+    assert!(!alerts.update(start, &thresholds, &link_down(1.)));
+    assert!(alerts.update(ms(60_000), &thresholds, &link_down(2.5)));
+    assert!(!alerts.update(ms(61_000), &thresholds, &link_down(f64::NEG_INFINITY)));
+}
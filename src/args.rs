@@ -0,0 +1,180 @@
+use anyhow::{bail, Context, Result};
+use crate::config::{self, Config};
+use crate::detect::Selection;
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub const USAGE: &str = "\
+usage: h2-net-lcd [options]
+
+options:
+  --config <path>   config file to use (default: /etc/h2-net-lcd.toml)
+  --bus <n>         I2C bus the display is on, i.e. /dev/i2c-<n>
+  --addr <addr>     I2C address of the display, e.g. 0x27
+  --interval <time> how often to update the display, e.g. 500ms or 1s
+  --iface <name>    network interface to show; repeat for more than one
+  --auto-ifaces     pick which network interfaces to show automatically
+  --takeover        make an already-running instance exit, and take over its display
+  --check           check the config, display, and stats can all be read, then exit
+  --mock            draw the display in the terminal instead of on a real one
+  --help            show this message
+
+--bus, --addr, --interval, --iface, and --auto-ifaces override what's in the config file.
+";
+
+/// Command-line arguments.
+#[derive(Debug, Default, PartialEq)]
+pub struct Args {
+    pub config: Option<PathBuf>,
+    pub bus: Option<u8>,
+    pub addr: Option<u16>,
+    pub interval: Option<Duration>,
+    pub interfaces: Vec<String>,
+    pub auto_interfaces: bool,
+    pub takeover: bool,
+    pub check: bool,
+    pub mock: bool,
+    pub help: bool,
+}
+
+impl Args {
+    /// Parse arguments, not including the program name.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            // allow both `--opt value` and `--opt=value`
+            let (name, mut inline_value) = match arg.split_once('=') {
+                Some((name, value)) if name.starts_with("--") => (name.to_owned(), Some(value.to_owned())),
+                _ => (arg, None),
+            };
+            let mut value = || match inline_value.take().or_else(|| args.next()) {
+                Some(value) => Ok(value),
+                None => bail!("{} needs a value", name),
+            };
+            match name.as_str() {
+                "--config" => parsed.config = Some(PathBuf::from(value()?)),
+                "--bus" => {
+                    let value = value()?;
+                    parsed.bus = Some(config::parse_int(&value)
+                        .with_context(|| format!("invalid --bus {:?}", value))?);
+                }
+                "--addr" => {
+                    let value = value()?;
+                    parsed.addr = Some(config::parse_int(&value)
+                        .with_context(|| format!("invalid --addr {:?}", value))?);
+                }
+                "--interval" => {
+                    let value = value()?;
+                    parsed.interval = Some(config::parse_duration(&value)
+                        .with_context(|| format!("invalid --interval {:?}", value))?);
+                }
+                "--iface" => parsed.interfaces.push(value()?),
+                "--auto-ifaces" => parsed.auto_interfaces = true,
+                "--takeover" => parsed.takeover = true,
+                "--check" => parsed.check = true,
+                "--mock" => parsed.mock = true,
+                "--help" | "-h" => parsed.help = true,
+                _ => bail!("unknown argument {:?}", name),
+            }
+            if inline_value.is_some() {
+                bail!("{} doesn't take a value", name);
+            }
+        }
+        if parsed.auto_interfaces && !parsed.interfaces.is_empty() {
+            bail!("--iface and --auto-ifaces can't be used together");
+        }
+        Ok(parsed)
+    }
+
+    /// Override settings in the config with any given on the command line.
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(bus) = self.bus {
+            config.bus = bus;
+        }
+        if let Some(addr) = self.addr {
+            config.addr = addr;
+            config.scan_addresses = false;
+        }
+        if let Some(interval) = self.interval {
+            config.interval = interval;
+        }
+        if !self.interfaces.is_empty() {
+            config.interfaces = self.interfaces.clone();
+            config.select_interfaces = None;
+        }
+        if self.auto_interfaces {
+            config.select_interfaces = Some(Selection::Auto);
+        }
+    }
+}
+
+#[cfg(test)]
+fn parse(args: &[&str]) -> Result<Args> {
+    Args::parse(args.iter().map(|&s| s.to_owned()))
+}
+
+#[cfg(test)]
+#[test]
+fn test_args() {
+    assert_eq!(Args::default(), parse(&[]).unwrap());
+    assert_eq!(Args { config: Some("/tmp/x.toml".into()), takeover: true, ..Args::default() },
+        parse(&["--config", "/tmp/x.toml", "--takeover"]).unwrap());
+    assert_eq!(Some(PathBuf::from("a=b.toml")), parse(&["--config=a=b.toml"]).unwrap().config);
+    assert_eq!(Args { mock: true, check: true, ..Args::default() },
+        parse(&["--mock", "--check"]).unwrap());
+    assert_eq!(Args {
+            bus: Some(1),
+            addr: Some(0x3f),
+            interval: Some(Duration::from_millis(250)),
+            interfaces: vec!["lan0".to_owned(), "lan1".to_owned()],
+            ..Args::default()
+        },
+        parse(&["--bus", "1", "--addr=0x3f", "--interval", "250ms",
+            "--iface", "lan0", "--iface=lan1"]).unwrap());
+
+    let err = |args: &[&str]| format!("{:#}", parse(args).unwrap_err());
+    assert_eq!("--config needs a value", err(&["--config"]));
+    assert_eq!("unknown argument \"--bogus\"", err(&["--bogus"]));
+    assert_eq!("--takeover doesn't take a value", err(&["--takeover=yes"]));
+    assert_eq!("invalid --bus \"x\": invalid digit found in string", err(&["--bus", "x"]));
+    assert_eq!("invalid --addr \"0x10000\": 0x10000 is out of range",
+        err(&["--addr", "0x10000"]));
+    assert_eq!("invalid --interval \"5\": missing unit (ms, s, or m)", err(&["--interval", "5"]));
+    assert_eq!("--iface and --auto-ifaces can't be used together",
+        err(&["--auto-ifaces", "--iface", "lan0"]));
+}
+
+#[cfg(test)]
+#[test]
+fn test_precedence() {
+    // command line > environment > config file > default
+    let mut config = Config::parse("interval = \"1s\"\n[i2c]\nbus = 3\naddress = 0x20").unwrap();
+    let env = [("H2LCD_BUS", "4"), ("H2LCD_INTERVAL", "2s")];
+    config.apply_env(|name| env.iter().find(|(n, _)| *n == name).map(|(_, v)| (*v).to_owned()))
+        .unwrap();
+    parse(&["--bus", "5"]).unwrap().apply(&mut config);
+    assert_eq!(5, config.bus);
+    assert_eq!(Duration::from_secs(2), config.interval);
+    assert_eq!(0x20, config.addr);
+    assert_eq!(Config::default().fallback_bus, config.fallback_bus);
+}
+
+#[cfg(test)]
+#[test]
+fn test_args_apply() {
+    let mut config = Config::default();
+    parse(&["--bus", "1"]).unwrap().apply(&mut config);
+    assert_eq!(Config { bus: 1, ..Config::default() }, config);
+
+    parse(&["--iface", "wg0", "--interval", "1s"]).unwrap().apply(&mut config);
+    assert_eq!(1, config.bus);
+    assert_eq!(vec!["wg0".to_owned()], config.interfaces);
+    assert_eq!(Duration::from_secs(1), config.interval);
+
+    let mut config = Config { select_interfaces: Some(Selection::Auto), ..Config::default() };
+    parse(&["--iface", "wg0"]).unwrap().apply(&mut config);
+    assert_eq!(None, config.select_interfaces);
+    parse(&["--auto-ifaces"]).unwrap().apply(&mut config);
+    assert_eq!(Some(Selection::Auto), config.select_interfaces);
+}
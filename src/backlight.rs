@@ -0,0 +1,66 @@
+use anyhow::{bail, Result};
+
+/// Times of day to turn the backlight off and back on, in minutes after local midnight. If `off`
+/// is later than `on`, the dark period spans midnight.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Schedule {
+    pub off: u32,
+    pub on: u32,
+}
+
+impl Schedule {
+    /// Whether the backlight should be on at the given time (minutes after midnight).
+    pub fn is_on(&self, minute: u32) -> bool {
+        if self.off <= self.on {
+            !(self.off <= minute && minute < self.on)
+        } else {
+            self.on <= minute && minute < self.off
+        }
+    }
+}
+
+/// Parse a time of day like `23:00` or `7:30`, into minutes after midnight.
+pub fn parse_time(s: &str) -> Result<u32> {
+    let (hour, minute) = match s.split_once(':') {
+        Some((hour, minute)) if minute.len() == 2 => (hour.parse::<u32>()?, minute.parse::<u32>()?),
+        _ => bail!("should be a time like \"23:00\""),
+    };
+    if hour > 23 || minute > 59 {
+        bail!("{} isn't a time of day", s);
+    }
+    Ok(hour * 60 + minute)
+}
+
+#[cfg(test)]
+#[test]
+fn test_schedule() {
+    let time = |s| parse_time(s).unwrap();
+
+    // off during the day
+    let day = Schedule { off: time("09:00"), on: time("17:30") };
+    assert!(day.is_on(time("08:59")));
+    assert!(!day.is_on(time("09:00")));
+    assert!(!day.is_on(time("17:29")));
+    assert!(day.is_on(time("17:30")));
+
+    // off overnight
+    let night = Schedule { off: time("23:00"), on: time("7:00") };
+    assert!(night.is_on(time("22:59")));
+    assert!(!night.is_on(time("23:00")));
+    assert!(!night.is_on(time("0:00")));
+    assert!(!night.is_on(time("06:59")));
+    assert!(night.is_on(time("07:00")));
+    assert!(night.is_on(time("12:00")));
+}
+
+#[cfg(test)]
+#[test]
+fn test_parse_time() {
+    assert_eq!(0, parse_time("00:00").unwrap());
+    assert_eq!(23 * 60 + 59, parse_time("23:59").unwrap());
+    assert_eq!(7 * 60 + 5, parse_time("7:05").unwrap());
+    assert_eq!("24:00 isn't a time of day", parse_time("24:00").unwrap_err().to_string());
+    assert_eq!("should be a time like \"23:00\"", parse_time("11pm").unwrap_err().to_string());
+    assert!(parse_time("7:5").is_err());
+    assert!(parse_time("-1:00").is_err());
+}
@@ -0,0 +1,185 @@
+//! Digits two rows tall, built out of custom characters, for a page that shows one figure big
+//! enough to read from across the room.
+
+use anyhow::{bail, Result};
+use crate::charset;
+
+/// What the big digits show.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BigMetric {
+    /// Local time, as HH:MM.
+    Clock,
+    /// CPU temperature.
+    Temp,
+    /// Current total throughput of all interfaces, both ways.
+    Throughput,
+}
+
+impl BigMetric {
+    pub fn parse(s: &str) -> Result<Self> {
+        Ok(match s {
+            "clock" => BigMetric::Clock,
+            "temp" => BigMetric::Temp,
+            "throughput" => BigMetric::Throughput,
+            _ => bail!("should be \"clock\", \"temp\", or \"throughput\", not {:?}", s),
+        })
+    }
+
+    /// What to show above the digits, if there's room.
+    pub fn title(self) -> &'static str {
+        match self {
+            BigMetric::Clock => "time",
+            BigMetric::Temp => "cpu temperature",
+            BigMetric::Throughput => "total throughput",
+        }
+    }
+}
+
+// The pieces digits are made of, as custom characters.
+const FULL: u8 = 0;
+const UPPER: u8 = 1;
+const LOWER: u8 = 2;
+const BOTH: u8 = 3;
+const LOWER_DOT: u8 = 4;
+const UPPER_DOT: u8 = 5;
+
+/// The custom characters to upload while big digits are shown, in place of the bar gauges'.
+pub const GLYPHS: [[u8; 8]; 8] = [
+    [0x1f, 0x1f, 0x1f, 0x1f, 0x1f, 0x1f, 0x1f, 0x1f], // FULL
+    [0x1f, 0x1f, 0x1f, 0, 0, 0, 0, 0],                // UPPER
+    [0, 0, 0, 0, 0, 0x1f, 0x1f, 0x1f],                // LOWER
+    [0x1f, 0x1f, 0x1f, 0, 0, 0x1f, 0x1f, 0x1f],       // BOTH
+    [0, 0, 0, 0, 0, 0x0e, 0x0e, 0],                   // LOWER_DOT
+    [0, 0x0e, 0x0e, 0, 0, 0, 0, 0],                   // UPPER_DOT
+    [0; 8],
+    [0; 8],
+];
+
+/// Each digit, 3 characters wide: the top row, then the bottom row.
+const DIGITS: [[[u8; 3]; 2]; 10] = [
+    [[FULL, UPPER, FULL], [FULL, LOWER, FULL]],
+    [[UPPER, FULL, b' '], [LOWER, FULL, LOWER]],
+    [[BOTH, BOTH, FULL], [FULL, LOWER, LOWER]],
+    [[BOTH, BOTH, FULL], [LOWER, LOWER, FULL]],
+    [[FULL, LOWER, FULL], [b' ', b' ', FULL]],
+    [[FULL, BOTH, BOTH], [LOWER, LOWER, FULL]],
+    [[FULL, BOTH, BOTH], [FULL, LOWER, FULL]],
+    [[UPPER, UPPER, FULL], [b' ', b' ', FULL]],
+    [[FULL, BOTH, FULL], [FULL, LOWER, FULL]],
+    [[FULL, BOTH, FULL], [LOWER, LOWER, FULL]],
+];
+
+/// Render text two rows tall: digits and colons big, with a column between neighbouring digits,
+/// and anything else (like units, or a decimal point) small, on the bottom row.
+pub fn render(text: &[u8]) -> [Vec<u8>; 2] {
+    let mut rows = [vec![], vec![]];
+    let mut last_digit = false;
+    for &c in text {
+        match c {
+            b'0' ..= b'9' => {
+                if last_digit {
+                    rows[0].push(b' ');
+                    rows[1].push(b' ');
+                }
+                let [top, bottom] = DIGITS[(c - b'0') as usize];
+                rows[0].extend_from_slice(&top);
+                rows[1].extend_from_slice(&bottom);
+            }
+            b':' => {
+                rows[0].push(LOWER_DOT);
+                rows[1].push(UPPER_DOT);
+            }
+            _ => {
+                rows[0].push(b' ');
+                rows[1].push(c);
+            }
+        }
+        last_digit = c.is_ascii_digit();
+    }
+    rows
+}
+
+/// The text for a metric's figure, with units.
+pub fn text(metric: BigMetric, time: Option<(i32, i32)>, temp: Option<f64>, mbps: f64)
+    -> Vec<u8>
+{
+    match metric {
+        BigMetric::Clock => match time {
+            Some((hour, minute)) => format!("{:02}:{:02}", hour, minute).into_bytes(),
+            None => b"--:--".to_vec(),
+        },
+        BigMetric::Temp => {
+            let mut text = match temp {
+                Some(temp) => format!("{:.0}", temp).into_bytes(),
+                None => b"--".to_vec(),
+            };
+            text.extend_from_slice(&[charset::DEGREE, b'C']);
+            text
+        }
+        BigMetric::Throughput if mbps < 999.5 => format!("{:.0}Mbps", mbps).into_bytes(),
+        BigMetric::Throughput => format!("{:.1}Gbps", mbps / 1000.).into_bytes(),
+    }
+}
+
+/// A whole page: the big figure centered, with the title above it if there are more than 2 rows.
+pub fn page(metric: BigMetric, text: &[u8], cols: usize, rows: usize) -> Vec<Vec<u8>> {
+    let center = |line: &[u8]| -> Vec<u8> {
+        let pad = cols.saturating_sub(line.len()) / 2;
+        std::iter::repeat_n(b' ', pad)
+            .chain(line.iter().copied())
+            .chain(std::iter::repeat(b' '))
+            .take(cols)
+            .collect()
+    };
+    let [top, bottom] = render(text);
+    let mut page = vec![];
+    if rows > 2 {
+        page.push(center(metric.title().as_bytes()));
+    }
+    page.push(center(&top));
+    page.push(center(&bottom));
+    while page.len() < rows {
+        page.push(vec![b' '; cols]);
+    }
+    page
+}
+
+#[cfg(test)]
+#[test]
+fn test_render() {
+    assert_eq!([vec![FULL, UPPER, FULL], vec![FULL, LOWER, FULL]], render(b"0"));
+    let [top, bottom] = render(b"12:30");
+    assert_eq!(vec![UPPER, FULL, b' ', b' ', BOTH, BOTH, FULL, LOWER_DOT,
+        BOTH, BOTH, FULL, b' ', FULL, UPPER, FULL], top);
+    assert_eq!(vec![LOWER, FULL, LOWER, b' ', FULL, LOWER, LOWER, UPPER_DOT,
+        LOWER, LOWER, FULL, b' ', FULL, LOWER, FULL], bottom);
+
+    // small characters go on the bottom row
+    let [top, bottom] = render(b"1.5G");
+    assert_eq!(vec![UPPER, FULL, b' ', b' ', FULL, BOTH, BOTH, b' '], top);
+    assert_eq!(vec![LOWER, FULL, LOWER, b'.', LOWER, LOWER, FULL, b'G'], bottom);
+}
+
+#[cfg(test)]
+#[test]
+fn test_text() {
+    assert_eq!(b"07:05".to_vec(), text(BigMetric::Clock, Some((7, 5)), None, 0.));
+    assert_eq!(b"--:--".to_vec(), text(BigMetric::Clock, None, None, 0.));
+    assert_eq!(b"52\x80C".to_vec(), text(BigMetric::Temp, None, Some(51.6), 0.));
+    assert_eq!(b"--\x80C".to_vec(), text(BigMetric::Temp, None, None, 0.));
+    assert_eq!(b"999Mbps".to_vec(), text(BigMetric::Throughput, None, None, 999.4));
+    assert_eq!(b"1.0Gbps".to_vec(), text(BigMetric::Throughput, None, None, 999.5));
+}
+
+#[cfg(test)]
+#[test]
+fn test_page() {
+    let lines = page(BigMetric::Temp, b"5", 8, 4);
+    assert_eq!(4, lines.len());
+    assert_eq!(b"cpu temp".to_vec(), lines[0]);
+    assert_eq!(vec![b' ', b' ', FULL, BOTH, BOTH, b' ', b' ', b' '], lines[1]);
+    assert_eq!(vec![b' '; 8], lines[3]);
+
+    // no room for the title on 2 rows
+    assert_eq!(2, page(BigMetric::Temp, b"5", 8, 2).len());
+}
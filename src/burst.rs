@@ -0,0 +1,68 @@
+use std::time::{Duration, Instant};
+
+/// Switches to a faster sampling interval for a while when traffic gets high, so the shape of
+/// short bursts shows up in the history without sampling quickly all the time.
+pub struct Burst {
+    trigger_mbps: f64,
+    normal: Duration,
+    fast: Duration,
+    duration: Duration,
+    until: Option<Instant>,
+    activations: u64,
+}
+
+impl Burst {
+    pub fn new(trigger_mbps: f64, normal: Duration, fast: Duration, duration: Duration) -> Self {
+        Self { trigger_mbps, normal, fast, duration, until: None, activations: 0 }
+    }
+
+    /// Update with the highest current rate, returning the interval until the next sample. While
+    /// the rate stays above the trigger, burst mode keeps getting extended.
+    pub fn update(&mut self, now: Instant, mbps: f64) -> Duration {
+        if mbps >= self.trigger_mbps {
+            if !self.is_active(now) {
+                self.activations += 1;
+                eprintln!("{} Mbps: sampling every {:?} (burst #{})",
+                    mbps.round(), self.fast, self.activations);
+            }
+            self.until = Some(now + self.duration);
+        }
+        if self.is_active(now) {
+            self.fast
+        } else {
+            self.until = None;
+            self.normal
+        }
+    }
+
+    fn is_active(&self, now: Instant) -> bool {
+        matches!(self.until, Some(until) if now < until)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_burst() {
+    let start = Instant::now();
+    let ms = |ms| start + Duration::from_millis(ms);
+    let normal = Duration::from_millis(1000);
+    let fast = Duration::from_millis(200);
+    let mut burst = Burst::new(100., normal, fast, Duration::from_secs(5));
+
+    assert_eq!(normal, burst.update(ms(0), 10.));
+    assert_eq!(fast, burst.update(ms(1000), 150.));
+    assert_eq!(1, burst.activations);
+    assert_eq!(fast, burst.update(ms(1200), 5.));
+
+    // extended while high
+    assert_eq!(fast, burst.update(ms(4000), 100.));
+    assert_eq!(fast, burst.update(ms(8800), 5.));
+    assert_eq!(1, burst.activations);
+
+    // and falls back afterwards
+    assert_eq!(normal, burst.update(ms(9000), 5.));
+    assert_eq!(normal, burst.update(ms(10000), 5.));
+
+    assert_eq!(fast, burst.update(ms(11000), 500.));
+    assert_eq!(2, burst.activations);
+}
@@ -0,0 +1,117 @@
+//! Characters beyond ASCII. Frames use codes of their own for these, which the LCD driver turns
+//! into whatever byte the module's character ROM has them at, since that varies.
+
+use anyhow::{bail, Result};
+use std::collections::BTreeMap;
+
+pub const DEGREE: u8 = 0x80;
+pub const MICRO: u8 = 0x81;
+pub const ARROW: u8 = 0x82;
+
+/// Each symbol: its code in frames, its name in the config, the Unicode character, and the byte
+/// for it in the A00 (Japanese) and A02 (European) ROMs.
+const SYMBOLS: &[(u8, &str, char, u8, u8)] = &[
+    (DEGREE, "degree", '°', 0xdf, 0xb0),
+    (MICRO, "micro", 'µ', 0xe4, 0xb5),
+    // A02 has no right arrow
+    (ARROW, "arrow", '→', 0x7e, b'>'),
+];
+
+/// Which character ROM an HD44780 has.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rom {
+    /// Japanese: ASCII, then katakana and some symbols. The most common one.
+    A00,
+    /// European: ASCII, then mostly Latin-1 and Cyrillic.
+    A02,
+}
+
+impl Rom {
+    pub fn parse(s: &str) -> Result<Self> {
+        Ok(match s {
+            "a00" => Rom::A00,
+            "a02" => Rom::A02,
+            _ => bail!("should be \"a00\" or \"a02\", not {:?}", s),
+        })
+    }
+}
+
+/// How to turn frames into the bytes a display shows them as.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Charset {
+    pub rom: Rom,
+    /// Bytes to send for particular characters instead, for modules that match neither ROM.
+    pub overrides: BTreeMap<u8, u8>,
+}
+
+impl Default for Charset {
+    fn default() -> Self {
+        Self { rom: Rom::A00, overrides: BTreeMap::new() }
+    }
+}
+
+impl Charset {
+    /// The byte to send to the display for a character in a frame.
+    pub fn byte(&self, c: u8) -> u8 {
+        if let Some(&byte) = self.overrides.get(&c) {
+            return byte;
+        }
+        match SYMBOLS.iter().find(|symbol| symbol.0 == c) {
+            Some(&(_, _, _, a00, a02)) => match self.rom {
+                Rom::A00 => a00,
+                Rom::A02 => a02,
+            },
+            None => c,
+        }
+    }
+}
+
+/// The frame code for a character to override: a symbol's name, or a single printable ASCII
+/// character.
+pub fn parse_character(s: &str) -> Result<u8> {
+    if let Some(symbol) = SYMBOLS.iter().find(|symbol| symbol.1 == s) {
+        return Ok(symbol.0);
+    }
+    match s.as_bytes() {
+        &[c] if c.is_ascii_graphic() || c == b' ' => Ok(c),
+        _ => bail!("should be \"degree\", \"micro\", \"arrow\", or a single ASCII character, \
+            not {:?}", s),
+    }
+}
+
+/// The frame code for a character: ASCII as is, symbols as their codes, and anything else as `?`.
+pub fn encode(c: char) -> u8 {
+    if c.is_ascii() {
+        return c as u8;
+    }
+    SYMBOLS.iter().find(|symbol| symbol.2 == c).map_or(b'?', |symbol| symbol.0)
+}
+
+/// The Unicode character for a symbol's frame code, for displays that don't have a ROM.
+pub fn unicode(c: u8) -> Option<char> {
+    SYMBOLS.iter().find(|symbol| symbol.0 == c).map(|symbol| symbol.2)
+}
+
+#[cfg(test)]
+#[test]
+fn test_charset() {
+    assert_eq!(b"a\x80\x82?".to_vec(), "a°→é".chars().map(encode).collect::<Vec<_>>());
+    assert_eq!(Some('µ'), unicode(MICRO));
+    assert_eq!(None, unicode(b'u'));
+
+    let a00 = Charset::default();
+    assert_eq!((0xdf, 0x7e, b'C'), (a00.byte(DEGREE), a00.byte(ARROW), a00.byte(b'C')));
+    let a02 = Charset { rom: Rom::A02, ..Charset::default() };
+    assert_eq!((0xb0, b'>'), (a02.byte(DEGREE), a02.byte(ARROW)));
+
+    let custom = Charset {
+        rom: Rom::A02,
+        overrides: vec![(DEGREE, 0xd2), (b'\\', b'/')].into_iter().collect(),
+    };
+    assert_eq!((0xd2, b'/', 0xb5), (custom.byte(DEGREE), custom.byte(b'\\'), custom.byte(MICRO)));
+
+    assert_eq!(DEGREE, parse_character("degree").unwrap());
+    assert_eq!(b'~', parse_character("~").unwrap());
+    assert!(parse_character("ab").is_err());
+    assert!(parse_character("é").is_err());
+}
@@ -0,0 +1,66 @@
+//! Whether the system clock is synchronized, going by the status the kernel keeps for NTP daemons
+//! like chrony and ntpd. For an NTP server, losing sync means everything downstream drifts too.
+
+use std::time::{Duration, Instant};
+
+/// Whether the clock is synchronized, given the status flags and clock state from `adjtimex`. The
+/// NTP daemon clears STA_UNSYNC once it's synchronized, and the kernel sets it again if the daemon
+/// stops keeping it up to date.
+pub fn synced(status: i32, state: i32) -> bool {
+    status & libc::STA_UNSYNC == 0 && state != libc::TIME_ERROR
+}
+
+/// Ask the kernel whether the clock is synchronized, or None if it can't be asked.
+pub fn read() -> Option<bool> {
+    // with no modes set, this only reads
+    let mut timex: libc::timex = unsafe { std::mem::zeroed() };
+    let state = unsafe { libc::adjtimex(&mut timex) };
+    if state < 0 {
+        return None;
+    }
+    Some(synced(timex.status, state))
+}
+
+/// Keeps track of how long the clock's been unsynchronized.
+#[derive(Debug, Default)]
+pub struct SyncWatch {
+    unsynced_since: Option<Instant>,
+}
+
+impl SyncWatch {
+    /// Record whether the clock is synchronized as of `now`, returning how long it's been
+    /// unsynchronized, if it is.
+    pub fn update(&mut self, now: Instant, synced: bool) -> Option<Duration> {
+        if synced {
+            self.unsynced_since = None;
+            return None;
+        }
+        let since = *self.unsynced_since.get_or_insert(now);
+        Some(now - since)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_synced() {
+    assert!(synced(libc::STA_PLL | libc::STA_NANO, libc::TIME_OK));
+    assert!(!synced(libc::STA_PLL | libc::STA_UNSYNC, libc::TIME_OK));
+    assert!(!synced(libc::STA_PLL, libc::TIME_ERROR));
+    // a leap second coming up is fine
+    assert!(synced(libc::STA_PLL | libc::STA_INS, libc::TIME_INS));
+    assert!(read().is_some());
+}
+
+#[cfg(test)]
+#[test]
+fn test_sync_watch() {
+    let start = Instant::now();
+    let mins = |n: u64| start + Duration::from_secs(n * 60);
+    let mut watch = SyncWatch::default();
+    assert_eq!(None, watch.update(start, true));
+    assert_eq!(Some(Duration::ZERO), watch.update(mins(1), false));
+    assert_eq!(Some(Duration::from_secs(4 * 60)), watch.update(mins(5), false));
+    // starting over once it's back in sync
+    assert_eq!(None, watch.update(mins(6), true));
+    assert_eq!(Some(Duration::ZERO), watch.update(mins(7), false));
+}
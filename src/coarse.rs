@@ -0,0 +1,76 @@
+//! Coarse gauges for the accessible rendering mode: each display row shows only empty, half, or
+//! full, so a 3-row gauge has 7 levels (0 to 6) instead of 24, and the level only changes when the
+//! value moves clearly past it, so the bars don't flicker.
+
+/// How far (in levels) past the current level a value has to move before the level changes.
+const HYSTERESIS: f64 = 0.75;
+
+/// The level currently shown for one gauge column.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CoarseGauge {
+    level: u8,
+}
+
+impl CoarseGauge {
+    /// Update with a new value, for a gauge `rows` tall, returning the level to show.
+    pub fn update(&mut self, value: f64, rows: u8) -> u8 {
+        // two steps per row: half and full
+        let levels = rows as f64 * 2.;
+        let value = if value.is_nan() { 0. } else { value.clamp(0., 1.) } * levels;
+        if (value - self.level as f64).abs() >= HYSTERESIS {
+            self.level = value.round() as u8;
+        }
+        self.level
+    }
+}
+
+/// Character for a coarse gauge `rows` tall at the given level and row (0 = top).
+pub fn display_char(level: u8, row: u8, rows: u8) -> u8 {
+    assert!(row < rows);
+    let bottom = rows - 1 - row;
+    match level.saturating_sub(bottom * 2).min(2) {
+        0 => b' ',
+        1 => 3, // the 4-pixel block
+        _ => 7, // full block
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_coarse_display_char() {
+    // draw the gauge for every level, top row first
+    let frames: Vec<String> = (0 ..= 6)
+        .map(|level| (0 .. 3)
+            .map(|row| match display_char(level, row, 3) {
+                b' ' => ' ',
+                3 => '-',
+                7 => '#',
+                _ => '?',
+            })
+            .collect())
+        .collect();
+    assert_eq!(vec!["   ", "  -", "  #", " -#", " ##", "-##", "###"], frames);
+
+    // a 1-row gauge only has 3 levels
+    let one_row: Vec<u8> = (0 ..= 2).map(|level| display_char(level, 0, 1)).collect();
+    assert_eq!(vec![b' ', 3, 7], one_row);
+}
+
+#[cfg(test)]
+#[test]
+fn test_coarse_hysteresis() {
+    let mut gauge = CoarseGauge::default();
+    assert_eq!(0, gauge.update(0.1, 3)); // 0.6 levels: not enough to move
+    assert_eq!(1, gauge.update(0.15, 3)); // 0.9
+    assert_eq!(1, gauge.update(0.1, 3)); // hovering around a boundary doesn't flicker
+    assert_eq!(1, gauge.update(0.24, 3));
+    assert_eq!(1, gauge.update(0.05, 3));
+    assert_eq!(6, gauge.update(1., 3));
+    assert_eq!(6, gauge.update(0.9, 3));
+    assert_eq!(4, gauge.update(0.7, 3));
+    assert_eq!(0, gauge.update(f64::NAN, 3));
+
+    let mut gauge = CoarseGauge::default();
+    assert_eq!(2, gauge.update(1., 1));
+    assert_eq!(1, gauge.update(0.5, 1));
+}
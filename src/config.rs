@@ -0,0 +1,2758 @@
+use anyhow::{bail, Context, Result};
+use crate::address;
+use crate::alert::Thresholds;
+use crate::backlight::{self, Schedule};
+use crate::bigdigits::BigMetric;
+use crate::charset::{self, Charset, Rom};
+use crate::cores::{self, CpuMode};
+use crate::detect::Selection;
+use crate::display::{Driver, GlyphStyle};
+use crate::hwmon::CpuTemp;
+use crate::iowait::Iowait;
+use crate::layout::{self, Compression, Geometry, Layout, NetColumns, Panel, Segment, StatusItem};
+use crate::leases;
+use crate::link;
+use crate::ping;
+use crate::priority::{self, SchedPolicy};
+use crate::scale;
+use crate::sessions;
+use crate::ssd1306;
+use crate::totals;
+use crate::toml::{self, Table, Value};
+use crate::units::Units;
+use crate::ups;
+use crate::watchdog;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Where the config file is read from if no other path is given.
+pub const DEFAULT_PATH: &str = "/etc/h2-net-lcd.toml";
+
+/// Shortest interval allowed. Any faster and the display can't keep up.
+const MIN_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How many intervals the watchdog's timeout has to be at least, so sleeping between frames, plus
+/// a slow frame or two, can't set it off.
+const MIN_WATCHDOG_INTERVALS: u32 = 6;
+
+/// Shortest ping_interval allowed, to keep from flooding the target.
+const MIN_PING_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Settings from the config file. Anything not in the file gets the default, which matches what
+/// the program did before it had a config file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// Network interfaces to show, in order.
+    pub interfaces: Vec<String>,
+    /// Pick the interfaces from the ones that exist instead; `interfaces` is filled in when
+    /// loading.
+    pub select_interfaces: Option<Selection>,
+    /// Interfaces that are the sum of others, like the ports of a bridge, by name. They can be
+    /// listed in `interfaces` like real ones.
+    pub aggregates: BTreeMap<String, Vec<String>>,
+    /// Speed at the top of the interface gauges, in Mbps, unless `net_scale` or `interface_max`
+    /// says otherwise.
+    pub net_max: u32,
+    /// Whether the interface gauges go up to `net_max` or to each interface's link speed.
+    pub net_scale: link::Scale,
+    /// Speed at the top of particular interfaces' gauges, in Mbps, by name.
+    pub interface_max: BTreeMap<String, u32>,
+    /// Interfaces whose gauges aren't on the usual log scale, by name.
+    pub interface_scale: BTreeMap<String, scale::Scale>,
+    /// How many decades below the top the log scale of the interface gauges and the sparkline
+    /// covers.
+    pub net_decades: u32,
+    /// Whether each interface gets a column for each direction, or one for both.
+    pub net_columns: NetColumns,
+    /// How long to mark an interface's columns after its negotiated speed or duplex changes.
+    pub link_change_mark: Duration,
+    /// Block devices for the disk panel, in order.
+    pub disks: Vec<String>,
+    /// Disk speed at the top of the disk gauges, in MB/s.
+    pub disk_max: u32,
+    /// How many decades below `disk_max` the disk gauges cover.
+    pub disk_decades: u32,
+    /// Mount points for the filesystem panel, in order.
+    pub filesystems: Vec<String>,
+    /// Wireless interfaces for the wifi panel and the client count, in order.
+    pub wireless: Vec<String>,
+    /// Temperature sensors for the temp panel and the `{tempN}` status fields, in order, by hwmon
+    /// label or device name. The first one is also what `{temp}` shows, if any are given.
+    pub temperatures: Vec<String>,
+    /// Where the CPU temperature comes from, without any `temperatures`.
+    pub cpu_temp: CpuTemp,
+    /// Temperatures at the bottom and top of the temp gauges, in °C.
+    pub temp_min: u32,
+    pub temp_max: u32,
+    /// Fans for the fan panel, the fan segment, and the fan_stopped alert, in order, by hwmon
+    /// label or device name.
+    pub fans: Vec<String>,
+    /// Fan speed at the top of the fan gauges, in RPM.
+    pub fan_max: u32,
+    /// Interfaces whose errors and drops aren't marked or counted, for ones that report junk.
+    pub ignore_errors: Vec<String>,
+    /// Interfaces whose columns light a single pixel for any packet activity, even when the byte
+    /// rate is too low to register on the gauge.
+    pub activity_blip: Vec<String>,
+    /// One-character labels for interfaces and filesystems, shown under their columns from time
+    /// to time.
+    pub labels: BTreeMap<String, char>,
+    /// Where to keep each interface's total bytes sent and received across restarts, if
+    /// anywhere.
+    pub totals_file: Option<PathBuf>,
+    /// What to ping for the ping panel, status field, and alert, and how often.
+    pub ping_target: ping::Target,
+    pub ping_interval: Duration,
+    /// Interface whose address the address segment shows, and where to look up the external
+    /// address to show instead, if anywhere.
+    pub address_interface: Option<String>,
+    pub address_url: Option<address::Url>,
+    /// dnsmasq's lease file, for the leases segment.
+    pub leases_file: PathBuf,
+    /// Whether kernel threads can be the busiest process, for the top segment.
+    pub top_kernel_threads: bool,
+    /// Where the sessions segment and alert count login sessions from.
+    pub session_source: sessions::Source,
+    /// Mark the status row while the clock isn't synchronized.
+    pub clock_sync: bool,
+    /// Where to ask about the UPS, for the ups panel, segment, and alert, if there is one.
+    pub ups: Option<ups::Server>,
+    /// Nice level to run at, if any.
+    pub nice: Option<i32>,
+    /// Scheduling policy to run with, if any.
+    pub sched_policy: Option<SchedPolicy>,
+    /// CPUs to restrict the daemon to, if any.
+    pub cpu_affinity: Option<Vec<usize>>,
+    /// When to give up on a stuck main loop, and what to do then.
+    pub watchdog: watchdog::Settings,
+    /// I2C bus the display is on (i.e. `/dev/i2c-<bus>`).
+    pub bus: u8,
+    /// Bus to try if the display doesn't respond on `bus`.
+    pub fallback_bus: Option<u8>,
+    /// I2C address of the display.
+    pub addr: u16,
+    /// If the display doesn't respond at `addr`, look for it at the other addresses it could be
+    /// at.
+    pub scan_addresses: bool,
+    /// What kind of display it is.
+    pub driver: Driver,
+    /// Size of the display.
+    pub geometry: Geometry,
+    /// How the gauges' custom characters look.
+    pub glyphs: GlyphStyle,
+    /// Which bytes the display shows symbols like the degree sign as.
+    pub charset: Charset,
+    /// Whether the bottom row is used for status text rather than gauges.
+    pub status_row: bool,
+    /// What to leave on the display when the program stops, with the time it stopped, or None to
+    /// blank it and turn it off.
+    pub shutdown_message: Option<String>,
+    /// Whether to leave the backlight on when stopping with a message.
+    pub shutdown_backlight: bool,
+    /// Leave the last frame on the display when the program stops, instead of the shutdown
+    /// message.
+    pub shutdown_freeze: bool,
+    /// Force the backlight on or off, instead of following the schedule.
+    pub backlight: Option<bool>,
+    /// Times of day to turn the backlight off and on again, in minutes after midnight.
+    pub backlight_off: Option<u32>,
+    pub backlight_on: Option<u32>,
+    /// How often to update the display.
+    pub interval: Duration,
+    /// How far back the peak speeds on the bottom row go.
+    pub history_window: Duration,
+    /// Mbps any interface has to go above to sample faster for a while, to catch short bursts, if
+    /// any.
+    pub burst_trigger: Option<u32>,
+    /// How often to sample during a burst.
+    pub burst_interval: Duration,
+    /// How long to keep sampling faster after the last time the trigger was crossed.
+    pub burst_duration: Duration,
+    /// How fast the peak speeds on the status row fall when they drop, in Mbps per second, rather
+    /// than all at once, if at all.
+    pub peak_decay: Option<u32>,
+    /// Time constant of the moving average the bars are smoothed with, or zero for none.
+    pub smoothing: Duration,
+    /// How long the peak-hold markers on the bars stay put before falling, or zero for none.
+    pub peak_hold: Duration,
+    /// How far back the sparkline panel goes, and how many columns it's spread across.
+    pub sparkline_span: Duration,
+    pub sparkline_columns: usize,
+    /// How often to clear the display and draw everything again, in case it got garbled.
+    pub redraw_interval: Option<Duration>,
+    /// How often to run the display's initialization again, in case it was unplugged and plugged
+    /// back in, if at all.
+    pub reinit_interval: Option<Duration>,
+    /// How often to re-read memory usage, temperatures, fan speeds, and filesystem usage, which
+    /// change slowly. Zero reads them every frame, like CPU and network.
+    pub mem_interval: Duration,
+    pub temp_interval: Duration,
+    pub fan_interval: Duration,
+    pub fs_interval: Duration,
+    /// Easier-to-read rendering: gauges only show empty, half, or full in each row and don't
+    /// flicker between levels, the display updates at most every ACCESSIBLE_INTERVAL, and the
+    /// status row only shows the temperature and overall CPU usage.
+    pub accessible: bool,
+    /// Gauge panels to show, in order.
+    pub panels: Vec<Panel>,
+    /// How cores map to CPU columns.
+    pub cpu_mode: CpuMode,
+    /// Whether time spent waiting on disk counts as busy or idle in the CPU columns, or is shown
+    /// apart.
+    pub iowait: Iowait,
+    /// What the status row shows, if not the default.
+    pub status: Option<Vec<StatusItem>>,
+    /// What the speeds on the status row are in.
+    pub units: Units,
+    /// Things for the status row to cycle between, one every `status_period`, or empty to always
+    /// show the status.
+    pub status_rotation: Vec<Segment>,
+    pub status_period: Duration,
+    /// Interfaces the rates segment takes turns showing, or empty for all of them.
+    pub rates_interfaces: Vec<String>,
+    /// Mbps an interface has to be going at, one way or the other, to get a turn in the rates
+    /// segment. 0 gives every interface one.
+    pub rates_floor: u32,
+    /// What to show in big digits from time to time, if anything.
+    pub big_digits: Option<BigMetric>,
+    /// More sets of panels for the main display to take turns showing with `panels`, one every
+    /// `page_period`.
+    pub pages: Vec<Page>,
+    pub page_period: Duration,
+    /// When to flash the backlight to get attention.
+    pub alerts: Thresholds,
+    /// More displays to draw on, each with its own panels.
+    pub extra_displays: Vec<ExtraDisplay>,
+}
+
+/// Another set of panels for the main display to take turns showing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page {
+    /// Shown on the status row for a moment on switching to it, if given.
+    pub name: Option<String>,
+    pub panels: Vec<Panel>,
+    /// What the status row shows on this page, if not the same as on the first.
+    pub status: Option<Vec<StatusItem>>,
+}
+
+/// Another display, besides the main one. Labels and the backlight schedule are shared with the
+/// main one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtraDisplay {
+    /// I2C bus it's on, if not the same as the main display.
+    pub bus: Option<u8>,
+    pub addr: u16,
+    pub driver: Driver,
+    pub geometry: Geometry,
+    pub glyphs: GlyphStyle,
+    pub charset: Charset,
+    pub status_row: bool,
+    pub panels: Vec<Panel>,
+    pub status: Option<Vec<StatusItem>>,
+}
+
+/// Everything about one display: where it is, and what's drawn on it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Screen {
+    pub bus: u8,
+    pub fallback_bus: Option<u8>,
+    pub addr: u16,
+    pub scan_addresses: bool,
+    pub driver: Driver,
+    pub geometry: Geometry,
+    pub glyphs: GlyphStyle,
+    pub charset: Charset,
+    pub status_row: bool,
+    pub panels: Vec<Panel>,
+    pub status: Option<Vec<StatusItem>>,
+}
+
+impl Screen {
+    /// Check the display's size makes sense.
+    fn validate(&self) -> Result<()> {
+        self.geometry.validate()?;
+        if self.driver == Driver::Ssd1306 && self.geometry.cols > ssd1306::MAX_COLS {
+            bail!("the SSD1306 only fits {} columns, not {}", ssd1306::MAX_COLS,
+                self.geometry.cols);
+        }
+        Ok(())
+    }
+
+    /// Number of rows the gauges take up: all of them but the status row, if there is one.
+    pub fn gauge_rows(&self) -> usize {
+        self.geometry.rows - self.status_row as usize
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            interfaces: ["ether0", "ether1", "ether2", "ether3", "ether4", "ether5"]
+                .iter().map(|&s| s.to_owned()).collect(),
+            select_interfaces: None,
+            aggregates: BTreeMap::new(),
+            net_max: 1000,
+            net_scale: link::Scale::Global,
+            interface_max: BTreeMap::new(),
+            interface_scale: BTreeMap::new(),
+            net_decades: 3,
+            net_columns: NetColumns::Split,
+            link_change_mark: Duration::from_secs(300),
+            disks: vec![],
+            disk_max: 1000,
+            disk_decades: 3,
+            filesystems: vec![],
+            wireless: vec![],
+            temperatures: vec![],
+            cpu_temp: CpuTemp::Auto,
+            temp_min: 30,
+            temp_max: 90,
+            fans: vec![],
+            fan_max: 3000,
+            ignore_errors: vec![],
+            activity_blip: vec![],
+            labels: BTreeMap::new(),
+            totals_file: Some(PathBuf::from(totals::DEFAULT_PATH)),
+            ping_target: ping::Target::Gateway,
+            ping_interval: Duration::from_secs(5),
+            address_interface: None,
+            address_url: None,
+            leases_file: PathBuf::from(leases::DEFAULT_PATH),
+            top_kernel_threads: true,
+            session_source: sessions::Source::Utmp,
+            clock_sync: false,
+            ups: None,
+            nice: None,
+            sched_policy: None,
+            cpu_affinity: None,
+            watchdog: watchdog::Settings::default(),
+            bus: 2,
+            fallback_bus: Some(1),
+            addr: 0x27,
+            scan_addresses: false,
+            driver: Driver::Hd44780,
+            geometry: Geometry::DEFAULT,
+            glyphs: GlyphStyle::Solid,
+            charset: Charset::default(),
+            status_row: true,
+            shutdown_message: Some("stopped".to_owned()),
+            shutdown_backlight: false,
+            shutdown_freeze: false,
+            backlight: None,
+            backlight_off: None,
+            backlight_on: None,
+            interval: Duration::from_millis(500),
+            history_window: Duration::from_secs(60),
+            burst_trigger: None,
+            burst_interval: Duration::from_millis(200),
+            burst_duration: Duration::from_secs(10),
+            peak_decay: None,
+            smoothing: Duration::ZERO,
+            peak_hold: Duration::ZERO,
+            sparkline_span: Duration::from_secs(600),
+            sparkline_columns: 6,
+            redraw_interval: Some(Duration::from_secs(60)),
+            reinit_interval: None,
+            mem_interval: Duration::from_secs(5),
+            temp_interval: Duration::from_secs(5),
+            fan_interval: Duration::from_secs(5),
+            fs_interval: Duration::from_secs(30),
+            accessible: false,
+            panels: layout::DEFAULT_PANELS.to_vec(),
+            cpu_mode: CpuMode::Auto,
+            iowait: Iowait::Busy,
+            status: None,
+            units: Units::Mbps,
+            status_rotation: vec![],
+            status_period: Duration::from_secs(5),
+            rates_interfaces: vec![],
+            rates_floor: 0,
+            big_digits: None,
+            pages: vec![],
+            page_period: Duration::from_secs(15),
+            alerts: Thresholds::default(),
+            extra_displays: vec![],
+        }
+    }
+}
+
+impl Config {
+    /// Load the config from the given path, or from DEFAULT_PATH if none is given. If the default
+    /// file doesn't exist, that's fine, and defaults are used for everything.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let (path, required) = match path {
+            Some(path) => (path, true),
+            None => (Path::new(DEFAULT_PATH), false),
+        };
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if !required && e.kind() == io::ErrorKind::NotFound => {
+                return Ok(Self::default());
+            }
+            Err(e) => {
+                return Err(e).with_context(|| format!("failed to read config file {:?}", path));
+            }
+        };
+        Self::parse(&text).with_context(|| format!("error in config file {:?}", path))
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut config = Self::default();
+        config.merge(&toml::parse(text)?)?;
+        Ok(config)
+    }
+
+    /// Override settings with any given in environment variables (see ENV_VARS), using `var` to
+    /// look them up.
+    pub fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<()> {
+        for &(name, path) in ENV_VARS {
+            let value = match var(name) {
+                Some(value) => value,
+                None => continue,
+            };
+            // Turn the variable into the same structure as in the config file, so the same code
+            // interprets it.
+            let (key, parent) = match path.split_once('.') {
+                Some((parent, key)) => (key, Some(parent)),
+                None => (path, None),
+            };
+            let mut table = Table::new();
+            table.insert(key.to_owned(), env_value(path, &value));
+            if let Some(parent) = parent {
+                table = std::iter::once((parent.to_owned(), Value::Table(table))).collect();
+            }
+            self.merge(&table).with_context(|| format!("invalid {} {:?}", name, value))?;
+        }
+        Ok(())
+    }
+
+    /// Apply the settings in a parsed config file (or part of one) on top of the current ones.
+    fn merge(&mut self, doc: &Table) -> Result<()> {
+        let root = Section { path: None, table: doc };
+        root.check_keys(&[
+            "interfaces", "aggregate", "net_max", "net_scale", "interface_max", "interface_scale",
+            "net_decades", "net_columns", "link_change_mark", "disks", "disk_max", "disk_decades",
+            "filesystems", "wireless", "temperatures", "cpu_temp", "temp_min", "temp_max", "fans",
+            "fan_max", "ignore_errors", "activity_blip", "labels", "totals_file", "ping_target",
+            "ping_interval", "address_interface", "address_url", "leases_file",
+            "top_kernel_threads", "session_source", "clock_sync", "ups", "nice", "sched_policy",
+            "cpu_affinity", "watchdog", "interval", "history_window", "burst_trigger",
+            "burst_interval", "burst_duration", "peak_decay", "smoothing", "peak_hold",
+            "sparkline_span", "sparkline_columns", "redraw_interval", "reinit_interval",
+            "mem_interval", "temp_interval", "fan_interval", "fs_interval", "accessible", "panels",
+            "cpu_mode", "iowait", "status", "units", "status_rotation", "status_period",
+            "rates_interfaces", "rates_floor", "big_digits", "pages", "page_period", "alerts",
+            "i2c", "display", "backlight", "backlight_off", "backlight_on", "extra_displays",
+        ])?;
+
+        match root.get("interfaces") {
+            Some(Value::String(s)) if s == "auto" => self.select_interfaces = Some(Selection::Auto),
+            Some(Value::Table(_)) => {
+                let patterns = root.section("interfaces")?.unwrap();
+                patterns.check_keys(&["include", "exclude"])?;
+                let include = match patterns.string_list("include")? {
+                    Some(include) => include,
+                    None => bail!("{:?} is missing", patterns.key_path("include")),
+                };
+                let exclude = patterns.string_list("exclude")?.unwrap_or_default();
+                self.select_interfaces = Some(Selection::Patterns { include, exclude });
+            }
+            Some(other @ Value::String(_)) => {
+                return root.wrong_type("interfaces", "a list of strings, \"auto\", or a table",
+                    other);
+            }
+            Some(_) => {
+                self.interfaces = root.string_list("interfaces")?.unwrap_or_default();
+                self.select_interfaces = None;
+            }
+            None => (),
+        }
+        if let Some(aggregates) = root.section("aggregate")? {
+            self.aggregates.clear();
+            for name in aggregates.table.keys() {
+                let aggregate = aggregates.section(name)?.unwrap();
+                aggregate.check_keys(&["members"])?;
+                match aggregate.string_list("members")? {
+                    Some(members) => self.aggregates.insert(name.clone(), members),
+                    None => bail!("{:?} is missing", aggregate.key_path("members")),
+                };
+            }
+        }
+        if let Some(max) = root.integer("net_max")? {
+            self.net_max = max;
+        }
+        match root.get("net_scale") {
+            Some(Value::String(s)) => {
+                self.net_scale = link::Scale::parse(s).context("invalid \"net_scale\"")?;
+            }
+            Some(other) => return root.wrong_type("net_scale", "a string", other),
+            None => (),
+        }
+        if let Some(maxes) = root.section("interface_max")? {
+            self.interface_max.clear();
+            for name in maxes.table.keys() {
+                self.interface_max.insert(name.clone(), maxes.integer(name)?.unwrap());
+            }
+        }
+        if let Some(scales) = root.section("interface_scale")? {
+            self.interface_scale.clear();
+            for (name, value) in scales.table {
+                let scale = match value {
+                    Value::String(s) => scale::Scale::parse(s)
+                        .with_context(|| format!("invalid {:?}", scales.key_path(name)))?,
+                    other => return scales.wrong_type(name, "a string", other),
+                };
+                self.interface_scale.insert(name.clone(), scale);
+            }
+        }
+        if let Some(decades) = root.integer("net_decades")? {
+            self.net_decades = decades;
+        }
+        match root.get("net_columns") {
+            Some(Value::String(s)) => {
+                self.net_columns = NetColumns::parse(s).context("invalid \"net_columns\"")?;
+            }
+            Some(other) => return root.wrong_type("net_columns", "a string", other),
+            None => (),
+        }
+        if let Some(mark) = root.duration("link_change_mark")? {
+            self.link_change_mark = mark;
+        }
+        if let Some(interval) = root.duration("interval")? {
+            self.interval = interval;
+        }
+        if let Some(disks) = root.string_list("disks")? {
+            self.disks = disks;
+        }
+        if let Some(max) = root.integer("disk_max")? {
+            self.disk_max = max;
+        }
+        if let Some(decades) = root.integer("disk_decades")? {
+            self.disk_decades = decades;
+        }
+        if let Some(filesystems) = root.string_list("filesystems")? {
+            self.filesystems = filesystems;
+        }
+        if let Some(wireless) = root.string_list("wireless")? {
+            self.wireless = wireless;
+        }
+        if let Some(temperatures) = root.string_list("temperatures")? {
+            self.temperatures = temperatures;
+        }
+        match root.get("cpu_temp") {
+            Some(Value::String(s)) => {
+                self.cpu_temp = CpuTemp::parse(s).context("invalid \"cpu_temp\"")?;
+            }
+            Some(other) => return root.wrong_type("cpu_temp", "a string", other),
+            None => (),
+        }
+        if let Some(min) = root.integer("temp_min")? {
+            self.temp_min = min;
+        }
+        if let Some(max) = root.integer("temp_max")? {
+            self.temp_max = max;
+        }
+        if let Some(fans) = root.string_list("fans")? {
+            self.fans = fans;
+        }
+        if let Some(max) = root.integer("fan_max")? {
+            self.fan_max = max;
+        }
+        if let Some(ignore) = root.string_list("ignore_errors")? {
+            self.ignore_errors = ignore;
+        }
+        if let Some(blip) = root.string_list("activity_blip")? {
+            self.activity_blip = blip;
+        }
+        match root.get("totals_file") {
+            // `totals_file = false` starts the totals from zero every time
+            Some(Value::Boolean(false)) => self.totals_file = None,
+            Some(Value::String(s)) => self.totals_file = Some(PathBuf::from(s)),
+            Some(other) => return root.wrong_type("totals_file", "a string or false", other),
+            None => (),
+        }
+        match root.get("ping_target") {
+            Some(Value::String(s)) => {
+                self.ping_target = ping::Target::parse(s).context("invalid \"ping_target\"")?;
+            }
+            Some(other) => return root.wrong_type("ping_target", "a string", other),
+            None => (),
+        }
+        if let Some(interval) = root.duration("ping_interval")? {
+            self.ping_interval = interval;
+        }
+        match root.get("address_interface") {
+            Some(Value::String(s)) => self.address_interface = Some(s.clone()),
+            Some(other) => return root.wrong_type("address_interface", "a string", other),
+            None => (),
+        }
+        match root.get("address_url") {
+            Some(Value::String(s)) => {
+                self.address_url = Some(address::Url::parse(s).context("invalid \"address_url\"")?);
+            }
+            Some(other) => return root.wrong_type("address_url", "a string", other),
+            None => (),
+        }
+        match root.get("leases_file") {
+            Some(Value::String(s)) => self.leases_file = PathBuf::from(s),
+            Some(other) => return root.wrong_type("leases_file", "a string", other),
+            None => (),
+        }
+        if let Some(kernel_threads) = root.boolean("top_kernel_threads")? {
+            self.top_kernel_threads = kernel_threads;
+        }
+        match root.get("session_source") {
+            Some(Value::String(s)) => {
+                self.session_source = sessions::Source::parse(s)
+                    .context("invalid \"session_source\"")?;
+            }
+            Some(other) => return root.wrong_type("session_source", "a string", other),
+            None => (),
+        }
+        if let Some(clock_sync) = root.boolean("clock_sync")? {
+            self.clock_sync = clock_sync;
+        }
+        if let Some(section) = root.section("ups")? {
+            section.check_keys(&["protocol", "host", "port", "name"])?;
+            let protocol = match section.get("protocol") {
+                Some(Value::String(s)) => Some(ups::Protocol::parse(s)
+                    .with_context(|| format!("invalid {:?}", section.key_path("protocol")))?),
+                Some(other) => return section.wrong_type("protocol", "a string", other),
+                None => None,
+            };
+            let mut server = match (self.ups.take(), protocol) {
+                (Some(server), _) => server,
+                (None, Some(protocol)) => ups::Server {
+                    protocol,
+                    host: "127.0.0.1".to_owned(),
+                    port: protocol.default_port(),
+                    name: "ups".to_owned(),
+                },
+                (None, None) => bail!("{:?} is missing", section.key_path("protocol")),
+            };
+            if let Some(protocol) = protocol {
+                // the port follows the protocol, unless it was set to something else
+                if server.port == server.protocol.default_port() {
+                    server.port = protocol.default_port();
+                }
+                server.protocol = protocol;
+            }
+            for (key, value) in [("host", &mut server.host), ("name", &mut server.name)] {
+                match section.get(key) {
+                    Some(Value::String(s)) => *value = s.clone(),
+                    Some(other) => return section.wrong_type(key, "a string", other),
+                    None => (),
+                }
+            }
+            if let Some(port) = section.integer("port")? {
+                server.port = port;
+            }
+            self.ups = Some(server);
+        }
+        match root.get("nice") {
+            // `nice = false` leaves it as it was started with
+            Some(Value::Boolean(false)) => self.nice = None,
+            Some(_) => self.nice = root.integer("nice")?,
+            None => (),
+        }
+        match root.get("sched_policy") {
+            Some(Value::String(s)) => {
+                self.sched_policy = SchedPolicy::parse(s).context("invalid \"sched_policy\"")?;
+            }
+            Some(other) => return root.wrong_type("sched_policy", "a string", other),
+            None => (),
+        }
+        match root.get("cpu_affinity") {
+            // `cpu_affinity = false` lets it run on any CPU
+            Some(Value::Boolean(false)) => self.cpu_affinity = None,
+            Some(Value::String(s)) => {
+                self.cpu_affinity = Some(priority::parse_cpu_list(s)
+                    .context("invalid \"cpu_affinity\"")?);
+            }
+            Some(other) => return root.wrong_type("cpu_affinity", "a string or false", other),
+            None => (),
+        }
+        if let Some(section) = root.section("watchdog")? {
+            section.check_keys(&["timeout", "action"])?;
+            match section.get("timeout") {
+                // `timeout = false` turns it off
+                Some(Value::Boolean(false)) => self.watchdog.timeout = None,
+                Some(_) => self.watchdog.timeout = section.duration("timeout")?,
+                None => (),
+            }
+            match section.get("action") {
+                Some(Value::String(s)) => {
+                    self.watchdog.action = watchdog::Action::parse(s)
+                        .with_context(|| format!("invalid {:?}", section.key_path("action")))?;
+                }
+                Some(other) => return section.wrong_type("action", "a string", other),
+                None => (),
+            }
+        }
+        if let Some(window) = root.duration("history_window")? {
+            self.history_window = window;
+        }
+        match root.get("burst_trigger") {
+            // `burst_trigger = false` turns it off
+            Some(Value::Boolean(false)) => self.burst_trigger = None,
+            Some(_) => self.burst_trigger = root.integer("burst_trigger")?,
+            None => (),
+        }
+        if let Some(interval) = root.duration("burst_interval")? {
+            self.burst_interval = interval;
+        }
+        if let Some(duration) = root.duration("burst_duration")? {
+            self.burst_duration = duration;
+        }
+        match root.get("peak_decay") {
+            // `peak_decay = false` has them drop all at once
+            Some(Value::Boolean(false)) => self.peak_decay = None,
+            Some(_) => self.peak_decay = root.integer("peak_decay")?,
+            None => (),
+        }
+        if let Some(smoothing) = root.duration("smoothing")? {
+            self.smoothing = smoothing;
+        }
+        if let Some(hold) = root.duration("peak_hold")? {
+            self.peak_hold = hold;
+        }
+        if let Some(span) = root.duration("sparkline_span")? {
+            self.sparkline_span = span;
+        }
+        if let Some(columns) = root.integer("sparkline_columns")? {
+            self.sparkline_columns = columns;
+        }
+        match root.get("redraw_interval") {
+            // `redraw_interval = false` turns it off
+            Some(Value::Boolean(false)) => self.redraw_interval = None,
+            Some(_) => self.redraw_interval = root.duration("redraw_interval")?,
+            None => (),
+        }
+        match root.get("reinit_interval") {
+            // `reinit_interval = false` turns it off
+            Some(Value::Boolean(false)) => self.reinit_interval = None,
+            Some(_) => self.reinit_interval = root.duration("reinit_interval")?,
+            None => (),
+        }
+        if let Some(interval) = root.duration("mem_interval")? {
+            self.mem_interval = interval;
+        }
+        if let Some(interval) = root.duration("temp_interval")? {
+            self.temp_interval = interval;
+        }
+        if let Some(interval) = root.duration("fan_interval")? {
+            self.fan_interval = interval;
+        }
+        if let Some(interval) = root.duration("fs_interval")? {
+            self.fs_interval = interval;
+        }
+        if let Some(accessible) = root.boolean("accessible")? {
+            self.accessible = accessible;
+        }
+        if let Some(panels) = root.string_list("panels")? {
+            self.panels = layout::parse_panels(&panels).context("invalid \"panels\"")?;
+        }
+        match root.get("cpu_mode") {
+            Some(Value::String(s)) => {
+                self.cpu_mode = CpuMode::parse(s).context("invalid \"cpu_mode\"")?;
+            }
+            Some(other) => return root.wrong_type("cpu_mode", "a string", other),
+            None => (),
+        }
+        match root.get("iowait") {
+            Some(Value::String(s)) => {
+                self.iowait = Iowait::parse(s).context("invalid \"iowait\"")?;
+            }
+            Some(other) => return root.wrong_type("iowait", "a string", other),
+            None => (),
+        }
+        if let Some(status) = root.status("status")? {
+            self.status = Some(status);
+        }
+        match root.get("units") {
+            Some(Value::String(s)) => {
+                self.units = Units::parse(s).context("invalid \"units\"")?;
+            }
+            Some(other) => return root.wrong_type("units", "a string", other),
+            None => (),
+        }
+        if let Some(segments) = root.string_list("status_rotation")? {
+            self.status_rotation =
+                layout::parse_segments(&segments).context("invalid \"status_rotation\"")?;
+        }
+        if let Some(period) = root.duration("status_period")? {
+            self.status_period = period;
+        }
+        if let Some(interfaces) = root.string_list("rates_interfaces")? {
+            self.rates_interfaces = interfaces;
+        }
+        if let Some(floor) = root.integer("rates_floor")? {
+            self.rates_floor = floor;
+        }
+        match root.get("big_digits") {
+            Some(Value::String(s)) if s == "off" => self.big_digits = None,
+            Some(Value::String(s)) => {
+                self.big_digits = Some(BigMetric::parse(s).context("invalid \"big_digits\"")?);
+            }
+            Some(other) => return root.wrong_type("big_digits", "a string", other),
+            None => (),
+        }
+        match root.get("pages") {
+            Some(Value::Array(items)) => {
+                self.pages.clear();
+                for (i, item) in items.iter().enumerate() {
+                    let table = match item {
+                        Value::Table(table) => table,
+                        other => return root.wrong_type("pages", "a list of tables", other),
+                    };
+                    let path = Some(format!("pages.{}", i + 1));
+                    self.pages.push(Section { path, table }.page()?);
+                }
+            }
+            Some(other) => return root.wrong_type("pages", "a list of tables", other),
+            None => (),
+        }
+        if let Some(period) = root.duration("page_period")? {
+            self.page_period = period;
+        }
+        match root.get("backlight") {
+            Some(Value::String(s)) => self.backlight = match s.as_str() {
+                "on" => Some(true),
+                "off" => Some(false),
+                "auto" => None,
+                _ => bail!("\"backlight\" should be \"on\", \"off\", or \"auto\", not {:?}", s),
+            },
+            Some(other) => return root.wrong_type("backlight", "a string", other),
+            None => (),
+        }
+        if let Some(off) = root.time("backlight_off")? {
+            self.backlight_off = Some(off);
+        }
+        if let Some(on) = root.time("backlight_on")? {
+            self.backlight_on = Some(on);
+        }
+        if let Some(labels) = root.section("labels")? {
+            self.labels.clear();
+            for (name, value) in labels.table {
+                let label = match value {
+                    Value::String(s) => s,
+                    other => return labels.wrong_type(name, "a string", other),
+                };
+                match label.chars().collect::<Vec<_>>()[..] {
+                    [c] if c.is_ascii_graphic() => self.labels.insert(name.clone(), c),
+                    _ => bail!("{:?} should be a single letter, digit, or symbol, not {:?}",
+                        labels.key_path(name), label),
+                };
+            }
+        }
+        if let Some(i2c) = root.section("i2c")? {
+            i2c.check_keys(&["bus", "fallback_bus", "address"])?;
+            if let Some(bus) = i2c.integer("bus")? {
+                self.bus = bus;
+            }
+            match i2c.get("fallback_bus") {
+                // `fallback_bus = false` disables the fallback
+                Some(Value::Boolean(false)) => self.fallback_bus = None,
+                Some(_) => self.fallback_bus = i2c.integer("fallback_bus")?,
+                None => (),
+            }
+            match i2c.get("address") {
+                // still try the current address first, but look further if it doesn't work
+                Some(Value::String(s)) if s == "auto" => self.scan_addresses = true,
+                Some(Value::String(s)) => {
+                    bail!("\"i2c.address\" should be an integer or \"auto\", not {:?}", s);
+                }
+                Some(_) => {
+                    self.addr = i2c.integer("address")?.unwrap();
+                    self.scan_addresses = false;
+                }
+                None => (),
+            }
+        }
+        if let Some(display) = root.section("display")? {
+            display.check_keys(&[
+                "driver", "cols", "rows", "glyphs", "rom", "characters", "status_row",
+                "shutdown_message", "shutdown_backlight", "shutdown_freeze",
+            ])?;
+            if let Some(driver) = display.driver("driver")? {
+                self.driver = driver;
+            }
+            if let Some(cols) = display.integer("cols")? {
+                self.geometry.cols = cols;
+            }
+            if let Some(rows) = display.integer("rows")? {
+                self.geometry.rows = rows;
+            }
+            if let Some(glyphs) = display.glyphs("glyphs")? {
+                self.glyphs = glyphs;
+            }
+            display.charset(&mut self.charset)?;
+            if let Some(status_row) = display.boolean("status_row")? {
+                self.status_row = status_row;
+            }
+            match display.get("shutdown_message") {
+                // `shutdown_message = false` blanks the display instead
+                Some(Value::Boolean(false)) => self.shutdown_message = None,
+                Some(Value::String(s)) => self.shutdown_message = Some(s.clone()),
+                Some(other) => {
+                    return display.wrong_type("shutdown_message", "a string or false", other);
+                }
+                None => (),
+            }
+            if let Some(backlight) = display.boolean("shutdown_backlight")? {
+                self.shutdown_backlight = backlight;
+            }
+            if let Some(freeze) = display.boolean("shutdown_freeze")? {
+                self.shutdown_freeze = freeze;
+            }
+        }
+        if let Some(alerts) = root.section("alerts")? {
+            alerts.check_keys(&["temp", "mem", "mbps", "swapping", "ping", "fan_stopped",
+                "sessions", "unsynced", "on_battery", "link_down", "sustain"])?;
+            let thresholds = [
+                ("temp", &mut self.alerts.temp),
+                ("mem", &mut self.alerts.mem),
+                ("mbps", &mut self.alerts.mbps),
+                ("swapping", &mut self.alerts.swapping),
+                ("ping", &mut self.alerts.ping),
+                ("fan_stopped", &mut self.alerts.fan_stopped),
+                ("sessions", &mut self.alerts.sessions),
+                ("unsynced", &mut self.alerts.unsynced),
+                ("on_battery", &mut self.alerts.on_battery),
+                ("link_down", &mut self.alerts.link_down),
+            ];
+            for (key, threshold) in thresholds {
+                match alerts.get(key) {
+                    // `false` turns one off, e.g. to override the config file
+                    Some(Value::Boolean(false)) => *threshold = None,
+                    Some(_) => *threshold = alerts.integer::<u32>(key)?.map(f64::from),
+                    None => (),
+                }
+            }
+            if let Some(sustain) = alerts.duration("sustain")? {
+                self.alerts.sustain = sustain;
+            }
+        }
+        match root.get("extra_displays") {
+            Some(Value::Array(items)) => {
+                self.extra_displays.clear();
+                for (i, item) in items.iter().enumerate() {
+                    let table = match item {
+                        Value::Table(table) => table,
+                        other => {
+                            return root.wrong_type("extra_displays", "a list of tables", other);
+                        }
+                    };
+                    let path = Some(format!("extra_displays.{}", i + 1));
+                    self.extra_displays.push(Section { path, table }.extra_display()?);
+                }
+            }
+            Some(other) => return root.wrong_type("extra_displays", "a list of tables", other),
+            None => (),
+        }
+        Ok(())
+    }
+
+    /// Leave a panel out of every display, e.g. because there's nothing for it to show.
+    pub fn remove_panel(&mut self, panel: Panel) {
+        self.panels.retain(|&p| p != panel);
+        for page in &mut self.pages {
+            page.panels.retain(|&p| p != panel);
+        }
+        for extra in &mut self.extra_displays {
+            extra.panels.retain(|&p| p != panel);
+        }
+    }
+
+    /// The main display, then any extra ones.
+    pub fn screens(&self) -> Vec<Screen> {
+        self.page_screens(0)
+    }
+
+    /// The main display as it is on the given page (0 for `panels` and `status`, then each of
+    /// `pages` in turn), then any extra ones, which don't change.
+    pub fn page_screens(&self, page: usize) -> Vec<Screen> {
+        let (panels, status) = match page.checked_sub(1).and_then(|i| self.pages.get(i)) {
+            Some(page) => (&page.panels, page.status.as_ref().or(self.status.as_ref())),
+            None => (&self.panels, self.status.as_ref()),
+        };
+        let main = Screen {
+            bus: self.bus,
+            fallback_bus: self.fallback_bus,
+            addr: self.addr,
+            scan_addresses: self.scan_addresses,
+            driver: self.driver,
+            geometry: self.geometry,
+            glyphs: self.glyphs,
+            charset: self.charset.clone(),
+            status_row: self.status_row,
+            panels: panels.clone(),
+            status: status.cloned(),
+        };
+        std::iter::once(main)
+            .chain(self.extra_displays.iter().map(|extra| Screen {
+                bus: extra.bus.unwrap_or(self.bus),
+                // only fall back along with the main display
+                fallback_bus: if extra.bus.is_none() { self.fallback_bus } else { None },
+                addr: extra.addr,
+                // a scan could just find the main display
+                scan_addresses: false,
+                driver: extra.driver,
+                geometry: extra.geometry,
+                glyphs: extra.glyphs,
+                charset: extra.charset.clone(),
+                status_row: extra.status_row,
+                panels: extra.panels.clone(),
+                status: extra.status.clone(),
+            }))
+            .collect()
+    }
+
+    /// The main display as it is on each page after the first.
+    fn other_pages(&self) -> Vec<Screen> {
+        (1 ..= self.pages.len()).map(|page| self.page_screens(page).swap_remove(0)).collect()
+    }
+
+    /// Every display, including the main one on every page.
+    fn all_screens(&self) -> Vec<Screen> {
+        let mut screens = self.screens();
+        screens.extend(self.other_pages());
+        screens
+    }
+
+    /// Leave a field out of every status row, e.g. because there's nothing for it to show.
+    pub fn remove_status_field(&mut self, item: &StatusItem) {
+        let remove = |status: &mut Option<Vec<StatusItem>>| {
+            if let Some(status) = status {
+                status.retain(|i| i != item);
+            }
+        };
+        remove(&mut self.status);
+        for page in &mut self.pages {
+            remove(&mut page.status);
+        }
+        for extra in &mut self.extra_displays {
+            remove(&mut extra.status);
+        }
+    }
+
+    /// Whether there's a CPU temperature to show: the first of `temperatures` stands in for it.
+    pub fn has_cpu_temp(&self) -> bool {
+        self.cpu_temp != CpuTemp::Off || !self.temperatures.is_empty()
+    }
+
+    /// Whether any display shows the given panel, on any page.
+    pub fn shows(&self, panel: Panel) -> bool {
+        self.all_screens().iter().any(|screen| screen.panels.contains(&panel))
+    }
+
+    /// Which page the main display is on, `elapsed` after starting.
+    pub fn page_at(&self, elapsed: Duration) -> usize {
+        let turns = elapsed.as_millis() / self.page_period.as_millis().max(1);
+        (turns % (self.pages.len() as u128 + 1)) as usize
+    }
+
+    /// What to call a page when switching to it.
+    pub fn page_name(&self, page: usize) -> String {
+        page.checked_sub(1)
+            .and_then(|i| self.pages.get(i))
+            .and_then(|page| page.name.clone())
+            .unwrap_or_else(|| format!("page {}", page + 1))
+    }
+
+    /// Number of columns a panel takes, given how many CPU columns there are.
+    pub fn panel_width(&self, panel: Panel, cpu_columns: usize) -> usize {
+        match panel {
+            Panel::Cpu => cpu_columns,
+            Panel::Net => self.interfaces.len() * self.net_columns.per_interface(),
+            Panel::Mem => 1,
+            Panel::Disk => self.disks.len() * 2,
+            Panel::Fs => self.filesystems.len(),
+            Panel::Wifi => self.wireless.len(),
+            Panel::Conntrack => 1,
+            Panel::Ping => 1,
+            Panel::Temp => self.temperatures.len(),
+            Panel::Fan => self.fans.len(),
+            Panel::Ups => 1,
+            Panel::Sparkline => self.sparkline_columns,
+        }
+    }
+
+    /// Speed at the top of an interface's gauges, in Mbps, given its negotiated link speed if it
+    /// has one.
+    pub fn max_mbps(&self, name: &str, link_mbps: Option<u32>) -> f64 {
+        let max = match (self.interface_max.get(name), self.net_scale, link_mbps) {
+            (Some(&max), _, _) => max,
+            (None, link::Scale::Link, Some(link)) => link,
+            _ => self.net_max,
+        };
+        max as f64
+    }
+
+    /// How an interface's gauges are scaled.
+    pub fn interface_scale(&self, name: &str) -> scale::Scale {
+        self.interface_scale.get(name).copied().unwrap_or(scale::Scale::Log)
+    }
+
+    /// Whether anything shows a ping or alerts on one, so it needs doing.
+    pub fn pings(&self) -> bool {
+        self.alerts.ping.is_some() || self.all_screens().iter().any(|screen| {
+            screen.panels.contains(&Panel::Ping)
+                || screen.status.as_ref().is_some_and(|status| status.contains(&StatusItem::Ping))
+        })
+    }
+
+    /// How many network interfaces fit alongside the other panels, on every display and page that
+    /// shows them.
+    pub fn max_interfaces(&self, cpu_columns: usize) -> usize {
+        self.all_screens().iter()
+            .map(|screen| layout::max_interfaces(&screen.panels,
+                |p| self.panel_width(p, cpu_columns), screen.geometry.cols,
+                self.net_columns.per_interface()))
+            .min()
+            .unwrap()
+    }
+
+    pub fn layout(&self, screen: &Screen, cpu_columns: usize) -> Result<Layout> {
+        Layout::new(&screen.panels, |p| self.panel_width(p, cpu_columns), screen.geometry.cols)
+    }
+
+    /// Make the panels narrower, a step at a time in the order of `Compression::ALL`, until they
+    /// fit on every display and page, given the number of CPUs. Returns the steps taken, which may
+    /// not be enough. The memory column is only left out where it doesn't fit.
+    pub fn compress(&mut self, cpu_count: usize) -> Vec<Compression> {
+        let mut steps = vec![];
+        for step in Compression::ALL {
+            let cpu_columns = cores::count(cpu_count, self.cpu_mode);
+            let crowded = self.all_screens().into_iter()
+                .filter(|screen| self.layout(screen, cpu_columns).is_err())
+                .collect::<Vec<_>>();
+            let shown = |panel| crowded.iter().any(|screen| screen.panels.contains(&panel));
+            match step {
+                Compression::AggregateCpu if shown(Panel::Cpu) && cpu_columns > 1 => {
+                    self.cpu_mode = CpuMode::Aggregate;
+                }
+                Compression::CombinedNet
+                    if shown(Panel::Net) && self.net_columns == NetColumns::Split =>
+                {
+                    self.net_columns = NetColumns::Combined;
+                }
+                Compression::NoMem if shown(Panel::Mem) => {
+                    let before = self.clone();
+                    let fits = |panels: &[Panel], cols| Layout::new(panels,
+                        |p| before.panel_width(p, cpu_columns), cols).is_ok();
+                    let drop_mem = |panels: &mut Vec<Panel>, cols| {
+                        if !fits(panels, cols) && panels.len() > 1 {
+                            panels.retain(|&p| p != Panel::Mem);
+                        }
+                    };
+                    drop_mem(&mut self.panels, self.geometry.cols);
+                    for page in &mut self.pages {
+                        drop_mem(&mut page.panels, self.geometry.cols);
+                    }
+                    for extra in &mut self.extra_displays {
+                        drop_mem(&mut extra.panels, extra.geometry.cols);
+                    }
+                }
+                _ => continue,
+            }
+            steps.push(step);
+        }
+        steps
+    }
+
+    /// Whether the backlight should be on at the given local time of day (minutes after
+    /// midnight), if it's known.
+    pub fn backlight_at(&self, minute: Option<u32>) -> bool {
+        if let Some(forced) = self.backlight {
+            return forced;
+        }
+        match (self.backlight_off, self.backlight_on, minute) {
+            (Some(off), Some(on), Some(minute)) => Schedule { off, on }.is_on(minute),
+            _ => true,
+        }
+    }
+
+    /// Check for settings that can't work, so they're caught up front with a helpful message,
+    /// rather than when the display is being drawn.
+    pub fn validate(&self, cpu_columns: usize) -> Result<()> {
+        let screens = self.screens();
+        // errors about the extra displays say which one
+        let which = |i: usize| move |e: anyhow::Error| match i {
+            0 => e,
+            _ => e.context(format!("extra display {}", i)),
+        };
+        for (i, screen) in screens.iter().enumerate() {
+            screen.validate().map_err(which(i))?;
+            if screens[.. i].iter().any(|s| (s.bus, s.addr) == (screen.bus, screen.addr)) {
+                bail!("extra display {} has the same I2C bus and address as another one", i);
+            }
+        }
+        let max_interfaces = self.max_interfaces(cpu_columns);
+        if self.alerts.mem.is_some_and(|mem| mem > 100.) {
+            bail!("alerts.mem is a percentage, so it can't be more than 100");
+        }
+        if self.interval < MIN_INTERVAL {
+            bail!("interval must be at least {:?}, not {:?}", MIN_INTERVAL, self.interval);
+        }
+        if let Some(timeout) = self.watchdog.timeout {
+            if timeout < self.interval * MIN_WATCHDOG_INTERVALS {
+                bail!("watchdog.timeout ({:?}) must be at least {} times as long as the interval \
+                    ({:?})", timeout, MIN_WATCHDOG_INTERVALS, self.interval);
+            }
+        }
+        if self.history_window < self.interval {
+            bail!("history_window ({:?}) must be at least as long as the interval ({:?})",
+                self.history_window, self.interval);
+        }
+        if self.burst_trigger == Some(0) {
+            bail!("burst_trigger must be more than 0");
+        }
+        // only checked when it's on, so a short interval doesn't need them changed too
+        if self.burst_trigger.is_some() {
+            if self.burst_interval.is_zero() || self.burst_interval >= self.interval {
+                bail!("burst_interval ({:?}) must be more than 0 and shorter than the interval \
+                    ({:?})", self.burst_interval, self.interval);
+            }
+            if self.burst_duration < self.interval {
+                bail!("burst_duration ({:?}) must be at least as long as the interval ({:?})",
+                    self.burst_duration, self.interval);
+            }
+        }
+        if self.peak_decay == Some(0) {
+            bail!("peak_decay must be more than 0");
+        }
+        if !self.status_rotation.is_empty() && self.status_period < self.interval {
+            bail!("status_period ({:?}) must be at least as long as the interval ({:?})",
+                self.status_period, self.interval);
+        }
+        if let Some(redraw) = self.redraw_interval {
+            if redraw < self.interval {
+                bail!("redraw_interval ({:?}) must be at least as long as the interval ({:?})",
+                    redraw, self.interval);
+            }
+        }
+        if let Some(reinit) = self.reinit_interval {
+            if reinit < self.interval {
+                bail!("reinit_interval ({:?}) must be at least as long as the interval ({:?})",
+                    reinit, self.interval);
+            }
+        }
+        if self.interfaces.is_empty() {
+            if self.select_interfaces.is_some() {
+                bail!("no network interfaces found to show");
+            }
+            bail!("no network interfaces to show");
+        }
+        for (i, name) in self.interfaces.iter().enumerate() {
+            if self.interfaces[.. i].contains(name) {
+                bail!("network interface {:?} is listed more than once", name);
+            }
+        }
+        for (name, members) in &self.aggregates {
+            if members.is_empty() {
+                bail!("aggregate {:?} needs some members", name);
+            }
+            for (i, member) in members.iter().enumerate() {
+                if members[.. i].contains(member) {
+                    bail!("{:?} is listed more than once in aggregate {:?}", member, name);
+                }
+                if self.aggregates.contains_key(member) {
+                    bail!("aggregate {:?} can't include another aggregate, {:?}", name, member);
+                }
+            }
+        }
+        if self.net_max == 0 {
+            bail!("net_max must be more than 0");
+        }
+        if self.net_decades == 0 {
+            bail!("net_decades must be more than 0");
+        }
+        if let Some((name, _)) = self.interface_max.iter().find(|&(_, &max)| max == 0) {
+            bail!("interface_max for {:?} must be more than 0", name);
+        }
+        for (name, &scale) in &self.interface_scale {
+            if scale == scale::Scale::Linear && !self.interface_max.contains_key(name) {
+                bail!("{:?} has a linear scale, so it needs a speed for the top of it in \
+                    interface_max", name);
+            }
+        }
+        if self.shows(Panel::Disk) {
+            if self.disks.is_empty() {
+                bail!("the disk panel needs some devices listed in \"disks\"");
+            }
+            if self.disk_max == 0 {
+                bail!("disk_max must be more than 0");
+            }
+            if self.disk_decades == 0 {
+                bail!("disk_decades must be more than 0");
+            }
+        }
+        for (i, name) in self.disks.iter().enumerate() {
+            if self.disks[.. i].contains(name) {
+                bail!("disk {:?} is listed more than once", name);
+            }
+        }
+        if self.shows(Panel::Fs) && self.filesystems.is_empty() {
+            bail!("the fs panel needs some mount points listed in \"filesystems\"");
+        }
+        for (i, path) in self.filesystems.iter().enumerate() {
+            if self.filesystems[.. i].contains(path) {
+                bail!("filesystem {:?} is listed more than once", path);
+            }
+        }
+        if self.shows(Panel::Wifi) && self.wireless.is_empty() {
+            bail!("the wifi panel needs some interfaces listed in \"wireless\"");
+        }
+        for (i, name) in self.wireless.iter().enumerate() {
+            if self.wireless[.. i].contains(name) {
+                bail!("wireless interface {:?} is listed more than once", name);
+            }
+        }
+        if self.status_rotation.contains(&Segment::Address) && self.address_interface.is_none() {
+            bail!("the address segment needs an interface in \"address_interface\"");
+        }
+        if self.ping_interval < MIN_PING_INTERVAL {
+            bail!("ping_interval must be at least {:?}, not {:?}", MIN_PING_INTERVAL,
+                self.ping_interval);
+        }
+        if self.shows(Panel::Temp) && self.temperatures.is_empty() {
+            bail!("the temp panel needs some sensors listed in \"temperatures\"");
+        }
+        for (i, name) in self.temperatures.iter().enumerate() {
+            if self.temperatures[.. i].contains(name) {
+                bail!("temperature sensor {:?} is listed more than once", name);
+            }
+        }
+        if self.temp_min >= self.temp_max {
+            bail!("temp_min ({}) must be less than temp_max ({})", self.temp_min, self.temp_max);
+        }
+        if self.shows(Panel::Fan) && self.fans.is_empty() {
+            bail!("the fan panel needs some fans listed in \"fans\"");
+        }
+        if self.status_rotation.contains(&Segment::Fan) && self.fans.is_empty() {
+            bail!("the fan segment needs some fans listed in \"fans\"");
+        }
+        if self.alerts.fan_stopped.is_some() && self.fans.is_empty() {
+            bail!("the fan_stopped alert needs some fans listed in \"fans\"");
+        }
+        for (i, name) in self.fans.iter().enumerate() {
+            if self.fans[.. i].contains(name) {
+                bail!("fan {:?} is listed more than once", name);
+            }
+        }
+        if self.shows(Panel::Sparkline) {
+            if self.sparkline_columns == 0 {
+                bail!("sparkline_columns must be more than 0");
+            }
+            if self.sparkline_span < self.interval * self.sparkline_columns as u32 {
+                bail!("sparkline_span ({:?}) must be at least as long as the interval ({:?}) for \
+                    each of the {} sparkline_columns", self.sparkline_span, self.interval,
+                    self.sparkline_columns);
+            }
+        }
+        if self.ups.is_none() {
+            if self.shows(Panel::Ups) {
+                bail!("the ups panel needs a [ups] section");
+            }
+            if self.status_rotation.contains(&Segment::Ups) {
+                bail!("the ups segment needs a [ups] section");
+            }
+            if self.alerts.on_battery.is_some() {
+                bail!("the on_battery alert needs a [ups] section");
+            }
+        }
+        if self.fan_max == 0 {
+            bail!("fan_max must be more than 0");
+        }
+        if let Some(nice) = self.nice {
+            if !priority::NICE_RANGE.contains(&nice) {
+                bail!("nice must be from {} to {}, not {}", priority::NICE_RANGE.start(),
+                    priority::NICE_RANGE.end(), nice);
+            }
+        }
+        if self.interfaces.len() > max_interfaces {
+            bail!("{} network interfaces given, but only {} fit on the display",
+                self.interfaces.len(), max_interfaces);
+        }
+        let check_screen = |screen: &Screen| -> Result<()> {
+            self.layout(screen, cpu_columns)?;
+            if let Some(status) = &screen.status {
+                for item in status {
+                    if let StatusItem::Sensor(n) = item {
+                        if *n >= self.temperatures.len() {
+                            bail!("status field {{temp{}}} needs {} sensors listed in \
+                                \"temperatures\"", n + 1, n + 1);
+                        }
+                    }
+                }
+                let width = layout::status_width(status, self.units);
+                if width > screen.geometry.cols {
+                    bail!("status is {} characters long, but the display only has {} columns",
+                        width, screen.geometry.cols);
+                }
+            }
+            Ok(())
+        };
+        for (i, screen) in screens.iter().enumerate() {
+            check_screen(screen).map_err(which(i))?;
+        }
+        for (i, screen) in self.other_pages().iter().enumerate() {
+            check_screen(screen).with_context(|| format!("page {}", i + 2))?;
+        }
+        if !self.pages.is_empty() && self.page_period < self.interval {
+            bail!("page_period ({:?}) must be at least as long as the interval ({:?})",
+                self.page_period, self.interval);
+        }
+        for (i, page) in self.pages.iter().enumerate() {
+            if page.name.as_ref().is_some_and(|name| !name.is_ascii()) {
+                bail!("the name of page {} can only have ASCII characters", i + 2);
+            }
+        }
+        match (self.backlight_off, self.backlight_on) {
+            (Some(_), None) | (None, Some(_)) => {
+                bail!("backlight_off and backlight_on have to be given together");
+            }
+            (Some(off), Some(on)) if off == on => {
+                bail!("backlight_off and backlight_on can't be the same time");
+            }
+            _ => (),
+        }
+        if !self.status_row && !self.labels.is_empty() {
+            bail!("labels are shown on the status row, so they can't be used without one");
+        }
+        Ok(())
+    }
+}
+
+/// Environment variables that can override settings, and the config file keys they correspond to.
+/// Every setting has one except the ones made of tables (`interfaces` patterns, `[aggregate]`,
+/// `[display.characters]`, `[[pages]]`, and `[[extra_displays]]`), which don't fit in a variable.
+pub const ENV_VARS: &[(&str, &str)] = &[
+    ("H2LCD_IFACES", "interfaces"),
+    ("H2LCD_LABELS", "labels"),
+    ("H2LCD_INTERVAL", "interval"),
+    ("H2LCD_HISTORY_WINDOW", "history_window"),
+    ("H2LCD_BURST_TRIGGER", "burst_trigger"),
+    ("H2LCD_BURST_INTERVAL", "burst_interval"),
+    ("H2LCD_BURST_DURATION", "burst_duration"),
+    ("H2LCD_PEAK_DECAY", "peak_decay"),
+    ("H2LCD_SMOOTHING", "smoothing"),
+    ("H2LCD_PEAK_HOLD", "peak_hold"),
+    ("H2LCD_SPARKLINE_SPAN", "sparkline_span"),
+    ("H2LCD_SPARKLINE_COLUMNS", "sparkline_columns"),
+    ("H2LCD_NET_MAX", "net_max"),
+    ("H2LCD_NET_SCALE", "net_scale"),
+    ("H2LCD_NET_DECADES", "net_decades"),
+    ("H2LCD_NET_COLUMNS", "net_columns"),
+    ("H2LCD_LINK_CHANGE_MARK", "link_change_mark"),
+    ("H2LCD_INTERFACE_MAX", "interface_max"),
+    ("H2LCD_INTERFACE_SCALE", "interface_scale"),
+    ("H2LCD_DISKS", "disks"),
+    ("H2LCD_DISK_MAX", "disk_max"),
+    ("H2LCD_DISK_DECADES", "disk_decades"),
+    ("H2LCD_FILESYSTEMS", "filesystems"),
+    ("H2LCD_WIRELESS", "wireless"),
+    ("H2LCD_TEMPERATURES", "temperatures"),
+    ("H2LCD_CPU_TEMP", "cpu_temp"),
+    ("H2LCD_TEMP_MIN", "temp_min"),
+    ("H2LCD_TEMP_MAX", "temp_max"),
+    ("H2LCD_FANS", "fans"),
+    ("H2LCD_FAN_MAX", "fan_max"),
+    ("H2LCD_IGNORE_ERRORS", "ignore_errors"),
+    ("H2LCD_ACTIVITY_BLIP", "activity_blip"),
+    ("H2LCD_TOTALS_FILE", "totals_file"),
+    ("H2LCD_PING_TARGET", "ping_target"),
+    ("H2LCD_PING_INTERVAL", "ping_interval"),
+    ("H2LCD_ADDRESS_INTERFACE", "address_interface"),
+    ("H2LCD_ADDRESS_URL", "address_url"),
+    ("H2LCD_LEASES_FILE", "leases_file"),
+    ("H2LCD_TOP_KERNEL_THREADS", "top_kernel_threads"),
+    ("H2LCD_SESSION_SOURCE", "session_source"),
+    ("H2LCD_CLOCK_SYNC", "clock_sync"),
+    ("H2LCD_NICE", "nice"),
+    ("H2LCD_SCHED_POLICY", "sched_policy"),
+    ("H2LCD_CPU_AFFINITY", "cpu_affinity"),
+    ("H2LCD_WATCHDOG_TIMEOUT", "watchdog.timeout"),
+    ("H2LCD_WATCHDOG_ACTION", "watchdog.action"),
+    ("H2LCD_UPS_PROTOCOL", "ups.protocol"),
+    ("H2LCD_UPS_HOST", "ups.host"),
+    ("H2LCD_UPS_PORT", "ups.port"),
+    ("H2LCD_UPS_NAME", "ups.name"),
+    ("H2LCD_REDRAW_INTERVAL", "redraw_interval"),
+    ("H2LCD_REINIT_INTERVAL", "reinit_interval"),
+    ("H2LCD_MEM_INTERVAL", "mem_interval"),
+    ("H2LCD_TEMP_INTERVAL", "temp_interval"),
+    ("H2LCD_FAN_INTERVAL", "fan_interval"),
+    ("H2LCD_FS_INTERVAL", "fs_interval"),
+    ("H2LCD_ACCESSIBLE", "accessible"),
+    ("H2LCD_PANELS", "panels"),
+    ("H2LCD_CPU_MODE", "cpu_mode"),
+    ("H2LCD_IOWAIT", "iowait"),
+    ("H2LCD_STATUS", "status"),
+    ("H2LCD_UNITS", "units"),
+    ("H2LCD_STATUS_ROTATION", "status_rotation"),
+    ("H2LCD_STATUS_PERIOD", "status_period"),
+    ("H2LCD_RATES_INTERFACES", "rates_interfaces"),
+    ("H2LCD_RATES_FLOOR", "rates_floor"),
+    ("H2LCD_BIG_DIGITS", "big_digits"),
+    ("H2LCD_PAGE_PERIOD", "page_period"),
+    ("H2LCD_BUS", "i2c.bus"),
+    ("H2LCD_FALLBACK_BUS", "i2c.fallback_bus"),
+    ("H2LCD_ADDR", "i2c.address"),
+    ("H2LCD_DRIVER", "display.driver"),
+    ("H2LCD_COLS", "display.cols"),
+    ("H2LCD_ROWS", "display.rows"),
+    ("H2LCD_GLYPHS", "display.glyphs"),
+    ("H2LCD_ROM", "display.rom"),
+    ("H2LCD_STATUS_ROW", "display.status_row"),
+    ("H2LCD_SHUTDOWN_MESSAGE", "display.shutdown_message"),
+    ("H2LCD_SHUTDOWN_BACKLIGHT", "display.shutdown_backlight"),
+    ("H2LCD_SHUTDOWN_FREEZE", "display.shutdown_freeze"),
+    ("H2LCD_ALERT_TEMP", "alerts.temp"),
+    ("H2LCD_ALERT_MEM", "alerts.mem"),
+    ("H2LCD_ALERT_MBPS", "alerts.mbps"),
+    ("H2LCD_ALERT_SWAPPING", "alerts.swapping"),
+    ("H2LCD_ALERT_PING", "alerts.ping"),
+    ("H2LCD_ALERT_FAN_STOPPED", "alerts.fan_stopped"),
+    ("H2LCD_ALERT_SESSIONS", "alerts.sessions"),
+    ("H2LCD_ALERT_UNSYNCED", "alerts.unsynced"),
+    ("H2LCD_ALERT_ON_BATTERY", "alerts.on_battery"),
+    ("H2LCD_ALERT_LINK_DOWN", "alerts.link_down"),
+    ("H2LCD_ALERT_SUSTAIN", "alerts.sustain"),
+    ("H2LCD_BACKLIGHT", "backlight"),
+    ("H2LCD_BACKLIGHT_OFF", "backlight_off"),
+    ("H2LCD_BACKLIGHT_ON", "backlight_on"),
+];
+
+/// Interpret an environment variable as the config file value it stands in for. Lists are
+/// comma-separated, and labels are `name=label` pairs. Anything that doesn't fit is left as a
+/// string, for `Config::merge` to complain about.
+fn env_value(path: &str, value: &str) -> Value {
+    let list = || value.split(',').map(str::trim).filter(|s| !s.is_empty());
+    match path {
+        "interfaces" | "panels" | "disks" | "filesystems" | "wireless" | "temperatures" | "fans"
+            | "ignore_errors" | "activity_blip" | "status_rotation" | "rates_interfaces"
+            if value != "auto" =>
+        {
+            Value::Array(list().map(|s| Value::String(s.to_owned())).collect())
+        }
+        "labels" | "interface_scale" => Value::Table(list()
+            .map(|pair| match pair.split_once('=') {
+                Some((name, label)) => (name.to_owned(), Value::String(label.to_owned())),
+                None => (pair.to_owned(), Value::Boolean(true)),
+            })
+            .collect()),
+        "interface_max" => Value::Table(list()
+            .map(|pair| match pair.split_once('=') {
+                Some((name, max)) => (name.to_owned(), parse_int(max).map(Value::Integer)
+                    .unwrap_or_else(|_| Value::String(max.to_owned()))),
+                None => (pair.to_owned(), Value::Boolean(true)),
+            })
+            .collect()),
+        "i2c.bus" | "i2c.fallback_bus" | "i2c.address" | "display.cols" | "display.rows"
+            | "alerts.temp" | "alerts.mem" | "alerts.mbps" | "alerts.swapping" | "alerts.ping"
+            | "alerts.fan_stopped" | "alerts.sessions" | "alerts.unsynced" | "alerts.on_battery"
+            | "alerts.link_down" | "ups.port" | "net_max" | "net_decades" | "disk_max"
+            | "disk_decades" | "temp_min" | "temp_max" | "fan_max" | "sparkline_columns"
+            | "rates_floor" | "burst_trigger" | "peak_decay" =>
+        {
+            match value {
+                "false" => Value::Boolean(false),
+                _ => parse_int(value).map(Value::Integer)
+                    .unwrap_or_else(|_| Value::String(value.to_owned())),
+            }
+        }
+        // the only setting that can be negative
+        "nice" => match value {
+            "false" => Value::Boolean(false),
+            _ => value.parse().map(Value::Integer)
+                .unwrap_or_else(|_| Value::String(value.to_owned())),
+        },
+        "redraw_interval" | "reinit_interval" | "display.shutdown_message" | "totals_file"
+            | "cpu_affinity" | "watchdog.timeout"
+            if value == "false" =>
+        {
+            Value::Boolean(false)
+        }
+        "display.status_row" | "display.shutdown_backlight" | "display.shutdown_freeze"
+            | "top_kernel_threads" | "clock_sync" | "accessible" =>
+        {
+            match value {
+                "true" => Value::Boolean(true),
+                "false" => Value::Boolean(false),
+                _ => Value::String(value.to_owned()),
+            }
+        }
+        _ => Value::String(value.to_owned()),
+    }
+}
+
+/// Parse an integer, in decimal or in hex with a `0x` prefix.
+pub fn parse_int<T: TryFrom<u64>>(s: &str) -> Result<T> {
+    let n = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16)?,
+        None => s.parse()?,
+    };
+    T::try_from(n).map_err(|_| anyhow::anyhow!("{} is out of range", s))
+}
+
+/// Parse a duration like `250ms`, `2s`, `1.5s`, or `5m`.
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    if let Some(ms) = s.strip_suffix("ms") {
+        return Ok(Duration::from_millis(ms.parse()?));
+    }
+    let (n, scale) = if let Some(secs) = s.strip_suffix('s') {
+        (secs, 1.)
+    } else if let Some(mins) = s.strip_suffix('m') {
+        (mins, 60.)
+    } else {
+        bail!("missing unit (ms, s, or m)");
+    };
+    let secs = n.parse::<f64>()? * scale;
+    if !secs.is_finite() || secs < 0. || secs > u32::MAX as f64 {
+        bail!("{} is out of range", s);
+    }
+    Ok(Duration::from_secs_f64(secs))
+}
+
+/// A table in the config file, for reading typed values out of, with errors that say which key
+/// was wrong.
+struct Section<'a> {
+    /// None for the top level.
+    path: Option<String>,
+    table: &'a Table,
+}
+
+impl<'a> Section<'a> {
+    fn key_path(&self, key: &str) -> String {
+        match &self.path {
+            Some(path) => format!("{}.{}", path, key),
+            None => key.to_owned(),
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<&'a Value> {
+        self.table.get(key)
+    }
+
+    fn wrong_type<T>(&self, key: &str, expected: &str, got: &Value) -> Result<T> {
+        bail!("{:?} should be {}, not {}", self.key_path(key), expected, got.type_name())
+    }
+
+    /// Error on any keys not in `valid`, suggesting the closest valid one if it's a likely typo.
+    fn check_keys(&self, valid: &[&str]) -> Result<()> {
+        for key in self.table.keys() {
+            if !valid.contains(&key.as_str()) {
+                let location = match &self.path {
+                    Some(path) => format!("in [{}]", path),
+                    None => "at top level".to_owned(),
+                };
+                match suggest(key, valid) {
+                    Some(suggestion) => bail!("unknown key {:?} {}; did you mean {:?}?",
+                        key, location, suggestion),
+                    None => bail!("unknown key {:?} {}", key, location),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn section(&self, key: &str) -> Result<Option<Section<'a>>> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Value::Table(table)) => Ok(Some(Section { path: Some(self.key_path(key)), table })),
+            Some(other) => self.wrong_type(key, "a table", other),
+        }
+    }
+
+    fn integer<T: TryFrom<i64>>(&self, key: &str) -> Result<Option<T>> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Value::Integer(n)) => T::try_from(*n).map(Some)
+                .map_err(|_| anyhow::anyhow!("{:?} is out of range: {}", self.key_path(key), n)),
+            Some(other) => self.wrong_type(key, "an integer", other),
+        }
+    }
+
+    fn boolean(&self, key: &str) -> Result<Option<bool>> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Value::Boolean(b)) => Ok(Some(*b)),
+            Some(other) => self.wrong_type(key, "true or false", other),
+        }
+    }
+
+    /// A time of day like `"23:00"`, in minutes after midnight.
+    fn time(&self, key: &str) -> Result<Option<u32>> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Value::String(s)) => backlight::parse_time(s).map(Some)
+                .with_context(|| format!("invalid {:?}", self.key_path(key))),
+            Some(other) => self.wrong_type(key, "a time like \"23:00\"", other),
+        }
+    }
+
+    fn duration(&self, key: &str) -> Result<Option<Duration>> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Value::String(s)) => parse_duration(s).map(Some)
+                .with_context(|| format!("invalid {:?}", self.key_path(key))),
+            Some(other) => self.wrong_type(key, "a duration like \"500ms\"", other),
+        }
+    }
+
+    fn driver(&self, key: &str) -> Result<Option<Driver>> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Value::String(s)) => match s.as_str() {
+                "hd44780" => Ok(Some(Driver::Hd44780)),
+                "ssd1306" => Ok(Some(Driver::Ssd1306)),
+                _ => bail!("{:?} should be \"hd44780\" or \"ssd1306\", not {:?}",
+                    self.key_path(key), s),
+            },
+            Some(other) => self.wrong_type(key, "a string", other),
+        }
+    }
+
+    fn glyphs(&self, key: &str) -> Result<Option<GlyphStyle>> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Value::String(s)) => match s.as_str() {
+                "solid" => Ok(Some(GlyphStyle::Solid)),
+                "thin" => Ok(Some(GlyphStyle::Thin)),
+                "ticked" => Ok(Some(GlyphStyle::Ticked)),
+                _ => bail!("{:?} should be \"solid\", \"thin\", or \"ticked\", not {:?}",
+                    self.key_path(key), s),
+            },
+            Some(other) => self.wrong_type(key, "a string", other),
+        }
+    }
+
+    /// The `rom` key, and overrides for particular characters in a `characters` table.
+    fn charset(&self, charset: &mut Charset) -> Result<()> {
+        match self.get("rom") {
+            Some(Value::String(s)) => {
+                charset.rom = Rom::parse(s)
+                    .with_context(|| format!("invalid {:?}", self.key_path("rom")))?;
+            }
+            Some(other) => return self.wrong_type("rom", "a string", other),
+            None => (),
+        }
+        if let Some(characters) = self.section("characters")? {
+            for name in characters.table.keys() {
+                let c = charset::parse_character(name)
+                    .with_context(|| format!("invalid {:?}", characters.key_path(name)))?;
+                let byte = characters.integer(name)?.unwrap();
+                charset.overrides.insert(c, byte);
+            }
+        }
+        Ok(())
+    }
+
+    fn status(&self, key: &str) -> Result<Option<Vec<StatusItem>>> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Value::String(s)) => layout::parse_status(s).map(Some)
+                .with_context(|| format!("invalid {:?}", self.key_path(key))),
+            Some(other) => self.wrong_type(key, "a string", other),
+        }
+    }
+
+    /// One of the `[[pages]]`.
+    fn page(&self) -> Result<Page> {
+        self.check_keys(&["name", "panels", "status"])?;
+        let name = match self.get("name") {
+            Some(Value::String(s)) => Some(s.clone()),
+            Some(other) => return self.wrong_type("name", "a string", other),
+            None => None,
+        };
+        let panels = match self.string_list("panels")? {
+            Some(panels) => layout::parse_panels(&panels)
+                .with_context(|| format!("invalid {:?}", self.key_path("panels")))?,
+            None => bail!("{:?} is missing", self.key_path("panels")),
+        };
+        Ok(Page { name, panels, status: self.status("status")? })
+    }
+
+    /// One of the `[[extra_displays]]`.
+    fn extra_display(&self) -> Result<ExtraDisplay> {
+        self.check_keys(&[
+            "bus", "address", "driver", "cols", "rows", "glyphs", "rom", "characters",
+            "status_row", "panels", "status",
+        ])?;
+        let addr = match self.integer("address")? {
+            Some(addr) => addr,
+            None => bail!("{:?} is missing", self.key_path("address")),
+        };
+        let mut charset = Charset::default();
+        self.charset(&mut charset)?;
+        let panels = match self.string_list("panels")? {
+            Some(panels) => layout::parse_panels(&panels)
+                .with_context(|| format!("invalid {:?}", self.key_path("panels")))?,
+            None => bail!("{:?} is missing", self.key_path("panels")),
+        };
+        Ok(ExtraDisplay {
+            bus: self.integer("bus")?,
+            addr,
+            driver: self.driver("driver")?.unwrap_or(Driver::Hd44780),
+            geometry: Geometry {
+                cols: self.integer("cols")?.unwrap_or(Geometry::DEFAULT.cols),
+                rows: self.integer("rows")?.unwrap_or(Geometry::DEFAULT.rows),
+            },
+            glyphs: self.glyphs("glyphs")?.unwrap_or(GlyphStyle::Solid),
+            charset,
+            status_row: self.boolean("status_row")?.unwrap_or(true),
+            panels,
+            status: self.status("status")?,
+        })
+    }
+
+    fn string_list(&self, key: &str) -> Result<Option<Vec<String>>> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Value::Array(items)) => items.iter()
+                .map(|item| match item {
+                    Value::String(s) => Ok(s.clone()),
+                    other => self.wrong_type(key, "a list of strings", other),
+                })
+                .collect::<Result<_>>()
+                .map(Some),
+            Some(other) => self.wrong_type(key, "a list of strings", other),
+        }
+    }
+}
+
+/// Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0 ..= b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let sub = prev[j] + if ca == *cb { 0 } else { 1 };
+            cur.push(sub.min(prev[j + 1] + 1).min(cur[j] + 1));
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+/// The closest of `candidates` to `word`, if it's close enough to plausibly be a typo.
+pub fn suggest<'a>(word: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    candidates.iter()
+        .map(|&c| (edit_distance(word, c), c))
+        .filter(|&(dist, c)| dist <= (c.len() / 3).max(1))
+        .min_by_key(|&(dist, _)| dist)
+        .map(|(_, c)| c)
+}
+
+#[cfg(test)]
+#[test]
+fn test_example_config() {
+    let config = Config::parse(include_str!("../examples/h2-net-lcd.toml")).unwrap();
+    assert_eq!(Config {
+        interfaces: vec!["enp1s0".to_owned(), "lan0".to_owned(), "wg0".to_owned()],
+        select_interfaces: None,
+        aggregates: BTreeMap::new(),
+        net_max: 1000,
+        net_scale: link::Scale::Global,
+        interface_max: BTreeMap::new(),
+        interface_scale: BTreeMap::new(),
+        net_decades: 3,
+        net_columns: NetColumns::Split,
+        link_change_mark: Duration::from_secs(300),
+        disks: vec![],
+        disk_max: 1000,
+        disk_decades: 3,
+        filesystems: vec![],
+        wireless: vec![],
+        temperatures: vec![],
+        cpu_temp: CpuTemp::Auto,
+        temp_min: 30,
+        temp_max: 90,
+        fans: vec![],
+        fan_max: 3000,
+        ignore_errors: vec![],
+        activity_blip: vec![],
+        labels: vec![("enp1s0".to_owned(), 'W'), ("lan0".to_owned(), 'L')].into_iter().collect(),
+        totals_file: Some(PathBuf::from(totals::DEFAULT_PATH)),
+        ping_target: ping::Target::Gateway,
+        ping_interval: Duration::from_secs(5),
+        address_interface: None,
+        address_url: None,
+        leases_file: PathBuf::from(leases::DEFAULT_PATH),
+        top_kernel_threads: true,
+        session_source: sessions::Source::Utmp,
+        clock_sync: false,
+        ups: None,
+        nice: None,
+        sched_policy: None,
+        cpu_affinity: None,
+        watchdog: watchdog::Settings::default(),
+        bus: 2,
+        fallback_bus: Some(1),
+        addr: 0x27,
+        scan_addresses: false,
+        driver: Driver::Hd44780,
+        geometry: Geometry::DEFAULT,
+        glyphs: GlyphStyle::Solid,
+        charset: Charset::default(),
+        status_row: true,
+        shutdown_message: Some("stopped".to_owned()),
+        shutdown_backlight: false,
+        shutdown_freeze: false,
+        backlight: None,
+        backlight_off: Some(23 * 60),
+        backlight_on: Some(7 * 60),
+        interval: Duration::from_secs(1),
+        history_window: Duration::from_secs(300),
+        burst_trigger: None,
+        burst_interval: Duration::from_millis(200),
+        burst_duration: Duration::from_secs(10),
+        peak_decay: None,
+        smoothing: Duration::ZERO,
+        peak_hold: Duration::ZERO,
+        sparkline_span: Duration::from_secs(600),
+        sparkline_columns: 6,
+        redraw_interval: Some(Duration::from_secs(30)),
+        reinit_interval: None,
+        mem_interval: Duration::from_secs(5),
+        temp_interval: Duration::from_secs(5),
+        fan_interval: Duration::from_secs(5),
+        fs_interval: Duration::from_secs(30),
+        accessible: false,
+        panels: vec![Panel::Net, Panel::Cpu, Panel::Mem],
+        cpu_mode: CpuMode::Auto,
+        iowait: Iowait::Busy,
+        status: Some(vec![
+            StatusItem::Speeds,
+            StatusItem::Text(" ".to_owned()),
+            StatusItem::Temp,
+            StatusItem::Text("   mem".to_owned()),
+        ]),
+        units: Units::Mbps,
+        status_rotation: vec![],
+        status_period: Duration::from_secs(5),
+        rates_interfaces: vec![],
+        rates_floor: 0,
+        big_digits: Some(BigMetric::Clock),
+        pages: vec![],
+        page_period: Duration::from_secs(15),
+        alerts: Thresholds::default(),
+        extra_displays: vec![],
+    }, config);
+}
+
+#[cfg(test)]
+#[test]
+fn test_config_defaults() {
+    assert_eq!(Config::default(), Config::parse("").unwrap());
+    assert_eq!(Config::default(), Config::parse("[i2c]").unwrap());
+
+    let config = Config::parse("[i2c]\naddress = 0x3f\nfallback_bus = false").unwrap();
+    assert_eq!(0x3f, config.addr);
+    assert!(!config.scan_addresses);
+    assert_eq!(None, config.fallback_bus);
+    let config = Config::parse("[i2c]\naddress = \"auto\"").unwrap();
+    assert_eq!(0x27, config.addr);
+    assert!(config.scan_addresses);
+    assert_eq!(Config::default().interfaces, config.interfaces);
+
+    let config = Config::parse("[display]\ncols = 16\nrows = 2").unwrap();
+    assert_eq!(Geometry { cols: 16, rows: 2 }, config.geometry);
+    assert_eq!(1, config.screens()[0].gauge_rows());
+    let config = Config::parse("[display]\nrows = 2\nstatus_row = false").unwrap();
+    assert_eq!(Geometry { cols: 20, rows: 2 }, config.geometry);
+    assert_eq!(2, config.screens()[0].gauge_rows());
+    let config = Config::parse("[display]\ndriver = \"ssd1306\"\nglyphs = \"ticked\"").unwrap();
+    assert_eq!(Driver::Ssd1306, config.driver);
+    assert_eq!(GlyphStyle::Ticked, config.glyphs);
+
+    let schedule = "backlight_off = \"1:00\"\nbacklight_on = \"6:30\"";
+    let config = Config::parse(schedule).unwrap();
+    assert!(config.backlight_at(Some(0)));
+    assert!(!config.backlight_at(Some(60)));
+    assert!(config.backlight_at(Some(390)));
+    // if the time can't be found out, leave it on
+    assert!(config.backlight_at(None));
+    let config = Config::parse(&format!("backlight = \"on\"\n{}", schedule)).unwrap();
+    assert!(config.backlight_at(Some(60)));
+    assert!(!Config::parse("backlight = \"off\"").unwrap().backlight_at(Some(0)));
+
+    let config = Config::parse("[[extra_displays]]\naddress = 0x3f\nrows = 2\ncols = 16\n\
+        glyphs = \"thin\"\npanels = [\"cpu\", \"mem\"]\nstatus = \"{temp}\"").unwrap();
+    assert_eq!(vec![ExtraDisplay {
+        bus: None,
+        addr: 0x3f,
+        driver: Driver::Hd44780,
+        geometry: Geometry { cols: 16, rows: 2 },
+        glyphs: GlyphStyle::Thin,
+        charset: Charset::default(),
+        status_row: true,
+        panels: vec![Panel::Cpu, Panel::Mem],
+        status: Some(vec![StatusItem::Temp]),
+    }], config.extra_displays);
+    let screens = config.screens();
+    assert_eq!(2, screens.len());
+    assert_eq!((2, Some(1), 0x3f), (screens[1].bus, screens[1].fallback_bus, screens[1].addr));
+    assert_eq!(1, screens[1].gauge_rows());
+
+    let config = Config::parse("page_period = \"5s\"\n[[pages]]\nname = \"disks\"\n\
+        panels = [\"disk\", \"fs\"]\n[[pages]]\npanels = [\"sparkline\"]\nstatus = \"{temp}\"")
+        .unwrap();
+    assert_eq!(vec![
+        Page { name: Some("disks".to_owned()), panels: vec![Panel::Disk, Panel::Fs], status: None },
+        Page { name: None, panels: vec![Panel::Sparkline], status: Some(vec![StatusItem::Temp]) },
+    ], config.pages);
+    assert_eq!(Duration::from_secs(5), config.page_period);
+    // the first page is the top-level panels, then each in turn, and round again
+    let pages = [0, 4, 5, 10, 15].map(|s| config.page_at(Duration::from_secs(s)));
+    assert_eq!([0, 0, 1, 2, 0], pages);
+    assert_eq!(["page 1", "disks", "page 3"].map(str::to_owned),
+        [0, 1, 2].map(|page| config.page_name(page)));
+    assert_eq!(vec![Panel::Disk, Panel::Fs], config.page_screens(1)[0].panels);
+    assert_eq!(None, config.page_screens(1)[0].status);
+    assert_eq!(Some(vec![StatusItem::Temp]), config.page_screens(2)[0].status);
+    assert!(config.shows(Panel::Sparkline));
+    assert!(!config.shows(Panel::Ping));
+    assert_eq!(0, Config::default().page_at(Duration::from_secs(1000)));
+
+    assert_eq!(None, Config::parse("redraw_interval = false").unwrap().redraw_interval);
+    let config = Config::parse("[display]\nrom = \"a02\"\n[display.characters]\n\
+        degree = 0xd2\n\"~\" = 0x2d").unwrap();
+    assert_eq!(Charset {
+        rom: Rom::A02,
+        overrides: vec![(charset::DEGREE, 0xd2), (b'~', 0x2d)].into_iter().collect(),
+    }, config.charset);
+    let config = Config::parse("[display]\nshutdown_message = false").unwrap();
+    assert_eq!(None, config.shutdown_message);
+    let config = Config::parse("[display]\nshutdown_message = \"bye\"\nshutdown_backlight = true")
+        .unwrap();
+    assert_eq!(Some("bye"), config.shutdown_message.as_deref());
+    assert!(config.shutdown_backlight);
+    assert!(Config::parse("[display]\nshutdown_freeze = true").unwrap().shutdown_freeze);
+    let config = Config::parse("[watchdog]\ntimeout = \"2m\"\naction = \"reset\"").unwrap();
+    assert_eq!(watchdog::Settings {
+        timeout: Some(Duration::from_secs(120)),
+        action: watchdog::Action::Reset,
+    }, config.watchdog);
+    assert_eq!(None, Config::parse("[watchdog]\ntimeout = false").unwrap().watchdog.timeout);
+    let config = Config::parse("burst_interval = \"100ms\"\nburst_duration = \"30s\"").unwrap();
+    assert_eq!((Duration::from_millis(100), Duration::from_secs(30)),
+        (config.burst_interval, config.burst_duration));
+    let config = Config::parse("mem_interval = \"0s\"\nfan_interval = \"1m\"").unwrap();
+    assert_eq!((Duration::ZERO, Duration::from_secs(5), Duration::from_secs(60)),
+        (config.mem_interval, config.temp_interval, config.fan_interval));
+    assert_eq!(Some(BigMetric::Temp), Config::parse("big_digits = \"temp\"").unwrap().big_digits);
+    assert_eq!(Iowait::Distinct, Config::parse("iowait = \"distinct\"").unwrap().iowait);
+    assert_eq!(Units::MBps, Config::parse("units = \"MBps\"").unwrap().units);
+    assert_eq!(CpuMode::Top(4), Config::parse("cpu_mode = \"top-4\"").unwrap().cpu_mode);
+    let config = Config::parse("disks = [\"nvme0n1\"]\ndisk_max = 3000\n\
+        panels = [\"cpu\", \"net\", \"disk\"]").unwrap();
+    assert_eq!((vec!["nvme0n1".to_owned()], 3000), (config.disks.clone(), config.disk_max));
+    assert_eq!(2, config.panel_width(Panel::Disk, 4));
+    assert_eq!(Duration::from_secs(2), Config::parse("smoothing = \"2s\"").unwrap().smoothing);
+    assert_eq!(Duration::from_secs(5), Config::parse("peak_hold = \"5s\"").unwrap().peak_hold);
+    let config = Config::parse("sparkline_span = \"30m\"\nsparkline_columns = 10").unwrap();
+    assert_eq!((Duration::from_secs(1800), 10), (config.sparkline_span, config.sparkline_columns));
+    assert_eq!(10, config.panel_width(Panel::Sparkline, 4));
+    let config = Config::parse("net_max = 100\nnet_scale = \"link\"\n\
+        [interface_max]\nwg0 = 50").unwrap();
+    assert_eq!((100, link::Scale::Link), (config.net_max, config.net_scale));
+    // an override wins, then the link speed, then net_max
+    assert_eq!(50., config.max_mbps("wg0", Some(1000)));
+    assert_eq!(2500., config.max_mbps("lan0", Some(2500)));
+    assert_eq!(100., config.max_mbps("lan0", None));
+    assert_eq!(1000., Config::default().max_mbps("lan0", Some(2500)));
+    let config = Config::parse("[interface_max]\nwan = 300\n\
+        [interface_scale]\nwan = \"linear\"\nlan0 = \"log\"").unwrap();
+    assert_eq!(scale::Scale::Linear, config.interface_scale("wan"));
+    assert_eq!(scale::Scale::Log, config.interface_scale("lan0"));
+    assert_eq!(scale::Scale::Log, config.interface_scale("lan1"));
+    assert_eq!("invalid \"interface_scale.wan\": should be \"log\" or \"linear\", not \"flat\"",
+        format!("{:#}", Config::parse("[interface_scale]\nwan = \"flat\"").unwrap_err()));
+    let config = Config::parse("net_decades = 2\ndisk_decades = 4").unwrap();
+    assert_eq!((2, 4), (config.net_decades, config.disk_decades));
+    assert_eq!(NetColumns::Combined,
+        Config::parse("net_columns = \"combined\"").unwrap().net_columns);
+    assert_eq!(Duration::from_secs(30),
+        Config::parse("link_change_mark = \"30s\"").unwrap().link_change_mark);
+    let config = Config::parse("status_rotation = [\"status\", \"hostname\"]\n\
+        status_period = \"3s\"").unwrap();
+    assert_eq!((vec![Segment::Status, Segment::Hostname], Duration::from_secs(3)),
+        (config.status_rotation.clone(), config.status_period));
+    let config = Config::parse("filesystems = [\"/\", \"/var\"]").unwrap();
+    assert_eq!(vec!["/".to_owned(), "/var".to_owned()], config.filesystems);
+    assert_eq!(2, config.panel_width(Panel::Fs, 4));
+    let config = Config::parse("wireless = [\"wlan0\"]\npanels = [\"net\", \"wifi\"]").unwrap();
+    assert_eq!(vec!["wlan0".to_owned()], config.wireless);
+    assert_eq!(1, config.panel_width(Panel::Wifi, 4));
+    let config = Config::parse("temperatures = [\"Tctl\", \"nvme\"]\ntemp_min = 20\n\
+        temp_max = 100\npanels = [\"net\", \"temp\"]\nstatus = \"{temp1} {temp2}\"").unwrap();
+    assert_eq!(vec!["Tctl".to_owned(), "nvme".to_owned()], config.temperatures);
+    assert_eq!((20, 100), (config.temp_min, config.temp_max));
+    assert_eq!(2, config.panel_width(Panel::Temp, 4));
+    assert!(config.validate(4).is_ok());
+    assert!(config.has_cpu_temp());
+    let mut config = Config::parse("cpu_temp = \"off\"\nstatus = \"{temp} {busy}\"").unwrap();
+    assert_eq!(CpuTemp::Off, config.cpu_temp);
+    assert!(!config.has_cpu_temp());
+    // so {temp} is left out, and what follows it moves up
+    config.remove_status_field(&StatusItem::Temp);
+    assert_eq!(Some(layout::parse_status(" {busy}").unwrap()), config.status);
+    // unless the first of the temperatures stands in for it
+    assert!(Config { temperatures: vec!["Tctl".to_owned()], ..config }.has_cpu_temp());
+    let config = Config::parse("fans = [\"CPU_FAN\", \"nct6775/CHA_FAN\"]\nfan_max = 2500\n\
+        panels = [\"net\", \"fan\"]\nstatus_rotation = [\"status\", \"fan\"]").unwrap();
+    assert_eq!(vec!["CPU_FAN".to_owned(), "nct6775/CHA_FAN".to_owned()], config.fans);
+    assert_eq!(2500, config.fan_max);
+    assert_eq!(2, config.panel_width(Panel::Fan, 4));
+    assert!(config.validate(4).is_ok());
+    let mut config = Config::parse("panels = [\"conntrack\", \"net\"]\n\
+        [[extra_displays]]\naddress = 0x3f\npanels = [\"cpu\", \"conntrack\"]").unwrap();
+    config.remove_panel(Panel::Conntrack);
+    assert_eq!(vec![Panel::Net], config.panels);
+    assert_eq!(vec![Panel::Cpu], config.extra_displays[0].panels);
+    assert!(!config.pings());
+    let config = Config::parse("ping_target = \"1.1.1.1\"\nping_interval = \"10s\"\n\
+        status = \"gw {ping}\"").unwrap();
+    assert_eq!(ping::Target::Addr("1.1.1.1".parse().unwrap()), config.ping_target);
+    assert_eq!(Duration::from_secs(10), config.ping_interval);
+    assert!(config.pings());
+    assert!(Config::parse("panels = [\"net\", \"ping\"]").unwrap().pings());
+    let config = Config::parse("status_rotation = [\"status\", \"address\"]\n\
+        address_interface = \"wan0\"\naddress_url = \"http://api.ipify.org/\"").unwrap();
+    assert_eq!(Some("wan0".to_owned()), config.address_interface);
+    assert_eq!(Some(address::Url::parse("http://api.ipify.org/").unwrap()), config.address_url);
+    let config = Config::parse("status_rotation = [\"status\", \"leases\"]\n\
+        leases_file = \"/tmp/dnsmasq.leases\"").unwrap();
+    assert_eq!(vec![Segment::Status, Segment::Leases], config.status_rotation);
+    assert_eq!(Path::new("/tmp/dnsmasq.leases"), config.leases_file);
+    let config = Config::parse("status_rotation = [\"top\"]\ntop_kernel_threads = false").unwrap();
+    assert_eq!(vec![Segment::Top], config.status_rotation);
+    assert!(!config.top_kernel_threads);
+    let config = Config::parse("status_rotation = [\"sessions\"]\nsession_source = \"sshd\"")
+        .unwrap();
+    assert_eq!(sessions::Source::Sshd, config.session_source);
+    assert!(Config::parse("clock_sync = true").unwrap().clock_sync);
+    let config = Config::parse("panels = [\"net\", \"ups\"]\n[ups]\nprotocol = \"nut\"\n\
+        host = \"nas.lan\"\nname = \"eaton\"").unwrap();
+    assert_eq!(Some(ups::Server {
+        protocol: ups::Protocol::Nut,
+        host: "nas.lan".to_owned(),
+        port: 3493,
+        name: "eaton".to_owned(),
+    }), config.ups);
+    let config = Config::parse("[ups]\nprotocol = \"apcupsd\"\nport = 3552").unwrap();
+    assert_eq!(Some(3552), config.ups.map(|ups| ups.port));
+    let config = Config::parse("ignore_errors = [\"usb0\"]\nstatus_rotation = [\"errors\"]")
+        .unwrap();
+    assert_eq!(vec!["usb0".to_owned()], config.ignore_errors);
+    assert_eq!(vec![Segment::Errors], config.status_rotation);
+    let config = Config::parse("activity_blip = [\"iot0\"]").unwrap();
+    assert_eq!(vec!["iot0".to_owned()], config.activity_blip);
+    let config = Config::parse("status_rotation = [\"rates\"]\nrates_interfaces = [\"wan\"]\n\
+        rates_floor = 5").unwrap();
+    assert_eq!(vec![Segment::Rates], config.status_rotation);
+    assert_eq!(vec!["wan".to_owned()], config.rates_interfaces);
+    assert_eq!(5, config.rates_floor);
+    let config = Config::parse("[alerts]\ntemp = 80\nmbps = 900\nswapping = 250\nping = 100\n\
+        fan_stopped = 60\nsessions = 0\nunsynced = 15\non_battery = 0\nlink_down = 2\n\
+        sustain = \"10s\"").unwrap();
+    assert_eq!(Thresholds {
+        temp: Some(80.),
+        mem: None,
+        mbps: Some(900.),
+        swapping: Some(250.),
+        ping: Some(100.),
+        fan_stopped: Some(60.),
+        sessions: Some(0.),
+        unsynced: Some(15.),
+        on_battery: Some(0.),
+        link_down: Some(2.),
+        sustain: Duration::from_secs(10),
+    }, config.alerts);
+
+    let config = Config::parse("interfaces = \"auto\"").unwrap();
+    assert_eq!(Some(Selection::Auto), config.select_interfaces);
+
+    let config = Config::parse("panels = [\"net\", \"mem\"]\nstatus = \"up {busy}\"").unwrap();
+    assert_eq!(vec![Panel::Net, Panel::Mem], config.panels);
+    assert_eq!(Some(vec![StatusItem::Text("up ".to_owned()), StatusItem::Busy]), config.status);
+
+    let config = Config::parse("[interfaces]\ninclude = [\"lan*\"]\nexclude = [\"lan7\"]").unwrap();
+    assert_eq!(Some(Selection::Patterns {
+        include: vec!["lan*".to_owned()],
+        exclude: vec!["lan7".to_owned()],
+    }), config.select_interfaces);
+
+    let config = Config::parse("interfaces = [\"wan\", \"lan\"]\n\
+        [aggregate.lan]\nmembers = [\"ether1\", \"ether2\"]").unwrap();
+    assert_eq!(Some(&vec!["ether1".to_owned(), "ether2".to_owned()]), config.aggregates.get("lan"));
+    assert_eq!(vec!["wan".to_owned(), "lan".to_owned()], config.interfaces);
+}
+
+#[cfg(test)]
+#[test]
+fn test_config_errors() {
+    let err = |text: &str| format!("{:#}", Config::parse(text).unwrap_err());
+    assert_eq!("\"i2c.bus\" is out of range: 256", err("[i2c]\nbus = 256"));
+    assert_eq!("invalid \"net_columns\": should be \"split\" or \"combined\", not \"one\"",
+        err("net_columns = \"one\""));
+    assert_eq!("\"i2c.bus\" should be an integer, not a string", err("[i2c]\nbus = \"2\""));
+    assert_eq!("\"i2c.address\" should be an integer or \"auto\", not \"0x27\"",
+        err("[i2c]\naddress = \"0x27\""));
+    assert_eq!("\"interfaces\" should be a list of strings, \"auto\", or a table, not a string",
+        err("interfaces = \"lan0\""));
+    assert_eq!("\"interfaces\" should be a list of strings, not an integer",
+        err("interfaces = [\"lan0\", 1]"));
+    assert_eq!("\"aggregate.lan.members\" is missing", err("[aggregate.lan]"));
+    assert_eq!("invalid \"session_source\": should be \"utmp\" or \"sshd\", not \"who\"",
+        err("session_source = \"who\""));
+    assert_eq!("invalid \"cpu_temp\": should be \"auto\", \"off\", or the full path of a file to \
+        read it from, not \"zone1\"", err("cpu_temp = \"zone1\""));
+    assert_eq!("invalid \"units\": should be \"mbps\", \"MBps\", or \"auto\", not \"gbps\"",
+        err("units = \"gbps\""));
+    assert_eq!("\"ups.protocol\" is missing", err("[ups]\nhost = \"nas.lan\""));
+    assert_eq!("invalid \"sched_policy\": should be \"normal\", \"idle\", or \"batch\", not \
+        \"fifo\"", err("sched_policy = \"fifo\""));
+    assert_eq!("invalid \"watchdog.action\": should be \"abort\" or \"reset\", not \"restart\"",
+        err("[watchdog]\naction = \"restart\""));
+    assert_eq!("invalid \"cpu_affinity\": backwards range \"3-1\" in CPU list \"0,3-1\"",
+        err("cpu_affinity = \"0,3-1\""));
+    assert_eq!("\"cpu_affinity\" should be a string or false, not an array",
+        err("cpu_affinity = [0, 1]"));
+    assert_eq!("invalid \"ups.protocol\": should be \"apcupsd\" or \"nut\", not \"apc\"",
+        err("[ups]\nprotocol = \"apc\""));
+    assert_eq!("\"aggregate.lan\" should be a table, not an array",
+        err("aggregate.lan = [\"ether1\"]"));
+    assert_eq!("unknown key \"member\" in [aggregate.lan]; did you mean \"members\"?",
+        err("[aggregate.lan]\nmember = [\"ether1\"]"));
+    assert_eq!("\"i2c\" should be a table, not an integer", err("i2c = 1"));
+    assert_eq!("invalid \"panels\": unknown panel \"nett\"; did you mean \"net\"?",
+        err("panels = [\"nett\"]"));
+    assert_eq!("invalid \"status\": unknown status field {tmp}; did you mean {temp}?",
+        err("status = \"{tmp}\""));
+    assert_eq!("\"interfaces.include\" is missing", err("[interfaces]\nexclude = [\"lo\"]"));
+    assert_eq!("unknown key \"exlude\" in [interfaces]; did you mean \"exclude\"?",
+        err("[interfaces]\ninclude = []\nexlude = []"));
+    assert_eq!("unknown key \"interfcaes\" at top level; did you mean \"interfaces\"?",
+        err("interfcaes = []"));
+    assert_eq!("unknown key \"adress\" in [i2c]; did you mean \"address\"?",
+        err("[i2c]\nadress = 0x27"));
+    assert_eq!("unknown key \"colour\" at top level", err("colour = \"blue\""));
+    assert_eq!("invalid \"backlight_off\": should be a time like \"23:00\"",
+        err("backlight_off = \"11pm\""));
+    assert_eq!(
+        "invalid \"big_digits\": should be \"clock\", \"temp\", or \"throughput\", not \"date\"",
+        err("big_digits = \"date\""));
+    assert_eq!(
+        "invalid \"ping_target\": \"example.com\" should be \"gateway\" or an IP address",
+        err("ping_target = \"example.com\""));
+    assert_eq!("\"backlight\" should be \"on\", \"off\", or \"auto\", not \"dim\"",
+        err("backlight = \"dim\""));
+    assert_eq!("\"display.status_row\" should be true or false, not a string",
+        err("[display]\nstatus_row = \"no\""));
+    assert_eq!("\"display.driver\" should be \"hd44780\" or \"ssd1306\", not \"oled\"",
+        err("[display]\ndriver = \"oled\""));
+    assert_eq!("\"display.glyphs\" should be \"solid\", \"thin\", or \"ticked\", not \"bold\"",
+        err("[display]\nglyphs = \"bold\""));
+    assert_eq!("\"extra_displays.1.address\" is missing",
+        err("[[extra_displays]]\npanels = [\"cpu\"]"));
+    assert_eq!("unknown key \"lables\" in [extra_displays.1]",
+        err("[[extra_displays]]\nlables = 1"));
+    assert_eq!("\"extra_displays\" should be a list of tables, not an integer",
+        err("extra_displays = 1"));
+    assert_eq!("invalid \"interval\": missing unit (ms, s, or m)", err("interval = \"5\""));
+    assert_eq!("\"redraw_interval\" should be a duration like \"500ms\", not a boolean",
+        err("redraw_interval = true"));
+    assert_eq!("invalid \"display.rom\": should be \"a00\" or \"a02\", not \"a01\"",
+        err("[display]\nrom = \"a01\""));
+    assert_eq!("\"display.characters.degree\" is out of range: 256",
+        err("[display.characters]\ndegree = 256"));
+    assert_eq!("\"display.shutdown_message\" should be a string or false, not a boolean",
+        err("[display]\nshutdown_message = true"));
+    assert_eq!("\"interval\" should be a duration like \"500ms\", not an integer",
+        err("interval = 5"));
+    assert_eq!("\"labels.lan0\" should be a string, not an integer", err("[labels]\nlan0 = 1"));
+    assert_eq!("\"labels.lan0\" should be a single letter, digit, or symbol, not \"LAN\"",
+        err("[labels]\nlan0 = \"LAN\""));
+    assert_eq!("\"labels.lan0\" should be a single letter, digit, or symbol, not \" \"",
+        err("[labels]\nlan0 = \" \""));
+    assert_eq!("line 1: invalid value \"lan0\" (strings need quotes)", err("interfaces = lan0"));
+}
+
+#[cfg(test)]
+fn env<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<String> + 'a {
+    move |name| vars.iter().find(|(n, _)| *n == name).map(|(_, v)| (*v).to_owned())
+}
+
+#[cfg(test)]
+#[test]
+fn test_config_env() {
+    let file = "interfaces = \"auto\"\n[i2c]\nbus = 3\naddress = 0x3f";
+    let mut config = Config::parse(file).unwrap();
+    config.apply_env(env(&[
+        ("H2LCD_IFACES", "lan0, lan1"),
+        ("H2LCD_BUS", "1"),
+        ("H2LCD_FALLBACK_BUS", "false"),
+        ("H2LCD_INTERVAL", "2s"),
+        ("H2LCD_REDRAW_INTERVAL", "false"),
+        ("H2LCD_LABELS", "lan0=W,lan1=1"),
+    ])).unwrap();
+    assert_eq!(Config {
+        interfaces: vec!["lan0".to_owned(), "lan1".to_owned()],
+        select_interfaces: None,
+        labels: vec![("lan0".to_owned(), 'W'), ("lan1".to_owned(), '1')].into_iter().collect(),
+        bus: 1,
+        fallback_bus: None,
+        addr: 0x3f, // from the file
+        interval: Duration::from_secs(2),
+        redraw_interval: None,
+        ..Config::default()
+    }, config);
+
+    config.apply_env(env(&[("H2LCD_IFACES", "auto"), ("H2LCD_ADDR", "0x20")])).unwrap();
+    assert_eq!(Some(Selection::Auto), config.select_interfaces);
+    assert_eq!(0x20, config.addr);
+    config.apply_env(env(&[("H2LCD_ADDR", "auto")])).unwrap();
+    assert_eq!(0x20, config.addr);
+    assert!(config.scan_addresses);
+
+    config.apply_env(env(&[
+        ("H2LCD_COLS", "16"),
+        ("H2LCD_ROWS", "2"),
+        ("H2LCD_STATUS_ROW", "false"),
+        ("H2LCD_DRIVER", "ssd1306"),
+    ])).unwrap();
+    assert_eq!(Geometry { cols: 16, rows: 2 }, config.geometry);
+    assert_eq!(Driver::Ssd1306, config.driver);
+    assert!(!config.status_row);
+
+    config.apply_env(env(&[("H2LCD_SHUTDOWN_MESSAGE", "false"), ("H2LCD_SHUTDOWN_FREEZE", "true")]))
+        .unwrap();
+    assert_eq!((None, true), (config.shutdown_message.as_deref(), config.shutdown_freeze));
+    config.apply_env(env(&[("H2LCD_TOTALS_FILE", "/tmp/totals")])).unwrap();
+    assert_eq!(Some(Path::new("/tmp/totals")), config.totals_file.as_deref());
+    config.apply_env(env(&[("H2LCD_TOTALS_FILE", "false")])).unwrap();
+    assert_eq!(None, config.totals_file);
+
+    // the port follows the protocol, unless it's been set
+    config.apply_env(env(&[("H2LCD_UPS_PROTOCOL", "apcupsd")])).unwrap();
+    assert_eq!(Some(3551), config.ups.as_ref().map(|ups| ups.port));
+    config.apply_env(env(&[("H2LCD_UPS_PROTOCOL", "nut")])).unwrap();
+    assert_eq!(Some(3493), config.ups.as_ref().map(|ups| ups.port));
+    config.apply_env(env(&[("H2LCD_UPS_PORT", "3500"), ("H2LCD_UPS_PROTOCOL", "apcupsd")]))
+        .unwrap();
+    assert_eq!(Some(3500), config.ups.as_ref().map(|ups| ups.port));
+
+    config.apply_env(env(&[
+        ("H2LCD_NET_MAX", "2500"),
+        ("H2LCD_NET_SCALE", "link"),
+        ("H2LCD_INTERFACE_MAX", "wan=100, wg0=50"),
+        ("H2LCD_INTERFACE_SCALE", "wan=linear"),
+    ])).unwrap();
+    assert_eq!((2500, link::Scale::Link), (config.net_max, config.net_scale));
+    assert_eq!(vec![("wan".to_owned(), 100), ("wg0".to_owned(), 50)],
+        config.interface_max.clone().into_iter().collect::<Vec<_>>());
+    assert_eq!(scale::Scale::Linear, config.interface_scale("wan"));
+
+    config.apply_env(env(&[
+        ("H2LCD_NICE", "-5"),
+        ("H2LCD_SCHED_POLICY", "idle"),
+        ("H2LCD_CPU_AFFINITY", "0,2-3"),
+    ])).unwrap();
+    assert_eq!((Some(-5), Some(SchedPolicy::Idle), Some(vec![0, 2, 3])),
+        (config.nice, config.sched_policy, config.cpu_affinity.clone()));
+    config.apply_env(env(&[
+        ("H2LCD_NICE", "false"),
+        ("H2LCD_SCHED_POLICY", "normal"),
+        ("H2LCD_CPU_AFFINITY", "false"),
+    ])).unwrap();
+    assert_eq!((None, None, None), (config.nice, config.sched_policy, config.cpu_affinity.clone()));
+    config.apply_env(env(&[("H2LCD_BURST_TRIGGER", "500")])).unwrap();
+    assert_eq!(Some(500), config.burst_trigger);
+    config.apply_env(env(&[("H2LCD_BURST_INTERVAL", "50ms"), ("H2LCD_BURST_DURATION", "1m")]))
+        .unwrap();
+    assert_eq!((Duration::from_millis(50), Duration::from_secs(60)),
+        (config.burst_interval, config.burst_duration));
+    config.apply_env(env(&[("H2LCD_BURST_TRIGGER", "false"), ("H2LCD_PEAK_DECAY", "20")]))
+        .unwrap();
+    assert_eq!((None, Some(20)), (config.burst_trigger, config.peak_decay));
+    config.apply_env(env(&[("H2LCD_PEAK_DECAY", "false")])).unwrap();
+    assert_eq!(None, config.peak_decay);
+    config.apply_env(env(&[("H2LCD_REINIT_INTERVAL", "5m")])).unwrap();
+    assert_eq!(Some(Duration::from_secs(300)), config.reinit_interval);
+    config.apply_env(env(&[("H2LCD_REINIT_INTERVAL", "false"), ("H2LCD_ACCESSIBLE", "true")]))
+        .unwrap();
+    assert_eq!((None, true), (config.reinit_interval, config.accessible));
+    config.apply_env(env(&[("H2LCD_ACTIVITY_BLIP", "iot0, iot1")])).unwrap();
+    assert_eq!(vec!["iot0".to_owned(), "iot1".to_owned()], config.activity_blip);
+    config.apply_env(env(&[
+        ("H2LCD_WATCHDOG_TIMEOUT", "false"),
+        ("H2LCD_WATCHDOG_ACTION", "reset"),
+    ])).unwrap();
+    assert_eq!(watchdog::Settings { timeout: None, action: watchdog::Action::Reset },
+        config.watchdog);
+    config.apply_env(env(&[("H2LCD_WATCHDOG_TIMEOUT", "90s")])).unwrap();
+    assert_eq!(Some(Duration::from_secs(90)), config.watchdog.timeout);
+    config.apply_env(env(&[("H2LCD_TEMP_INTERVAL", "0s"), ("H2LCD_FS_INTERVAL", "5m")])).unwrap();
+    config.apply_env(env(&[("H2LCD_LINK_CHANGE_MARK", "10m")])).unwrap();
+    assert_eq!(Duration::from_secs(600), config.link_change_mark);
+    assert_eq!((Duration::ZERO, Duration::from_secs(300)),
+        (config.temp_interval, config.fs_interval));
+
+    let err = |vars| format!("{:#}", Config::default().apply_env(env(vars)).unwrap_err());
+    assert_eq!("invalid H2LCD_BUS \"banana\": \"i2c.bus\" should be an integer, not a string",
+        err(&[("H2LCD_BUS", "banana")]));
+    assert_eq!("invalid H2LCD_BUS \"300\": \"i2c.bus\" is out of range: 300",
+        err(&[("H2LCD_BUS", "300")]));
+    assert_eq!("invalid H2LCD_INTERVAL \"fast\": invalid \"interval\": missing unit (ms, s, or m)",
+        err(&[("H2LCD_INTERVAL", "fast")]));
+    assert_eq!("invalid H2LCD_LABELS \"lan0\": \"labels.lan0\" should be a string, not a boolean",
+        err(&[("H2LCD_LABELS", "lan0")]));
+    assert_eq!("invalid H2LCD_INTERFACE_MAX \"wan=fast\": \"interface_max.wan\" should be an \
+        integer, not a string", err(&[("H2LCD_INTERFACE_MAX", "wan=fast")]));
+}
+
+#[cfg(test)]
+#[test]
+fn test_config_validate() {
+    // 4 CPU columns leaves room for 6 interfaces
+    assert!(Config::default().validate(4).is_ok());
+    let err = |config: Config| Config::validate(&config, 4).unwrap_err().to_string();
+    assert_eq!("interval must be at least 100ms, not 50ms",
+        err(Config { interval: Duration::from_millis(50), ..Config::default() }));
+    assert_eq!("watchdog.timeout (60s) must be at least 6 times as long as the interval (20s)",
+        err(Config { interval: Duration::from_secs(20), ..Config::default() }));
+    let unwatched = watchdog::Settings { timeout: None, ..watchdog::Settings::default() };
+    Config { interval: Duration::from_secs(20), watchdog: unwatched, ..Config::default() }
+        .validate(4).unwrap();
+    assert_eq!("burst_trigger must be more than 0",
+        err(Config { burst_trigger: Some(0), ..Config::default() }));
+    assert_eq!("burst_interval (500ms) must be more than 0 and shorter than the interval (500ms)",
+        err(Config {
+            burst_trigger: Some(500),
+            burst_interval: Duration::from_millis(500),
+            ..Config::default()
+        }));
+    assert_eq!("burst_duration (100ms) must be at least as long as the interval (500ms)",
+        err(Config {
+            burst_trigger: Some(500),
+            burst_duration: Duration::from_millis(100),
+            ..Config::default()
+        }));
+    // off, they're not looked at
+    Config { burst_interval: Duration::ZERO, ..Config::default() }.validate(4).unwrap();
+    assert_eq!("peak_decay must be more than 0",
+        err(Config { peak_decay: Some(0), ..Config::default() }));
+    assert_eq!("reinit_interval (100ms) must be at least as long as the interval (500ms)",
+        err(Config { reinit_interval: Some(Duration::from_millis(100)), ..Config::default() }));
+    assert_eq!("nice must be from -20 to 19, not 20",
+        err(Config { nice: Some(20), ..Config::default() }));
+    assert!(Config {
+        interval: Duration::from_secs(10),
+        history_window: Duration::from_secs(60),
+        ..Config::default()
+    }.validate(4).is_ok());
+    assert_eq!("history_window (2s) must be at least as long as the interval (5s)", err(Config {
+        interval: Duration::from_secs(5),
+        history_window: Duration::from_secs(2),
+        ..Config::default()
+    }));
+    assert_eq!("net_max must be more than 0", err(Config { net_max: 0, ..Config::default() }));
+    assert_eq!("net_decades must be more than 0",
+        err(Config { net_decades: 0, ..Config::default() }));
+    let sparkline = |span, columns| Config {
+        panels: vec![Panel::Cpu, Panel::Net, Panel::Sparkline],
+        interfaces: vec!["lan0".to_owned()],
+        sparkline_span: Duration::from_secs(span),
+        sparkline_columns: columns,
+        ..Config::default()
+    };
+    assert!(sparkline(3, 6).validate(4).is_ok());
+    assert_eq!("sparkline_columns must be more than 0", err(sparkline(3, 0)));
+    assert_eq!("sparkline_span (2s) must be at least as long as the interval (500ms) for each of \
+        the 6 sparkline_columns", err(sparkline(2, 6)));
+    assert_eq!("interface_max for \"wan\" must be more than 0", err(Config {
+        interface_max: vec![("wan".to_owned(), 0)].into_iter().collect(),
+        ..Config::default()
+    }));
+    let linear = |max: &[(&str, u32)]| Config {
+        interface_max: max.iter().map(|&(name, max)| (name.to_owned(), max)).collect(),
+        interface_scale: vec![("wan".to_owned(), scale::Scale::Linear)].into_iter().collect(),
+        ..Config::default()
+    };
+    assert!(linear(&[("wan", 300)]).validate(4).is_ok());
+    assert_eq!("\"wan\" has a linear scale, so it needs a speed for the top of it in \
+        interface_max", err(linear(&[("lan0", 300)])));
+    assert_eq!("redraw_interval (1s) must be at least as long as the interval (5s)", err(Config {
+        interval: Duration::from_secs(5),
+        history_window: Duration::from_secs(60),
+        redraw_interval: Some(Duration::from_secs(1)),
+        ..Config::default()
+    }));
+    assert_eq!("status_period (1s) must be at least as long as the interval (5s)", err(Config {
+        interval: Duration::from_secs(5),
+        history_window: Duration::from_secs(60),
+        redraw_interval: None,
+        status_rotation: vec![Segment::Status, Segment::Uptime],
+        status_period: Duration::from_secs(1),
+        ..Config::default()
+    }));
+    let interfaces = |names: &[&str]| Config {
+        interfaces: names.iter().map(|&s| s.to_owned()).collect(),
+        ..Config::default()
+    };
+    assert_eq!("no network interfaces to show", err(interfaces(&[])));
+    assert_eq!("network interface \"lan0\" is listed more than once",
+        err(interfaces(&["lan0", "lan0"])));
+    let mut config = Config::default();
+    config.interfaces.push("wg0".to_owned());
+    assert_eq!("7 network interfaces given, but only 6 fit on the display", err(config.clone()));
+    // but they do without the CPU panel
+    assert!(Config { panels: vec![Panel::Net, Panel::Mem], ..config.clone() }.validate(4).is_ok());
+    // or in combined columns
+    assert!(Config { net_columns: NetColumns::Combined, ..config }.validate(4).is_ok());
+    let cpu_only = Config { panels: vec![Panel::Cpu, Panel::Mem], ..Config::default() };
+    assert_eq!("the panels need 26 columns (cpu 24, mem 1, and 1 between them), but the display \
+        only has 20",
+        cpu_only.validate(24).unwrap_err().to_string());
+    assert_eq!("status is 22 characters long, but the display only has 20 columns", err(Config {
+        status: Some(layout::parse_status("a very long status bar").unwrap()),
+        ..Config::default()
+    }));
+    // speeds with suffixes take more room
+    let status = Some(layout::parse_status("cpu {temp}{rates}{decay}mem").unwrap());
+    assert!(Config { status: status.clone(), ..Config::default() }.validate(4).is_ok());
+    assert_eq!("status is 22 characters long, but the display only has 20 columns", err(Config {
+        status,
+        units: Units::Auto,
+        ..Config::default()
+    }));
+
+    // a 16x2 display fits 4 interfaces alongside 4 CPUs
+    let small = Config { geometry: Geometry { cols: 16, rows: 2 }, ..Config::default() };
+    assert_eq!("6 network interfaces given, but only 4 fit on the display", err(small.clone()));
+    assert!(Config { interfaces: small.interfaces[.. 4].to_vec(), ..small }.validate(4).is_ok());
+    assert_eq!("4-row displays must have 20 columns, not 16", err(Config {
+        geometry: Geometry { cols: 16, rows: 4 },
+        ..Config::default()
+    }));
+    assert_eq!("the SSD1306 only fits 21 columns, not 40", err(Config {
+        driver: Driver::Ssd1306,
+        geometry: Geometry { cols: 40, rows: 2 },
+        ..Config::default()
+    }));
+    let extra = |geometry, panels: &[Panel]| ExtraDisplay {
+        bus: None,
+        addr: 0x3f,
+        driver: Driver::Hd44780,
+        geometry,
+        glyphs: GlyphStyle::Solid,
+        charset: Charset::default(),
+        status_row: true,
+        panels: panels.to_vec(),
+        status: None,
+    };
+    let cpu_mem = extra(Geometry { cols: 16, rows: 2 }, &[Panel::Cpu, Panel::Mem]);
+    assert!(Config { extra_displays: vec![cpu_mem.clone()], ..Config::default() }
+        .validate(4).is_ok());
+    assert_eq!("extra display 1: 4-row displays must have 20 columns, not 16", format!("{:#}",
+        Config {
+            extra_displays: vec![extra(Geometry { cols: 16, rows: 4 }, &[Panel::Cpu])],
+            ..Config::default()
+        }.validate(4).unwrap_err()));
+    assert_eq!("extra display 2 has the same I2C bus and address as another one", err(Config {
+        extra_displays: vec![cpu_mem.clone(), cpu_mem],
+        ..Config::default()
+    }));
+    // interfaces have to fit on every display that shows them
+    assert_eq!("6 network interfaces given, but only 4 fit on the display", err(Config {
+        extra_displays: vec![extra(Geometry { cols: 16, rows: 2 }, layout::DEFAULT_PANELS)],
+        ..Config::default()
+    }));
+    // and on every page
+    let page = |panels: &[Panel], name: Option<&str>| Page {
+        name: name.map(str::to_owned),
+        panels: panels.to_vec(),
+        status: None,
+    };
+    assert_eq!("6 network interfaces given, but only 4 fit on the display", err(Config {
+        pages: vec![page(&[Panel::Cpu, Panel::Net, Panel::Sparkline], None)],
+        ..Config::default()
+    }));
+    assert_eq!("page 2: status is 24 characters long, but the display only has 20 columns",
+        format!("{:#}", Config {
+            pages: vec![Page {
+                status: Some(layout::parse_status("a status that's too long").unwrap()),
+                ..page(&[Panel::Mem], None)
+            }],
+            ..Config::default()
+        }.validate(4).unwrap_err()));
+    assert_eq!("the name of page 2 can only have ASCII characters", err(Config {
+        pages: vec![page(&[Panel::Mem], Some("température"))],
+        ..Config::default()
+    }));
+    assert_eq!("page_period (100ms) must be at least as long as the interval (500ms)",
+        err(Config {
+            pages: vec![page(&[Panel::Mem], None)],
+            page_period: Duration::from_millis(100),
+            ..Config::default()
+        }));
+    let aggregate = |members: &[&str]| Config {
+        aggregates: vec![
+            ("lan".to_owned(), members.iter().map(|&m| m.to_owned()).collect()),
+            ("lan2".to_owned(), vec!["ether5".to_owned()]),
+        ].into_iter().collect(),
+        ..Config::default()
+    };
+    assert!(aggregate(&["ether1", "ether2"]).validate(4).is_ok());
+    assert_eq!("aggregate \"lan\" needs some members", err(aggregate(&[])));
+    assert_eq!("\"ether1\" is listed more than once in aggregate \"lan\"",
+        err(aggregate(&["ether1", "ether1"])));
+    assert_eq!("aggregate \"lan\" can't include another aggregate, \"lan2\"",
+        err(aggregate(&["ether1", "lan2"])));
+    let disk = Config { panels: vec![Panel::Cpu, Panel::Disk], ..Config::default() };
+    assert_eq!("the disk panel needs some devices listed in \"disks\"", err(disk.clone()));
+    assert!(Config { disks: vec!["sda".to_owned()], ..disk.clone() }.validate(4).is_ok());
+    assert_eq!("disk \"sda\" is listed more than once",
+        err(Config { disks: vec!["sda".to_owned(), "sda".to_owned()], ..disk }));
+    let fs = Config { panels: vec![Panel::Cpu, Panel::Fs], ..Config::default() };
+    assert_eq!("the fs panel needs some mount points listed in \"filesystems\"", err(fs.clone()));
+    assert_eq!("filesystem \"/\" is listed more than once",
+        err(Config { filesystems: vec!["/".to_owned(), "/".to_owned()], ..fs }));
+    let wifi = Config { panels: vec![Panel::Cpu, Panel::Wifi], ..Config::default() };
+    assert_eq!("the wifi panel needs some interfaces listed in \"wireless\"", err(wifi.clone()));
+    assert_eq!("wireless interface \"wlan0\" is listed more than once",
+        err(Config { wireless: vec!["wlan0".to_owned(), "wlan0".to_owned()], ..wifi }));
+    let temp = Config { panels: vec![Panel::Cpu, Panel::Temp], ..Config::default() };
+    assert_eq!("the temp panel needs some sensors listed in \"temperatures\"", err(temp.clone()));
+    assert_eq!("temperature sensor \"Tctl\" is listed more than once",
+        err(Config { temperatures: vec!["Tctl".to_owned(), "Tctl".to_owned()], ..temp }));
+    assert_eq!("temp_min (90) must be less than temp_max (90)",
+        err(Config { temp_min: 90, ..Config::default() }));
+    assert_eq!("status field {temp2} needs 2 sensors listed in \"temperatures\"", err(Config {
+        temperatures: vec!["Tctl".to_owned()],
+        status: Some(layout::parse_status("{temp1} {temp2}").unwrap()),
+        ..Config::default()
+    }));
+    let fan = Config { panels: vec![Panel::Cpu, Panel::Fan], ..Config::default() };
+    assert_eq!("the fan panel needs some fans listed in \"fans\"", err(fan.clone()));
+    assert_eq!("the fan segment needs some fans listed in \"fans\"",
+        err(Config { status_rotation: vec![Segment::Fan], ..Config::default() }));
+    assert_eq!("the fan_stopped alert needs some fans listed in \"fans\"", err(Config {
+        alerts: Thresholds { fan_stopped: Some(60.), ..Thresholds::default() },
+        ..Config::default()
+    }));
+    assert_eq!("fan \"CPU_FAN\" is listed more than once",
+        err(Config { fans: vec!["CPU_FAN".to_owned(), "CPU_FAN".to_owned()], ..fan }));
+    assert_eq!("fan_max must be more than 0", err(Config { fan_max: 0, ..Config::default() }));
+    assert_eq!("the ups panel needs a [ups] section",
+        err(Config { panels: vec![Panel::Cpu, Panel::Ups], ..Config::default() }));
+    assert_eq!("the on_battery alert needs a [ups] section", err(Config {
+        alerts: Thresholds { on_battery: Some(0.), ..Thresholds::default() },
+        ..Config::default()
+    }));
+    assert_eq!("the address segment needs an interface in \"address_interface\"",
+        err(Config { status_rotation: vec![Segment::Address], ..Config::default() }));
+    assert_eq!("ping_interval must be at least 1s, not 500ms",
+        err(Config { ping_interval: Duration::from_millis(500), ..Config::default() }));
+    assert_eq!("alerts.mem is a percentage, so it can't be more than 100", err(Config {
+        alerts: Thresholds { mem: Some(101.), ..Thresholds::default() },
+        ..Config::default()
+    }));
+    assert_eq!("backlight_off and backlight_on have to be given together",
+        err(Config { backlight_off: Some(0), ..Config::default() }));
+    assert_eq!("backlight_off and backlight_on can't be the same time",
+        err(Config { backlight_off: Some(0), backlight_on: Some(0), ..Config::default() }));
+    assert_eq!("labels are shown on the status row, so they can't be used without one",
+        err(Config {
+            status_row: false,
+            labels: vec![("ether0".to_owned(), 'W')].into_iter().collect(),
+            ..Config::default()
+        }));
+}
+
+#[cfg(test)]
+#[test]
+fn test_compress() {
+    let interfaces = |n| Config {
+        interfaces: (0 .. n).map(|i| format!("ether{}", i)).collect(),
+        ..Config::default()
+    };
+    // 4 + 12 + 1, and separators: fits already
+    let mut config = interfaces(6);
+    assert_eq!(Vec::<Compression>::new(), config.compress(4));
+    assert_eq!(interfaces(6), config);
+
+    // 8 + 12 + 1 doesn't, but 1 + 12 + 1 does
+    let mut config = interfaces(6);
+    assert_eq!(vec![Compression::AggregateCpu], config.compress(8));
+    assert_eq!(CpuMode::Aggregate, config.cpu_mode);
+    assert_eq!(NetColumns::Split, config.net_columns);
+    assert!(config.validate(1).is_ok());
+
+    // 1 + 18 + 1 doesn't either, but 1 + 9 + 1 does
+    let mut config = interfaces(9);
+    assert_eq!(vec![Compression::AggregateCpu, Compression::CombinedNet], config.compress(4));
+    assert_eq!(NetColumns::Combined, config.net_columns);
+    assert_eq!(layout::DEFAULT_PANELS, config.panels);
+    assert!(config.validate(1).is_ok());
+
+    // already one CPU column; 1 + 18 + 1 needs the memory column gone, but only where it doesn't
+    // fit
+    let mut config = Config {
+        cpu_mode: CpuMode::Aggregate,
+        pages: vec![Page { name: None, panels: vec![Panel::Net, Panel::Mem], status: None }],
+        ..interfaces(18)
+    };
+    assert_eq!(vec![Compression::CombinedNet, Compression::NoMem], config.compress(4));
+    assert_eq!(vec![Panel::Cpu, Panel::Net], config.panels);
+    assert_eq!(vec![Panel::Net, Panel::Mem], config.pages[0].panels);
+    assert!(config.validate(1).is_ok());
+
+    // there's nothing to compress on the other panels
+    let mut config = Config {
+        panels: vec![Panel::Cpu, Panel::Sparkline],
+        sparkline_columns: 19,
+        ..interfaces(1)
+    };
+    assert_eq!(vec![Compression::AggregateCpu], config.compress(4));
+    assert_eq!("the panels need 21 columns (cpu 1, sparkline 19, and 1 between them), but the \
+        display only has 20", config.validate(1).unwrap_err().to_string());
+}
+
+#[cfg(test)]
+#[test]
+fn test_parse_values() {
+    assert_eq!(0x3f, parse_int::<u16>("0x3f").unwrap());
+    assert_eq!(63, parse_int::<u16>("63").unwrap());
+    assert_eq!("0x100 is out of range", parse_int::<u8>("0x100").unwrap_err().to_string());
+    assert!(parse_int::<u8>("-1").is_err());
+
+    assert_eq!(Duration::from_millis(250), parse_duration("250ms").unwrap());
+    assert_eq!(Duration::from_secs(2), parse_duration("2s").unwrap());
+    assert_eq!(Duration::from_millis(1500), parse_duration("1.5s").unwrap());
+    assert_eq!(Duration::from_secs(300), parse_duration("5m").unwrap());
+    assert_eq!("missing unit (ms, s, or m)", parse_duration("5").unwrap_err().to_string());
+    assert!(parse_duration("-1s").is_err());
+    assert!(parse_duration("fast").is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn test_suggest() {
+    let keys = ["interfaces", "i2c", "bus", "fallback_bus"];
+    assert_eq!(Some("interfaces"), suggest("interface", &keys));
+    assert_eq!(Some("interfaces"), suggest("intrefaces", &keys));
+    assert_eq!(Some("i2c"), suggest("i2d", &keys));
+    assert_eq!(Some("fallback_bus"), suggest("fallbackbus", &keys));
+    assert_eq!(None, suggest("xyz", &keys));
+    assert_eq!(None, suggest("interval", &keys));
+}
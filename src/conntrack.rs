@@ -0,0 +1,72 @@
+//! How full the netfilter connection tracking table is. On a NAT router, that's what runs out
+//! first under load.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where the conntrack sysctls are, when the module is loaded.
+pub const SYSCTL_DIR: &str = "/proc/sys/net/netfilter";
+
+pub struct Conntrack {
+    dir: PathBuf,
+    /// The table's size, once read. It's only changed by hand, so it's read just once.
+    max: Option<u64>,
+}
+
+impl Conntrack {
+    /// Read the sysctls in `dir` (normally SYSCTL_DIR).
+    pub fn new(dir: &Path) -> Self {
+        Self { dir: dir.to_owned(), max: None }
+    }
+
+    /// Whether there's a conntrack table to show.
+    pub fn available(dir: &Path) -> bool {
+        dir.join("nf_conntrack_count").exists()
+    }
+
+    /// Connections being tracked, and the most there's room for, or None if they can't be read.
+    pub fn sample(&mut self) -> Option<(u64, u64)> {
+        let dir = &self.dir;
+        let read = |name: &str| {
+            fs::read_to_string(dir.join(name)).ok()?.trim().parse::<u64>().ok()
+        };
+        let max = match self.max {
+            Some(max) => max,
+            None => read("nf_conntrack_max")?,
+        };
+        self.max = Some(max);
+        Some((read("nf_conntrack_count")?, max))
+    }
+}
+
+/// How full the table is, from a sample.
+pub fn used((count, max): (u64, u64)) -> f64 {
+    count as f64 / max.max(1) as f64
+}
+
+#[cfg(test)]
+#[test]
+fn test_conntrack() {
+    let dir = std::env::temp_dir()
+        .join(format!("h2-net-lcd-test-conntrack-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    assert!(!Conntrack::available(&dir));
+    assert_eq!(None, Conntrack::new(&dir).sample());
+
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("nf_conntrack_count"), "1024\n").unwrap();
+    fs::write(dir.join("nf_conntrack_max"), "65536\n").unwrap();
+    assert!(Conntrack::available(&dir));
+    let mut conntrack = Conntrack::new(&dir);
+    assert_eq!(Some((1024, 65536)), conntrack.sample());
+    assert_eq!(1024. / 65536., used((1024, 65536)));
+
+    // the max is only read once
+    fs::write(dir.join("nf_conntrack_count"), "2048\n").unwrap();
+    fs::write(dir.join("nf_conntrack_max"), "131072\n").unwrap();
+    assert_eq!(Some((2048, 65536)), conntrack.sample());
+
+    fs::write(dir.join("nf_conntrack_count"), "garbage").unwrap();
+    assert_eq!(None, conntrack.sample());
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,172 @@
+//! Turning per-core loads into CPU gauge columns, for machines with more cores than there's room
+//! for.
+
+use anyhow::{bail, Context, Result};
+use crate::iowait::CoreLoad;
+use std::cmp::Ordering;
+
+/// How per-core loads are turned into CPU gauge columns.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CpuMode {
+    /// Pick one of the others based on the number of cores.
+    Auto,
+    /// One column per core.
+    PerCore,
+    /// Pairs of cores share a column, showing the busier of the two.
+    Paired,
+    /// Four columns: min, median, 90th percentile, and max across all cores.
+    Spread,
+    /// One column: the mean across all cores.
+    Aggregate,
+    /// The N busiest cores, busiest first, whichever they are at the moment.
+    Top(usize),
+}
+
+impl CpuMode {
+    pub fn parse(s: &str) -> Result<Self> {
+        Ok(match s {
+            "auto" => CpuMode::Auto,
+            "per-core" => CpuMode::PerCore,
+            "paired" => CpuMode::Paired,
+            "spread" => CpuMode::Spread,
+            "aggregate" => CpuMode::Aggregate,
+            _ => match s.strip_prefix("top-") {
+                Some(n) => {
+                    let n = n.parse().with_context(|| format!("invalid core count in {:?}", s))?;
+                    if n == 0 {
+                        bail!("{:?} needs at least one core", s);
+                    }
+                    CpuMode::Top(n)
+                }
+                None => bail!("should be \"auto\", \"per-core\", \"paired\", \"spread\", \
+                    \"aggregate\", or like \"top-4\", not {:?}", s),
+            },
+        })
+    }
+
+    pub fn resolve(self, cores: usize) -> Self {
+        match self {
+            CpuMode::Auto if cores <= 8 => CpuMode::PerCore,
+            CpuMode::Auto if cores <= 16 => CpuMode::Paired,
+            CpuMode::Auto => CpuMode::Spread,
+            other => other,
+        }
+    }
+
+    /// Character drawn after the CPU columns. Paired columns get a different separator so it's
+    /// clear each column is more than one core.
+    pub fn separator(self) -> u8 {
+        match self {
+            CpuMode::Paired => b':',
+            _ => b'|',
+        }
+    }
+}
+
+/// Reduce per-core loads to the columns to display for the given mode. Cores are compared by their
+/// columns' whole height, iowait and all.
+pub fn columns(loads: &[CoreLoad], mode: CpuMode) -> Vec<CoreLoad> {
+    let by_total = |a: &CoreLoad, b: &CoreLoad| {
+        a.total().partial_cmp(&b.total()).unwrap_or(Ordering::Equal)
+    };
+    let mean = |loads: &[CoreLoad]| {
+        let n = loads.len() as f64;
+        CoreLoad {
+            busy: loads.iter().map(|core| core.busy).sum::<f64>() / n,
+            iowait: loads.iter().map(|core| core.iowait).sum::<f64>() / n,
+        }
+    };
+    match mode.resolve(loads.len()) {
+        CpuMode::Auto | CpuMode::PerCore => loads.to_vec(),
+        CpuMode::Paired => loads.chunks(2)
+            .map(|pair| pair.iter().cloned().max_by(by_total).unwrap_or_default())
+            .collect(),
+        CpuMode::Spread => {
+            if loads.is_empty() {
+                return vec![];
+            }
+            let mut sorted = loads.to_vec();
+            sorted.sort_by(by_total);
+            let n = sorted.len();
+            let median = if n % 2 == 0 {
+                mean(&sorted[n / 2 - 1 ..= n / 2])
+            } else {
+                sorted[n / 2]
+            };
+            // nearest-rank percentile
+            let p90 = sorted[((n as f64 * 0.9).ceil() as usize).max(1) - 1];
+            vec![sorted[0], median, p90, sorted[n - 1]]
+        }
+        CpuMode::Aggregate if loads.is_empty() => vec![],
+        CpuMode::Aggregate => vec![mean(loads)],
+        CpuMode::Top(n) => {
+            let mut sorted = loads.to_vec();
+            sorted.sort_by(|a, b| by_total(b, a));
+            sorted.truncate(n);
+            sorted
+        }
+    }
+}
+
+/// How many columns the CPU panel takes for the given number of cores.
+pub fn count(cores: usize, mode: CpuMode) -> usize {
+    columns(&vec![CoreLoad::default(); cores], mode).len()
+}
+
+#[cfg(test)]
+#[test]
+fn test_columns() {
+    let busy = |loads: &[f64]| -> Vec<CoreLoad> {
+        loads.iter().map(|&busy| CoreLoad { busy, iowait: 0. }).collect()
+    };
+    let columns = |loads: &[f64], mode| -> Vec<f64> {
+        columns(&busy(loads), mode).iter().map(|column| column.busy).collect()
+    };
+    let four = [0.1, 0.9, 0.5, 0.3];
+    assert_eq!(four.to_vec(), columns(&four, CpuMode::Auto));
+    assert_eq!(vec![0.9, 0.5], columns(&four, CpuMode::Paired));
+
+    let ten: Vec<f64> = (0 .. 10).map(|i| i as f64 / 10.).collect();
+    assert_eq!(CpuMode::Paired, CpuMode::Auto.resolve(ten.len()));
+    assert_eq!(vec![0.1, 0.3, 0.5, 0.7, 0.9], columns(&ten, CpuMode::Auto));
+    assert_eq!(ten, columns(&ten, CpuMode::PerCore));
+
+    // odd core count: the last column is a single core
+    assert_eq!(vec![0.5, 0.2], columns(&[0.5, 0.1, 0.2], CpuMode::Paired));
+
+    let twenty: Vec<f64> = (0 .. 20).rev().map(|i| i as f64 / 20.).collect();
+    assert_eq!(CpuMode::Spread, CpuMode::Auto.resolve(twenty.len()));
+    assert_eq!(vec![0., 0.475, 0.85, 0.95], columns(&twenty, CpuMode::Auto));
+
+    assert_eq!(vec![0.4, 0.4, 0.4, 0.4], columns(&[0.4], CpuMode::Spread));
+    assert!(columns(&[], CpuMode::Spread).is_empty());
+
+    assert_eq!(vec![0.45], columns(&four, CpuMode::Aggregate));
+    assert!(columns(&[], CpuMode::Aggregate).is_empty());
+    assert_eq!(vec![0.9, 0.5], columns(&four, CpuMode::Top(2)));
+    assert_eq!(vec![0.9, 0.5, 0.3, 0.1], columns(&four, CpuMode::Top(8)));
+    assert_eq!(1, count(16, CpuMode::Aggregate));
+    assert_eq!(4, count(16, CpuMode::Top(4)));
+    assert_eq!(2, count(2, CpuMode::Top(4)));
+    assert_eq!(8, count(16, CpuMode::Auto));
+
+    // iowait goes along with the core it's from
+    let waiting = CoreLoad { busy: 0.1, iowait: 0.8 };
+    let busier = CoreLoad { busy: 0.5, iowait: 0. };
+    assert_eq!(vec![waiting], self::columns(&[busier, waiting], CpuMode::Paired));
+    assert_eq!(vec![busier, CoreLoad { busy: 0.3, iowait: 0.4 }, waiting, waiting],
+        self::columns(&[waiting, busier], CpuMode::Spread));
+    assert_eq!(vec![waiting], self::columns(&[busier, waiting], CpuMode::Top(1)));
+}
+
+#[cfg(test)]
+#[test]
+fn test_parse() {
+    assert_eq!(CpuMode::PerCore, CpuMode::parse("per-core").unwrap());
+    assert_eq!(CpuMode::Top(4), CpuMode::parse("top-4").unwrap());
+    let err = |s| format!("{:#}", CpuMode::parse(s).unwrap_err());
+    assert_eq!("\"top-0\" needs at least one core", err("top-0"));
+    assert_eq!("invalid core count in \"top-n\": invalid digit found in string", err("top-n"));
+    assert_eq!("should be \"auto\", \"per-core\", \"paired\", \"spread\", \"aggregate\", or like \
+        \"top-4\", not \"all\"", err("all"));
+}
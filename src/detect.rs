@@ -0,0 +1,207 @@
+use std::fs;
+use std::path::Path;
+
+/// Where the kernel lists network interfaces.
+pub const SYSFS_NET: &str = "/sys/class/net";
+
+/// IFF_UP in the sysfs `flags` file: the interface is administratively up.
+const IFF_UP: u32 = 0x1;
+/// IFF_LOOPBACK in the sysfs `flags` file.
+const IFF_LOOPBACK: u32 = 0x8;
+
+/// How to pick interfaces from the ones that exist, instead of listing them by name.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selection {
+    /// Everything that's up, isn't loopback, and isn't a port of a bridge (the bridge itself is
+    /// shown instead), in order of name.
+    Auto,
+    /// Everything matching any of the `include` glob patterns and none of the `exclude` ones, in
+    /// the order of the pattern they first matched, then by name.
+    Patterns { include: Vec<String>, exclude: Vec<String> },
+}
+
+impl Selection {
+    /// Pick interfaces from the ones in `dir` (normally SYSFS_NET), at most `max` of them. Any
+    /// that had to be left out are logged.
+    pub fn select(&self, dir: &Path, max: usize) -> Vec<String> {
+        let mut names = self.names(dir);
+        if names.len() > max {
+            eprintln!("warning: only {} network interfaces fit on the display; not showing {}",
+                max, names[max ..].join(", "));
+            names.truncate(max);
+        }
+        names
+    }
+
+    /// The interfaces in `dir` it would pick now that aren't in `shown`, as those there's room
+    /// for alongside them, at most `max` in all, and those there isn't.
+    pub fn new_ones(&self, dir: &Path, shown: &[String], max: usize) -> (Vec<String>, Vec<String>) {
+        let mut names: Vec<String> = self.names(dir).into_iter()
+            .filter(|name| !shown.contains(name))
+            .collect();
+        let crowded = names.split_off(max.saturating_sub(shown.len()).min(names.len()));
+        (names, crowded)
+    }
+
+    fn names(&self, dir: &Path) -> Vec<String> {
+        match self {
+            Selection::Auto => list(dir, true),
+            Selection::Patterns { include, exclude } => expand(&list(dir, false), include, exclude),
+        }
+    }
+}
+
+/// Names of the interfaces in `dir`, sorted. With `auto`, only the ones Selection::Auto picks.
+fn list(dir: &Path, auto: bool) -> Vec<String> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("failed to list network interfaces in {:?}: {}", dir, e);
+            return vec![];
+        }
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let path = entry.path();
+            let flags = fs::read_to_string(path.join("flags")).ok()
+                .and_then(|s| u32::from_str_radix(s.trim().trim_start_matches("0x"), 16).ok());
+            match flags {
+                Some(flags) => !auto || (flags & IFF_UP != 0
+                    && flags & IFF_LOOPBACK == 0
+                    && !path.join("brport").exists()),
+                // gone already, or not an interface
+                None => false,
+            }
+        })
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+    names.sort();
+    names
+}
+
+/// The `names` matching any of the `include` patterns and none of the `exclude` ones. Ordered by
+/// which include pattern matched first, then by the order of `names`.
+pub fn expand(names: &[String], include: &[String], exclude: &[String]) -> Vec<String> {
+    let mut matched: Vec<String> = vec![];
+    for pattern in include {
+        for name in names {
+            if glob_match(pattern, name)
+                && !exclude.iter().any(|ex| glob_match(ex, name))
+                && !matched.contains(name)
+            {
+                matched.push(name.clone());
+            }
+        }
+    }
+    matched
+}
+
+/// Shell-style glob match, supporting `*` (any run of characters) and `?` (any one character).
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    // Position to backtrack to after the last `*`: (pattern index after it, name index).
+    let mut star = None;
+    let (mut p, mut n) = (0, 0);
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                // let the `*` swallow one more character
+                Some((sp, sn)) => {
+                    star = Some((sp, sn + 1));
+                    p = sp;
+                    n = sn + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p ..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+#[test]
+fn test_glob_match() {
+    assert!(glob_match("lan*", "lan0"));
+    assert!(glob_match("lan*", "lan"));
+    assert!(!glob_match("lan*", "wlan0"));
+    assert!(glob_match("*lan*", "wlan0"));
+    assert!(glob_match("lan?", "lan1"));
+    assert!(!glob_match("lan?", "lan10"));
+    assert!(glob_match("e*0.*", "eth0.10"));
+    assert!(glob_match("*", ""));
+    assert!(glob_match("wan0", "wan0"));
+    assert!(!glob_match("wan0", "wan00"));
+}
+
+#[cfg(test)]
+#[test]
+fn test_expand() {
+    let names: Vec<String> = ["br0", "lan1", "lan2", "lan7", "lan8", "lo", "wan0"]
+        .iter().map(|&s| s.to_owned()).collect();
+    let strings = |s: &[&str]| s.iter().map(|&s| s.to_owned()).collect::<Vec<_>>();
+
+    assert_eq!(strings(&["wan0", "lan1", "lan2"]),
+        expand(&names, &strings(&["wan*", "lan*"]), &strings(&["lan7", "lan8"])));
+    // overlapping includes don't duplicate, and exclude wins over include
+    assert_eq!(strings(&["lan1", "lan2", "lan7"]),
+        expand(&names, &strings(&["lan?", "lan*", "*1"]), &strings(&["*8"])));
+    assert_eq!(strings(&["br0", "lan1", "lan2", "lan7", "lan8", "wan0"]),
+        expand(&names, &strings(&["*"]), &strings(&["lo"])));
+    assert!(expand(&names, &strings(&["eth*"]), &[]).is_empty());
+    assert!(expand(&names, &strings(&["lan*"]), &strings(&["*"])).is_empty());
+}
+
+#[cfg(test)]
+#[test]
+fn test_select_interfaces() {
+    let dir = std::env::temp_dir().join(format!("h2-net-lcd-test-net-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let add = |name: &str, flags: &str, brport: bool| {
+        fs::create_dir_all(dir.join(name)).unwrap();
+        fs::write(dir.join(name).join("flags"), flags).unwrap();
+        if brport {
+            fs::create_dir(dir.join(name).join("brport")).unwrap();
+        }
+    };
+    add("lo", "0x9\n", false);
+    add("wan0", "0x1003\n", false);
+    add("br0", "0x1003\n", false);
+    add("lan0", "0x1003\n", true);
+    add("lan1", "0x1003\n", true);
+    add("down0", "0x1002\n", false);
+    add("eth9", "0x1003\n", false);
+    fs::create_dir(dir.join("junk")).unwrap();
+
+    assert_eq!(vec!["br0", "eth9", "wan0"], Selection::Auto.select(&dir, 6));
+    assert_eq!(vec!["br0", "eth9"], Selection::Auto.select(&dir, 2));
+
+    // patterns also match bridge ports and interfaces that are down
+    let patterns = Selection::Patterns {
+        include: vec!["lan*".to_owned(), "*0".to_owned()],
+        exclude: vec!["lo".to_owned()],
+    };
+    assert_eq!(vec!["lan0", "lan1", "br0", "down0", "wan0"], patterns.select(&dir, 6));
+    assert_eq!(vec!["lan0", "lan1", "br0"], patterns.select(&dir, 3));
+
+    // ones that have appeared since, as many as fit
+    let shown = vec!["eth9".to_owned()];
+    assert_eq!((vec!["br0".to_owned(), "wan0".to_owned()], vec![]),
+        Selection::Auto.new_ones(&dir, &shown, 6));
+    assert_eq!((vec!["br0".to_owned()], vec!["wan0".to_owned()]),
+        Selection::Auto.new_ones(&dir, &shown, 2));
+    assert_eq!((vec![], vec!["br0".to_owned(), "wan0".to_owned()]),
+        Selection::Auto.new_ones(&dir, &shown, 1));
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(Selection::Auto.select(&dir, 6).is_empty());
+}
@@ -0,0 +1,133 @@
+//! Read and write speeds of block devices, from `/proc/diskstats`.
+
+use crate::{NetSpeed, NetSpeeds};
+use std::time::Instant;
+
+pub const DISKSTATS_PATH: &str = "/proc/diskstats";
+
+/// `/proc/diskstats` counts in 512-byte sectors, whatever the device's actual sector size.
+const SECTOR_BYTES: u64 = 512;
+
+pub struct DiskStats {
+    pub name: String,
+    /// Sectors read and written as of the last sample, if the device was there.
+    sectors: Option<(u64, u64)>,
+    time: Instant,
+}
+
+impl DiskStats {
+    /// Start tracking a device. It doesn't have to exist yet, e.g. a USB drive that isn't plugged
+    /// in.
+    pub fn new(name: String, diskstats: &str) -> Self {
+        let sectors = parse(diskstats, &name);
+        Self { name, sectors, time: Instant::now() }
+    }
+
+    /// Take a sample from the contents of `/proc/diskstats`, returning the speeds since the last
+    /// one, with writes as `tx` and reads as `rx`. A device that's missing, or just came back, is
+    /// idle.
+    pub fn get_speeds(&mut self, now: Instant, diskstats: &str) -> NetSpeeds {
+        let secs = (now - self.time).as_secs_f64();
+        let sectors = parse(diskstats, &self.name);
+        let (read, written) = match (sectors, self.sectors) {
+            (Some((read, written)), Some((old_read, old_written))) => {
+                (sector_delta(read, old_read), sector_delta(written, old_written))
+            }
+            _ => (0, 0),
+        };
+        self.sectors = sectors;
+        self.time = now;
+        NetSpeeds {
+            tx: NetSpeed::from_bytes(secs, written * SECTOR_BYTES, 0),
+            rx: NetSpeed::from_bytes(secs, read * SECTOR_BYTES, 0),
+        }
+    }
+}
+
+/// Make `disks` match the given list of device names, keeping the stats of any that were already
+/// there.
+pub fn update(disks: &mut Vec<DiskStats>, names: &[String], diskstats: &str) {
+    let mut old = std::mem::take(disks);
+    for name in names {
+        let disk = match old.iter().position(|disk| &disk.name == name) {
+            Some(i) => old.swap_remove(i),
+            None => DiskStats::new(name.clone(), diskstats),
+        };
+        disks.push(disk);
+    }
+}
+
+/// Sectors read and written by a device, from the contents of `/proc/diskstats`.
+pub fn parse(diskstats: &str, name: &str) -> Option<(u64, u64)> {
+    diskstats.lines().find_map(|line| {
+        // major, minor, name, reads, reads merged, sectors read, time reading, writes, writes
+        // merged, sectors written, ...
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.get(2) != Some(&name) {
+            return None;
+        }
+        Some((fields.get(5)?.parse().ok()?, fields.get(9)?.parse().ok()?))
+    })
+}
+
+/// Sectors between two readings of a counter. The counters are only 32 bits on 32-bit kernels,
+/// so a smaller reading from a counter that fit in 32 bits wrapped around there; anything else
+/// going backwards means it was reset, e.g. the device was replaced by another with the same
+/// name, and there's no telling how much it did.
+fn sector_delta(new: u64, old: u64) -> u64 {
+    if new >= old {
+        new - old
+    } else if old <= u32::MAX as u64 {
+        new + (1 << 32) - old
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_parse() {
+    let diskstats = "\
+ 259       0 nvme0n1 1000 10 200000 300 4000 20 800000 500 0 600 800 0 0 0 0
+ 259       1 nvme0n1p1 10 0 2000 3 40 0 8000 5 0 6 8 0 0 0 0
+   8       0 sda 5 0 10 1 0 0 0 0 0 1 1
+";
+    assert_eq!(Some((200000, 800000)), parse(diskstats, "nvme0n1"));
+    assert_eq!(Some((2000, 8000)), parse(diskstats, "nvme0n1p1"));
+    assert_eq!(Some((10, 0)), parse(diskstats, "sda"));
+    assert_eq!(None, parse(diskstats, "sdb"));
+    assert_eq!(None, parse("   8 0 sdb 5 0", "sdb"));
+}
+
+#[cfg(test)]
+#[test]
+fn test_sector_delta() {
+    assert_eq!(100, sector_delta(200, 100));
+    // a 32-bit counter wrapping
+    assert_eq!(20, sector_delta(10, u32::MAX as u64 - 9));
+    // a 64-bit one being reset
+    assert_eq!(0, sector_delta(10, 1 << 40));
+}
+
+#[cfg(test)]
+#[test]
+fn test_disk_speeds() {
+    let start = Instant::now();
+    let secs = |n| start + std::time::Duration::from_secs(n);
+    let stats = |read: u64, written: u64| format!("8 0 sdb 0 0 {} 0 0 0 {} 0", read, written);
+    let mut disk = DiskStats { name: "sdb".to_owned(), sectors: None, time: start };
+
+    // the first sample after it appears is just a starting point
+    let speeds = disk.get_speeds(secs(1), &stats(1000, 0));
+    assert_eq!((0., 0.), (speeds.rx.mbps(), speeds.tx.mbps()));
+
+    // 2000 sectors read in a second is 1,024,000 bytes
+    let speeds = disk.get_speeds(secs(2), &stats(3000, 0));
+    assert_eq!(8.192, speeds.rx.mbps());
+
+    // unplugged: idle, and when it's back, its counters start over
+    let speeds = disk.get_speeds(secs(3), "");
+    assert_eq!(0., speeds.rx.mbps());
+    let speeds = disk.get_speeds(secs(4), &stats(10, 10));
+    assert_eq!((0., 0.), (speeds.rx.mbps(), speeds.tx.mbps()));
+}
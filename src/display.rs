@@ -0,0 +1,156 @@
+use crate::charset;
+
+/// Kinds of display that can be driven.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Driver {
+    /// An HD44780 character LCD on a PCF8574 I2C backpack.
+    Hd44780,
+    /// A 128x64 SSD1306 OLED, drawn with a built-in font.
+    Ssd1306,
+}
+
+/// How the gauges' custom characters look.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GlyphStyle {
+    /// Solid blocks, the full width of the character.
+    Solid,
+    /// 3 pixels wide, so there's a gap between neighbouring columns.
+    Thin,
+    /// Thin, with a full-width line marking the top.
+    Ticked,
+}
+
+impl GlyphStyle {
+    /// Rows of pixels for the top of a bar, and for the rest of it below.
+    fn rows(self) -> (u8, u8) {
+        match self {
+            GlyphStyle::Solid => (0b11111, 0b11111),
+            GlyphStyle::Thin => (0b01110, 0b01110),
+            GlyphStyle::Ticked => (0b11111, 0b01110),
+        }
+    }
+}
+
+/// The custom character for a bar `height` pixels tall (1 to 8): 8 rows of 5 pixels, top first,
+/// with the leftmost pixel in bit 4.
+pub fn bar_glyph(style: GlyphStyle, height: usize) -> [u8; 8] {
+    let (top, body) = style.rows();
+    let mut bits = [0u8; 8];
+    for (row, bits) in bits.iter_mut().enumerate().skip(8 - height) {
+        *bits = if row == 8 - height { top } else { body };
+    }
+    bits
+}
+
+impl Driver {
+    /// The addresses this kind of display can be strapped to.
+    pub fn addresses(self) -> &'static [u16] {
+        match self {
+            // 0x20 to 0x27 for the PCF8574, and 0x38 to 0x3f for the PCF8574A
+            Driver::Hd44780 => &[
+                0x27, 0x3f, 0x20, 0x21, 0x22, 0x23, 0x24, 0x25, 0x26,
+                0x38, 0x39, 0x3a, 0x3b, 0x3c, 0x3d, 0x3e,
+            ],
+            Driver::Ssd1306 => &[0x3c, 0x3d],
+        }
+    }
+}
+
+/// What the render loop needs from a display: an HD44780-style character display, with 8
+/// uploadable custom characters.
+pub trait DisplayBackend {
+    /// Move the cursor to the given column and row (0-based).
+    fn position(&mut self, col: u8, row: u8);
+
+    /// Write one character at the cursor, and advance it.
+    fn write(&mut self, c: u8);
+
+    fn print(&mut self, s: &str) {
+        for c in s.chars() {
+            self.write(charset::encode(c));
+        }
+    }
+
+    fn clear(&mut self);
+
+    /// Set custom character `location` (0 to 7) to the given bitmap: 8 rows of 5 pixels, top
+    /// first.
+    fn upload_character(&mut self, location: u8, map: [u8; 8]);
+
+    /// What the gauges' custom characters should look like.
+    fn glyphs(&self) -> GlyphStyle {
+        GlyphStyle::Solid
+    }
+
+    /// Run the controller's initialization sequence and upload the custom characters. Safe to
+    /// repeat on an already-initialized display, e.g. if it may have lost power.
+    fn reinit(&mut self);
+
+    /// Turn the backlight on or off, leaving what's on the display alone.
+    fn set_backlight(&mut self, on: bool);
+
+    /// Clear the display and upload the custom characters again, in case either got garbled
+    /// (e.g. by a nibble lost on the bus), so the next frame is drawn onto a clean slate.
+    fn redraw(&mut self) {
+        self.clear();
+        upload_bar_characters(self);
+    }
+
+    /// Whether any writes failed since the last call, so what's on the display may not be what
+    /// was written.
+    fn take_error(&mut self) -> bool {
+        false
+    }
+
+    /// Called when a whole frame has been drawn.
+    fn frame_done(&mut self) {}
+
+    /// Shut down the display: if a message is given, clear the screen and leave the message
+    /// showing, otherwise turn the display off. Then set the backlight.
+    fn stop(self: Box<Self>, message: Option<&str>, backlight: bool);
+}
+
+/// Upload the custom characters the gauges are drawn with: bars of 8 different heights, from 1
+/// pixel in character 0 to full height in character 7.
+pub fn upload_bar_characters(display: &mut (impl DisplayBackend + ?Sized)) {
+    let style = display.glyphs();
+    for i in 0 .. 8 {
+        display.upload_character(i as u8, bar_glyph(style, i + 1));
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_bar_glyph() {
+    let glyphs = |style| (1 ..= 8).map(|height| bar_glyph(style, height)).collect::<Vec<_>>();
+    assert_eq!(vec![
+        [0, 0, 0, 0, 0, 0, 0, 0x1f],
+        [0, 0, 0, 0, 0, 0, 0x1f, 0x1f],
+        [0, 0, 0, 0, 0, 0x1f, 0x1f, 0x1f],
+        [0, 0, 0, 0, 0x1f, 0x1f, 0x1f, 0x1f],
+        [0, 0, 0, 0x1f, 0x1f, 0x1f, 0x1f, 0x1f],
+        [0, 0, 0x1f, 0x1f, 0x1f, 0x1f, 0x1f, 0x1f],
+        [0, 0x1f, 0x1f, 0x1f, 0x1f, 0x1f, 0x1f, 0x1f],
+        [0x1f, 0x1f, 0x1f, 0x1f, 0x1f, 0x1f, 0x1f, 0x1f],
+    ], glyphs(GlyphStyle::Solid));
+    assert_eq!(vec![
+        [0, 0, 0, 0, 0, 0, 0, 0x0e],
+        [0, 0, 0, 0, 0, 0, 0x0e, 0x0e],
+        [0, 0, 0, 0, 0, 0x0e, 0x0e, 0x0e],
+        [0, 0, 0, 0, 0x0e, 0x0e, 0x0e, 0x0e],
+        [0, 0, 0, 0x0e, 0x0e, 0x0e, 0x0e, 0x0e],
+        [0, 0, 0x0e, 0x0e, 0x0e, 0x0e, 0x0e, 0x0e],
+        [0, 0x0e, 0x0e, 0x0e, 0x0e, 0x0e, 0x0e, 0x0e],
+        [0x0e, 0x0e, 0x0e, 0x0e, 0x0e, 0x0e, 0x0e, 0x0e],
+    ], glyphs(GlyphStyle::Thin));
+    assert_eq!(vec![
+        [0, 0, 0, 0, 0, 0, 0, 0x1f],
+        [0, 0, 0, 0, 0, 0, 0x1f, 0x0e],
+        [0, 0, 0, 0, 0, 0x1f, 0x0e, 0x0e],
+        [0, 0, 0, 0, 0x1f, 0x0e, 0x0e, 0x0e],
+        [0, 0, 0, 0x1f, 0x0e, 0x0e, 0x0e, 0x0e],
+        [0, 0, 0x1f, 0x0e, 0x0e, 0x0e, 0x0e, 0x0e],
+        [0, 0x1f, 0x0e, 0x0e, 0x0e, 0x0e, 0x0e, 0x0e],
+        [0x1f, 0x0e, 0x0e, 0x0e, 0x0e, 0x0e, 0x0e, 0x0e],
+    ], glyphs(GlyphStyle::Ticked));
+}
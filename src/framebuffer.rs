@@ -0,0 +1,117 @@
+//! A copy of what's currently on the display, so each frame only has to send the characters that
+//! changed. Every character written is several I2C transactions through the PCF8574, and most of a
+//! frame (separators, labels, idle gauges) stays the same from one to the next.
+
+use crate::display::DisplayBackend;
+
+/// A changed stretch of a row: the row, the column it starts at, and the new characters.
+pub type Run<'a> = (usize, usize, &'a [u8]);
+
+pub struct Framebuffer {
+    /// What's on the display, or None if that isn't known and the next frame has to be drawn in
+    /// full.
+    shown: Option<Vec<Vec<u8>>>,
+}
+
+impl Framebuffer {
+    pub fn new() -> Self {
+        Self { shown: None }
+    }
+
+    /// Forget what's on the display, e.g. after it was cleared or re-initialized, or a write to
+    /// it failed, so the next frame is drawn in full.
+    pub fn invalidate(&mut self) {
+        self.shown = None;
+    }
+
+    /// Draw a frame (one `Vec` per row), only writing what differs from the last one.
+    pub fn draw(&mut self, display: &mut dyn DisplayBackend, frame: Vec<Vec<u8>>) {
+        let runs = match &self.shown {
+            Some(shown) if shown.len() == frame.len() => diff(shown, &frame),
+            _ => frame.iter().enumerate().map(|(row, line)| (row, 0, &line[..])).collect(),
+        };
+        for (row, col, chars) in runs {
+            display.position(col as u8, row as u8);
+            for &c in chars {
+                display.write(c);
+            }
+        }
+        self.shown = Some(frame);
+    }
+}
+
+/// The runs of characters in `new` that differ from `old`.
+pub fn diff<'a>(old: &[Vec<u8>], new: &'a [Vec<u8>]) -> Vec<Run<'a>> {
+    let mut runs = vec![];
+    for (row, (old, new)) in old.iter().zip(new).enumerate() {
+        let mut col = 0;
+        while col < new.len() {
+            if old.get(col) == Some(&new[col]) {
+                col += 1;
+                continue;
+            }
+            let start = col;
+            while col < new.len() && old.get(col) != Some(&new[col]) {
+                col += 1;
+            }
+            runs.push((row, start, &new[start .. col]));
+        }
+    }
+    runs
+}
+
+#[cfg(test)]
+#[test]
+fn test_diff() {
+    let old = vec![b"cpu|aa| m".to_vec(), b"12/34 mem".to_vec()];
+    let new = vec![b"cpu|ab| m".to_vec(), b"99/34 meh".to_vec()];
+    assert_eq!(vec![(0, 5, &b"b"[..]), (1, 0, &b"99"[..]), (1, 8, &b"h"[..])], diff(&old, &new));
+    assert!(diff(&new, &new).is_empty());
+
+    // a longer row than before: the new part is always written
+    let longer = [b"cpu|aa| mx".to_vec()];
+    assert_eq!(vec![(0, 9, &b"x"[..])], diff(&old[.. 1], &longer));
+}
+
+#[cfg(test)]
+struct Recorder(Vec<String>);
+
+#[cfg(test)]
+impl DisplayBackend for Recorder {
+    fn position(&mut self, col: u8, row: u8) {
+        self.0.push(format!("@{},{}", col, row));
+    }
+    fn write(&mut self, c: u8) {
+        self.0.push((c as char).to_string());
+    }
+    fn clear(&mut self) {}
+    fn upload_character(&mut self, _location: u8, _map: [u8; 8]) {}
+    fn reinit(&mut self) {}
+    fn set_backlight(&mut self, _on: bool) {}
+    fn stop(self: Box<Self>, _message: Option<&str>, _backlight: bool) {}
+}
+
+#[cfg(test)]
+#[test]
+fn test_framebuffer() {
+    let mut display = Recorder(vec![]);
+    let mut fb = Framebuffer::new();
+    let frame = |a: &[u8], b: &[u8]| vec![a.to_vec(), b.to_vec()];
+
+    // the first frame is drawn in full
+    fb.draw(&mut display, frame(b"ab", b"cd"));
+    assert_eq!(vec!["@0,0", "a", "b", "@0,1", "c", "d"], display.0);
+
+    display.0.clear();
+    fb.draw(&mut display, frame(b"ab", b"ce"));
+    assert_eq!(vec!["@1,1", "e"], display.0);
+
+    display.0.clear();
+    fb.draw(&mut display, frame(b"ab", b"ce"));
+    assert!(display.0.is_empty());
+
+    // after invalidating, everything is drawn again
+    fb.invalidate();
+    fb.draw(&mut display, frame(b"ab", b"ce"));
+    assert_eq!(6, display.0.len());
+}
@@ -0,0 +1,256 @@
+//! Custom characters chosen frame by frame, for gauge cells that need more than the 8 plain bar
+//! heights: a bar with a peak-hold marker above it, or one hanging from the top, for combined
+//! network columns. There are only 8 slots, so the ones a frame needs are fitted into them,
+//! leaving alone any already holding something still needed, so as little as possible is
+//! uploaded.
+
+use crate::display::{bar_glyph, GlyphStyle};
+use std::collections::BTreeMap;
+
+/// What a gauge cell looks like.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Glyph {
+    /// Height of the bar, in pixels (0 to 8).
+    pub fill: u8,
+    /// Height of a one-pixel marker above the bar, if there is one (1 to 8).
+    pub marker: Option<u8>,
+    /// Height of a bar hanging down from the top, in pixels (0 to 8).
+    pub top: u8,
+}
+
+impl Glyph {
+    pub const fn bar(fill: u8) -> Self {
+        Self { fill, marker: None, top: 0 }
+    }
+
+    /// A bar `fill` pixels tall with another `top` pixels tall hanging over it. Ones that meet
+    /// are a full block, so there's only one glyph for each shape.
+    pub fn split(fill: u8, top: u8) -> Self {
+        if fill + top >= 8 {
+            Self::bar(8)
+        } else {
+            Self { top, ..Self::bar(fill) }
+        }
+    }
+
+    /// The glyph for a character the gauges draw with: a space, or one of the plain bars in
+    /// custom characters 0 to 7 as `upload_bar_characters` leaves them. None for anything else.
+    pub fn from_char(c: u8) -> Option<Self> {
+        match c {
+            b' ' => Some(Self::bar(0)),
+            0 ..= 7 => Some(Self::bar(c + 1)),
+            _ => None,
+        }
+    }
+
+    /// The custom character's bitmap.
+    pub fn bitmap(self, style: GlyphStyle) -> [u8; 8] {
+        let mut bits = if self.fill > 0 { bar_glyph(style, self.fill as usize) } else { [0; 8] };
+        // full width whatever the style, to stand out from the bars
+        if let Some(height) = self.marker {
+            bits[8 - height as usize] = 0b11111;
+        }
+        // the same as a bar, upside down
+        if self.top > 0 {
+            let top = bar_glyph(style, self.top as usize);
+            for (bits, top) in bits.iter_mut().zip(top.iter().rev()) {
+                *bits |= top;
+            }
+        }
+        bits
+    }
+
+    /// Which rows of pixels are lit, as bits from the top row in bit 0.
+    fn rows(self) -> u8 {
+        let bottom = (0xff00u16 >> self.fill) as u8;
+        let top = (0xffu16 >> (8 - self.top)) as u8;
+        let marker = self.marker.map_or(0, |height| 1 << (8 - height));
+        bottom | top | marker
+    }
+
+    /// How many rows of pixels differ between two glyphs.
+    fn distance(self, other: Self) -> u32 {
+        (self.rows() ^ other.rows()).count_ones()
+    }
+}
+
+/// What's uploaded in each custom character.
+pub type Slots = [Glyph; 8];
+
+/// The plain bars, as `upload_bar_characters` uploads them.
+pub const BAR_SLOTS: Slots = [
+    Glyph::bar(1), Glyph::bar(2), Glyph::bar(3), Glyph::bar(4),
+    Glyph::bar(5), Glyph::bar(6), Glyph::bar(7), Glyph::bar(8),
+];
+
+/// Where a peak-hold marker at `peak` (0 to 1) goes on a gauge `rows` tall showing `value`: the
+/// row (0 = top), and its height within that row's character (1 to 8). None if it's within the
+/// bar, or at the bottom.
+pub fn marker_cell(value: f64, peak: f64, rows: u8) -> Option<(u8, u8)> {
+    let pixels = |v: f64| {
+        let v = if v.is_nan() { 0. } else { v.clamp(0., 1.) };
+        (v * (rows * 8) as f64).ceil() as u8
+    };
+    let peak = pixels(peak);
+    if peak == 0 || peak <= pixels(value) {
+        return None;
+    }
+    Some((rows - 1 - (peak - 1) / 8, (peak - 1) % 8 + 1))
+}
+
+/// Fit the glyphs a frame's cells want into the 8 custom characters, given what's in them now.
+/// Returns what should be in them, and the character to write for each cell. If more than 8
+/// shapes are wanted, markers are left off until they fit: first ones that leave a bar that's
+/// wanted anyway, so that leaving them off saves a character, then the least used. After that,
+/// the least used bars hanging from the top are shown as whichever shape left is closest.
+pub fn allocate(wanted: &[Glyph], slots: &Slots) -> (Slots, Vec<u8>) {
+    let mut counts = BTreeMap::new();
+    for &glyph in wanted {
+        *counts.entry(glyph).or_insert(0) += 1;
+    }
+    counts.remove(&Glyph::bar(0));
+    // what's shown in place of each glyph that didn't fit
+    let mut replaced = BTreeMap::new();
+    while counts.len() > 8 {
+        let marked = counts.iter()
+            .filter(|(glyph, _)| glyph.marker.is_some())
+            .min_by_key(|&(glyph, &count)| {
+                let bar = Glyph { marker: None, ..*glyph };
+                (bar != Glyph::bar(0) && !counts.contains_key(&bar), count)
+            });
+        let (glyph, with) = match marked {
+            Some((&glyph, _)) => (glyph, Glyph { marker: None, ..glyph }),
+            None => {
+                let (&glyph, _) = counts.iter()
+                    .filter(|(glyph, _)| glyph.top > 0)
+                    .min_by_key(|&(_, &count)| count)
+                    .expect("only 8 plain bars");
+                let closest = std::iter::once(Glyph::bar(0))
+                    .chain(counts.keys().copied())
+                    .filter(|&other| other != glyph)
+                    .min_by_key(|&other| glyph.distance(other))
+                    .unwrap();
+                (glyph, closest)
+            }
+        };
+        let count = counts.remove(&glyph).unwrap();
+        if with != Glyph::bar(0) {
+            *counts.entry(with).or_insert(0) += count;
+        }
+        replaced.insert(glyph, with);
+    }
+
+    // what's already uploaded stays put, and the rest goes in the slots no longer needed
+    let mut new = *slots;
+    let mut free = (0 .. 8).filter(|&i| !counts.contains_key(&slots[i]));
+    for &glyph in counts.keys() {
+        if !slots.contains(&glyph) {
+            new[free.next().unwrap()] = glyph;
+        }
+    }
+
+    let chars = wanted.iter()
+        .map(|&glyph| {
+            let mut glyph = glyph;
+            while let Some(&with) = replaced.get(&glyph) {
+                glyph = with;
+            }
+            match new.iter().position(|&slot| slot == glyph) {
+                Some(i) => i as u8,
+                None => b' ',
+            }
+        })
+        .collect();
+    (new, chars)
+}
+
+#[cfg(test)]
+#[test]
+fn test_glyph_bitmap() {
+    let marked = Glyph { marker: Some(5), ..Glyph::bar(2) };
+    assert_eq!([0, 0, 0, 0x1f, 0, 0, 0x1f, 0x1f], marked.bitmap(GlyphStyle::Solid));
+    assert_eq!([0, 0, 0, 0x1f, 0, 0, 0x0e, 0x0e], marked.bitmap(GlyphStyle::Thin));
+    assert_eq!([0x1f, 0, 0, 0, 0, 0, 0, 0],
+        Glyph { marker: Some(8), ..Glyph::bar(0) }.bitmap(GlyphStyle::Thin));
+    assert_eq!(bar_glyph(GlyphStyle::Ticked, 3), Glyph::bar(3).bitmap(GlyphStyle::Ticked));
+    assert_eq!(Some(Glyph::bar(8)), Glyph::from_char(7));
+    assert_eq!(Some(Glyph::bar(0)), Glyph::from_char(b' '));
+    assert_eq!(None, Glyph::from_char(b'!'));
+
+    // hanging from the top
+    let split = Glyph::split(3, 2);
+    assert_eq!([0x1f, 0x1f, 0, 0, 0, 0x1f, 0x1f, 0x1f], split.bitmap(GlyphStyle::Solid));
+    assert_eq!([0x0e, 0x1f, 0, 0, 0, 0x1f, 0x0e, 0x0e], split.bitmap(GlyphStyle::Ticked));
+    assert_eq!(Glyph::bar(8), Glyph::split(4, 4));
+    assert_eq!(Glyph::bar(8), Glyph::split(0, 8));
+    assert_eq!(Glyph::bar(5), Glyph::split(5, 0));
+    assert_eq!(0b1110_0011, split.rows());
+    assert_eq!(2, split.distance(Glyph::bar(3)));
+}
+
+#[cfg(test)]
+#[test]
+fn test_marker_cell() {
+    // 3 rows of 8 pixels: the peak at 20 pixels is 4 up in the top row
+    assert_eq!(Some((0, 4)), marker_cell(0.1, 20. / 24., 3));
+    assert_eq!(Some((2, 8)), marker_cell(0., 8. / 24., 3));
+    assert_eq!(Some((1, 1)), marker_cell(0., 9. / 24., 3));
+    // within the bar, or nothing to mark
+    assert_eq!(None, marker_cell(0.5, 0.5, 3));
+    assert_eq!(None, marker_cell(0.6, 0.5, 3));
+    assert_eq!(None, marker_cell(0., 0., 3));
+    assert_eq!(None, marker_cell(f64::NAN, f64::NAN, 3));
+}
+
+#[cfg(test)]
+#[test]
+fn test_allocate() {
+    let marked = |fill, marker| Glyph { marker: Some(marker), ..Glyph::bar(fill) };
+
+    // plain bars stay where upload_bar_characters put them
+    let wanted = [Glyph::bar(0), Glyph::bar(3), Glyph::bar(8), Glyph::bar(3)];
+    assert_eq!((BAR_SLOTS, vec![b' ', 2, 7, 2]), allocate(&wanted, &BAR_SLOTS));
+
+    // a marker takes the slot of a bar that isn't needed
+    let wanted = [Glyph::bar(8), marked(2, 6), marked(0, 3)];
+    let (slots, chars) = allocate(&wanted, &BAR_SLOTS);
+    assert_eq!(vec![7, 1, 0], chars);
+    assert_eq!([marked(0, 3), marked(2, 6)], slots[.. 2]);
+    assert_eq!(BAR_SLOTS[2 ..], slots[2 ..]);
+
+    // and keeps it in the next frame, while the bar it pushed out comes back elsewhere
+    let wanted = [marked(0, 3), Glyph::bar(1)];
+    let (again, chars) = allocate(&wanted, &slots);
+    assert_eq!(vec![0, 1], chars);
+    assert_eq!(marked(0, 3), again[0]);
+    assert_eq!(Glyph::bar(1), again[1]);
+
+    // too many shapes: markers go, leaving their bars, first where that saves a character
+    let mut wanted: Vec<Glyph> = (1 ..= 7).map(Glyph::bar).collect();
+    wanted.extend([marked(8, 1), marked(2, 5), marked(2, 5), marked(0, 4)]);
+    let (slots, chars) = allocate(&wanted, &BAR_SLOTS);
+    assert_eq!(b' ', chars[10]);
+    assert_eq!(chars[1], chars[8]);
+    assert_eq!(chars[1], chars[9]);
+    for (&glyph, &c) in wanted.iter().zip(&chars).take(8) {
+        assert_eq!(glyph, slots[c as usize]);
+    }
+
+    // and otherwise the least used, which can make leaving off others save one
+    let mut wanted: Vec<Glyph> = (1 ..= 6).map(Glyph::bar).collect();
+    wanted.extend([marked(8, 1), marked(7, 1), marked(7, 1), marked(8, 2), marked(8, 2)]);
+    let (slots, chars) = allocate(&wanted, &BAR_SLOTS);
+    assert_eq!(Glyph::bar(8), slots[chars[6] as usize]);
+    assert_eq!(vec![chars[6], chars[6]], chars[9 ..]);
+    assert_eq!(marked(7, 1), slots[chars[7] as usize]);
+
+    // bars hanging from the top that don't fit are shown as the closest shape there's room for
+    let mut wanted: Vec<Glyph> = (1 ..= 7).map(Glyph::bar).collect();
+    wanted.extend([Glyph::split(0, 3), Glyph::split(0, 3), Glyph::split(2, 2), Glyph::split(0, 1)]);
+    let (slots, chars) = allocate(&wanted, &BAR_SLOTS);
+    assert_eq!(BAR_SLOTS[.. 7], slots[.. 7]);
+    assert_eq!(Glyph::split(0, 3), slots[7]);
+    // one row off from a 2-pixel bar, and from nothing at all
+    assert_eq!(chars[1], chars[9]);
+    assert_eq!(b' ', chars[10]);
+}
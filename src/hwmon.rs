@@ -0,0 +1,248 @@
+//! Temperature sensors and fans from the kernel's hwmon drivers, for when `cpu_temp` picks the
+//! wrong sensor, or there's more than one thing worth watching.
+
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const HWMON_DIR: &str = "/sys/class/hwmon";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    /// Read in millidegrees, and given in °C.
+    Temp,
+    /// Read and given in RPM.
+    Fan,
+}
+
+impl Kind {
+    /// What its files start with, like `temp1_input`.
+    fn prefix(self) -> &'static str {
+        match self {
+            Kind::Temp => "temp",
+            Kind::Fan => "fan",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sensor {
+    pub kind: Kind,
+    /// The driver's name for the device, like `k10temp` or `nvme`.
+    pub name: String,
+    /// What this sensor measures, like `Tctl`, if the driver says.
+    pub label: Option<String>,
+    /// The file its reading is in.
+    input: PathBuf,
+}
+
+impl Sensor {
+    /// Whether this is the sensor a `temperatures` or `fans` entry asks for: its label, its
+    /// device's name (for the device's first sensor of its kind), or both, like `k10temp/Tccd1`.
+    fn matches(&self, spec: &str) -> bool {
+        match (spec.split_once('/'), &self.label) {
+            (Some((name, label)), Some(own)) => name == self.name && label == own,
+            (Some(_), None) => false,
+            (None, label) => spec == self.name || label.as_deref() == Some(spec),
+        }
+    }
+
+    /// The temperature in °C or the fan speed in RPM, or None if it can't be read, e.g. because
+    /// the device is asleep.
+    pub fn read(&self) -> Option<f64> {
+        let value: f64 = fs::read_to_string(&self.input).ok()?.trim().parse().ok()?;
+        match self.kind {
+            Kind::Temp => Some(value / 1000.),
+            Kind::Fan => Some(value),
+        }
+    }
+}
+
+impl std::fmt::Display for Sensor {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self.label {
+            Some(label) => write!(f, "{}/{}", self.name, label),
+            None => f.write_str(&self.name),
+        }
+    }
+}
+
+/// Every sensor of a kind under `dir` (normally HWMON_DIR), in order of device, then sensor
+/// number.
+pub fn list(dir: &Path, kind: Kind) -> Vec<Sensor> {
+    let mut devices: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|entry| Some(entry.ok()?.path())).collect(),
+        Err(_) => return vec![],
+    };
+    // hwmon2 before hwmon10
+    devices.sort_by_key(|path| (path.as_os_str().len(), path.clone()));
+    let mut sensors = vec![];
+    for device in devices {
+        let name = match fs::read_to_string(device.join("name")) {
+            Ok(name) => name.trim().to_owned(),
+            Err(_) => continue,
+        };
+        let mut numbers: Vec<u32> = match fs::read_dir(&device) {
+            Ok(entries) => entries
+                .filter_map(|entry| {
+                    let file = entry.ok()?.file_name();
+                    let file = file.to_str()?.strip_prefix(kind.prefix())?;
+                    file.strip_suffix("_input")?.parse().ok()
+                })
+                .collect(),
+            Err(_) => continue,
+        };
+        numbers.sort_unstable();
+        for n in numbers {
+            let file = |suffix| device.join(format!("{}{}_{}", kind.prefix(), n, suffix));
+            let label = fs::read_to_string(file("label"))
+                .ok()
+                .map(|label| label.trim().to_owned());
+            sensors.push(Sensor { kind, name: name.clone(), label, input: file("input") });
+        }
+    }
+    sensors
+}
+
+/// The sensor each of `specs` asks for (see `Sensor::matches`), if there is one.
+pub fn find(sensors: &[Sensor], specs: &[String]) -> Vec<Option<Sensor>> {
+    specs.iter()
+        .map(|spec| sensors.iter().find(|sensor| sensor.matches(spec)).cloned())
+        .collect()
+}
+
+/// Where the CPU temperature comes from, when there are no `temperatures` to stand in for it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CpuTemp {
+    /// Wherever systemstat finds it: the first thermal zone.
+    Auto,
+    /// Nowhere: it's left out.
+    Off,
+    /// A file with a reading in millidegrees, like a thermal zone's `temp` or a sensor's
+    /// `temp1_input`.
+    File(PathBuf),
+}
+
+impl CpuTemp {
+    pub fn parse(s: &str) -> Result<Self> {
+        Ok(match s {
+            "auto" => CpuTemp::Auto,
+            "off" => CpuTemp::Off,
+            _ if s.starts_with('/') => CpuTemp::File(PathBuf::from(s)),
+            _ => bail!("should be \"auto\", \"off\", or the full path of a file to read it from, \
+                not {:?}", s),
+        })
+    }
+}
+
+/// Read a temperature in °C from a file with a reading in millidegrees.
+pub fn read_temp(path: &Path) -> Result<f64> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let millidegrees: f64 = text.trim().parse()
+        .with_context(|| format!("no temperature in {}", path.display()))?;
+    Ok(millidegrees / 1000.)
+}
+
+/// Where a reading falls on a gauge from `min` to `max`.
+pub fn fraction(value: f64, min: f64, max: f64) -> f64 {
+    ((value - min) / (max - min)).clamp(0., 1.)
+}
+
+/// A fan speed in 4 columns or less, like `850` or `2.1k`.
+pub fn format_rpm(rpm: f64) -> String {
+    if rpm < 999.5 {
+        format!("{:.0}", rpm.max(0.))
+    } else {
+        format!("{:.1}k", (rpm / 1000.).min(99.9))
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_sensors() {
+    let dir = std::env::temp_dir().join(format!("h2-net-lcd-test-hwmon-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let device = |n: u32, name: &str, sensors: &[(u32, Option<&str>, &str)]| {
+        let device = dir.join(format!("hwmon{}", n));
+        fs::create_dir_all(&device).unwrap();
+        fs::write(device.join("name"), format!("{}\n", name)).unwrap();
+        for &(i, label, input) in sensors {
+            fs::write(device.join(format!("temp{}_input", i)), input).unwrap();
+            if let Some(label) = label {
+                fs::write(device.join(format!("temp{}_label", i)), format!("{}\n", label))
+                    .unwrap();
+            }
+        }
+    };
+    device(10, "k10temp", &[(3, Some("Tccd1"), "48250\n"), (1, Some("Tctl"), "51000\n")]);
+    device(2, "nvme", &[(1, Some("Composite"), "38850\n")]);
+    device(3, "cpu_thermal", &[(1, None, "")]);
+    let fans = dir.join("hwmon4");
+    fs::create_dir_all(&fans).unwrap();
+    fs::write(fans.join("name"), "nct6775\n").unwrap();
+    fs::write(fans.join("fan2_input"), "2100\n").unwrap();
+    fs::write(fans.join("fan2_label"), "CHA_FAN\n").unwrap();
+    fs::write(fans.join("fan1_input"), "0\n").unwrap();
+
+    let sensors = list(&dir, Kind::Temp);
+    let names: Vec<String> = sensors.iter().map(|sensor| sensor.to_string()).collect();
+    assert_eq!(vec!["nvme/Composite", "cpu_thermal", "k10temp/Tctl", "k10temp/Tccd1"], names);
+
+    let specs: Vec<String> = ["Tctl", "k10temp/Tccd1", "nvme", "cpu_thermal", "acpitz"].iter()
+        .map(|&s| s.to_owned())
+        .collect();
+    let found = find(&sensors, &specs);
+    let readings: Vec<Option<f64>> =
+        found.iter().map(|sensor| sensor.as_ref().and_then(Sensor::read)).collect();
+    // cpu_thermal can't be read, and there's no acpitz
+    assert_eq!(vec![Some(51.), Some(48.25), Some(38.85), None, None], readings);
+    assert!(found[3].is_some());
+    assert!(found[4].is_none());
+
+    let fans = list(&dir, Kind::Fan);
+    let found = find(&fans, &["nct6775".to_owned(), "CHA_FAN".to_owned(), "Tctl".to_owned()]);
+    let readings: Vec<Option<f64>> =
+        found.iter().map(|sensor| sensor.as_ref().and_then(Sensor::read)).collect();
+    assert_eq!(vec![Some(0.), Some(2100.), None], readings);
+
+    // a sensor's file can stand in for the CPU temperature
+    let input = dir.join("hwmon10/temp1_input");
+    assert_eq!(51., read_temp(&input).unwrap());
+    assert_eq!(format!("no temperature in {}: cannot parse float from empty string",
+        dir.join("hwmon3/temp1_input").display()),
+        format!("{:#}", read_temp(&dir.join("hwmon3/temp1_input")).unwrap_err()));
+
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(list(&dir, Kind::Temp).is_empty());
+    assert!(read_temp(&input).unwrap_err().to_string().starts_with("failed to read "));
+}
+
+#[cfg(test)]
+#[test]
+fn test_cpu_temp_parse() {
+    assert_eq!(CpuTemp::Off, CpuTemp::parse("off").unwrap());
+    assert_eq!(CpuTemp::File(PathBuf::from("/sys/class/thermal/thermal_zone1/temp")),
+        CpuTemp::parse("/sys/class/thermal/thermal_zone1/temp").unwrap());
+    assert_eq!("should be \"auto\", \"off\", or the full path of a file to read it from, not \
+        \"thermal_zone1\"", CpuTemp::parse("thermal_zone1").unwrap_err().to_string());
+}
+
+#[cfg(test)]
+#[test]
+fn test_fraction() {
+    assert_eq!(0., fraction(20., 30., 90.));
+    assert_eq!(0.5, fraction(60., 30., 90.));
+    assert_eq!(1., fraction(95., 30., 90.));
+}
+
+#[cfg(test)]
+#[test]
+fn test_format_rpm() {
+    assert_eq!("0", format_rpm(0.));
+    assert_eq!("850", format_rpm(850.));
+    assert_eq!("999", format_rpm(999.4));
+    assert_eq!("1.0k", format_rpm(999.5));
+    assert_eq!("2.1k", format_rpm(2100.));
+    assert_eq!("99.9k", format_rpm(250_000.));
+}
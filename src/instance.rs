@@ -0,0 +1,162 @@
+use anyhow::{bail, Context, Result};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Directory the lock files go in.
+pub const LOCK_DIR: &str = "/run";
+
+/// Held for as long as this process owns the display. The lock is an flock on the file, so it's
+/// released by the kernel however the process exits; the pid written in it is only informational.
+pub struct InstanceLock {
+    _file: File,
+}
+
+/// Lock file path for a given display, so different displays don't conflict.
+pub fn lock_path(dir: &Path, bus: u8, addr: u16) -> PathBuf {
+    dir.join(format!("h2-net-lcd.i2c-{}-{:#x}.lock", bus, addr))
+}
+
+/// Try to take the lock at the given path.
+pub fn acquire(path: &Path) -> Result<InstanceLock> {
+    match try_acquire(path)? {
+        Ok(lock) => Ok(lock),
+        Err(Some(pid)) => bail!("another instance (pid {}) is already using this display; \
+            stop it or use --takeover", pid),
+        Err(None) => bail!("another instance is already using this display (lock file {:?}); \
+            stop it or use --takeover", path),
+    }
+}
+
+/// Take the lock, asking the current holder (if any) to exit with `signal` and waiting up to
+/// `timeout` for it to release the lock.
+pub fn takeover(
+    path: &Path,
+    timeout: Duration,
+    mut signal: impl FnMut(i32) -> Result<()>,
+) -> Result<InstanceLock> {
+    let start = Instant::now();
+    let mut signalled = false;
+    loop {
+        match try_acquire(path)? {
+            Ok(lock) => return Ok(lock),
+            Err(Some(pid)) if !signalled => {
+                eprintln!("taking over from existing instance (pid {})", pid);
+                signal(pid)?;
+                signalled = true;
+            }
+            Err(None) if !signalled => {
+                bail!("lock file {:?} is held but doesn't say which process holds it", path);
+            }
+            Err(_) => (),
+        }
+        if start.elapsed() > timeout {
+            bail!("timed out waiting for the existing instance to exit");
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+}
+
+/// Send SIGTERM to the given process.
+pub fn terminate(pid: i32) -> Result<()> {
+    // Safety: plain syscall with no pointer arguments.
+    if unsafe { libc::kill(pid, libc::SIGTERM) } != 0 {
+        return Err(std::io::Error::last_os_error())
+            .with_context(|| format!("failed to signal pid {}", pid));
+    }
+    Ok(())
+}
+
+/// On success, returns the lock. If it's held by someone else, returns the pid in the lock file,
+/// if it contains a sensible one.
+fn try_acquire(path: &Path) -> Result<std::result::Result<InstanceLock, Option<i32>>> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .with_context(|| format!("failed to open lock file {:?}", path))?;
+
+    // Safety: plain syscall on a file descriptor we own.
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+        let err = std::io::Error::last_os_error();
+        if err.raw_os_error() != Some(libc::EWOULDBLOCK) {
+            return Err(err).with_context(|| format!("failed to lock {:?}", path));
+        }
+        let mut contents = String::new();
+        let pid = file.read_to_string(&mut contents).ok()
+            .and_then(|_| contents.trim().parse::<i32>().ok())
+            .filter(|&pid| pid > 0);
+        return Ok(Err(pid));
+    }
+
+    // Whatever was in the file is from a previous instance that's gone now.
+    file.set_len(0)
+        .and_then(|()| file.seek(SeekFrom::Start(0)))
+        .and_then(|_| writeln!(file, "{}", std::process::id()))
+        .with_context(|| format!("failed to write pid to {:?}", path))?;
+
+    Ok(Ok(InstanceLock { _file: file }))
+}
+
+#[cfg(test)]
+fn test_lock_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir()
+        .join(format!("h2-net-lcd-test-{}-{}.lock", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+#[cfg(test)]
+#[test]
+fn test_instance_lock() {
+    let path = test_lock_path("lock");
+
+    let lock = acquire(&path).unwrap();
+    let pid = std::process::id().to_string();
+    assert_eq!(pid, std::fs::read_to_string(&path).unwrap().trim());
+
+    // flock is per open file, so this conflicts even within one process
+    let err = acquire(&path).err().unwrap().to_string();
+    assert!(err.contains(&format!("pid {}", pid)), "{}", err);
+
+    drop(lock);
+    let _lock = acquire(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(test)]
+#[test]
+fn test_instance_lock_stale() {
+    let path = test_lock_path("stale");
+
+    // left over from a previous instance, with a longer pid than ours
+    std::fs::write(&path, "99999999999\nmore junk\n").unwrap();
+    let _lock = acquire(&path).unwrap();
+    assert_eq!(format!("{}\n", std::process::id()), std::fs::read_to_string(&path).unwrap());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(test)]
+#[test]
+fn test_instance_takeover() {
+    let path = test_lock_path("takeover");
+    let mut old = Some(acquire(&path).unwrap());
+
+    let mut signalled = vec![];
+    let _lock = takeover(&path, Duration::from_secs(5), |pid| {
+        signalled.push(pid);
+        old.take(); // the old instance exits
+        Ok(())
+    }).unwrap();
+    assert_eq!(vec![std::process::id() as i32], signalled);
+
+    // nobody releases it this time
+    let err = takeover(&path, Duration::from_millis(300), |_| Ok(())).err().unwrap();
+    assert!(err.to_string().contains("timed out"), "{}", err);
+    std::fs::remove_file(&path).unwrap();
+}
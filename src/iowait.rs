@@ -0,0 +1,66 @@
+//! Time spent waiting on disk, which the kernel counts apart from both busy and idle time. A core
+//! stuck waiting on a thrashing disk isn't doing any work, so whether that looks busy is a choice.
+
+use anyhow::{bail, Result};
+
+/// What to make of a core's iowait.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Iowait {
+    /// Count it as busy, like it always was.
+    Busy,
+    /// Count it as idle.
+    Idle,
+    /// Show it stacked on top of the busy part of each CPU column, in `HATCH`.
+    Distinct,
+}
+
+impl Iowait {
+    pub fn parse(s: &str) -> Result<Self> {
+        Ok(match s {
+            "busy" => Iowait::Busy,
+            "idle" => Iowait::Idle,
+            "distinct" => Iowait::Distinct,
+            _ => bail!("should be \"busy\", \"idle\", or \"distinct\", not {:?}", s),
+        })
+    }
+}
+
+/// What marks iowait in a CPU column. All 8 custom characters are taken by the bars, so it's an
+/// ordinary one, and only fills whole rows.
+pub const HATCH: u8 = b'#';
+
+/// How much of the time a core (or a column of them) was busy, and waiting on disk besides.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct CoreLoad {
+    pub busy: f64,
+    pub iowait: f64,
+}
+
+impl CoreLoad {
+    /// From the fractions of the time a core was idle and waiting on disk, which don't overlap.
+    pub fn new(idle: f64, iowait: f64, mode: Iowait) -> Self {
+        let busy = (1. - idle - iowait).max(0.);
+        match mode {
+            Iowait::Busy => Self { busy: busy + iowait, iowait: 0. },
+            Iowait::Idle => Self { busy, iowait: 0. },
+            Iowait::Distinct => Self { busy, iowait },
+        }
+    }
+
+    /// The whole height of the column.
+    pub fn total(self) -> f64 {
+        self.busy + self.iowait
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_core_load() {
+    assert_eq!(CoreLoad { busy: 0.75, iowait: 0. }, CoreLoad::new(0.25, 0.5, Iowait::Busy));
+    assert_eq!(CoreLoad { busy: 0.25, iowait: 0. }, CoreLoad::new(0.25, 0.5, Iowait::Idle));
+    assert_eq!(CoreLoad { busy: 0.25, iowait: 0.5 }, CoreLoad::new(0.25, 0.5, Iowait::Distinct));
+    assert_eq!(0.75, CoreLoad::new(0.25, 0.5, Iowait::Distinct).total());
+    assert_eq!(Iowait::Distinct, Iowait::parse("distinct").unwrap());
+    assert_eq!("should be \"busy\", \"idle\", or \"distinct\", not \"wait\"",
+        Iowait::parse("wait").unwrap_err().to_string());
+}
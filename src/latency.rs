@@ -0,0 +1,83 @@
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Upper bounds (exclusive) of each histogram bucket, in microseconds. There's one more bucket for
+/// everything above the last bound.
+const BOUNDS_US: [u64; 4] = [1_000, 2_000, 5_000, 10_000];
+const LABELS: [&str; 5] = ["<1ms", "<2ms", "<5ms", "<10ms", ">=10ms"];
+
+/// Latency of each I2C transaction to the display. A bus or backpack that's going bad tends to get
+/// slower for a while before it fails outright.
+pub static I2C_WRITES: Histogram = Histogram::new();
+
+/// A fixed-bucket latency histogram, cheap enough to update on every write.
+pub struct Histogram {
+    buckets: [AtomicU64; 5],
+}
+
+impl Histogram {
+    pub const fn new() -> Self {
+        Self {
+            buckets: [
+                AtomicU64::new(0),
+                AtomicU64::new(0),
+                AtomicU64::new(0),
+                AtomicU64::new(0),
+                AtomicU64::new(0),
+            ],
+        }
+    }
+
+    pub fn record(&self, latency: Duration) {
+        let us = latency.as_micros() as u64;
+        let idx = BOUNDS_US.iter().position(|&bound| us < bound).unwrap_or(BOUNDS_US.len());
+        self.buckets[idx].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn counts(&self) -> [u64; 5] {
+        let mut counts = [0; 5];
+        for (count, bucket) in counts.iter_mut().zip(&self.buckets) {
+            *count = bucket.load(Ordering::Relaxed);
+        }
+        counts
+    }
+}
+
+/// Describe the difference between two sets of histogram counts, or None if nothing was recorded
+/// in between.
+pub fn summary(now: &[u64; 5], before: &[u64; 5]) -> Option<String> {
+    let deltas: Vec<u64> = now.iter().zip(before).map(|(n, b)| n.saturating_sub(*b)).collect();
+    let total: u64 = deltas.iter().sum();
+    if total == 0 {
+        return None;
+    }
+    let mut s = format!("{} writes:", total);
+    for (label, count) in LABELS.iter().zip(&deltas) {
+        write!(&mut s, " {} {:.1}%", label, *count as f64 * 100. / total as f64).unwrap();
+    }
+    Some(s)
+}
+
+#[cfg(test)]
+#[test]
+fn test_histogram() {
+    let hist = Histogram::new();
+    hist.record(Duration::from_micros(300));
+    hist.record(Duration::from_micros(999));
+    hist.record(Duration::from_micros(1000));
+    hist.record(Duration::from_micros(4999));
+    hist.record(Duration::from_millis(10));
+    hist.record(Duration::from_secs(3));
+    assert_eq!([2, 1, 1, 0, 2], hist.counts());
+
+    let before = hist.counts();
+    assert_eq!(None, summary(&hist.counts(), &before));
+    for _ in 0 .. 3 {
+        hist.record(Duration::from_micros(500));
+    }
+    hist.record(Duration::from_millis(20));
+    assert_eq!(
+        Some("4 writes: <1ms 75.0% <2ms 0.0% <5ms 0.0% <10ms 0.0% >=10ms 25.0%".to_owned()),
+        summary(&hist.counts(), &before));
+}
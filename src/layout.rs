@@ -0,0 +1,1135 @@
+//! Arrangement of the display: which gauge panels appear across the top rows and in what order,
+//! and what the status row at the bottom says.
+
+use anyhow::{bail, Result};
+use crate::charset;
+use crate::ping::Ping;
+use crate::totals::human_bytes;
+use crate::units::{format_rate, Units};
+use crate::ups::Reading;
+use std::fmt::Write;
+use std::net::IpAddr;
+use std::time::Duration;
+
+/// Size of the display, in characters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Geometry {
+    pub cols: usize,
+    pub rows: usize,
+}
+
+impl Geometry {
+    /// The 20x4 display sold by Hardkernel.
+    pub const DEFAULT: Geometry = Geometry { cols: 20, rows: 4 };
+
+    /// Check it's a size the display controller can be driven at. HD44780 displays with 4 rows
+    /// are addressed as if they were 2 rows of double width, which only works out at 20 columns.
+    pub fn validate(&self) -> Result<()> {
+        if self.rows != 2 && self.rows != 4 {
+            bail!("the display must have 2 or 4 rows, not {}", self.rows);
+        }
+        if self.cols < 16 || self.cols > 40 {
+            bail!("the display must have 16 to 40 columns, not {}", self.cols);
+        }
+        if self.rows == 4 && self.cols != 20 {
+            bail!("4-row displays must have 20 columns, not {}", self.cols);
+        }
+        Ok(())
+    }
+}
+
+/// A group of gauge columns.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Panel {
+    /// One column per CPU (or group of CPUs).
+    Cpu,
+    /// Transmit and receive columns for each network interface, or one combined column.
+    Net,
+    /// One column for memory usage.
+    Mem,
+    /// Write and read columns for each disk.
+    Disk,
+    /// One column for how full each filesystem is.
+    Fs,
+    /// One column for the signal level of each wireless interface.
+    Wifi,
+    /// One column for how full the connection tracking table is.
+    Conntrack,
+    /// One column for ping round trip time.
+    Ping,
+    /// One column for each temperature sensor.
+    Temp,
+    /// One column for each fan's speed.
+    Fan,
+    /// One column for the UPS's battery charge.
+    Ups,
+    /// Total network throughput over the last several minutes, one column per slice of it.
+    Sparkline,
+}
+
+/// How the network panel shows each interface.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NetColumns {
+    /// A column for transmit and one for receive.
+    Split,
+    /// One column, with receive filling up from the bottom and transmit down from the top, each
+    /// over half of it.
+    Combined,
+}
+
+impl NetColumns {
+    pub fn parse(s: &str) -> Result<Self> {
+        Ok(match s {
+            "split" => NetColumns::Split,
+            "combined" => NetColumns::Combined,
+            _ => bail!("should be \"split\" or \"combined\", not {:?}", s),
+        })
+    }
+
+    /// Columns each interface takes.
+    pub fn per_interface(self) -> usize {
+        match self {
+            NetColumns::Split => 2,
+            NetColumns::Combined => 1,
+        }
+    }
+}
+
+const PANEL_NAMES: &[&str] =
+    &["cpu", "net", "mem", "disk", "fs", "wifi", "conntrack", "ping", "temp", "fan", "ups",
+        "sparkline"];
+
+impl Panel {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "cpu" => Some(Panel::Cpu),
+            "net" => Some(Panel::Net),
+            "mem" => Some(Panel::Mem),
+            "disk" => Some(Panel::Disk),
+            "fs" => Some(Panel::Fs),
+            "wifi" => Some(Panel::Wifi),
+            "conntrack" => Some(Panel::Conntrack),
+            "ping" => Some(Panel::Ping),
+            "temp" => Some(Panel::Temp),
+            "fan" => Some(Panel::Fan),
+            "ups" => Some(Panel::Ups),
+            "sparkline" => Some(Panel::Sparkline),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        PANEL_NAMES[self as usize]
+    }
+}
+
+/// The original layout: CPUs, then network, then memory.
+pub const DEFAULT_PANELS: &[Panel] = &[Panel::Cpu, Panel::Net, Panel::Mem];
+
+/// Parse a list of panel names, rejecting unknown and repeated ones.
+pub fn parse_panels(names: &[String]) -> Result<Vec<Panel>> {
+    if names.is_empty() {
+        bail!("there must be at least one panel");
+    }
+    let mut panels = vec![];
+    for name in names {
+        let panel = match Panel::from_name(name) {
+            Some(panel) => panel,
+            None => match crate::config::suggest(name, PANEL_NAMES) {
+                Some(suggestion) => {
+                    bail!("unknown panel {:?}; did you mean {:?}?", name, suggestion)
+                }
+                None => {
+                    bail!("unknown panel {:?}; the panels are {}", name, PANEL_NAMES.join(", "))
+                }
+            },
+        };
+        if panels.contains(&panel) {
+            bail!("panel {:?} is listed more than once", name);
+        }
+        panels.push(panel);
+    }
+    Ok(panels)
+}
+
+/// The contents of a panel: its columns, each one a character per gauge row from the top down,
+/// and the separator drawn after it if another panel follows.
+pub struct PanelColumns {
+    pub columns: Vec<Vec<u8>>,
+    pub separator: u8,
+}
+
+/// Where each panel starts across the display. Panels are drawn left to right with a separator
+/// column after each, and the last one is pushed to the right edge, so the status row can label
+/// it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layout {
+    starts: Vec<(Panel, usize)>,
+    cols: usize,
+}
+
+impl Layout {
+    /// Lay out the panels, given how many columns each needs, on a display `cols` wide.
+    pub fn new(panels: &[Panel], width: impl Fn(Panel) -> usize, cols: usize) -> Result<Self> {
+        let separators = panels.len().saturating_sub(1);
+        let total = panels.iter().map(|&p| width(p)).sum::<usize>() + separators;
+        if total > cols {
+            let mut widths = panels.iter()
+                .map(|&p| format!("{} {}", p.name(), width(p)))
+                .collect::<Vec<_>>();
+            if separators > 0 {
+                widths.push(format!("and {} between them", separators));
+            }
+            bail!("the panels need {} columns ({}), but the display only has {}", total,
+                widths.join(", "), cols);
+        }
+        let mut starts = vec![];
+        let mut col = 0;
+        for (i, &panel) in panels.iter().enumerate() {
+            if i > 0 && i == panels.len() - 1 {
+                col = cols - width(panel);
+            }
+            starts.push((panel, col));
+            col += width(panel) + 1;
+        }
+        Ok(Self { starts, cols })
+    }
+
+    /// Column the given panel starts at, if it's shown.
+    pub fn start(&self, panel: Panel) -> Option<usize> {
+        self.starts.iter().find(|(p, _)| *p == panel).map(|&(_, col)| col)
+    }
+
+    /// Draw the gauge rows, each `cols` wide.
+    pub fn render(&self, rows: usize, mut contents: impl FnMut(Panel) -> PanelColumns)
+        -> Vec<Vec<u8>>
+    {
+        let mut lines = vec![vec![b' '; self.cols]; rows];
+        for (i, &(panel, start)) in self.starts.iter().enumerate() {
+            let PanelColumns { columns, separator } = contents(panel);
+            for (col, column) in columns.iter().enumerate() {
+                for (line, &c) in lines.iter_mut().zip(column) {
+                    line[start + col] = c;
+                }
+            }
+            if i + 1 < self.starts.len() {
+                for line in &mut lines {
+                    line[start + columns.len()] = separator;
+                }
+            }
+        }
+        lines
+    }
+}
+
+/// Largest number of interfaces that fit alongside the other panels, given each other panel's
+/// width, and the columns each interface takes. Unlimited if there's no network panel.
+pub fn max_interfaces(panels: &[Panel], width: impl Fn(Panel) -> usize, cols: usize,
+    per_interface: usize) -> usize
+{
+    if !panels.contains(&Panel::Net) {
+        return usize::MAX;
+    }
+    let others = panels.iter()
+        .filter(|&&p| p != Panel::Net)
+        .map(|&p| width(p) + 1)
+        .sum::<usize>();
+    cols.saturating_sub(others) / per_interface
+}
+
+/// A way of making the panels narrower when they don't fit on the display.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    /// All the CPUs in one column, as `cpu_mode = "aggregate"`.
+    AggregateCpu,
+    /// One column per network interface, as `net_columns = "combined"`.
+    CombinedNet,
+    /// No memory column.
+    NoMem,
+}
+
+impl Compression {
+    /// The order they're tried in: the ones that lose the least first.
+    pub const ALL: [Compression; 3] =
+        [Compression::AggregateCpu, Compression::CombinedNet, Compression::NoMem];
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            Compression::AggregateCpu => "showing all the CPUs in one column",
+            Compression::CombinedNet => "showing each network interface in one column",
+            Compression::NoMem => "leaving out the memory column",
+        }
+    }
+}
+
+/// Part of the status row.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StatusItem {
+    Text(String),
+    /// CPU temperature, like `66°C`, or `--°C` if it can't be read.
+    Temp,
+    /// Peak transmit/receive speeds, like `120/940`, or `1.2G/854M` in auto units.
+    Speeds,
+    /// Transmit/receive speeds, taking turns at being the current, average, and peak ones, with a
+    /// symbol saying which, like `~ 35/210`.
+    Rates,
+    /// A `'` while the peak speeds are decaying, otherwise a space.
+    Decay,
+    /// Overall CPU usage, like ` 23%`.
+    Busy,
+    /// 1-minute load average, like ` 3.2`.
+    Load,
+    /// 1-minute load average divided by the number of CPUs, so `1.0` is fully loaded.
+    LoadPerCore,
+    /// Wi-Fi clients connected, like `  5`.
+    Clients,
+    /// Ping round trip time, like `  9ms`, or `   --` with no reply.
+    Ping,
+    /// One of the configured temperature sensors, counting from 0, like `48°`, or `--°` if it
+    /// can't be read.
+    Sensor(usize),
+    /// Memory available, like `1.4G` or `850M`.
+    Avail,
+}
+
+const STATUS_FIELDS: &[&str] =
+    &["temp", "speeds", "rates", "decay", "busy", "load", "load_per_core", "clients", "ping",
+        "avail"];
+
+/// The usual status row.
+pub const DEFAULT_STATUS: &str = "cpu {temp}{rates}{decay}mem";
+/// The usual status row, with how much memory is available, for displays with room for it.
+pub const WIDE_STATUS: &str = "cpu {temp}{rates}{decay}mem {avail}";
+/// Status row for accessible mode.
+pub const ACCESSIBLE_STATUS: &str = "temp  {temp}  busy{busy}";
+/// Shorter versions of those for displays narrower than 20 columns.
+pub const NARROW_STATUS: &str = "{temp}{rates}{decay}mem";
+pub const NARROW_ACCESSIBLE_STATUS: &str = "{temp}  busy{busy}";
+/// Status row for accessible mode with no CPU temperature.
+pub const NO_TEMP_ACCESSIBLE_STATUS: &str = "busy{busy}";
+
+/// The status row to show if none is configured, on a display `cols` wide, with speeds in
+/// `units`, and whether there's a CPU temperature to show.
+pub fn default_status(accessible: bool, cols: usize, units: Units, temp: bool) -> String {
+    // the speeds take up more with suffixes
+    let wide = |template| status_width(&parse_status(template).unwrap(), units) <= cols;
+    let template = match (accessible, cols < 20) {
+        (false, _) if wide(WIDE_STATUS) => WIDE_STATUS,
+        (false, _) if wide(DEFAULT_STATUS) => DEFAULT_STATUS,
+        (false, _) => NARROW_STATUS,
+        (true, false) => ACCESSIBLE_STATUS,
+        (true, true) => NARROW_ACCESSIBLE_STATUS,
+    };
+    match (temp, accessible) {
+        (true, _) => template.to_owned(),
+        // CPU usage takes its place, in as many columns, so the rest stays where it was
+        (false, false) => template.replace("{temp}", "{busy}"),
+        (false, true) => NO_TEMP_ACCESSIBLE_STATUS.to_owned(),
+    }
+}
+
+/// Parse a status row template: text with `{field}` placeholders.
+pub fn parse_status(template: &str) -> Result<Vec<StatusItem>> {
+    let mut items = vec![];
+    let mut rest = template;
+    while !rest.is_empty() {
+        let (text, field) = match rest.find('{') {
+            Some(open) => {
+                let close = match rest[open ..].find('}') {
+                    Some(close) => open + close,
+                    None => bail!("unclosed '{{' in status"),
+                };
+                let field = &rest[open + 1 .. close];
+                let text = &rest[.. open];
+                rest = &rest[close + 1 ..];
+                (text, Some(field))
+            }
+            None => {
+                let text = rest;
+                rest = "";
+                (text, None)
+            }
+        };
+        if !text.is_empty() {
+            if !text.chars().all(|c| c == ' ' || c.is_ascii_graphic()) {
+                bail!("status text {:?} can only have ASCII letters, digits, and symbols", text);
+            }
+            items.push(StatusItem::Text(text.to_owned()));
+        }
+        if let Some(field) = field {
+            items.push(match field {
+                "temp" => StatusItem::Temp,
+                "speeds" => StatusItem::Speeds,
+                "rates" => StatusItem::Rates,
+                "decay" => StatusItem::Decay,
+                "busy" => StatusItem::Busy,
+                "load" => StatusItem::Load,
+                "load_per_core" => StatusItem::LoadPerCore,
+                "clients" => StatusItem::Clients,
+                "ping" => StatusItem::Ping,
+                "avail" => StatusItem::Avail,
+                // {temp1}, {temp2}, ...
+                _ if sensor_number(field).is_some() => {
+                    StatusItem::Sensor(sensor_number(field).unwrap() - 1)
+                }
+                _ => match crate::config::suggest(field, STATUS_FIELDS) {
+                    Some(suggestion) => bail!("unknown status field {{{}}}; did you mean {{{}}}?",
+                        field, suggestion),
+                    None => bail!("unknown status field {{{}}}", field),
+                },
+            });
+        }
+    }
+    Ok(items)
+}
+
+/// N from a `{tempN}` field.
+fn sensor_number(field: &str) -> Option<usize> {
+    field.strip_prefix("temp")?.parse().ok().filter(|&n| n >= 1)
+}
+
+/// Which speeds the `{rates}` field is showing, in the order it shows them.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Rate {
+    /// The latest.
+    Current,
+    /// The average over `history_window`.
+    Mean,
+    /// The peak over `history_window`, like `{speeds}`.
+    #[default]
+    Peak,
+}
+
+impl Rate {
+    pub const ALL: [Rate; 3] = [Rate::Current, Rate::Mean, Rate::Peak];
+
+    /// The symbol in front of the speeds, saying which they are.
+    pub fn symbol(self) -> char {
+        match self {
+            Rate::Current => '>',
+            Rate::Mean => '~',
+            Rate::Peak => '^',
+        }
+    }
+}
+
+/// The figures the status row can show.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct StatusValues {
+    /// CPU temperature, if it could be read.
+    pub temp: Option<f64>,
+    /// What the speeds are shown in.
+    pub units: Units,
+    pub max_tx_mbps: f64,
+    pub max_rx_mbps: f64,
+    /// Which speeds `{rates}` is showing, and the transmit and receive ones.
+    pub rate: Rate,
+    pub rate_tx_mbps: f64,
+    pub rate_rx_mbps: f64,
+    pub decaying: bool,
+    /// Fraction of all CPUs' time that was busy.
+    pub busy: f64,
+    /// 1-minute load average, and that per CPU, if it could be read.
+    pub load: Option<f64>,
+    pub load_per_core: Option<f64>,
+    /// Stations connected to the wireless interfaces, if it could be told.
+    pub clients: Option<usize>,
+    /// The last ping, if there's been one.
+    pub ping: Option<Ping>,
+    /// Each configured temperature sensor's reading, if it could be read.
+    pub sensors: Vec<Option<f64>>,
+    /// Memory available, in MiB, if it could be read.
+    pub avail_mib: Option<u64>,
+}
+
+/// Number of columns a status row takes, with speeds in `units`.
+pub fn status_width(items: &[StatusItem], units: Units) -> usize {
+    render_status(items, &StatusValues { units, ..Default::default() }).len()
+}
+
+/// Draw the status row. The result uses `charset` codes for symbols, and may be longer than
+/// fits, so check `status_width` first.
+pub fn render_status(items: &[StatusItem], values: &StatusValues) -> Vec<u8> {
+    let mut text = String::new();
+    let mut out = vec![];
+    for item in items {
+        text.clear();
+        match item {
+            StatusItem::Text(s) => text.push_str(s),
+            StatusItem::Temp => match values.temp {
+                Some(temp) => write!(text, "{:>2}\u{b0}C", temp.round()).unwrap(),
+                None => text.push_str("--\u{b0}C"),
+            },
+            StatusItem::Speeds => {
+                write_speeds(&mut text, values.max_tx_mbps, values.max_rx_mbps, values.units);
+            }
+            StatusItem::Rates => {
+                text.push(values.rate.symbol());
+                write_speeds(&mut text, values.rate_tx_mbps, values.rate_rx_mbps, values.units);
+            }
+            StatusItem::Decay => text.push(if values.decaying { '\'' } else { ' ' }),
+            StatusItem::Busy => write!(text, "{:>3}%", (values.busy * 100.).round()).unwrap(),
+            StatusItem::Load => write_load(&mut text, values.load),
+            StatusItem::LoadPerCore => write_load(&mut text, values.load_per_core),
+            StatusItem::Clients => match values.clients {
+                Some(clients) => write!(text, "{:>3}", clients.min(999)).unwrap(),
+                None => text.push_str("   "),
+            },
+            StatusItem::Ping => write_ping(&mut text, values.ping),
+            StatusItem::Sensor(i) => match values.sensors.get(*i) {
+                Some(Some(temp)) => write!(text, "{:>2}\u{b0}", temp.round()).unwrap(),
+                Some(None) => text.push_str("--\u{b0}"),
+                None => text.push_str("   "),
+            },
+            StatusItem::Avail => match values.avail_mib {
+                Some(mib) => write_mib(&mut text, mib),
+                None => text.push_str("    "),
+            },
+        }
+        out.extend(text.chars().map(charset::encode));
+    }
+    out
+}
+
+/// Transmit and receive speeds, given in Mbps, like `120/940`.
+fn write_speeds(text: &mut String, tx_mbps: f64, rx_mbps: f64, units: Units) {
+    let width = units.width();
+    let bytes = |mbps: f64| mbps * 1e6 / 8.;
+    write!(text, "{}/{}", format_rate(bytes(tx_mbps), units, width),
+        format_rate(bytes(rx_mbps), units, width)).unwrap();
+}
+
+/// A load average in 4 columns: one decimal place below 10, none above, and blank if it's unknown.
+fn write_load(text: &mut String, load: Option<f64>) {
+    match load {
+        Some(load) if load < 9.95 => write!(text, "{:>4.1}", load).unwrap(),
+        Some(load) => write!(text, "{:>4.0}", load.min(9999.)).unwrap(),
+        None => text.push_str("    "),
+    }
+}
+
+/// An amount of memory in 4 columns: MiB below 1000, then GiB, with one decimal place below 10.
+fn write_mib(text: &mut String, mib: u64) {
+    let gib = mib as f64 / 1024.;
+    if mib < 1000 {
+        write!(text, "{:>3}M", mib).unwrap();
+    } else if gib < 9.95 {
+        write!(text, "{:.1}G", gib).unwrap();
+    } else {
+        write!(text, "{:>3.0}G", gib.min(999.)).unwrap();
+    }
+}
+
+/// A ping's round trip time in 5 columns: milliseconds up to a second, then seconds, `--` with no
+/// reply, and blank before the first one.
+fn write_ping(text: &mut String, ping: Option<Ping>) {
+    match ping {
+        Some(Ping::Reply(rtt)) if rtt.as_secs_f64() < 0.9995 => {
+            write!(text, "{:>3.0}ms", rtt.as_secs_f64() * 1000.).unwrap();
+        }
+        Some(Ping::Reply(rtt)) => write!(text, "{:>4.1}s", rtt.as_secs_f64().min(99.)).unwrap(),
+        Some(Ping::Timeout) => text.push_str("   --"),
+        None => text.push_str("     "),
+    }
+}
+
+/// What the status row shows, when it rotates between several things.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Segment {
+    /// The usual status row.
+    Status,
+    Hostname,
+    /// Time since boot, like `up 12d 03:41`.
+    Uptime,
+    /// Bytes sent and received, like `wan 142.0G/38.0G`, taking a turn for each interface.
+    Totals,
+    /// Connections tracked, like `conntrack 1024/65536`.
+    Conntrack,
+    /// An interface's address, like `wan 203.0.113.42`.
+    Address,
+    /// Fan speeds, like `fan 2.1k 850`.
+    Fan,
+    /// DHCP leases handed out, like `24 cli`.
+    Leases,
+    /// Errors and drops, like `wan err 12 drop 3`, taking a turn for each interface.
+    Errors,
+    /// Current transmit/receive speeds, like `wan 243/18 Mb`, taking a turn for each of
+    /// `rates_interfaces` that isn't idle.
+    Rates,
+    /// The process using the most CPU, like `top nginx 45%`.
+    Top,
+    /// People logged in, like `ssh 2`.
+    Sessions,
+    /// Whether the UPS is on battery, like `AC` or `BATT 34m`.
+    Ups,
+}
+
+const SEGMENT_NAMES: &[&str] = &[
+    "status", "hostname", "uptime", "totals", "conntrack", "address", "fan", "leases", "errors",
+    "rates", "top", "sessions", "ups",
+];
+
+/// Parse a list of status row segment names, rejecting unknown and repeated ones.
+pub fn parse_segments(names: &[String]) -> Result<Vec<Segment>> {
+    let mut segments = vec![];
+    for name in names {
+        let segment = match name.as_str() {
+            "status" => Segment::Status,
+            "hostname" => Segment::Hostname,
+            "uptime" => Segment::Uptime,
+            "totals" => Segment::Totals,
+            "conntrack" => Segment::Conntrack,
+            "address" => Segment::Address,
+            "fan" => Segment::Fan,
+            "leases" => Segment::Leases,
+            "errors" => Segment::Errors,
+            "rates" => Segment::Rates,
+            "top" => Segment::Top,
+            "sessions" => Segment::Sessions,
+            "ups" => Segment::Ups,
+            _ => match crate::config::suggest(name, SEGMENT_NAMES) {
+                Some(suggestion) => {
+                    bail!("unknown segment {:?}; did you mean {:?}?", name, suggestion)
+                }
+                None => {
+                    bail!("unknown segment {:?}; the segments are {}", name,
+                        SEGMENT_NAMES.join(", "))
+                }
+            },
+        };
+        if segments.contains(&segment) {
+            bail!("segment {:?} is listed more than once", name);
+        }
+        segments.push(segment);
+    }
+    Ok(segments)
+}
+
+/// Uptime like `up 12d 03:41`, leaving out the days for less than one, or `up --:--` if it's
+/// unknown.
+fn format_uptime(uptime: Option<Duration>) -> String {
+    let minutes = match uptime {
+        Some(uptime) => uptime.as_secs() / 60,
+        None => return "up --:--".to_owned(),
+    };
+    let (days, hours, minutes) = (minutes / (24 * 60), minutes / 60 % 24, minutes % 60);
+    if days == 0 {
+        format!("up {:02}:{:02}", hours, minutes)
+    } else {
+        format!("up {}d {:02}:{:02}", days, hours, minutes)
+    }
+}
+
+/// An interface's speeds, like `wan 243/18 Mb`, in no more than `cols` columns. The figures take
+/// up to 5 digits, or 4 characters with a suffix, so it's the name that gets shortened to fit.
+fn format_interface_rates(name: &str, tx_mbps: f64, rx_mbps: f64, units: Units, cols: usize)
+    -> String
+{
+    let width = match units {
+        Units::Mbps | Units::MBps => 5,
+        Units::Auto => 4,
+    };
+    let figure = |mbps: f64| format_rate(mbps * 1e6 / 8., units, width).trim_start().to_owned();
+    let figures = format!(" {}/{}{}", figure(tx_mbps), figure(rx_mbps), units.label());
+    let name: String = name.chars().take(cols.saturating_sub(figures.len())).collect();
+    (name + &figures).chars().take(cols).collect()
+}
+
+/// Which segment the status row is showing at the given step of its rotation, and for totals,
+/// errors, and rates, which interface: one of `interfaces`, or for rates, one of `rates`.
+pub fn segment_at(rotation: &[Segment], interfaces: usize, rates: usize, step: u64)
+    -> (Segment, usize)
+{
+    let turns = |segment: &Segment| match segment {
+        Segment::Totals | Segment::Errors => interfaces,
+        Segment::Rates => rates,
+        _ => 1,
+    };
+    let total: usize = rotation.iter().map(turns).sum();
+    if total == 0 {
+        return (Segment::Status, 0);
+    }
+    let mut turn = (step % total as u64) as usize;
+    for segment in rotation {
+        if turn < turns(segment) {
+            return (*segment, turn);
+        }
+        turn -= turns(segment);
+    }
+    unreachable!()
+}
+
+/// What the segments other than the status show.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SegmentValues {
+    pub hostname: String,
+    pub uptime: Option<Duration>,
+    /// An interface's name, and the bytes it's sent and received.
+    pub totals: Option<(String, u128, u128)>,
+    /// Connections tracked, and the most there's room for.
+    pub conntrack: Option<(u64, u64)>,
+    /// An interface's name, and its address, if it has one.
+    pub address: Option<(String, Option<IpAddr>)>,
+    /// Each fan's speed in RPM, if it can be read.
+    pub fans: Vec<Option<f64>>,
+    /// Active DHCP leases.
+    pub leases: Option<usize>,
+    /// An interface's name, and its errors and drops, unless they're ignored.
+    pub errors: Option<(String, Option<(u64, u64)>)>,
+    /// An interface's name, and its current transmit and receive speeds, in Mbps, to be shown in
+    /// `units`.
+    pub rates: Option<(String, f64, f64)>,
+    pub units: Units,
+    /// The busiest process's name, and the percentage of one core it's using.
+    pub top: Option<(String, f64)>,
+    /// Login sessions.
+    pub sessions: Option<usize>,
+    /// How the UPS is doing, if it could be asked.
+    pub ups: Option<Reading>,
+}
+
+/// The status row while it's showing `segment`, exactly `cols` wide: `status` (the rendered
+/// status row), or one of the other values, padded or cut off to fit.
+pub fn render_segment(segment: Segment, status: &[u8], values: &SegmentValues, cols: usize)
+    -> Vec<u8>
+{
+    let line = match segment {
+        Segment::Status => status.to_vec(),
+        Segment::Hostname => values.hostname.chars().map(charset::encode).collect(),
+        Segment::Uptime => format_uptime(values.uptime).into_bytes(),
+        Segment::Totals => match &values.totals {
+            Some((name, sent, received)) => {
+                let figures = format!(" {}/{}", human_bytes(*sent), human_bytes(*received));
+                // shorten the name rather than lose the figures
+                let name: String = name.chars().take(cols.saturating_sub(figures.len())).collect();
+                (name + &figures).chars().map(charset::encode).collect()
+            }
+            None => vec![],
+        },
+        Segment::Conntrack => match values.conntrack {
+            Some((count, max)) => format!("conntrack {}/{}", count, max).into_bytes(),
+            None => b"conntrack --".to_vec(),
+        },
+        Segment::Address => match &values.address {
+            Some((name, addr)) => {
+                let addr = match addr {
+                    Some(addr) => format!(" {}", addr),
+                    None => " down".to_owned(),
+                };
+                let name: String = name.chars().take(cols.saturating_sub(addr.len())).collect();
+                (name + &addr).chars().map(charset::encode).collect()
+            }
+            None => vec![],
+        },
+        Segment::Fan => {
+            let speeds: String = values.fans.iter()
+                .map(|rpm| format!(" {}", rpm.map_or("--".to_owned(), crate::hwmon::format_rpm)))
+                .collect();
+            format!("fan{}", speeds).into_bytes()
+        }
+        Segment::Leases => match values.leases {
+            Some(leases) => format!("{} cli", leases).into_bytes(),
+            None => vec![],
+        },
+        Segment::Errors => match &values.errors {
+            Some((name, counts)) => {
+                let counts = match counts {
+                    Some((errors, drops)) => format!(" err {} drop {}", errors, drops),
+                    None => " ignored".to_owned(),
+                };
+                let name: String = name.chars().take(cols.saturating_sub(counts.len())).collect();
+                (name + &counts).chars().map(charset::encode).collect()
+            }
+            None => vec![],
+        },
+        Segment::Rates => match &values.rates {
+            Some((name, tx_mbps, rx_mbps)) => {
+                format_interface_rates(name, *tx_mbps, *rx_mbps, values.units, cols)
+                    .chars().map(charset::encode).collect()
+            }
+            None => vec![],
+        },
+        Segment::Top => match &values.top {
+            Some((name, percent)) => {
+                let percent = format!(" {:.0}%", percent);
+                let name: String = name.chars()
+                    .take(cols.saturating_sub("top ".len() + percent.len()))
+                    .collect();
+                format!("top {}{}", name, percent).chars().map(charset::encode).collect()
+            }
+            None => b"top --".to_vec(),
+        },
+        Segment::Sessions => match values.sessions {
+            Some(sessions) => format!("ssh {}", sessions).into_bytes(),
+            None => vec![],
+        },
+        Segment::Ups => match values.ups {
+            Some(Reading { on_battery: false, .. }) => b"AC".to_vec(),
+            Some(Reading { runtime: Some(runtime), .. }) => {
+                format!("BATT {}m", runtime.as_secs() / 60).into_bytes()
+            }
+            Some(Reading { runtime: None, .. }) => b"BATT".to_vec(),
+            None => b"UPS ?".to_vec(),
+        },
+    };
+    line.into_iter().chain(std::iter::repeat(b' ')).take(cols).collect()
+}
+
+#[cfg(test)]
+fn panel_columns(panel: Panel, cpus: usize, ifaces: usize) -> PanelColumns {
+    let (c, n, separator) = match panel {
+        Panel::Cpu => (b'c', cpus, b':'),
+        Panel::Net => (b'n', ifaces * 2, b'|'),
+        Panel::Mem => (b'm', 1, b'|'),
+        Panel::Disk => (b'd', 2, b'|'),
+        Panel::Fs => (b'f', 1, b'|'),
+        Panel::Wifi => (b'w', 1, b'|'),
+        Panel::Conntrack => (b'k', 1, b'|'),
+        Panel::Ping => (b'p', 1, b'|'),
+        Panel::Temp => (b't', 1, b'|'),
+        Panel::Fan => (b'F', 1, b'|'),
+        Panel::Ups => (b'u', 1, b'|'),
+        Panel::Sparkline => (b's', 3, b'|'),
+    };
+    PanelColumns { columns: vec![vec![c, c + 1]; n], separator }
+}
+
+#[cfg(test)]
+#[test]
+fn test_layout() {
+    let width = |panel| panel_columns(panel, 4, 6).columns.len();
+    let layout = Layout::new(DEFAULT_PANELS, width, 20).unwrap();
+    assert_eq!(Some(5), layout.start(Panel::Net));
+    let rows = layout.render(2, |panel| panel_columns(panel, 4, 6));
+    assert_eq!(b"cccc:nnnnnnnnnnnn| m".to_vec(), rows[0]);
+    assert_eq!(b"dddd:oooooooooooo| n".to_vec(), rows[1]);
+
+    // fewer interfaces: memory still goes at the right edge
+    let width = |panel| panel_columns(panel, 4, 2).columns.len();
+    let layout = Layout::new(DEFAULT_PANELS, width, 20).unwrap();
+    let rows = layout.render(1, |panel| panel_columns(panel, 4, 2));
+    assert_eq!(b"cccc:nnnn|         m".to_vec(), rows[0]);
+
+    let panels = [Panel::Net, Panel::Mem];
+    let width = |panel| panel_columns(panel, 16, 9).columns.len();
+    assert_eq!(9, max_interfaces(&panels, width, 20, 2));
+    let layout = Layout::new(&panels, width, 20).unwrap();
+    assert_eq!(Some(0), layout.start(Panel::Net));
+    assert_eq!(None, layout.start(Panel::Cpu));
+    let rows = layout.render(1, |panel| panel_columns(panel, 16, 9));
+    assert_eq!(b"nnnnnnnnnnnnnnnnnn|m".to_vec(), rows[0]);
+
+    assert_eq!("the panels need 37 columns (cpu 16, net 18, mem 1, and 2 between them), but the \
+        display only has 20",
+        Layout::new(DEFAULT_PANELS, width, 20).unwrap_err().to_string());
+
+    let layout = Layout::new(&[Panel::Mem], width, 20).unwrap();
+    assert_eq!(b"m                   ".to_vec(), layout.render(1, |p| panel_columns(p, 0, 0))[0]);
+}
+
+#[cfg(test)]
+#[test]
+fn test_geometry() {
+    assert!(Geometry::DEFAULT.validate().is_ok());
+    assert!(Geometry { cols: 16, rows: 2 }.validate().is_ok());
+    assert!(Geometry { cols: 20, rows: 2 }.validate().is_ok());
+    let err = |cols, rows| Geometry { cols, rows }.validate().unwrap_err().to_string();
+    assert_eq!("the display must have 2 or 4 rows, not 3", err(20, 3));
+    assert_eq!("the display must have 16 to 40 columns, not 8", err(8, 2));
+    assert_eq!("4-row displays must have 20 columns, not 16", err(16, 4));
+}
+
+#[cfg(test)]
+#[test]
+fn test_max_interfaces() {
+    // the ODROID H2+ has 4 cores
+    let width = |cpus| move |panel| if panel == Panel::Cpu { cpus } else { 1 };
+    assert_eq!(6, max_interfaces(DEFAULT_PANELS, width(4), 20, 2));
+    assert_eq!(6, max_interfaces(DEFAULT_PANELS, width(5), 20, 2));
+    assert_eq!(5, max_interfaces(DEFAULT_PANELS, width(6), 20, 2));
+    assert_eq!(0, max_interfaces(DEFAULT_PANELS, width(20), 20, 2));
+    assert_eq!(7, max_interfaces(&[Panel::Net, Panel::Cpu], width(4), 20, 2));
+    assert_eq!(usize::MAX, max_interfaces(&[Panel::Cpu, Panel::Mem], width(4), 20, 2));
+    // combined columns fit twice as many
+    assert_eq!(13, max_interfaces(DEFAULT_PANELS, width(4), 20, 1));
+}
+
+#[cfg(test)]
+#[test]
+fn test_segments() {
+    let names = |names: &[&str]| names.iter().map(|&s| s.to_owned()).collect::<Vec<_>>();
+    assert_eq!(vec![Segment::Status, Segment::Uptime],
+        parse_segments(&names(&["status", "uptime"])).unwrap());
+    let err = |list: &[&str]| parse_segments(&names(list)).unwrap_err().to_string();
+    assert_eq!("unknown segment \"hostnam\"; did you mean \"hostname\"?", err(&["hostnam"]));
+    assert_eq!("unknown segment \"ip\"; the segments are status, hostname, uptime, totals, \
+        conntrack, address, fan, leases, errors, rates, top, sessions, ups", err(&["ip"]));
+    assert_eq!("segment \"status\" is listed more than once", err(&["status", "status"]));
+
+    let mins = |m: u64| Some(Duration::from_secs(m * 60 + 59));
+    assert_eq!("up 00:00", format_uptime(Some(Duration::from_secs(59))));
+    assert_eq!("up 03:41", format_uptime(mins(3 * 60 + 41)));
+    assert_eq!("up 12d 03:41", format_uptime(mins(12 * 24 * 60 + 3 * 60 + 41)));
+    assert_eq!("up --:--", format_uptime(None));
+
+    let render = |segment, hostname: &str| {
+        let values = SegmentValues {
+            hostname: hostname.to_owned(),
+            uptime: mins(61),
+            totals: Some(("enp1s0".to_owned(), 142_000_000_000, 38_000_000_000)),
+            conntrack: Some((1024, 65536)),
+            address: Some(("wan".to_owned(), Some(IpAddr::from([203, 0, 113, 42])))),
+            fans: vec![Some(2100.), None, Some(850.)],
+            leases: Some(24),
+            errors: Some(("wan".to_owned(), Some((12, 3)))),
+            rates: Some(("wan".to_owned(), 242.1, 17.5)),
+            units: Units::Mbps,
+            top: Some(("nginx".to_owned(), 45.4)),
+            sessions: Some(2),
+            ups: Some(Reading {
+                on_battery: true,
+                charge: Some(87.),
+                runtime: Some(Duration::from_secs(34 * 60 + 30)),
+            }),
+        };
+        String::from_utf8(render_segment(segment, b"cpu 66", &values, 20)).unwrap()
+    };
+    assert_eq!("cpu 66              ", render(Segment::Status, "router"));
+    assert_eq!("router              ", render(Segment::Hostname, "router"));
+    assert_eq!("a-very-long-hostname", render(Segment::Hostname, "a-very-long-hostname.lan"));
+    assert_eq!("up 01:01            ", render(Segment::Uptime, "router"));
+    assert_eq!("enp1s0 142.0G/38.0G ", render(Segment::Totals, "router"));
+    assert_eq!("conntrack 1024/65536", render(Segment::Conntrack, "router"));
+    assert_eq!("wan 203.0.113.42    ", render(Segment::Address, "router"));
+    assert_eq!("fan 2.1k -- 850     ", render(Segment::Fan, "router"));
+    assert_eq!("24 cli              ", render(Segment::Leases, "router"));
+    assert_eq!("wan err 12 drop 3   ", render(Segment::Errors, "router"));
+    assert_eq!("wan 243/18 Mb       ", render(Segment::Rates, "router"));
+    assert_eq!("top nginx 45%       ", render(Segment::Top, "router"));
+    assert_eq!("ssh 2               ", render(Segment::Sessions, "router"));
+    assert_eq!("BATT 34m            ", render(Segment::Ups, "router"));
+    let ups = |ups| SegmentValues { ups, ..SegmentValues::default() };
+    let online = Reading { on_battery: false, charge: Some(100.), runtime: None };
+    assert_eq!(b"AC  ".to_vec(), render_segment(Segment::Ups, b"", &ups(Some(online)), 4));
+    let unknown_runtime = Reading { on_battery: true, ..online };
+    assert_eq!(b"BATT".to_vec(),
+        render_segment(Segment::Ups, b"", &ups(Some(unknown_runtime)), 4));
+    assert_eq!(b"UPS ?".to_vec(), render_segment(Segment::Ups, b"", &ups(None), 5));
+    let top = |name: &str| SegmentValues {
+        top: Some((name.to_owned(), 112.)),
+        ..SegmentValues::default()
+    };
+    assert_eq!(b"top kworker/ 112%".to_vec(),
+        render_segment(Segment::Top, b"", &top("kworker/u8:2-events"), 17));
+    assert_eq!(b"top --".to_vec(), render_segment(Segment::Top, b"", &SegmentValues::default(), 6));
+    let errors = |counts| SegmentValues {
+        errors: Some(("enp1s0".to_owned(), counts)),
+        ..SegmentValues::default()
+    };
+    assert_eq!(b"enp1 err 12 drop 3".to_vec(),
+        render_segment(Segment::Errors, b"", &errors(Some((12, 3))), 18));
+    assert_eq!(b"enp1s0 ignored  ".to_vec(),
+        render_segment(Segment::Errors, b"", &errors(None), 16));
+    let address = |name: &str, addr| SegmentValues {
+        address: Some((name.to_owned(), addr)),
+        ..SegmentValues::default()
+    };
+    assert_eq!(b"wan down        ".to_vec(),
+        render_segment(Segment::Address, b"", &address("wan", None), 16));
+    assert_eq!(b"en 203.0.113.42".to_vec(), render_segment(Segment::Address, b"",
+        &address("enp1s0", Some(IpAddr::from([203, 0, 113, 42]))), 15));
+    assert_eq!(b"conntrack --    ".to_vec(),
+        render_segment(Segment::Conntrack, b"", &SegmentValues::default(), 16));
+    let values = SegmentValues {
+        totals: Some(("enp1s0".to_owned(), 142_000_000_000, 38_000_000_000)),
+        ..SegmentValues::default()
+    };
+    assert_eq!(b"en 142.0G/38.0G".to_vec(), render_segment(Segment::Totals, b"", &values, 15));
+    let rates = |name: &str, tx, rx, units| SegmentValues {
+        rates: Some((name.to_owned(), tx, rx)),
+        units,
+        ..SegmentValues::default()
+    };
+    assert_eq!(b"wan 31/3 MB         ".to_vec(),
+        render_segment(Segment::Rates, b"", &rates("wan", 242.1, 17.5, Units::MBps), 20));
+    assert_eq!(b"wan 243M/18M        ".to_vec(),
+        render_segment(Segment::Rates, b"", &rates("wan", 242.1, 17.5, Units::Auto), 20));
+    // 5-digit speeds fit by shortening the name, and past that they get a suffix
+    assert_eq!("enp1 24000/12345 Mb",
+        format_interface_rates("enp1s0f0", 24_000., 12_345., Units::Mbps, 19));
+    assert_eq!("enp1s0f0 100G/0 Mb",
+        format_interface_rates("enp1s0f0", 100_000., 0., Units::Mbps, 19));
+    assert_eq!("enp 24G/13G",
+        format_interface_rates("enp1s0f0", 24_000., 12_345., Units::Auto, 11));
+    for units in [Units::Mbps, Units::MBps, Units::Auto] {
+        let text = format_interface_rates("bridge0", 99_999., 99_999., units, 20);
+        assert!(text.len() <= 20, "{}", text);
+    }
+
+    // totals take a turn for each interface
+    let rotation = [Segment::Status, Segment::Totals, Segment::Uptime];
+    let turns: Vec<_> = (0 .. 5).map(|step| segment_at(&rotation, 2, 0, step)).collect();
+    assert_eq!(vec![(Segment::Status, 0), (Segment::Totals, 0), (Segment::Totals, 1),
+        (Segment::Uptime, 0), (Segment::Status, 0)], turns);
+    // and so do errors
+    assert_eq!((Segment::Errors, 1), segment_at(&[Segment::Status, Segment::Errors], 2, 0, 2));
+    assert_eq!((Segment::Uptime, 0), segment_at(&rotation, 0, 0, 1));
+    assert_eq!((Segment::Status, 0), segment_at(&[Segment::Totals], 0, 0, 7));
+    assert_eq!((Segment::Status, 0), segment_at(&[], 3, 0, 7));
+    // rates take a turn for each interface that's busy, and none while they're all idle
+    let rotation = [Segment::Rates, Segment::Uptime];
+    assert_eq!((Segment::Rates, 1), segment_at(&rotation, 4, 2, 1));
+    assert_eq!((Segment::Uptime, 0), segment_at(&rotation, 4, 2, 2));
+    assert_eq!((Segment::Uptime, 0), segment_at(&rotation, 4, 0, 5));
+    // long uptimes still fit a 16x2
+    assert!(format_uptime(mins(9_999 * 24 * 60)).len() <= 16);
+}
+
+#[cfg(test)]
+#[test]
+fn test_parse_panels() {
+    let names = |s: &[&str]| s.iter().map(|&s| s.to_owned()).collect::<Vec<_>>();
+    assert_eq!(vec![Panel::Net, Panel::Mem], parse_panels(&names(&["net", "mem"])).unwrap());
+    let err = |s: &[&str]| parse_panels(&names(s)).unwrap_err().to_string();
+    assert_eq!("unknown panel \"nett\"; did you mean \"net\"?", err(&["nett"]));
+    assert_eq!("unknown panel \"battery\"; the panels are cpu, net, mem, disk, fs, wifi, \
+        conntrack, ping, temp, fan, ups, sparkline", err(&["battery"]));
+    assert_eq!("panel \"cpu\" is listed more than once", err(&["cpu", "net", "cpu"]));
+    assert_eq!("there must be at least one panel", err(&[]));
+}
+
+#[cfg(test)]
+#[test]
+fn test_status() {
+    let values = StatusValues {
+        temp: Some(65.6),
+        units: Units::Mbps,
+        max_tx_mbps: 11.2,
+        max_rx_mbps: 940.,
+        rate: Rate::Mean,
+        rate_tx_mbps: 3.,
+        rate_rx_mbps: 210.,
+        decaying: true,
+        busy: 0.234,
+        load: Some(3.21),
+        load_per_core: Some(0.8025),
+        clients: Some(5),
+        ping: Some(Ping::Reply(Duration::from_micros(9120))),
+        sensors: vec![Some(48.25), None],
+        avail_mib: Some(850),
+    };
+    let status = parse_status(DEFAULT_STATUS).unwrap();
+    assert_eq!(20, status_width(&status, Units::Mbps));
+    assert_eq!(b"cpu 66\x80C~  3/210'mem".to_vec(), render_status(&status, &values));
+    let rate = |rate| render_status(&status, &StatusValues { rate, ..values.clone() });
+    assert_eq!(b"cpu 66\x80C>  3/210'mem".to_vec(), rate(Rate::Current));
+    assert_eq!(b"cpu 66\x80C^  3/210'mem".to_vec(), rate(Rate::Peak));
+
+    // the temperature can't be read for now
+    let status = parse_status(DEFAULT_STATUS).unwrap();
+    assert_eq!(b"cpu --\x80C~  3/210'mem".to_vec(),
+        render_status(&status, &StatusValues { temp: None, ..values.clone() }));
+    // or at all, so CPU usage takes its place
+    let status = parse_status(&default_status(false, 20, Units::Mbps, false)).unwrap();
+    assert_eq!(b"cpu  23%~  3/210'mem".to_vec(), render_status(&status, &values));
+    let status = parse_status(&default_status(false, 16, Units::Mbps, false)).unwrap();
+    assert_eq!(b" 23%~  3/210'mem".to_vec(), render_status(&status, &values));
+    assert_eq!(NO_TEMP_ACCESSIBLE_STATUS, default_status(true, 20, Units::Mbps, false));
+
+    let status = parse_status(ACCESSIBLE_STATUS).unwrap();
+    assert_eq!(20, status_width(&status, Units::Mbps));
+    assert_eq!(b"temp  66\x80C  busy 23%".to_vec(), render_status(&status, &values));
+
+    let status = parse_status(&default_status(false, 16, Units::Mbps, true)).unwrap();
+    assert_eq!(16, status_width(&status, Units::Mbps));
+    assert_eq!(b"66\x80C~  3/210'mem".to_vec(), render_status(&status, &values));
+
+    let status = parse_status("{speeds} {temp}").unwrap();
+    assert_eq!(12, status_width(&status, Units::Mbps));
+    assert_eq!(b" 12/940 66\x80C".to_vec(), render_status(&status, &values));
+    // too fast for 3 digits
+    let fast = StatusValues { max_rx_mbps: 2350., ..values.clone() };
+    assert_eq!(b" 12/ 3G 66\x80C".to_vec(), render_status(&status, &fast));
+    // with suffixes, which take another column each
+    let auto = StatusValues { units: Units::Auto, ..fast };
+    assert_eq!(14, status_width(&status, Units::Auto));
+    assert_eq!(b" 12M/2.4G 66\x80C".to_vec(), render_status(&status, &auto));
+    // which pushes the usual status row past 20 columns, so it's the shorter one instead
+    let status = parse_status(&default_status(false, 20, Units::Auto, true)).unwrap();
+    assert_eq!(18, status_width(&status, Units::Auto));
+    assert_eq!(b"66\x80C~3.0M/210M'mem".to_vec(), render_status(&status, &auto));
+
+    let status = parse_status(&default_status(true, 16, Units::Mbps, true)).unwrap();
+    assert!(status_width(&status, Units::Mbps) <= 16);
+    assert_eq!(b"66\x80C  busy 23%".to_vec(), render_status(&status, &values));
+
+    let status = parse_status("router {busy}").unwrap();
+    assert_eq!(vec![StatusItem::Text("router ".to_owned()), StatusItem::Busy], status);
+    assert_eq!(11, status_width(&status, Units::Mbps));
+
+    let status = parse_status("ld{load} {load_per_core}").unwrap();
+    assert_eq!(11, status_width(&status, Units::Mbps));
+    assert_eq!(b"ld 3.2  0.8".to_vec(), render_status(&status, &values));
+    let load = |load| render_status(&status, &StatusValues { load, ..values.clone() });
+    assert_eq!(b"ld 9.9  0.8".to_vec(), load(Some(9.94)));
+    assert_eq!(b"ld  10  0.8".to_vec(), load(Some(9.95)));
+    assert_eq!(b"ld 123  0.8".to_vec(), load(Some(123.4)));
+    // couldn't be read
+    assert_eq!(b"ld      0.8".to_vec(), load(None));
+
+    let status = parse_status("wifi{clients}").unwrap();
+    assert_eq!(7, status_width(&status, Units::Mbps));
+    assert_eq!(b"wifi  5".to_vec(), render_status(&status, &values));
+    let clients = |clients| render_status(&status, &StatusValues { clients, ..values.clone() });
+    assert_eq!(b"wifi999".to_vec(), clients(Some(1234)));
+    assert_eq!(b"wifi   ".to_vec(), clients(None));
+
+    let status = parse_status("gw{ping}").unwrap();
+    assert_eq!(7, status_width(&status, Units::Mbps));
+    assert_eq!(b"gw  9ms".to_vec(), render_status(&status, &values));
+    let ping = |ping| render_status(&status, &StatusValues { ping, ..values.clone() });
+    let ms = |ms| Some(Ping::Reply(Duration::from_micros(ms)));
+    assert_eq!(b"gw999ms".to_vec(), ping(ms(999_400)));
+    assert_eq!(b"gw 1.0s".to_vec(), ping(ms(999_500)));
+    assert_eq!(b"gw   --".to_vec(), ping(Some(Ping::Timeout)));
+    assert_eq!(b"gw     ".to_vec(), ping(None));
+
+    let status = parse_status("{temp1} {temp2} {temp3}").unwrap();
+    assert_eq!(vec![StatusItem::Sensor(0), StatusItem::Text(" ".to_owned()),
+        StatusItem::Sensor(1), StatusItem::Text(" ".to_owned()), StatusItem::Sensor(2)], status);
+    assert_eq!(11, status_width(&status, Units::Mbps));
+    // the third isn't configured
+    assert_eq!(b"48\x80 --\x80    ".to_vec(), render_status(&status, &values));
+
+    let status = parse_status("mem{avail}").unwrap();
+    assert_eq!(7, status_width(&status, Units::Mbps));
+    assert_eq!(b"mem850M".to_vec(), render_status(&status, &values));
+    let avail = |mib| render_status(&status,
+        &StatusValues { avail_mib: Some(mib), ..values.clone() });
+    assert_eq!(b"mem  0M".to_vec(), avail(0));
+    assert_eq!(b"mem999M".to_vec(), avail(999));
+    // 1000M would take 5 columns, so it's GiB from there, a little before 1024
+    assert_eq!(b"mem1.0G".to_vec(), avail(1000));
+    assert_eq!(b"mem1.0G".to_vec(), avail(1023));
+    assert_eq!(b"mem1.0G".to_vec(), avail(1024));
+    assert_eq!(b"mem1.4G".to_vec(), avail(1434));
+    assert_eq!(b"mem9.9G".to_vec(), avail(10188));
+    assert_eq!(b"mem 10G".to_vec(), avail(10189));
+    assert_eq!(b"mem999G".to_vec(), avail(2_000_000));
+    // couldn't be read
+    assert_eq!(b"mem    ".to_vec(),
+        render_status(&status, &StatusValues { avail_mib: None, ..values.clone() }));
+    // on displays with room for it, the usual status row has it too
+    let status = parse_status(&default_status(false, 25, Units::Mbps, true)).unwrap();
+    assert_eq!(25, status_width(&status, Units::Mbps));
+    assert_eq!(b"cpu 66\x80C~  3/210'mem 850M".to_vec(), render_status(&status, &values));
+    assert_eq!(DEFAULT_STATUS, default_status(false, 24, Units::Mbps, true));
+
+    let err = |s| parse_status(s).unwrap_err().to_string();
+    assert_eq!("unknown status field {tmp}; did you mean {temp}?", err("{tmp}"));
+    assert_eq!("unknown status field {battery}", err("{battery}"));
+    assert_eq!("unknown status field {temp0}; did you mean {temp}?", err("{temp0}"));
+    assert_eq!("unclosed '{' in status", err("cpu {temp"));
+    assert_eq!("status text \"\u{b0}C\" can only have ASCII letters, digits, and symbols",
+        err("{temp}\u{b0}C"));
+}
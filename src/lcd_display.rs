@@ -1,19 +1,67 @@
 use anyhow::{Context, Result};
+use crate::charset::Charset;
+use crate::display::{self, DisplayBackend, GlyphStyle};
+use crate::latency;
 use i2cdev::linux::LinuxI2CError;
-pub use lcd::Display;
 use lcd::{
+    Display,
+    Delay,
     DisplayBlink,
     DisplayCursor,
     DisplayMode,
     FunctionDots,
     FunctionLine,
+    Hardware,
 };
 use lcd_pcf8574::{Pcf8574, ErrorHandling};
 use nix::errno::Errno;
 use std::cell::Cell;
 use std::rc::Rc;
+use std::time::Instant;
 
-pub fn init_display(bus: u8, addr: u16) -> Result<Display<Pcf8574>> {
+/// Wraps the PCF8574 backpack to record how long each I2C write takes.
+pub struct TimedPcf8574(Pcf8574);
+
+// Implemented for a reference, so a `Display` can be made around it whenever it's needed, without
+// giving up ownership of the backpack (which also controls the backlight).
+impl Hardware for &mut TimedPcf8574 {
+    fn rs(&mut self, bit: bool) {
+        self.0.rs(bit);
+    }
+
+    fn enable(&mut self, bit: bool) {
+        self.0.enable(bit);
+    }
+
+    fn data(&mut self, bits: u8) {
+        self.0.data(bits);
+    }
+
+    fn apply(&mut self) {
+        let start = Instant::now();
+        self.0.apply();
+        latency::I2C_WRITES.record(start.elapsed());
+    }
+}
+
+impl Delay for &mut TimedPcf8574 {
+    fn delay_us(&mut self, delay_usec: u32) {
+        self.0.delay_us(delay_usec);
+    }
+}
+
+/// An LCD with an HD44780 controller, attached via a PCF8574 I2C backpack.
+pub struct LcdDisplay {
+    hw: TimedPcf8574,
+    glyphs: GlyphStyle,
+    charset: Charset,
+    /// Set when a write fails after initialization.
+    failed: Rc<Cell<bool>>,
+}
+
+pub fn init_display(bus: u8, addr: u16, glyphs: GlyphStyle, charset: Charset)
+    -> Result<LcdDisplay>
+{
     let mut dev = Pcf8574::new(bus, addr)
         .context("failed to open I2C device")?;
 
@@ -21,20 +69,23 @@ pub fn init_display(bus: u8, addr: u16) -> Result<Display<Pcf8574>> {
     // know if the parameters are wrong.
     let save_error = Rc::new(Cell::new(true));
     let error = Rc::new(Cell::new(Option::<anyhow::Error>::None));
+    let failed = Rc::new(Cell::new(false));
     dev.on_error(ErrorHandling::Custom(Box::new({
         let save_error = Rc::clone(&save_error);
         let error = Rc::clone(&error);
+        let failed = Rc::clone(&failed);
         move |e| {
             if save_error.get() {
                 error.set(Some(e.into()));
             } else {
                 eprintln!("I/O error: {}", e);
+                failed.set(true);
             }
         }
     })));
 
-    let mut display = Display::new(dev);
-    display.init(FunctionLine::Line2, FunctionDots::Dots5x8);
+    let mut display = LcdDisplay { hw: TimedPcf8574(dev), glyphs, charset, failed };
+    display.reinit();
 
     if let Some(e) = error.replace(None) {
         // Something went wrong during init, bail out now.
@@ -44,29 +95,71 @@ pub fn init_display(bus: u8, addr: u16) -> Result<Display<Pcf8574>> {
     // If it successfully init'd, we're probably good to just print errors now.
     save_error.set(false);
 
-    display.display(
-        DisplayMode::DisplayOn,
-        DisplayCursor::CursorOff,
-        DisplayBlink::BlinkOff);
+    Ok(display)
+}
 
-    // The display controller supports 8 custom characters. Characters are
-    // 5 pixels wide by 8 pixels tall.
-    // We'll use this to draw blocks of 8 different heights for our bar gauges.
-    let mut bits = [0u8; 8]; // 8 bytes in array for 8 pixels tall
-    for i in 0 .. 8 {
-        bits[7 - i] = 0b11111; // 5 bits for 5 pixels wide
-        display.upload_character(i as u8, bits);
+impl LcdDisplay {
+    /// The HD44780 commands. The `Display` keeps no state of its own, so it's fine to make a new
+    /// one each time.
+    fn lcd(&mut self) -> Display<&mut TimedPcf8574> {
+        Display::new(&mut self.hw)
     }
-
-    Ok(display)
 }
 
-pub fn stop_display(mut display: Display<Pcf8574>) {
-    display.display(
-        DisplayMode::DisplayOff,
-        DisplayCursor::CursorOff,
-        DisplayBlink::BlinkOff);
-    display.unwrap().backlight(false);
+impl DisplayBackend for LcdDisplay {
+    fn position(&mut self, col: u8, row: u8) {
+        self.lcd().position(col, row);
+    }
+
+    fn write(&mut self, c: u8) {
+        let byte = self.charset.byte(c);
+        self.lcd().write(byte);
+    }
+
+    fn clear(&mut self) {
+        self.lcd().clear();
+    }
+
+    fn upload_character(&mut self, location: u8, map: [u8; 8]) {
+        self.lcd().upload_character(location, map);
+    }
+
+    fn glyphs(&self) -> GlyphStyle {
+        self.glyphs
+    }
+
+    fn take_error(&mut self) -> bool {
+        self.failed.replace(false)
+    }
+
+    fn reinit(&mut self) {
+        self.lcd().init(FunctionLine::Line2, FunctionDots::Dots5x8);
+
+        self.lcd().display(
+            DisplayMode::DisplayOn,
+            DisplayCursor::CursorOff,
+            DisplayBlink::BlinkOff);
+
+        display::upload_bar_characters(self);
+    }
+
+    fn set_backlight(&mut self, on: bool) {
+        self.hw.0.backlight(on);
+    }
+
+    fn stop(mut self: Box<Self>, message: Option<&str>, backlight: bool) {
+        if let Some(message) = message {
+            self.clear();
+            self.position(0, 0);
+            self.print(message);
+        } else {
+            self.lcd().display(
+                DisplayMode::DisplayOff,
+                DisplayCursor::CursorOff,
+                DisplayBlink::BlinkOff);
+        }
+        self.set_backlight(backlight);
+    }
 }
 
 /// Is the given error indicative of the wrong I2C bus being used? (i.e. should you retry on a
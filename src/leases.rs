@@ -0,0 +1,60 @@
+//! How many devices are on the network, going by the DHCP leases dnsmasq has handed out.
+
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const DEFAULT_PATH: &str = "/var/lib/misc/dnsmasq.leases";
+
+/// Leases in the contents of a dnsmasq lease file that haven't expired as of `now`, in seconds
+/// since the epoch. Each lease is a line starting with when it expires, like
+/// `1718000000 aa:bb:cc:dd:ee:ff 192.168.1.23 laptop 01:aa:bb:cc:dd:ee:ff`.
+pub fn count(leases: &str, now: u64) -> usize {
+    leases.lines()
+        .filter(|line| match line.split_whitespace().next() {
+            // dnsmasq's own DUID, for DHCPv6, rather than a lease
+            Some("duid") | None => false,
+            Some(expiry) => match expiry.parse::<u64>() {
+                // never expires
+                Ok(0) => true,
+                Ok(expiry) => expiry > now,
+                Err(_) => false,
+            },
+        })
+        .count()
+}
+
+/// Active leases in the lease file at `path`, or None if it can't be read, e.g. because dnsmasq
+/// isn't running here.
+pub fn read(path: &Path) -> Option<usize> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    Some(count(&fs::read_to_string(path).ok()?, now))
+}
+
+#[cfg(test)]
+#[test]
+fn test_count() {
+    let dir = std::env::temp_dir().join(format!("h2-net-lcd-test-leases-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("dnsmasq.leases");
+    fs::write(&path, "\
+1718003600 aa:bb:cc:dd:ee:01 192.168.1.23 laptop 01:aa:bb:cc:dd:ee:01
+1717999000 aa:bb:cc:dd:ee:02 192.168.1.24 phone *
+0 aa:bb:cc:dd:ee:03 192.168.1.2 printer *
+duid 00:01:00:01:2c:4f:5e:6a:aa:bb:cc:dd:ee:ff
+1718007200 1234567 fd00::1:23 laptop 00:01:00:01:2b:3c:4d:5e:aa:bb:cc:dd:ee:01
+
+garbage
+").unwrap();
+    let text = fs::read_to_string(&path).unwrap();
+    // the phone's lease has run out
+    assert_eq!(3, count(&text, 1_718_000_000));
+    assert_eq!(2, count(&text, 1_718_003_600));
+    assert_eq!(1, count(&text, 1_718_010_000));
+    assert_eq!(0, count("", 1_718_000_000));
+    assert!(read(&path).is_some());
+
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(None, read(&path));
+}
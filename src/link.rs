@@ -0,0 +1,207 @@
+use anyhow::{bail, Result};
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// What the top of each interface's gauges stands for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scale {
+    /// `net_max`, the same for every interface.
+    Global,
+    /// The interface's negotiated link speed, or `net_max` for one that doesn't report one.
+    Link,
+}
+
+impl Scale {
+    pub fn parse(s: &str) -> Result<Self> {
+        Ok(match s {
+            "global" => Scale::Global,
+            "link" => Scale::Link,
+            _ => bail!("should be \"global\" or \"link\", not {:?}", s),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Duplex {
+    Full,
+    Half,
+    Unknown,
+}
+
+/// Negotiated link parameters of an interface.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinkInfo {
+    /// None if the interface doesn't report one (virtual interfaces, or link down).
+    pub speed_mbps: Option<u32>,
+    pub duplex: Duplex,
+}
+
+impl LinkInfo {
+    /// Parse the contents of the sysfs `speed` and `duplex` files.
+    pub fn parse(speed: &str, duplex: &str) -> Self {
+        // -1 means unknown
+        let speed_mbps = speed.trim().parse::<u32>().ok().filter(|&n| n > 0);
+        let duplex = match duplex.trim() {
+            "full" => Duplex::Full,
+            "half" => Duplex::Half,
+            _ => Duplex::Unknown,
+        };
+        Self { speed_mbps, duplex }
+    }
+
+    /// Read from sysfs. None if the interface has no such files.
+    pub fn read(name: &str) -> Option<Self> {
+        let dir = format!("/sys/class/net/{}", name);
+        // These can fail to read with EINVAL while the link is down.
+        let speed = fs::read_to_string(format!("{}/speed", dir)).unwrap_or_default();
+        let duplex = fs::read_to_string(format!("{}/duplex", dir)).ok()?;
+        Some(Self::parse(&speed, &duplex))
+    }
+}
+
+/// Whether an interface's link is up, from the contents of its sysfs `operstate` file and, if it
+/// could be read, its `carrier` file. None if it can't be told. Virtual interfaces often say
+/// `unknown`, so for those it's up to the carrier.
+pub fn parse_up(operstate: &str, carrier: Option<&str>) -> Option<bool> {
+    match operstate.trim() {
+        "up" => Some(true),
+        "down" | "lowerlayerdown" | "notpresent" | "dormant" => Some(false),
+        _ => match carrier.map(str::trim) {
+            Some("1") => Some(true),
+            Some("0") => Some(false),
+            _ => None,
+        },
+    }
+}
+
+/// Whether an interface is there at all.
+pub fn exists(name: &str) -> bool {
+    Path::new(&format!("/sys/class/net/{}", name)).exists()
+}
+
+/// Whether an interface's link is up, read from sysfs. None if it can't be told, e.g. because the
+/// interface is gone.
+pub fn read_up(name: &str) -> Option<bool> {
+    let dir = format!("/sys/class/net/{}", name);
+    let operstate = fs::read_to_string(format!("{}/operstate", dir)).ok()?;
+    // this fails to read with EINVAL while the interface is administratively down
+    let carrier = fs::read_to_string(format!("{}/carrier", dir)).ok();
+    parse_up(&operstate, carrier.as_deref())
+}
+
+impl std::fmt::Display for LinkInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.speed_mbps {
+            Some(speed) => write!(f, "{}", speed)?,
+            None => f.write_str("?")?,
+        }
+        f.write_str(match self.duplex {
+            Duplex::Full => "FD",
+            Duplex::Half => "HD",
+            Duplex::Unknown => "",
+        })
+    }
+}
+
+/// Tracks changes in an interface's link parameters, and how long to keep showing that a change
+/// happened.
+pub struct LinkWatch {
+    last: Option<LinkInfo>,
+    marked_until: Option<Instant>,
+}
+
+impl LinkWatch {
+    pub fn new() -> Self {
+        Self { last: None, marked_until: None }
+    }
+
+    /// Feed in the current link parameters. Returns the previous ones if they changed. The first
+    /// reading isn't a change.
+    pub fn update(&mut self, now: Instant, info: LinkInfo, hold: Duration) -> Option<LinkInfo> {
+        let prev = self.last.replace(info)?;
+        if prev == info {
+            return None;
+        }
+        self.marked_until = Some(now + hold);
+        Some(prev)
+    }
+
+    /// The negotiated link speed as of the last update, if there is one.
+    pub fn speed_mbps(&self) -> Option<u32> {
+        self.last.and_then(|info| info.speed_mbps)
+    }
+
+    /// Whether the interface should currently be marked as having had a link change, or as being
+    /// in half-duplex, which is almost always a fault.
+    pub fn marked(&self, now: Instant) -> bool {
+        matches!(self.marked_until, Some(until) if now < until)
+            || matches!(self.last, Some(LinkInfo { duplex: Duplex::Half, .. }))
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_link_info_parse() {
+    let gig = LinkInfo { speed_mbps: Some(1000), duplex: Duplex::Full };
+    assert_eq!(gig, LinkInfo::parse("1000\n", "full\n"));
+    assert_eq!("1000FD", gig.to_string());
+    let down = LinkInfo::parse("-1\n", "unknown\n");
+    assert_eq!(LinkInfo { speed_mbps: None, duplex: Duplex::Unknown }, down);
+    assert_eq!("?", down.to_string());
+    assert_eq!(LinkInfo { speed_mbps: None, duplex: Duplex::Half }, LinkInfo::parse("", "half"));
+}
+
+#[cfg(test)]
+#[test]
+fn test_parse_up() {
+    assert_eq!(Some(true), parse_up("up\n", Some("1\n")));
+    assert_eq!(Some(false), parse_up("down\n", Some("0\n")));
+    assert_eq!(Some(false), parse_up("down\n", None));
+    assert_eq!(Some(false), parse_up("lowerlayerdown\n", Some("0\n")));
+    // a WireGuard or loopback interface
+    assert_eq!(Some(true), parse_up("unknown\n", Some("1\n")));
+    assert_eq!(Some(false), parse_up("unknown\n", Some("0\n")));
+    assert_eq!(None, parse_up("unknown\n", None));
+    assert_eq!(None, parse_up("", None));
+}
+
+#[cfg(test)]
+#[test]
+fn test_scale_parse() {
+    assert_eq!(Scale::Link, Scale::parse("link").unwrap());
+    assert_eq!("should be \"global\" or \"link\", not \"auto\"",
+        Scale::parse("auto").unwrap_err().to_string());
+}
+
+#[cfg(test)]
+#[test]
+fn test_link_watch() {
+    let start = Instant::now();
+    let secs = |s| start + Duration::from_secs(s);
+    let hold = Duration::from_secs(30);
+    let gig = LinkInfo { speed_mbps: Some(1000), duplex: Duplex::Full };
+    let fast = LinkInfo { speed_mbps: Some(100), duplex: Duplex::Full };
+    let half = LinkInfo { speed_mbps: Some(100), duplex: Duplex::Half };
+
+    let mut watch = LinkWatch::new();
+    assert_eq!(None, watch.speed_mbps());
+    assert_eq!(None, watch.update(secs(0), gig, hold));
+    assert!(!watch.marked(secs(0)));
+    assert_eq!(Some(1000), watch.speed_mbps());
+    assert_eq!(None, watch.update(secs(5), gig, hold));
+
+    assert_eq!(Some(gig), watch.update(secs(10), fast, hold));
+    assert!(watch.marked(secs(10)));
+    assert!(watch.marked(secs(39)));
+    assert!(!watch.marked(secs(40)));
+
+    // half duplex stays marked
+    assert_eq!(Some(fast), watch.update(secs(50), half, hold));
+    assert!(watch.marked(secs(1000)));
+
+    // back to full: marked only for the hold time
+    assert_eq!(Some(half), watch.update(secs(1000), gig, hold));
+    assert!(watch.marked(secs(1029)));
+    assert!(!watch.marked(secs(1030)));
+}
@@ -1,47 +1,317 @@
-use anyhow::{Context, Result};
-use std::collections::VecDeque;
+use anyhow::{bail, Context, Result};
+use std::collections::{BTreeMap, VecDeque};
 use std::fmt::Write;
+use std::panic::{self, AssertUnwindSafe};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
 use systemstat::{Platform, System};
 
-// TODO: make this configurable
-const NET_DEV_NAMES: [&str; 6] = [
-    "ether0", "ether1", "ether2", "ether3", "ether4", "ether5",
+/// How often to re-read slowly-changing values. CPU and network are read every frame, and memory,
+/// temperatures, fans, and filesystems as often as the config says.
+const ADDRESS_INTERVAL: Duration = Duration::from_secs(60);
+const LEASES_INTERVAL: Duration = Duration::from_secs(15);
+const TOP_INTERVAL: Duration = Duration::from_secs(5);
+const SESSIONS_INTERVAL: Duration = Duration::from_secs(5);
+const CLOCK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// With a `[ups]` section, how often to ask about the UPS, and how long to wait before asking again
+/// after it doesn't answer. The last one repeats.
+const UPS_INTERVAL: Duration = Duration::from_secs(15);
+const UPS_BACKOFF: &[Duration] = &[
+    Duration::from_secs(15),
+    Duration::from_secs(30),
+    Duration::from_secs(60),
+    Duration::from_secs(300),
 ];
 
-const I2C_BUS: u8 = 2;
-const I2C_BUS_FALLBACK: u8 = 1;
-const I2C_ADDR: u16 = 0x27;
+/// With `address_url`, how often to ask it for the external address, and how soon to ask again if
+/// it doesn't answer.
+const EXTERNAL_ADDRESS_INTERVAL: Duration = Duration::from_secs(30 * 60);
+const EXTERNAL_ADDRESS_RETRY: Duration = Duration::from_secs(60);
 
-#[cfg(not(feature = "mock"))]
-mod lcd_display;
+/// How fast the bars' peak-hold markers fall once `peak_hold` is up, in gauge heights per second.
+const PEAK_HOLD_FALL: f64 = 0.5;
+
+/// After this many frames in a row with errors writing to the display, assume it's gone (e.g. its
+/// cable was bumped) and open it again from scratch.
+const RECOVER_AFTER_FRAMES: u32 = 3;
+
+/// How long to wait between attempts to open the display again, if it doesn't work right away.
+/// The last one repeats for as long as it takes.
+const RECOVER_BACKOFF: &[Duration] = &[
+    Duration::from_secs(1),
+    Duration::from_secs(5),
+    Duration::from_secs(30),
+];
+
+/// How often to log a summary of display write latencies.
+const LATENCY_LOG_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// How often to check interfaces' negotiated speed and duplex.
+const LINK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often to look for configured interfaces that didn't exist yet at startup, and for
+/// auto-detected ones that have appeared since.
+const PENDING_RETRY: Duration = Duration::from_secs(5);
+const DETECT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long to mark an interface's columns with a `!` after it reports errors or drops.
+const ERROR_MARK: Duration = Duration::from_secs(5);
+
+/// Fastest a sample can have an interface going, as a multiple of its link speed (with some slack
+/// for samples taken late), or in Mbps if it doesn't have one. Anything faster is taken to be its
+/// counters jumping, e.g. on a driver reset, and the speeds before it are kept instead. Without a
+/// link speed, that's low enough to catch a 32-bit counter jumping a whole wrap (4 GiB) in samples
+/// up to about 3 seconds apart, which also means loopback going faster than it isn't believed.
+const MAX_LINK_MULTIPLE: f64 = 2.;
+const MAX_MBPS: f64 = 10_000.;
+
+/// With `accessible`, how often the display updates at most.
+const ACCESSIBLE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// If any interfaces have labels configured, the bottom row shows them under the interfaces'
+/// columns for LEGEND_DURATION out of every LEGEND_PERIOD.
+const LEGEND_PERIOD: Duration = Duration::from_secs(10);
+const LEGEND_DURATION: Duration = Duration::from_secs(2);
+
+/// If `big_digits` is configured, the whole display shows it in big digits for BIG_DIGITS_DURATION
+/// at the end of every BIG_DIGITS_PERIOD.
+const BIG_DIGITS_PERIOD: Duration = Duration::from_secs(30);
+const BIG_DIGITS_DURATION: Duration = Duration::from_secs(5);
+
+/// If `pages` are configured, the main display's status row shows the name of each page for
+/// PAGE_NAME_DURATION on switching to it.
+const PAGE_NAME_DURATION: Duration = Duration::from_secs(2);
+
+/// The `{rates}` status field shows each of the current, average, and peak speeds for RATE_PERIOD
+/// in turn.
+const RATE_PERIOD: Duration = Duration::from_secs(3);
+
+/// When an alert goes off, the backlight flashes off and on ALERT_FLASHES times, for
+/// ALERT_FLASH_PERIOD each way, and again every ALERT_REPEAT while it's still going. It clears once
+/// the value drops ALERT_HYSTERESIS (a fraction of the threshold) below the threshold.
+const ALERT_FLASH_PERIOD: Duration = Duration::from_millis(500);
+const ALERT_FLASHES: u32 = 3;
+const ALERT_REPEAT: Duration = Duration::from_secs(30);
+const ALERT_HYSTERESIS: f64 = 0.05;
+
+/// When the watchdog finds the main loop stuck, how long to spend trying to leave the shutdown
+/// message before giving up on it.
+const PARTING_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// With --takeover, how long to wait for an already-running instance to exit.
+const TAKEOVER_TIMEOUT: Duration = Duration::from_secs(10);
+
+mod address;
+
+mod alert;
+use alert::{Alerts, Readings};
+
+mod args;
+use args::Args;
+
+mod backlight;
+
+mod bigdigits;
+
+mod burst;
+use burst::Burst;
+
+mod charset;
+
+mod clock;
+use clock::SyncWatch;
+
+mod coarse;
+use coarse::CoarseGauge;
+
+mod detect;
+
+mod disk;
+
+mod display;
+use display::{DisplayBackend, Driver};
+
+mod framebuffer;
+use framebuffer::Framebuffer;
+
+mod glyphs;
 
-#[cfg(not(feature = "mock"))]
-use lcd_display::{init_display, stop_display, is_bus_fubar_error};
+mod hwmon;
+use hwmon::CpuTemp;
+
+mod instance;
+
+mod iowait;
+use iowait::{CoreLoad, Iowait};
+
+mod config;
+
+mod cores;
+use config::{Config, Screen};
+
+mod conntrack;
+use conntrack::Conntrack;
+
+mod latency;
+
+mod layout;
+use layout::{Layout, NetColumns, Panel, PanelColumns, Segment, SegmentValues, StatusItem,
+    StatusValues};
+
+mod leases;
+
+mod link;
+use link::{Duplex, LinkInfo, LinkWatch};
+
+mod toml;
+
+mod totals;
+
+mod peak;
+use peak::{PeakDecay, PeakHold, PeakHolds};
+
+mod ping;
+use ping::Pinger;
+
+mod probe;
+
+mod procs;
+use procs::TopProcess;
+
+mod recovery;
+use recovery::Recovery;
+
+mod priority;
+
+mod watchdog;
+use watchdog::{Stage, Watchdog};
+
+mod lcd_display;
+use lcd_display::{init_display, is_bus_fubar_error};
 
-#[cfg(feature = "mock")]
 mod mock_display;
+use mock_display::MockDisplay;
+
+mod scale;
+use scale::Scale;
+
+mod sessions;
+
+mod smoothing;
+use smoothing::{Ema, Emas};
+
+mod sparkline;
+use sparkline::History;
 
-#[cfg(feature = "mock")]
-use mock_display::{init_display, stop_display, is_bus_fubar_error};
+mod ssd1306;
+
+mod swap;
+use swap::SwapActivity;
+
+mod units;
+
+mod ups;
+
+mod wireless;
 
 struct NetStats {
     name: String,
+    link: LinkWatch,
     last: NetSample,
+    tx_total: ByteTotal,
+    rx_total: ByteTotal,
+    errors: ErrorCount,
+    /// When its link went down, while it's down.
+    down_since: Option<Instant>,
+    /// When it stopped being there to read, e.g. because it was unplugged, while it's gone.
+    missing_since: Option<Instant>,
+    /// While it's yet to appear for the first time, when it was last looked for.
+    pending: Option<Instant>,
+    /// For an aggregate, the interfaces it sums. Empty for a real interface.
+    members: Vec<Member>,
     pub buckets: VecDeque<(Instant, NetSpeeds)>,
 }
 
+/// One of the interfaces an aggregate sums.
+struct Member {
+    name: String,
+    /// None until it's been read, and while it can't be.
+    last: Option<NetSample>,
+    failing: bool,
+    /// Its negotiated link speed, as of the last check, if it has one.
+    link_mbps: Option<u32>,
+}
+
+impl Member {
+    fn list(names: &[String]) -> Vec<Self> {
+        names.iter()
+            .map(|name| Member { name: name.clone(), last: None, failing: false, link_mbps: None })
+            .collect()
+    }
+
+    /// Fastest a sample can believably have it going.
+    fn max_mbps(&self) -> f64 {
+        max_mbps(self.link_mbps)
+    }
+}
+
+/// Fastest a sample can believably have an interface with the given link speed going.
+fn max_mbps(link_mbps: Option<u32>) -> f64 {
+    link_mbps.map_or(MAX_MBPS, |link| link as f64 * MAX_LINK_MULTIPLE)
+}
+
 impl NetStats {
-    pub fn new(name: String) -> Result<Self> {
-        let last = Self::sample(&name)?;
-        Ok(Self {
+    /// Stats for the named interface, or for an aggregate of `members` if there are any.
+    pub fn new(name: String, members: &[String]) -> Result<Self> {
+        let mut members = Member::list(members);
+        let last = if members.is_empty() {
+            Self::sample(&name)?
+        } else {
+            // an aggregate's counters start from zero
+            let mut sum = NetSample::zero(Instant::now());
+            add_members(&name, &mut members, &mut sum);
+            if members.iter().all(|member| member.last.is_none()) {
+                bail!("failed to get stats for any of {}'s members", name);
+            }
+            sum
+        };
+        Ok(Self::from_sample(name, members, last))
+    }
+
+    /// Stats for an interface (or aggregate) that isn't there yet, like a PPP or WireGuard one
+    /// that's still being brought up at boot. It shows nothing until it appears, and is looked for
+    /// every PENDING_RETRY.
+    pub fn pending(name: String, members: &[String]) -> Self {
+        let now = Instant::now();
+        Self {
+            pending: Some(now),
+            ..Self::from_sample(name, Member::list(members), NetSample::zero(now))
+        }
+    }
+
+    fn from_sample(name: String, members: Vec<Member>, last: NetSample) -> Self {
+        Self {
+            link: LinkWatch::new(),
+            tx_total: ByteTotal::default(),
+            rx_total: ByteTotal::default(),
+            errors: ErrorCount::default(),
+            down_since: None,
+            missing_since: None,
+            pending: None,
             name,
             last,
+            members,
             buckets: VecDeque::new(),
-        })
+        }
+    }
+
+    /// Whether these are the stats for the given interface, or for the given aggregate.
+    fn matches(&self, name: &str, members: &[String]) -> bool {
+        self.name == name && self.members.iter().map(|member| &member.name).eq(members)
     }
 
     fn sample(name: &str) -> Result<NetSample> {
@@ -50,55 +320,509 @@ impl NetStats {
         let now = Instant::now();
         let rx_bytes = stats.rx_bytes.as_u64();
         let tx_bytes = stats.tx_bytes.as_u64();
-        Ok(NetSample { time: now, rx_bytes, tx_bytes })
+        // systemstat doesn't read these, and not every driver has them
+        let dropped = |file: &str| {
+            std::fs::read_to_string(format!("/sys/class/net/{}/statistics/{}", name, file))
+                .ok()
+                .and_then(|count| count.trim().parse::<u64>().ok())
+                .unwrap_or(0)
+        };
+        Ok(NetSample {
+            time: now,
+            rx_bytes,
+            tx_bytes,
+            rx_packets: stats.rx_packets,
+            tx_packets: stats.tx_packets,
+            errors: stats.rx_errors.wrapping_add(stats.tx_errors),
+            drops: dropped("rx_dropped").wrapping_add(dropped("tx_dropped")),
+        })
+    }
+
+    /// Check for changes in the negotiated link speed/duplex, logging any, and marking the
+    /// interface for `mark` after one.
+    pub fn check_link(&mut self, now: Instant, mark: Duration) {
+        if !self.members.is_empty() {
+            // an aggregate has no link of its own, but its members' speeds say how fast each
+            // could be going
+            for member in &mut self.members {
+                member.link_mbps = LinkInfo::read(&member.name).and_then(|info| info.speed_mbps);
+            }
+            return;
+        }
+        let info = match LinkInfo::read(&self.name) {
+            Some(info) => info,
+            None => return,
+        };
+        if let Some(prev) = self.link.update(now, info, mark) {
+            eprintln!("{}: link changed from {} to {}", self.name, prev, info);
+            if info.duplex == Duplex::Half {
+                eprintln!("{}: warning: link is half-duplex", self.name);
+            }
+        }
+    }
+
+    /// Check whether the link is up, logging when it goes down and comes back up. One that can't
+    /// be told is taken to be up. `link_mark` is as for `check_link`.
+    fn check_up(&mut self, now: Instant, link_mark: Duration) {
+        if !self.members.is_empty() {
+            // an aggregate has no link of its own
+            return;
+        }
+        let up = match link::read_up(&self.name) {
+            Some(up) => up,
+            // gone altogether, which sampling it deals with
+            None if !link::exists(&self.name) => return,
+            None => true,
+        };
+        match (up, self.down_since) {
+            (false, None) => {
+                eprintln!("{}: link is down", self.name);
+                self.down_since = Some(now);
+            }
+            (true, Some(since)) => {
+                eprintln!("{}: link is up again after {}s", self.name, (now - since).as_secs());
+                self.down_since = None;
+                // it may have come back at a different speed
+                self.check_link(now, link_mark);
+            }
+            _ => (),
+        }
+    }
+
+    /// How long the link has been down, or the interface gone, if it is. One that's yet to appear
+    /// doesn't count.
+    pub fn down_for(&self, now: Instant) -> Option<Duration> {
+        self.down_since.or(self.missing_since).map(|since| now - since)
+    }
+
+    /// Whether the interface is gone, for now, or yet to appear.
+    pub fn missing(&self) -> bool {
+        self.missing_since.is_some() || self.pending.is_some()
+    }
+
+    /// Whether it's there to sample, which it is unless it's yet to appear. If it's been
+    /// PENDING_RETRY since it was last looked for, it's looked for again, and if it's there now,
+    /// its counters are read to start from.
+    fn appeared(&mut self, now: Instant) -> bool {
+        match self.pending {
+            None => return true,
+            Some(tried) if now - tried < PENDING_RETRY => return false,
+            Some(_) => (),
+        }
+        let sample = if self.members.is_empty() {
+            Self::sample(&self.name).ok()
+        } else {
+            let mut sum = self.last.clone();
+            add_members(&self.name, &mut self.members, &mut sum);
+            self.members.iter().any(|member| member.last.is_some()).then_some(sum)
+        };
+        match sample {
+            Some(sample) => {
+                eprintln!("{}: appeared", self.name);
+                self.last = NetSample { time: now, ..sample };
+                self.pending = None;
+                true
+            }
+            None => {
+                self.pending = Some(now);
+                false
+            }
+        }
     }
 
-    pub fn get_speeds(&mut self) -> Result<NetSpeeds> {
-        let sample = Self::sample(&self.name)?;
-        let now = sample.time;
-        let speeds = sample.speeds(&self.last);
+    /// The latest transmit and receive speeds, in Mbps.
+    pub fn current_mbps(&self) -> (f64, f64) {
+        self.buckets.back().map_or((0., 0.), |(_, NetSpeeds { tx, rx })| (tx.mbps(), rx.mbps()))
+    }
+
+    /// The average transmit and receive speeds over the window, in Mbps. The samples aren't evenly
+    /// spaced, so rather than the mean of their speeds, it's everything sent and received over
+    /// the total time they cover.
+    pub fn mean_mbps(&self) -> (f64, f64) {
+        let secs: f64 = self.buckets.iter().map(|(_, speeds)| speeds.tx.secs).sum();
+        if secs <= 0. {
+            return (0., 0.);
+        }
+        let mbps = |bytes: u64| bytes as f64 / secs * 8. / 1_000_000.;
+        (mbps(self.buckets.iter().map(|(_, speeds)| speeds.tx.bytes).sum()),
+            mbps(self.buckets.iter().map(|(_, speeds)| speeds.rx.bytes).sum()))
+    }
+
+    /// The peak transmit and receive speeds over the window, in Mbps, which needn't be from the
+    /// same sample.
+    pub fn peak_mbps(&self) -> (f64, f64) {
+        self.buckets.iter().fold((0., 0.), |(tx, rx), (_, speeds)| {
+            (speeds.tx.mbps().max(tx), speeds.rx.mbps().max(rx))
+        })
+    }
+
+    /// Take a sample, returning the speeds since the last one. Samples older than `window` are
+    /// dropped from `buckets`. An interface that's gone shows nothing moving, and is tried again
+    /// each time; one that's yet to appear shows nothing moving until it does. `link_mark` is how
+    /// long to mark it if it comes back up at a different speed.
+    pub fn get_speeds(&mut self, window: Duration, link_mark: Duration) -> NetSpeeds {
+        if !self.appeared(Instant::now()) {
+            let idle = NetSample { time: Instant::now(), ..self.last.clone() };
+            let speeds = idle.speeds(&self.last);
+            self.last = idle;
+            return self.record(speeds, window);
+        }
+        self.check_up(Instant::now(), link_mark);
+        // whether there's no telling how far the counters went since the last sample
+        let mut gap = false;
+        let sample = if self.members.is_empty() {
+            match Self::sample(&self.name) {
+                Ok(sample) => {
+                    if let Some(since) = self.missing_since.take() {
+                        eprintln!("{}: back after {}s", self.name, (sample.time - since).as_secs());
+                        // its counters may well have started again from zero
+                        gap = true;
+                    }
+                    sample
+                }
+                // some drivers' stats can't be read while the link is down, which is no reason to
+                // stop; nothing's moving anyway
+                Err(_) if self.down_since.is_some() && link::exists(&self.name) => {
+                    NetSample { time: Instant::now(), ..self.last.clone() }
+                }
+                Err(e) => {
+                    if self.missing_since.is_none() {
+                        eprintln!("{}: gone until it can be read again: {:#}", self.name, e);
+                        self.missing_since = Some(Instant::now());
+                        self.down_since = None;
+                    }
+                    gap = true;
+                    NetSample { time: Instant::now(), ..self.last.clone() }
+                }
+            }
+        } else {
+            let mut sum = self.last.clone();
+            add_members(&self.name, &mut self.members, &mut sum);
+            sum.time = Instant::now();
+            sum
+        };
+        let speeds = if gap {
+            sample.speeds(&NetSample { time: self.last.time, ..sample.clone() })
+        } else {
+            let max_mbps = if self.members.is_empty() {
+                max_mbps(self.link.speed_mbps())
+            } else {
+                self.members.iter().map(Member::max_mbps).sum()
+            };
+            let speeds = sample.speeds(&self.last);
+            // a jump that isn't believed isn't counted either, or it'd stay in the totals for good
+            if speeds.plausible(max_mbps) {
+                self.tx_total.add(sample.tx_bytes, self.last.tx_bytes);
+                self.rx_total.add(sample.rx_bytes, self.last.rx_bytes);
+                self.errors.add(sample.time, (sample.errors, sample.drops),
+                    (self.last.errors, self.last.drops), ERROR_MARK);
+            }
+            speeds.or_previous(self.buckets.back().map(|(_, speeds)| speeds), max_mbps)
+        };
         self.last = sample;
+        self.record(speeds, window)
+    }
 
+    /// Add the speeds since the last sample to `buckets`, dropping any older than `window`, and
+    /// return them.
+    fn record(&mut self, speeds: NetSpeeds, window: Duration) -> NetSpeeds {
+        let now = self.last.time;
         while let Some((time, _)) = self.buckets.front() {
-            if (now - *time).as_secs_f64() < 60. {
+            if now - *time < window {
                 break;
             }
             self.buckets.pop_front();
         }
         self.buckets.push_back((now, speeds.clone()));
 
-        Ok(speeds)
+        speeds
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_net_stats_rates() {
+    let mut dev = NetStats::new("lo".to_owned(), &[]).unwrap();
+    assert_eq!((0., 0.), dev.current_mbps());
+    assert_eq!((0., 0.), dev.mean_mbps());
+    assert_eq!((0., 0.), dev.peak_mbps());
+
+    // a 1-second burst of 900 Mbps received, among 9 seconds of 100 Mbps sent, sampled unevenly
+    let start = Instant::now();
+    let mbps = |mbps: f64, secs: f64| NetSpeed { bytes: (mbps * secs * 125_000.) as u64,
+        packets: 0, secs };
+    for (tx, rx, secs) in [(100., 0., 4.), (0., 900., 1.), (100., 0., 0.5), (100., 0., 4.5)] {
+        dev.buckets.push_back((start, NetSpeeds { tx: mbps(tx, secs), rx: mbps(rx, secs) }));
+    }
+    assert_eq!((100., 0.), dev.current_mbps());
+    // averaging the samples' speeds would give 75 and 225
+    let (tx, rx) = dev.mean_mbps();
+    assert!((tx - 90.).abs() < 1e-9 && (rx - 90.).abs() < 1e-9, "{} {}", tx, rx);
+    assert_eq!((100., 900.), dev.peak_mbps());
+}
+
+#[cfg(test)]
+#[test]
+fn test_net_stats_missing() {
+    let window = Duration::from_secs(60);
+    let mut dev = NetStats::new("lo".to_owned(), &[]).unwrap();
+    // unplugged, as far as it can tell
+    dev.name = "h2lcd-gone0".to_owned();
+    let speeds = dev.get_speeds(window, Duration::ZERO);
+    assert_eq!((0., 0.), (speeds.tx.mbps(), speeds.rx.mbps()));
+    assert!(dev.missing());
+    assert!(dev.down_for(Instant::now()).is_some());
+    dev.get_speeds(window, Duration::ZERO);
+    assert!(dev.missing());
+
+    // back, with counters nothing like before: nothing's counted across the gap
+    dev.name = "lo".to_owned();
+    dev.last.rx_bytes = u64::MAX / 2;
+    dev.last.tx_bytes = u64::MAX / 2;
+    let speeds = dev.get_speeds(window, Duration::ZERO);
+    assert_eq!((0., 0.), (speeds.tx.mbps(), speeds.rx.mbps()));
+    assert!(!dev.missing());
+    assert_eq!(None, dev.down_for(Instant::now()));
+    assert_ne!(u64::MAX / 2, dev.last.rx_bytes);
+    assert_eq!((0, 0), (dev.tx_total.bytes, dev.rx_total.bytes));
+    assert_eq!(3, dev.buckets.len());
+}
+
+#[cfg(test)]
+#[test]
+fn test_net_stats_pending() {
+    let window = Duration::from_secs(60);
+    let mut dev = NetStats::pending("h2lcd-later0".to_owned(), &[]);
+    let speeds = dev.get_speeds(window, Duration::ZERO);
+    assert_eq!((0., 0.), (speeds.tx.mbps(), speeds.rx.mbps()));
+    assert!(dev.missing());
+    // not down, since it was never up
+    assert_eq!(None, dev.down_for(Instant::now()));
+
+    // only looked for again once it's been long enough
+    dev.name = "lo".to_owned();
+    dev.get_speeds(window, Duration::ZERO);
+    assert!(dev.missing());
+    dev.pending = Some(Instant::now() - PENDING_RETRY);
+    let speeds = dev.get_speeds(window, Duration::ZERO);
+    assert!(!dev.missing());
+    // counted from when it appeared, not from zero
+    assert!(speeds.rx.bytes < 1_000_000, "{:?}", speeds);
+    assert!(dev.rx_total.bytes < 1_000_000);
+    assert_eq!(3, dev.buckets.len());
+}
+
+/// Add how much each of an aggregate's members' counters went up since their last sample to those
+/// in `sum`. Byte counts are summed rather than speeds, so members being sampled at slightly
+/// different times doesn't matter. Any that can't be read are left out until they can be, rather
+/// than failing the whole aggregate, and so is any jump in one's counters too fast to believe,
+/// which summing them would hide.
+fn add_members(aggregate: &str, members: &mut [Member], sum: &mut NetSample) {
+    for member in members {
+        match NetStats::sample(&member.name) {
+            Ok(new) => {
+                if member.failing {
+                    eprintln!("{}: {} can be read again", aggregate, member.name);
+                    member.failing = false;
+                }
+                if let Some(old) = &member.last {
+                    if new.speeds(old).plausible(member.max_mbps()) {
+                        sum.add_delta(&new, old);
+                    }
+                }
+                member.last = Some(new);
+            }
+            Err(e) => {
+                if !member.failing {
+                    eprintln!("{}: leaving out {} until it can be read: {:#}", aggregate,
+                        member.name, e);
+                    member.failing = true;
+                }
+                member.last = None;
+            }
+        }
+    }
+}
+
+/// How much a counter went up from `old` to `new`. One that went down is taken to have wrapped
+/// around: at 32 bits if it was within them, since some drivers' counters are only that wide, and
+/// at 64 otherwise.
+fn counter_delta(new: u64, old: u64) -> u64 {
+    if new < old && old <= u32::MAX as u64 {
+        (new as u32).wrapping_sub(old as u32) as u64
+    } else {
+        new.wrapping_sub(old)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_counter_delta() {
+    assert_eq!(0, counter_delta(5, 5));
+    assert_eq!(995, counter_delta(1000, 5));
+    // a 32-bit counter wrapping
+    assert_eq!(20, counter_delta(10, u32::MAX as u64 - 9));
+    // and a 64-bit one
+    assert_eq!(20, counter_delta(10, u64::MAX - 9));
+    assert_eq!(1 << 32, counter_delta(1 << 33, 1 << 32));
+    // a counter reset to zero looks like a 32-bit wrap, if it was within 32 bits, and a 64-bit one
+    // otherwise; either way, the speed it gives is too fast to be believed
+    assert_eq!((1 << 32) - 1000, counter_delta(0, 1000));
+    assert_eq!(u64::MAX - (1 << 32) + 1, counter_delta(0, 1 << 32));
+}
+
+/// Exact running total of a byte counter, plus some figures useful for checking its accuracy.
+#[derive(Debug, Default, Clone, PartialEq)]
+struct ByteTotal {
+    bytes: u128,
+    /// How many times the counter went backwards and was assumed to have wrapped around. Each of
+    /// these is a potential source of error, e.g. if it was actually reset.
+    wraps: u64,
+    /// The largest increase seen in one sample.
+    largest_delta: u64,
+}
+
+impl ByteTotal {
+    pub fn add(&mut self, new: u64, old: u64) {
+        if new < old {
+            self.wraps += 1;
+        }
+        let delta = counter_delta(new, old);
+        self.bytes += delta as u128;
+        self.largest_delta = self.largest_delta.max(delta);
+    }
+}
+
+impl std::fmt::Display for ByteTotal {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} bytes ({} wraps, largest delta {})",
+            self.bytes, self.wraps, self.largest_delta)
+    }
+}
+
+/// Errors and drops an interface has reported, both ways, since it was first seen.
+#[derive(Debug, Default, Clone, PartialEq)]
+struct ErrorCount {
+    errors: u64,
+    drops: u64,
+    /// Until when to mark the interface's columns, after the last new one.
+    marked_until: Option<Instant>,
+}
+
+impl ErrorCount {
+    /// Count the errors and drops between two samples of the counters, marking the interface for
+    /// `hold` if there were any.
+    pub fn add(&mut self, now: Instant, new: (u64, u64), old: (u64, u64), hold: Duration) {
+        let errors = counter_delta(new.0, old.0);
+        let drops = counter_delta(new.1, old.1);
+        self.errors = self.errors.saturating_add(errors);
+        self.drops = self.drops.saturating_add(drops);
+        if errors > 0 || drops > 0 {
+            self.marked_until = Some(now + hold);
+        }
+    }
+
+    pub fn marked(&self, now: Instant) -> bool {
+        matches!(self.marked_until, Some(until) if now < until)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_error_count() {
+    let start = Instant::now();
+    let secs = |n| start + Duration::from_secs(n);
+    let hold = Duration::from_secs(5);
+    let mut count = ErrorCount::default();
+    count.add(secs(0), (10, 3), (10, 3), hold);
+    assert!(!count.marked(secs(0)));
+    count.add(secs(1), (12, 3), (10, 3), hold);
+    assert_eq!((2, 0), (count.errors, count.drops));
+    assert!(count.marked(secs(5)));
+    assert!(!count.marked(secs(6)));
+    count.add(secs(2), (12, 4), (12, 3), hold);
+    assert_eq!((2, 1), (count.errors, count.drops));
+    assert!(count.marked(secs(6)));
+    // wraps like the byte counters
+    count.add(secs(3), (1, 4), (u64::MAX, 4), hold);
+    assert_eq!((4, 1), (count.errors, count.drops));
+}
+
+#[cfg(test)]
+#[test]
+fn test_byte_total() {
+    let mut total = ByteTotal::default();
+    let counters = [100, 100, 1100, 5000, 5001];
+    for pair in counters.windows(2) {
+        total.add(pair[1], pair[0]);
     }
+    assert_eq!(ByteTotal { bytes: 4901, wraps: 0, largest_delta: 3900 }, total);
+
+    // a 64-bit wrap is exact
+    let mut total = ByteTotal::default();
+    total.add(u64::MAX - 10, u64::MAX - 20);
+    total.add(5, u64::MAX - 10);
+    assert_eq!(ByteTotal { bytes: 26, wraps: 1, largest_delta: 16 }, total);
+
+    // sums past u64::MAX don't overflow
+    total.add(u64::MAX - 1, 5);
+    total.add(u64::MAX - 2, u64::MAX - 1);
+    assert!(total.bytes > u64::MAX as u128);
+    assert_eq!(2, total.wraps);
+
+    // a counter reset looks like a wrap, which is too fast to be believed, so it's skipped rather
+    // than going into the totals (and the error count)
+    let mut dev = NetStats::new("lo".to_owned(), &[]).unwrap();
+    dev.last.rx_bytes = u64::MAX / 2;
+    dev.last.tx_bytes = u64::MAX / 2;
+    dev.last.errors = u64::MAX / 2;
+    dev.get_speeds(Duration::from_secs(60), Duration::ZERO);
+    assert_eq!(ByteTotal::default(), dev.rx_total);
+    assert_eq!(ByteTotal::default(), dev.tx_total);
+    assert_eq!(0, dev.errors.errors);
+    assert!(!dev.errors.marked(Instant::now()));
+    // and counting carries on from where it was reset to
+    assert!(dev.last.rx_bytes < u64::MAX / 2);
 }
 
 #[derive(Debug, Clone)]
 struct NetSpeed {
     bytes: u64,
+    packets: u64,
     secs: f64,
 }
 
 impl NetSpeed {
     pub fn from_bytes(secs: f64, new: u64, old: u64) -> Self {
-        let bytes = if new < old {
-            // wrap-around
-            u64::MAX - old + new
-        } else {
-            new - old
-        };
-        Self { bytes, secs }
+        Self { bytes: counter_delta(new, old), packets: 0, secs }
+    }
+
+    pub fn with_packets(self, new: u64, old: u64) -> Self {
+        Self { packets: counter_delta(new, old), ..self }
     }
 
     pub fn mbps(&self) -> f64 {
+        if self.secs <= 0. {
+            return 0.;
+        }
         self.bytes as f64 / self.secs * 8. / 1_000_000.
     }
 
-    #[allow(dead_code)]
-    pub fn linear_display(&self) -> f64 {
-        (self.mbps() / 1000.).clamp(0., 1.)
+    /// Where this speed falls on a log scale covering `decades` decades up to `max_mbps`.
+    pub fn log_scale(&self, max_mbps: f64, decades: u32) -> f64 {
+        scale::log(self.mbps(), max_mbps, decades)
     }
 
-    pub fn log_display(&self) -> f64 {
-        (self.mbps().log10() / 3.).clamp(0., 1.)
+    /// Character for this speed's gauge column at the given row, of `rows`, showing `level` (where
+    /// it falls on its scale, perhaps smoothed). With `blip`, any packet activity lights at least
+    /// the bottom pixel.
+    pub fn display_char(&self, level: f64, row: u8, rows: u8, blip: bool) -> u8 {
+        let c = display_char(level, row, rows);
+        if blip && row == rows - 1 && c == b' ' && self.packets > 0 {
+            0
+        } else {
+            c
+        }
     }
 }
 
@@ -108,104 +832,1508 @@ struct NetSpeeds {
     rx: NetSpeed,
 }
 
+impl NetSpeeds {
+    /// Whether neither speed is faster than `max_mbps`, which only a counter jumping could give.
+    pub fn plausible(&self, max_mbps: f64) -> bool {
+        self.tx.mbps() <= max_mbps && self.rx.mbps() <= max_mbps
+    }
+
+    /// These speeds, unless either is faster than `max_mbps`, which only a counter jumping could
+    /// give, in which case the `previous` ones (or none), over the same time.
+    pub fn or_previous(self, previous: Option<&NetSpeeds>, max_mbps: f64) -> Self {
+        if self.plausible(max_mbps) {
+            return self;
+        }
+        let secs = self.tx.secs;
+        let keep = |speed: Option<&NetSpeed>| NetSpeed {
+            bytes: speed.map_or(0, |speed| (speed.mbps() * secs * 125_000.) as u64),
+            packets: speed.map_or(0, |speed| speed.packets),
+            secs,
+        };
+        Self { tx: keep(previous.map(|p| &p.tx)), rx: keep(previous.map(|p| &p.rx)) }
+    }
+
+    /// Glyph for this interface's combined column at the given row, of `rows`, showing `tx_level`
+    /// and `rx_level`. With `blip`, any packet activity lights at least the pixel at that end.
+    pub fn combined_glyph(&self, tx_level: f64, rx_level: f64, row: u8, rows: u8, blip: bool)
+        -> glyphs::Glyph
+    {
+        let glyph = combined_glyph(tx_level, rx_level, row, rows);
+        let rx = blip && row == rows - 1 && self.rx.packets > 0;
+        let tx = blip && row == 0 && self.tx.packets > 0;
+        glyphs::Glyph::split(glyph.fill.max(rx as u8), glyph.top.max(tx as u8))
+    }
+}
+
 #[derive(Debug, Clone)]
 struct NetSample {
     time: Instant,
     rx_bytes: u64,
     tx_bytes: u64,
+    rx_packets: u64,
+    tx_packets: u64,
+    /// Errors and drops, both ways.
+    errors: u64,
+    drops: u64,
 }
 
 impl NetSample {
+    /// A sample with all its counters at zero, to count up from.
+    pub fn zero(time: Instant) -> Self {
+        Self { time, rx_bytes: 0, tx_bytes: 0, rx_packets: 0, tx_packets: 0, errors: 0, drops: 0 }
+    }
+
     pub fn speeds(&self, last: &NetSample) -> NetSpeeds {
         let secs = (self.time - last.time).as_secs_f64();
         NetSpeeds {
-            tx: NetSpeed::from_bytes(secs, self.tx_bytes, last.tx_bytes),
-            rx: NetSpeed::from_bytes(secs, self.rx_bytes, last.rx_bytes),
+            tx: NetSpeed::from_bytes(secs, self.tx_bytes, last.tx_bytes)
+                .with_packets(self.tx_packets, last.tx_packets),
+            rx: NetSpeed::from_bytes(secs, self.rx_bytes, last.rx_bytes)
+                .with_packets(self.rx_packets, last.rx_packets),
         }
     }
-}
-
-struct CPUStats {
-    last: systemstat::DelayedMeasurement<Vec<systemstat::CPULoad>>
-}
-
-impl CPUStats {
-    pub fn new() -> Result<Self> {
-        Ok(Self {
-            last: System::new().cpu_load().context("failed to get CPU load")?,
-        })
-    }
 
-    fn get_load(&mut self) -> Result<Vec<f64>> {
-        let last = std::mem::replace(
-            &mut self.last,
-            System::new().cpu_load().context("failed to get CPU load")?);
-        let meas = last.done().context("failed to update CPU load measurement")?;
-        let mut result = vec![];
-        for core in meas {
-            result.push(1. - core.idle as f64);
-        }
-        Ok(result)
+    /// Add how much the counters went up from `old` to `new` to these ones.
+    pub fn add_delta(&mut self, new: &NetSample, old: &NetSample) {
+        let add = |sum: &mut u64, new: u64, old: u64| {
+            *sum = sum.wrapping_add(counter_delta(new, old));
+        };
+        add(&mut self.rx_bytes, new.rx_bytes, old.rx_bytes);
+        add(&mut self.tx_bytes, new.tx_bytes, old.tx_bytes);
+        add(&mut self.rx_packets, new.rx_packets, old.rx_packets);
+        add(&mut self.tx_packets, new.tx_packets, old.tx_packets);
+        add(&mut self.errors, new.errors, old.errors);
+        add(&mut self.drops, new.drops, old.drops);
     }
 }
 
-fn avail_mem_mib() -> Result<(u64, u64)> {
-    let mem = System::new().memory()?;
-    let total = mem.total.as_u64() / 1_048_576;
-    let avail = mem.platform_memory.meminfo.get("MemAvailable").unwrap().as_u64() / 1_048_576;
-    Ok((avail, total))
-}
-
-fn display_char(value: f64, row: u8) -> u8 {
-    assert!(value >= 0.);
-    assert!(value <= 1.);
-    assert!(row < 3);
-
-    // we've got 3 rows each 8 pixels high, so 24 values
-    let quantized = (value * 24.).ceil() as u8;
-    let row = 2 - row;
-    let pixels = match (quantized / 8).cmp(&row) {
-        std::cmp::Ordering::Greater => 8,
-        std::cmp::Ordering::Less => 0,
-        std::cmp::Ordering::Equal => quantized - (8 * row)
-    };
-
-    if pixels == 0 {
-        // zero pixels is a space char
-        b' '
-    } else {
-        // otherwise it's in custom chars 0 thru 7
-        pixels - 1
-    }
+#[cfg(test)]
+#[test]
+fn test_log_scale() {
+    let speed = |mbps: f64| NetSpeed { bytes: (mbps * 125_000.) as u64, packets: 0, secs: 1. };
+    assert!((speed(100.).log_scale(1000., 3) - 2. / 3.).abs() < 1e-9);
+    assert_eq!(0., speed(0.).log_scale(1000., 3));
+    assert_eq!(1., speed(2000.).log_scale(1000., 3));
+    // 3 decades below the top
+    assert_eq!(0., speed(8.).log_scale(8000., 3));
+    assert!((speed(800.).log_scale(8000., 3) - 2. / 3.).abs() < 1e-9);
 }
 
 #[cfg(test)]
 #[test]
-fn test_display_char() {
-    assert_eq!(32, display_char(0., 0));
-    assert_eq!(32, display_char(0., 1));
-    assert_eq!(32, display_char(0., 2));
+fn test_log_scale_decades() {
+    // a 3-row gauge, top first, at each decade
+    let column = |mbps, max, decades| -> Vec<u8> {
+        (0 .. 3).map(|row| display_char(scale::log(mbps, max, decades), row, 3)).collect()
+    };
 
-    assert_eq!(7, display_char(1., 0));
-    assert_eq!(7, display_char(1., 1));
-    assert_eq!(7, display_char(1., 2));
+    // a 2.5G switch: a decade per row
+    assert_eq!(b"   ", &column(1., 2500., 3)[..]);
+    assert_eq!(b"   ", &column(2.5, 2500., 3)[..]);
+    assert_eq!(b"  \x07", &column(25., 2500., 3)[..]);
+    assert_eq!(b" \x07\x07", &column(250., 2500., 3)[..]);
+    assert_eq!(b"\x07\x07\x07", &column(2500., 2500., 3)[..]);
+    assert_eq!(b"\x07\x07\x07", &column(10000., 2500., 3)[..]);
 
-    assert_eq!(32, display_char(0.5, 0));
-    assert_eq!(3, display_char(0.5, 1));
-    assert_eq!(7, display_char(0.5, 2));
+    // a 50 Mbps WAN link over 2 decades: a decade per row and a half
+    assert_eq!(b"   ", &column(0.5, 50., 2)[..]);
+    assert_eq!(b" \x03\x07", &column(5., 50., 2)[..]);
+    assert_eq!(b"\x07\x07\x07", &column(50., 50., 2)[..]);
 
-    assert_eq!(32, display_char(0.666, 0));
-    assert_eq!(7, display_char(0.666, 1));
-    assert_eq!(7, display_char(0.666, 2));
+    // gigabit over 4 decades
+    assert_eq!(b"   ", &column(0.1, 1000., 4)[..]);
+    assert_eq!(b"  \x05", &column(1., 1000., 4)[..]);
+    assert_eq!(b" \x03\x07", &column(10., 1000., 4)[..]);
+    assert_eq!(b"\x01\x07\x07", &column(100., 1000., 4)[..]);
+    assert_eq!(b"   ", &column(0., 1000., 4)[..]);
 }
 
-fn main() -> Result<()> {
-    let mut display = init_display(I2C_BUS, I2C_ADDR)
-        .or_else(|e| {
-            if is_bus_fubar_error(&e) {
-                eprintln!("error on I2C bus {I2C_BUS}: {e}");
-                eprintln!("trying I2C bus {I2C_BUS_FALLBACK} as fallback");
-                match init_display(I2C_BUS_FALLBACK, I2C_ADDR) {
+#[cfg(test)]
+#[test]
+fn test_fs_used() {
+    let used = fs_used("/").unwrap();
+    assert!((0. ..= 1.).contains(&used));
+    assert_eq!(None, fs_used("/nonexistent/h2lcd"));
+}
+
+#[cfg(test)]
+#[test]
+fn test_activity_blip() {
+    let start = Instant::now();
+    let sample = |secs, bytes, packets| NetSample {
+        time: start + Duration::from_secs(secs),
+        rx_bytes: bytes,
+        tx_bytes: 0,
+        rx_packets: packets,
+        tx_packets: 0,
+        errors: 0,
+        drops: 0,
+    };
+    let first = sample(0, 1000, 10);
+
+    // one small packet in a second is far below one pixel
+    let speeds = sample(1, 1064, 11).speeds(&first);
+    assert_eq!(b' ', speeds.rx.display_char(speeds.rx.log_scale(1000., 3), 2, 3, false));
+    assert_eq!(0, speeds.rx.display_char(speeds.rx.log_scale(1000., 3), 2, 3, true));
+    assert_eq!(b' ', speeds.rx.display_char(speeds.rx.log_scale(1000., 3), 1, 3, true));
+    assert_eq!(b' ', speeds.tx.display_char(speeds.tx.log_scale(1000., 3), 2, 3, true));
+    assert_eq!(0, speeds.rx.display_char(speeds.rx.log_scale(1000., 3), 0, 1, true));
+
+    // samples taken at the same instant don't produce infinite or NaN speeds
+    let speeds = sample(0, 5000, 20).speeds(&first);
+    assert_eq!(0., speeds.rx.mbps());
+    assert_eq!(0., speeds.tx.mbps());
+
+    // no packets, no blip
+    let speeds = sample(1, 1000, 10).speeds(&first);
+    assert_eq!(b' ', speeds.rx.display_char(speeds.rx.log_scale(1000., 3), 2, 3, true));
+
+    // the blip never lowers a column that already registers
+    let speeds = sample(1, 1000 + 1_000_000, 1000).speeds(&first);
+    assert_eq!(speeds.rx.display_char(speeds.rx.log_scale(1000., 3), 2, 3, false),
+        speeds.rx.display_char(speeds.rx.log_scale(1000., 3), 2, 3, true));
+    assert_ne!(b' ', speeds.rx.display_char(speeds.rx.log_scale(1000., 3), 2, 3, true));
+
+    // 8 Mbps fills the column of a 10 Mbps link, but is the bottom row of a gigabit one
+    let speeds = sample(1, 1000 + 1_000_000, 1000).speeds(&first);
+    assert_eq!(7, speeds.rx.display_char(speeds.rx.log_scale(10., 3), 0, 3, false));
+    assert_eq!(b' ', speeds.rx.display_char(speeds.rx.log_scale(1000., 3), 0, 3, false));
+}
+
+#[cfg(test)]
+#[test]
+fn test_add_delta() {
+    let start = Instant::now();
+    let sample = |secs, bytes| NetSample {
+        time: start + Duration::from_secs(secs),
+        rx_bytes: bytes,
+        tx_bytes: bytes.wrapping_mul(2),
+        rx_packets: 0,
+        tx_packets: 0,
+        errors: 0,
+        drops: 0,
+    };
+    // members' byte counts are summed, whenever each one was sampled
+    let mut sum = sample(0, 0);
+    sum.add_delta(&sample(1, 1_000_000), &sample(0, 500_000));
+    sum.add_delta(&sample(3, 250_000), &sample(2, 0));
+    assert_eq!((750_000, 1_500_000), (sum.rx_bytes, sum.tx_bytes));
+    // a member's counter wrapping around adds what it went up by
+    sum.add_delta(&sample(4, 10), &sample(3, u64::MAX - 9));
+    assert_eq!(750_020, sum.rx_bytes);
+
+    let speeds = NetSample { time: start + Duration::from_secs(2), ..sum }.speeds(&sample(0, 20));
+    assert_eq!(3., speeds.rx.mbps());
+}
+
+#[cfg(test)]
+#[test]
+fn test_implausible_speeds() {
+    let start = Instant::now();
+    let sample = |secs, bytes| NetSample {
+        time: start + Duration::from_secs(secs),
+        rx_bytes: bytes,
+        tx_bytes: 0,
+        rx_packets: 0,
+        tx_packets: 0,
+        errors: 0,
+        drops: 0,
+    };
+    let mbps = |speeds: &NetSpeeds| (speeds.tx.mbps(), speeds.rx.mbps());
+    // 100 Mbps received on a gigabit link
+    let before = sample(1, 12_500_000).speeds(&sample(0, 0));
+    assert_eq!((0., 100.), mbps(&before));
+    let checked = |new: &NetSample, old: &NetSample| {
+        new.speeds(old).or_previous(Some(&before), 2000.)
+    };
+
+    // a 32-bit counter wrapping is believable
+    let speeds = checked(&sample(3, 12_500_000 - 1000), &sample(2, u32::MAX as u64 - 999));
+    assert_eq!((0., 100.), mbps(&speeds));
+    // and so is a 64-bit one
+    let speeds = checked(&sample(3, 25_000_000 - 1000), &sample(2, u64::MAX - 999));
+    assert_eq!((0., 200.), mbps(&speeds));
+    // but not a reset to zero: that's 34 Gbps, so the speeds before it are kept, over the new time
+    let speeds = checked(&sample(4, 0), &sample(2, 1000));
+    assert_eq!((0., 100.), mbps(&speeds));
+    assert_eq!(2., speeds.rx.secs);
+    // with nothing before, it's nothing
+    let speeds = sample(3, 0).speeds(&sample(2, 1 << 40)).or_previous(None, MAX_MBPS);
+    assert_eq!((0., 0.), mbps(&speeds));
+    // without a link speed, a 32-bit counter reset a second apart is still caught
+    assert!(!sample(3, 0).speeds(&sample(2, 1000)).plausible(max_mbps(None)));
+    assert!(sample(3, 0).speeds(&sample(2, 1000)).plausible(max_mbps(Some(40_000))));
+
+    // an aggregate's members are each checked against their own link speed
+    let mut dev = NetStats::new("lan".to_owned(), &["lo".to_owned(), "lo".to_owned()]).unwrap();
+    let before = dev.last.clone();
+    for member in &mut dev.members {
+        member.last.as_mut().unwrap().rx_bytes = u64::MAX / 2;
+    }
+    dev.members[1].link_mbps = Some(1000);
+    let mut sum = before.clone();
+    add_members("lan", &mut dev.members, &mut sum);
+    assert!(sum.rx_bytes < u64::MAX / 4, "{:?}", sum);
+    assert!(dev.members.iter().all(|member| member.last.as_ref().unwrap().rx_bytes < u64::MAX / 2));
+}
+
+struct CPUStats {
+    last: systemstat::DelayedMeasurement<Vec<systemstat::CPULoad>>
+}
+
+impl CPUStats {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            last: System::new().cpu_load().context("failed to get CPU load")?,
+        })
+    }
+
+    fn get_load(&mut self, iowait: Iowait) -> Result<Vec<CoreLoad>> {
+        let last = std::mem::replace(
+            &mut self.last,
+            System::new().cpu_load().context("failed to get CPU load")?);
+        let meas = last.done().context("failed to update CPU load measurement")?;
+        let mut result = vec![];
+        for core in meas {
+            result.push(CoreLoad::new(core.idle as f64, core.platform.iowait as f64, iowait));
+        }
+        Ok(result)
+    }
+}
+
+/// A value that only gets re-read once per interval, for things that don't change quickly enough
+/// to be worth reading every frame.
+struct SlowSample<T> {
+    interval: Duration,
+    last: Option<(Instant, T)>,
+}
+
+impl<T: Clone> SlowSample<T> {
+    pub fn new(interval: Duration) -> Self {
+        Self { interval, last: None }
+    }
+
+    /// Get the value, calling `read` for a new one if the last one is older than the interval.
+    /// Errors are not cached, so a failed read is retried next time.
+    pub fn get(&mut self, now: Instant, read: impl FnOnce() -> Result<T>) -> Result<T> {
+        match &self.last {
+            Some((time, value)) if now.saturating_duration_since(*time) < self.interval => {
+                Ok(value.clone())
+            }
+            _ => {
+                let value = read()?;
+                self.last = Some((now, value.clone()));
+                Ok(value)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_slow_sample() {
+    let start = Instant::now();
+    let secs = |s| start + Duration::from_secs(s);
+    let mut reads = 0;
+    let mut slow = SlowSample::new(Duration::from_secs(5));
+    let mut read = |v| {
+        reads += 1;
+        Ok(v)
+    };
+    assert_eq!(1, slow.get(secs(0), || read(1)).unwrap());
+    assert_eq!(1, slow.get(secs(1), || read(2)).unwrap());
+    assert_eq!(1, slow.get(secs(4), || read(3)).unwrap());
+    assert_eq!(4, slow.get(secs(5), || read(4)).unwrap());
+    assert_eq!(4, slow.get(secs(9), || read(5)).unwrap());
+    assert!(slow.get(secs(10), || anyhow::bail!("nope")).is_err());
+    assert_eq!(6, slow.get(secs(10), || read(6)).unwrap());
+    assert_eq!(3, reads);
+}
+
+/// The contents of `/proc/diskstats`, if there are any disks to look for in it.
+fn read_diskstats(disks: &[String]) -> Result<String> {
+    if disks.is_empty() {
+        return Ok(String::new());
+    }
+    std::fs::read_to_string(disk::DISKSTATS_PATH)
+        .with_context(|| format!("failed to read {}", disk::DISKSTATS_PATH))
+}
+
+/// How full the filesystem mounted at `path` is, as a fraction of the space that isn't reserved
+/// for root (like `df` shows), or None if it can't be read.
+fn fs_used(path: &str) -> Option<f64> {
+    let stats = nix::sys::statvfs::statvfs(path).ok()?;
+    let used = stats.blocks().saturating_sub(stats.blocks_free()) as f64;
+    let usable = used + stats.blocks_available() as f64;
+    if usable == 0. {
+        return None;
+    }
+    Some(used / usable)
+}
+
+/// Signal level of each wireless interface (None for any that isn't wireless), and the total
+/// number of clients connected to them, if it can be told. Neither is an error if it can't be
+/// read.
+fn read_wireless(names: &[String]) -> (Vec<Option<f64>>, Option<usize>) {
+    if names.is_empty() {
+        return (vec![], None);
+    }
+    let text = std::fs::read_to_string(wireless::WIRELESS_PATH).unwrap_or_default();
+    let signals = names.iter().map(|name| wireless::parse(&text, name)).collect();
+    let dir = std::path::Path::new(wireless::DEBUGFS_IEEE80211);
+    let clients = names.iter()
+        .filter_map(|name| wireless::stations(dir, name))
+        .fold(None, |sum, stations| Some(sum.unwrap_or(0) + stations));
+    (signals, clients)
+}
+
+/// Memory and swap, in MiB.
+#[derive(Debug, Clone, Copy)]
+struct MemInfo {
+    avail: u64,
+    total: u64,
+    swap_free: u64,
+    swap_total: u64,
+}
+
+impl MemInfo {
+    /// Work it out from the fields of /proc/meminfo, in bytes. Kernels before 3.14 don't have
+    /// MemAvailable, so it's estimated from what's free and what's only used for caches.
+    pub fn parse(meminfo: &BTreeMap<String, u64>) -> Result<Self> {
+        let mib = |key| meminfo.get(key).map(|bytes| bytes / 1_048_576);
+        let avail = match mib("MemAvailable") {
+            Some(avail) => avail,
+            None => match (mib("MemFree"), mib("Buffers"), mib("Cached")) {
+                (Some(free), Some(buffers), Some(cached)) => free + buffers + cached,
+                _ => bail!("no MemAvailable, or MemFree, Buffers, and Cached, in meminfo"),
+            },
+        };
+        Ok(Self {
+            avail,
+            total: mib("MemTotal").context("no MemTotal in meminfo")?,
+            // no swap configured can mean no swap lines at all
+            swap_free: mib("SwapFree").unwrap_or(0),
+            swap_total: mib("SwapTotal").unwrap_or(0),
+        })
+    }
+}
+
+fn mem_info(system: &System) -> Result<MemInfo> {
+    let mem = system.memory()?;
+    MemInfo::parse(&mem.platform_memory.meminfo.iter()
+        .map(|(key, size)| (key.clone(), size.as_u64()))
+        .collect())
+}
+
+#[cfg(test)]
+#[test]
+fn test_mem_info() {
+    let meminfo = |fields: &[(&str, u64)]| MemInfo::parse(&fields.iter()
+        .map(|&(key, mib)| (key.to_owned(), mib * 1_048_576))
+        .collect());
+    let mem = meminfo(&[("MemTotal", 3800), ("MemFree", 200), ("MemAvailable", 2000),
+        ("Buffers", 100), ("Cached", 1500), ("SwapTotal", 1024), ("SwapFree", 1000)]).unwrap();
+    assert_eq!((2000, 3800, 1000, 1024), (mem.avail, mem.total, mem.swap_free, mem.swap_total));
+
+    // an older kernel, with no swap
+    let mem = meminfo(&[("MemTotal", 3800), ("MemFree", 200), ("Buffers", 100),
+        ("Cached", 1500)]).unwrap();
+    assert_eq!((1800, 3800, 0, 0), (mem.avail, mem.total, mem.swap_free, mem.swap_total));
+
+    // what systemstat makes up when there's no /proc/meminfo at all
+    assert_eq!("no MemAvailable, or MemFree, Buffers, and Cached, in meminfo",
+        meminfo(&[("MemTotal", 3800), ("MemFree", 200), ("Buffers", 100)])
+            .unwrap_err().to_string());
+    assert_eq!("no MemTotal in meminfo",
+        meminfo(&[("MemAvailable", 2000)]).unwrap_err().to_string());
+}
+
+/// Read the CPU temperature, in °C, from wherever `source` says.
+fn read_cpu_temp(system: &System, source: &CpuTemp) -> Result<f64> {
+    match source {
+        CpuTemp::Auto => Ok(system.cpu_temp().context("no thermal zone")? as f64),
+        CpuTemp::Off => bail!("it's turned off"),
+        CpuTemp::File(path) => hwmon::read_temp(path),
+    }
+}
+
+fn local_hour_minute() -> Option<(i32, i32)> {
+    // Safety: localtime_r only writes to the tm we give it.
+    unsafe {
+        let now = libc::time(std::ptr::null_mut());
+        let mut tm: libc::tm = std::mem::zeroed();
+        if libc::localtime_r(&now, &mut tm).is_null() {
+            return None;
+        }
+        Some((tm.tm_hour, tm.tm_min))
+    }
+}
+
+fn shutdown_message(message: &str, crashed: bool, time: Option<(i32, i32)>, cols: usize)
+    -> String
+{
+    let mut msg = if crashed {
+        "h2lcd crashed".to_owned()
+    } else {
+        message.to_owned()
+    };
+    if let Some((hour, minute)) = time {
+        write!(&mut msg, " {:02}:{:02}", hour, minute).unwrap();
+    }
+    msg.chars().take(cols).collect()
+}
+
+#[cfg(test)]
+#[test]
+fn test_shutdown_message() {
+    assert_eq!("stopped 09:05", shutdown_message("stopped", false, Some((9, 5)), 20));
+    assert_eq!("h2lcd crashed 23:59", shutdown_message("stopped", true, Some((23, 59)), 20));
+    assert_eq!("stopped", shutdown_message("stopped", false, None, 20));
+    assert_eq!("router stopped 0", shutdown_message("router stopped", false, Some((9, 5)), 16));
+}
+
+/// Leave a display the way the config says to when the program stops: with its last frame still
+/// up, with the shutdown message, or blank and dark.
+fn stop_display(display: Box<dyn DisplayBackend>, config: &Config, cols: usize, crashed: bool) {
+    if config.shutdown_freeze {
+        return;
+    }
+    match &config.shutdown_message {
+        Some(message) => {
+            let message = shutdown_message(message, crashed, local_hour_minute(), cols);
+            display.stop(Some(&message), config.shutdown_backlight);
+        }
+        None => display.stop(None, false),
+    }
+}
+
+/// For when the main loop is stuck, maybe on a display: open each display again with `open`, and
+/// leave it the way the config says to for a crash. That's done on a thread of its own, and given
+/// up on after `timeout`, so a hung display can't keep the watchdog from going on to abort. Returns
+/// whether it finished in time.
+fn stop_displays_within(
+    config: Config,
+    open: impl Fn(&Screen, usize) -> Result<Box<dyn DisplayBackend>> + Send + 'static,
+    timeout: Duration,
+) -> bool {
+    // opening the display would clear the last frame
+    if config.shutdown_freeze {
+        return true;
+    }
+    let (tx, rx) = mpsc::channel();
+    let spawned = thread::Builder::new()
+        .name("shutdown".to_owned())
+        .spawn(move || {
+            for (i, screen) in config.screens().iter().enumerate() {
+                match open(screen, i) {
+                    Ok(display) => stop_display(display, &config, screen.geometry.cols, true),
+                    Err(e) => eprintln!("failed to open {} to leave the shutdown message: {:#}",
+                        display_name(i), e),
+                }
+            }
+            let _ = tx.send(());
+        });
+    spawned.is_ok() && rx.recv_timeout(timeout).is_ok()
+}
+
+/// A display that records what's done to it, for testing the shutdown sequence, and optionally
+/// hangs on stopping, like one whose bus is wedged.
+#[cfg(test)]
+struct Recorder {
+    log: Arc<Mutex<Vec<String>>>,
+    hang: bool,
+}
+
+#[cfg(test)]
+impl DisplayBackend for Recorder {
+    fn position(&mut self, _col: u8, _row: u8) {}
+    fn write(&mut self, _c: u8) {}
+    fn clear(&mut self) {}
+    fn upload_character(&mut self, _location: u8, _map: [u8; 8]) {}
+    fn reinit(&mut self) {}
+    fn set_backlight(&mut self, _on: bool) {}
+
+    fn stop(self: Box<Self>, message: Option<&str>, backlight: bool) {
+        if self.hang {
+            thread::sleep(Duration::from_secs(3600));
+        }
+        self.log.lock().unwrap().push(format!("stop {:?} {}", message, backlight));
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_stop_display() {
+    let log = Arc::new(Mutex::new(vec![]));
+    let stop = |config: &Config, crashed| {
+        let display = Box::new(Recorder { log: Arc::clone(&log), hang: false });
+        stop_display(display, config, 20, crashed);
+        log.lock().unwrap().drain(..).collect::<Vec<_>>()
+    };
+    let config = Config { shutdown_message: Some("bye".to_owned()), ..Config::default() };
+    let stopped = stop(&config, false);
+    assert!(stopped[0].starts_with("stop Some(\"bye"), "{:?}", stopped);
+    assert!(stopped[0].ends_with("\") false"), "{:?}", stopped);
+    let config = Config { shutdown_backlight: true, ..config };
+    let stopped = stop(&config, true);
+    assert!(stopped[0].starts_with("stop Some(\"h2lcd crashed"), "{:?}", stopped);
+    assert!(stopped[0].ends_with("\") true"), "{:?}", stopped);
+    // blank, and the backlight off, whatever it's set to
+    assert_eq!(vec!["stop None false"], stop(&Config { shutdown_message: None, ..config.clone() },
+        false));
+    // and frozen, the display isn't touched at all
+    assert!(stop(&Config { shutdown_freeze: true, ..config }, false).is_empty());
+}
+
+#[cfg(test)]
+#[test]
+fn test_stop_displays_within() {
+    let log = Arc::new(Mutex::new(vec![]));
+    let open = |hang| {
+        let log = Arc::clone(&log);
+        move |_: &Screen, i| -> Result<Box<dyn DisplayBackend>> {
+            log.lock().unwrap().push(format!("open {}", i));
+            Ok(Box::new(Recorder { log: Arc::clone(&log), hang }))
+        }
+    };
+    let config = Config { shutdown_message: None, ..Config::default() };
+    assert!(stop_displays_within(config.clone(), open(false), Duration::from_secs(5)));
+    assert_eq!(vec!["open 0", "stop None false"],
+        log.lock().unwrap().drain(..).collect::<Vec<_>>());
+
+    // a display that never finishes is given up on
+    let start = Instant::now();
+    assert!(!stop_displays_within(config.clone(), open(true), Duration::from_millis(50)));
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert_eq!(vec!["open 0"], log.lock().unwrap().drain(..).collect::<Vec<_>>());
+
+    // frozen, it isn't even opened, since that would clear it
+    let frozen = Config { shutdown_freeze: true, ..config };
+    assert!(stop_displays_within(frozen, open(false), Duration::from_secs(5)));
+    assert!(log.lock().unwrap().is_empty());
+}
+
+/// What to show while starting up, until the first frame is drawn over it: the hostname, and the
+/// program's name and version.
+fn splash(hostname: &str, cols: usize) -> [String; 2] {
+    let version = concat!("h2lcd v", env!("CARGO_PKG_VERSION"));
+    [hostname.chars().take(cols).collect(), version.chars().take(cols).collect()]
+}
+
+#[cfg(test)]
+#[test]
+fn test_splash() {
+    assert_eq!(["router".to_owned(), format!("h2lcd v{}", env!("CARGO_PKG_VERSION"))],
+        splash("router", 20));
+    assert_eq!("a-very-long-hos", splash("a-very-long-hostname", 15)[0]);
+}
+
+fn hostname() -> String {
+    let mut buf = [0u8; 64];
+    nix::unistd::gethostname(&mut buf)
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn show_splash(display: &mut dyn DisplayBackend, cols: usize) {
+    display.clear();
+    for (row, line) in splash(&hostname(), cols).iter().enumerate() {
+        display.position(0, row as u8);
+        display.print(line);
+    }
+    display.frame_done();
+}
+
+/// Character for a gauge `rows` tall showing `value` (0 to 1), at the given row (0 = top).
+fn display_char(value: f64, row: u8, rows: u8) -> u8 {
+    assert!(row < rows);
+
+    // Don't trust the value to be in range: a NaN or infinity from some degenerate sample shouldn't
+    // be able to take down the whole program.
+    let value = if value.is_nan() { 0. } else { value.clamp(0., 1.) };
+
+    // each row is 8 pixels high, so e.g. 3 rows have 24 values
+    let quantized = (value * (rows * 8) as f64).ceil() as u8;
+    let row = rows - 1 - row;
+    let pixels = match (quantized / 8).cmp(&row) {
+        std::cmp::Ordering::Greater => 8,
+        std::cmp::Ordering::Less => 0,
+        std::cmp::Ordering::Equal => quantized - (8 * row)
+    };
+
+    if pixels == 0 {
+        // zero pixels is a space char
+        b' '
+    } else {
+        // otherwise it's in custom chars 0 thru 7
+        pixels - 1
+    }
+}
+
+/// Glyph for a combined network column `rows` tall at the given row (0 = top), with receive
+/// (`rx`, 0 to 1) filling up from the bottom and transmit (`tx`) down from the top, each over half
+/// the column, so both full meet in the middle.
+fn combined_glyph(tx: f64, rx: f64, row: u8, rows: u8) -> glyphs::Glyph {
+    assert!(row < rows);
+
+    // each row is 8 pixels high, and each direction gets half of them, rounded up like the bars
+    let pixels = |value: f64| {
+        let value = if value.is_nan() { 0. } else { value.clamp(0., 1.) };
+        (value * (rows * 4) as f64).ceil() as u8
+    };
+    // how much of a bar that many pixels long, starting `skip` pixels from its end, is in this row
+    let within = |pixels: u8, skip: u8| pixels.saturating_sub(skip).min(8);
+    glyphs::Glyph::split(within(pixels(rx), 8 * (rows - 1 - row)), within(pixels(tx), 8 * row))
+}
+
+#[cfg(test)]
+#[test]
+fn test_display_char() {
+    assert_eq!(32, display_char(0., 0, 3));
+    assert_eq!(32, display_char(0., 1, 3));
+    assert_eq!(32, display_char(0., 2, 3));
+
+    assert_eq!(7, display_char(1., 0, 3));
+    assert_eq!(7, display_char(1., 1, 3));
+    assert_eq!(7, display_char(1., 2, 3));
+
+    assert_eq!(32, display_char(0.5, 0, 3));
+    assert_eq!(3, display_char(0.5, 1, 3));
+    assert_eq!(7, display_char(0.5, 2, 3));
+
+    assert_eq!(32, display_char(0.666, 0, 3));
+    assert_eq!(7, display_char(0.666, 1, 3));
+    assert_eq!(7, display_char(0.666, 2, 3));
+
+    assert_eq!(32, display_char(f64::NAN, 2, 3));
+    assert_eq!(32, display_char(-1., 2, 3));
+    assert_eq!(32, display_char(f64::NEG_INFINITY, 2, 3));
+    assert_eq!(7, display_char(f64::INFINITY, 0, 3));
+    assert_eq!(7, display_char(1.5, 0, 3));
+
+    // 2 rows: 16 values
+    assert_eq!(32, display_char(0.5, 0, 2));
+    assert_eq!(7, display_char(0.5, 1, 2));
+    assert_eq!(0, display_char(0.51, 0, 2));
+    assert_eq!(3, display_char(0.25, 1, 2));
+    assert_eq!(7, display_char(1., 0, 2));
+
+    // 1 row: 8 values
+    assert_eq!(32, display_char(0., 0, 1));
+    assert_eq!(0, display_char(0.1, 0, 1));
+    assert_eq!(3, display_char(0.5, 0, 1));
+    assert_eq!(6, display_char(0.8, 0, 1));
+    assert_eq!(7, display_char(1., 0, 1));
+}
+
+#[cfg(test)]
+#[test]
+fn test_combined_glyph() {
+    use glyphs::Glyph;
+    let column = |tx, rx, rows| (0 .. rows).map(|row| combined_glyph(tx, rx, row, rows))
+        .collect::<Vec<_>>();
+    let (blank, full) = (Glyph::bar(0), Glyph::bar(8));
+
+    // 3 rows: 12 pixels each way, meeting halfway up the middle row
+    assert_eq!(vec![blank, blank, blank], column(0., 0., 3));
+    assert_eq!(vec![full, full, full], column(1., 1., 3));
+    assert_eq!(vec![full, Glyph::split(0, 4), blank], column(1., 0., 3));
+    assert_eq!(vec![blank, Glyph::split(4, 0), full], column(0., 1., 3));
+    assert_eq!(vec![Glyph::split(0, 6), blank, Glyph::bar(6)], column(0.5, 0.5, 3));
+    // either side of the row boundary: 7.5 pixels rounds up to a whole row, then 9 spills over
+    assert_eq!(vec![full, blank, full], column(0.625, 0.625, 3));
+    assert_eq!(vec![full, Glyph::split(1, 1), full], column(0.75, 0.75, 3));
+    assert_eq!(vec![full, Glyph::split(4, 3), full], column(0.875, 0.95, 3));
+    // any traffic at all lights a pixel
+    assert_eq!(vec![Glyph::split(0, 1), blank, Glyph::bar(1)], column(0.01, 0.01, 3));
+
+    // 2 rows: a row each way
+    assert_eq!(vec![blank, full], column(0., 1., 2));
+    assert_eq!(vec![full, blank], column(1., 0., 2));
+    assert_eq!(vec![Glyph::split(0, 4), Glyph::bar(4)], column(0.5, 0.5, 2));
+    assert_eq!(vec![Glyph::split(0, 7), Glyph::bar(8)], column(0.8, 0.95, 2));
+
+    // 1 row: 4 pixels each way
+    assert_eq!(vec![Glyph::split(2, 1)], column(0.25, 0.5, 1));
+    assert_eq!(vec![Glyph::bar(4)], column(0., 1., 1));
+    assert_eq!(vec![Glyph::split(0, 4)], column(1., 0., 1));
+    assert_eq!(vec![full], column(1., 1., 1));
+    assert_eq!(vec![Glyph::split(3, 4)], column(1., 0.6, 1));
+
+    // 4 rows: two rows each way
+    assert_eq!(vec![full, Glyph::split(0, 2), Glyph::bar(8), full], column(0.625, 1., 4));
+    assert_eq!(vec![Glyph::split(0, 8), blank, blank, Glyph::bar(8)], column(0.5, 0.5, 4));
+
+    // out of range
+    assert_eq!(vec![blank], column(f64::NAN, -1., 1));
+    assert_eq!(vec![full], column(f64::INFINITY, 1.5, 1));
+    assert_eq!(vec![Glyph::bar(4)], column(f64::NEG_INFINITY, 2., 1));
+}
+
+#[cfg(test)]
+#[test]
+fn test_cpu_char() {
+    let column = |busy, iowait| {
+        gauge_column(2, |row| cpu_char(CoreLoad { busy, iowait }, row, 2))
+    };
+    // no iowait: the same as any other gauge
+    assert_eq!(vec![32, 7], column(0.5, 0.));
+    // a whole row of it
+    assert_eq!(vec![b'#', 7], column(0.5, 0.5));
+    // half a row still shows, but less doesn't
+    assert_eq!(vec![b'#', 7], column(0.5, 0.25));
+    assert_eq!(vec![32, 7], column(0.5, 0.1875));
+    // the bar takes the row it ends in
+    assert_eq!(vec![0, 7], column(0.5625, 0.4375));
+    assert_eq!(vec![b'#', 6], column(0.4375, 0.5));
+    assert_eq!(vec![b'#', b'#'], column(0., 1.));
+    assert_eq!(vec![7, 7], column(1., 0.5));
+}
+
+#[cfg(test)]
+#[test]
+fn test_mem_char() {
+    // no swap in use: the same as any other gauge
+    let column = |swap, rows| gauge_column(rows, |row| mem_char(0.5, swap, row, rows));
+    assert_eq!(vec![32, 3, 7], column(None, 3));
+    // swap on top, memory in the rows below it
+    assert_eq!(vec![1, 32, 7], column(Some(0.25), 3));
+    // no room for it on one row
+    assert_eq!(vec![3], column(Some(0.25), 1));
+}
+
+/// Bottom row showing labels (for those that have one) under their columns, given the column and
+/// name of each interface and filesystem, and the width of the display.
+fn legend(columns: &[(usize, &str)], labels: &BTreeMap<String, char>, cols: usize) -> String {
+    let mut row = vec![' '; cols];
+    for &(col, name) in columns {
+        if let (Some(&label), Some(cell)) = (labels.get(name), row.get_mut(col)) {
+            *cell = label;
+        }
+    }
+    row.iter().collect()
+}
+
+#[cfg(test)]
+#[test]
+fn test_legend() {
+    let labels = vec![("wan".to_owned(), 'W'), ("lan1".to_owned(), '1')].into_iter().collect();
+    let columns = [(5, "wan"), (7, "lan0"), (9, "lan1")];
+    assert_eq!("     W   1          ", legend(&columns, &labels, 20));
+    assert_eq!("                    ", legend(&[(5, "eth0")], &labels, 20));
+    assert_eq!("                    ", legend(&[], &labels, 20));
+    assert_eq!("     W   1      ", legend(&columns, &labels, 16));
+    // off the edge
+    assert_eq!("     W  ", legend(&columns, &labels, 8));
+}
+
+/// The memory column: memory usage, with the top row given over to swap usage while any swap is
+/// in use, if there's more than one row.
+fn mem_char(mem: f64, swap: Option<f64>, row: u8, rows: u8) -> u8 {
+    match swap {
+        Some(swap) if rows > 1 && row == 0 => display_char(swap, 0, 1),
+        Some(_) if rows > 1 => display_char(mem, row - 1, rows - 1),
+        _ => display_char(mem, row, rows),
+    }
+}
+
+/// A CPU column: the busy part as a bar, with any iowait above it filling the rows it covers at
+/// least half of with `iowait::HATCH`.
+fn cpu_char(core: CoreLoad, row: u8, rows: u8) -> u8 {
+    let pixels = |value: f64| {
+        let value = if value.is_nan() { 0. } else { value.clamp(0., 1.) };
+        (value * (rows * 8) as f64).ceil() as u8
+    };
+    let bottom = (rows - 1 - row) * 8;
+    if core.iowait <= 0. || pixels(core.busy) > bottom {
+        return display_char(core.busy, row, rows);
+    }
+    // the bar's top can't share a character with the hatching
+    let waiting = pixels(core.total()).min(bottom + 8).saturating_sub(bottom);
+    if waiting >= 4 {
+        iowait::HATCH
+    } else {
+        b' '
+    }
+}
+
+/// One gauge column `rows` tall, from the character for each row.
+fn gauge_column(rows: u8, row_char: impl Fn(u8) -> u8) -> Vec<u8> {
+    (0 .. rows).map(row_char).collect()
+}
+
+/// In accessible mode, the coarse level each panel's gauges are drawn from.
+#[derive(Default)]
+struct CoarseLevels {
+    cpu: Vec<u8>,
+    net: Vec<u8>,
+    mem: u8,
+    disk: Vec<u8>,
+    fs: Vec<u8>,
+    wifi: Vec<u8>,
+    temp: Vec<u8>,
+    fan: Vec<u8>,
+    sparkline: Vec<u8>,
+    ups: u8,
+    conntrack: u8,
+    ping: u8,
+}
+
+fn render_cpu(cores: &[CoreLoad], separator: u8, accessible: Option<&CoarseLevels>, rows: u8)
+    -> PanelColumns
+{
+    PanelColumns {
+        columns: cores.iter().enumerate()
+            .map(|(i, &core)| gauge_column(rows, |row| match accessible {
+                Some(levels) => coarse::display_char(levels.cpu[i], row, rows),
+                None => cpu_char(core, row, rows),
+            }))
+            .collect(),
+        separator,
+    }
+}
+
+/// The network panel, and with combined columns, their glyphs as (row, column within the panel,
+/// glyph). Like the peak-hold markers, those are put on the frame as it's drawn.
+fn render_net(ifstats: &[NetStats], speeds: &[NetSpeeds], net_levels: &[f64], config: &Config,
+    accessible: Option<&CoarseLevels>, now: Instant, rows: u8)
+    -> (PanelColumns, Vec<(usize, usize, glyphs::Glyph)>)
+{
+    let mut combined = vec![];
+    let columns = ifstats.iter().zip(speeds).enumerate()
+        .flat_map(|(i, (dev, speeds))| {
+            let marked = dev.link.marked(now);
+            let errored = dev.errors.marked(now) && !config.ignore_errors.contains(&dev.name);
+            let down = dev.down_since.is_some();
+            let gone = dev.missing();
+            let mark = |row| if gone {
+                // a ? across the middle, like other things that can't be read
+                Some(if row == rows / 2 { b'?' } else { b' ' })
+            } else if down {
+                // an x across the middle, rather than looking idle
+                Some(if row == rows / 2 { b'x' } else { b' ' })
+            } else if row == 0 && errored {
+                // recent errors or drops
+                Some(b'!')
+            } else if row == 0 && marked {
+                // link speed/duplex recently changed, or is half-duplex
+                Some(b'*')
+            } else {
+                None
+            };
+            let blip = config.activity_blip.contains(&dev.name);
+            let column = |way: usize, speed: &NetSpeed| {
+                gauge_column(rows, |row| match (mark(row), accessible) {
+                    (Some(c), _) => c,
+                    (None, Some(levels)) => {
+                        coarse::display_char(levels.net[i * 2 + way], row, rows)
+                    }
+                    (None, None) => speed.display_char(net_levels[i * 2 + way], row, rows, blip),
+                })
+            };
+            match (config.net_columns, accessible) {
+                (NetColumns::Split, _) => vec![column(0, &speeds.tx), column(1, &speeds.rx)],
+                // the accessible gauges can't be split, so they show the busier way
+                (NetColumns::Combined, Some(levels)) => {
+                    vec![gauge_column(rows, |row| {
+                        mark(row).unwrap_or_else(|| {
+                            let level = levels.net[i * 2].max(levels.net[i * 2 + 1]);
+                            coarse::display_char(level, row, rows)
+                        })
+                    })]
+                }
+                (NetColumns::Combined, None) => {
+                    let (tx, rx) = (net_levels[i * 2], net_levels[i * 2 + 1]);
+                    combined.extend((0 .. rows)
+                        .filter(|&row| mark(row).is_none())
+                        .map(|row| {
+                            (row as usize, i, speeds.combined_glyph(tx, rx, row, rows, blip))
+                        }));
+                    vec![gauge_column(rows, |row| mark(row).unwrap_or(b' '))]
+                }
+            }
+        })
+        .collect();
+    (PanelColumns { columns, separator: b'|' }, combined)
+}
+
+fn render_mem(mem: Option<f64>, swap: Option<f64>, accessible: Option<&CoarseLevels>, rows: u8)
+    -> PanelColumns
+{
+    PanelColumns {
+        columns: vec![gauge_column(rows, |row| match (mem, accessible) {
+            // couldn't be read
+            (None, _) if row == rows - 1 => b'?',
+            (None, _) => b' ',
+            (Some(_), Some(levels)) => coarse::display_char(levels.mem, row, rows),
+            (Some(mem), None) => mem_char(mem, swap, row, rows),
+        })],
+        separator: b'|',
+    }
+}
+
+fn render_disk(disk_speeds: &[NetSpeeds], max: f64, decades: u32, accessible: Option<&CoarseLevels>,
+    rows: u8) -> PanelColumns
+{
+    PanelColumns {
+        columns: disk_speeds.iter().enumerate()
+            .flat_map(|(i, NetSpeeds { rx, tx })| {
+                let column = |way: usize, speed: &NetSpeed| {
+                    gauge_column(rows, |row| match accessible {
+                        Some(levels) => coarse::display_char(levels.disk[i * 2 + way], row, rows),
+                        None => display_char(speed.log_scale(max, decades), row, rows),
+                    })
+                };
+                vec![column(0, tx), column(1, rx)]
+            })
+            .collect(),
+        separator: b'|',
+    }
+}
+
+fn render_fs(fs: &[Option<f64>], accessible: Option<&CoarseLevels>, rows: u8) -> PanelColumns {
+    PanelColumns {
+        columns: fs.iter().enumerate()
+            .map(|(i, &used)| gauge_column(rows, |row| match (used, accessible) {
+                // couldn't be read
+                (None, _) if row == rows - 1 => b'?',
+                (None, _) => b' ',
+                (Some(_), Some(levels)) => coarse::display_char(levels.fs[i], row, rows),
+                (Some(used), None) => display_char(used, row, rows),
+            }))
+            .collect(),
+        separator: b'|',
+    }
+}
+
+fn render_wifi(signals: &[Option<f64>], accessible: Option<&CoarseLevels>, rows: u8)
+    -> PanelColumns
+{
+    PanelColumns {
+        columns: signals.iter().enumerate()
+            .map(|(i, &level)| gauge_column(rows, |row| match (level, accessible) {
+                // not wireless, or not connected
+                (None, _) => b' ',
+                (Some(_), Some(levels)) => coarse::display_char(levels.wifi[i], row, rows),
+                (Some(level), None) => {
+                    display_char(wireless::signal_fraction(level), row, rows)
+                }
+            }))
+            .collect(),
+        separator: b'|',
+    }
+}
+
+fn render_conntrack(connections: Option<(u64, u64)>, accessible: Option<&CoarseLevels>, rows: u8)
+    -> PanelColumns
+{
+    PanelColumns {
+        columns: vec![gauge_column(rows, |row| match (connections, accessible) {
+            // couldn't be read
+            (None, _) => b' ',
+            (Some(_), Some(levels)) => coarse::display_char(levels.conntrack, row, rows),
+            (Some(sample), None) => display_char(conntrack::used(sample), row, rows),
+        })],
+        separator: b'|',
+    }
+}
+
+fn render_temp(temps: &[Option<f64>], min: f64, max: f64, accessible: Option<&CoarseLevels>,
+    rows: u8) -> PanelColumns
+{
+    PanelColumns {
+        columns: temps.iter().enumerate()
+            .map(|(i, &temp)| gauge_column(rows, |row| match (temp, accessible) {
+                // couldn't be read
+                (None, _) if row == rows - 1 => b'?',
+                (None, _) => b' ',
+                (Some(_), Some(levels)) => coarse::display_char(levels.temp[i], row, rows),
+                (Some(temp), None) => display_char(hwmon::fraction(temp, min, max), row, rows),
+            }))
+            .collect(),
+        separator: b'|',
+    }
+}
+
+fn render_fan(fan_speeds: &[Option<f64>], max: f64, accessible: Option<&CoarseLevels>, rows: u8)
+    -> PanelColumns
+{
+    PanelColumns {
+        columns: fan_speeds.iter().enumerate()
+            .map(|(i, &rpm)| gauge_column(rows, |row| match (rpm, accessible) {
+                // couldn't be read
+                (None, _) if row == rows - 1 => b'?',
+                (None, _) => b' ',
+                (Some(_), Some(levels)) => coarse::display_char(levels.fan[i], row, rows),
+                (Some(rpm), None) => display_char(hwmon::fraction(rpm, 0., max), row, rows),
+            }))
+            .collect(),
+        separator: b'|',
+    }
+}
+
+fn render_ups(charge: Option<f64>, accessible: Option<&CoarseLevels>, rows: u8) -> PanelColumns {
+    PanelColumns {
+        columns: vec![gauge_column(rows, |row| match (charge, accessible) {
+            // no answer, or no charge in it
+            (None, _) if row == rows - 1 => b'?',
+            (None, _) => b' ',
+            (Some(_), Some(levels)) => coarse::display_char(levels.ups, row, rows),
+            (Some(charge), None) => display_char(charge, row, rows),
+        })],
+        separator: b'|',
+    }
+}
+
+fn render_sparkline(sparkline: &[Option<f64>], max: f64, decades: u32,
+    accessible: Option<&CoarseLevels>, rows: u8) -> PanelColumns
+{
+    PanelColumns {
+        columns: sparkline.iter().enumerate()
+            .map(|(i, &mbps)| gauge_column(rows, |row| match (mbps, accessible) {
+                // from before the program started, or a gap in sampling
+                (None, _) => b' ',
+                (Some(_), Some(levels)) => coarse::display_char(levels.sparkline[i], row, rows),
+                (Some(mbps), None) => display_char(scale::log(mbps, max, decades), row, rows),
+            }))
+            .collect(),
+        separator: b'|',
+    }
+}
+
+fn render_ping(ping: Option<ping::Ping>, accessible: Option<&CoarseLevels>, rows: u8)
+    -> PanelColumns
+{
+    PanelColumns {
+        columns: vec![gauge_column(rows, |row| match (ping, accessible) {
+            // no result yet
+            (None, _) => b' ',
+            (Some(_), Some(levels)) => coarse::display_char(levels.ping, row, rows),
+            (Some(ping), None) => display_char(ping.fraction(), row, rows),
+        })],
+        separator: b'|',
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_render_accessible() {
+    let levels = CoarseLevels { mem: 6, fs: vec![0, 3], ..Default::default() };
+    // things that can't be read still show a ?, and the rest are drawn from their coarse levels
+    assert_eq!(vec![vec![7, 7, 7]], render_mem(Some(0.1), None, Some(&levels), 3).columns);
+    assert_eq!(vec![vec![b' ', b' ', b'?']], render_mem(None, None, Some(&levels), 3).columns);
+    assert_eq!(vec![vec![b' ', b' ', b' '], vec![b' ', 3, 7]],
+        render_fs(&[Some(0.9), Some(0.1)], Some(&levels), 3).columns);
+    // and otherwise from the values themselves
+    let fine: Vec<u8> = (0 .. 3).map(|row| display_char(0.9, row, 3)).collect();
+    assert_eq!(vec![fine], render_fs(&[Some(0.9)], None, 3).columns);
+}
+
+/// Look up the configured temperature sensors or fans, warning about any that aren't there.
+fn find_sensors(kind: hwmon::Kind, specs: &[String]) -> Vec<Option<hwmon::Sensor>> {
+    let all = hwmon::list(std::path::Path::new(hwmon::HWMON_DIR), kind);
+    let sensors = hwmon::find(&all, specs);
+    for (spec, sensor) in specs.iter().zip(&sensors) {
+        if sensor.is_none() {
+            let what = match kind {
+                hwmon::Kind::Temp => "temperature sensor",
+                hwmon::Kind::Fan => "fan",
+            };
+            eprintln!("no {} {:?} in {}", what, spec, hwmon::HWMON_DIR);
+        }
+    }
+    sensors
+}
+
+/// Read the config file, apply environment variable and command-line overrides, and check the
+/// result.
+fn load_config(args: &Args) -> Result<Config> {
+    let mut config = Config::load(args.config.as_deref())?;
+    config.apply_env(|name| std::env::var(name).ok())?;
+    args.apply(&mut config);
+    // Safety: plain syscall with no pointer arguments.
+    let cpu_count = unsafe { libc::sysconf(libc::_SC_NPROCESSORS_ONLN) }.max(1) as usize;
+    let cpu_columns = cores::count(cpu_count, config.cpu_mode);
+    if let Some(selection) = &config.select_interfaces {
+        let max = config.max_interfaces(cpu_columns);
+        config.interfaces = selection.select(std::path::Path::new(detect::SYSFS_NET), max);
+        eprintln!("showing network interfaces: {}", config.interfaces.join(", "));
+    }
+    // quietly, since most machines that aren't routers won't have it
+    if !Conntrack::available(std::path::Path::new(conntrack::SYSCTL_DIR)) {
+        config.remove_panel(Panel::Conntrack);
+    }
+    for step in config.compress(cpu_count) {
+        eprintln!("the panels don't fit, so {}", step.describe());
+    }
+    // a board with no thermal zone is no reason not to start
+    if config.temperatures.is_empty() && config.cpu_temp != CpuTemp::Off {
+        if let Err(e) = read_cpu_temp(&System::new(), &config.cpu_temp) {
+            eprintln!("leaving out the CPU temperature: {:#}", e);
+            config.cpu_temp = CpuTemp::Off;
+        }
+    }
+    if !config.has_cpu_temp() {
+        config.remove_status_field(&StatusItem::Temp);
+    }
+    config.validate(cores::count(cpu_count, config.cpu_mode))?;
+    Ok(config)
+}
+
+fn apply_priority(config: &Config) {
+    priority::apply(config.nice, config.sched_policy, config.cpu_affinity.as_deref());
+}
+
+/// Make `ifstats` match the given list of interface names, keeping the stats of any that were
+/// already there, and returning those of any that were removed. Names in `aggregates` get the sum
+/// of their members; one whose members changed counts as removed and added again. Any new
+/// interface that can't be read is taken to be yet to appear, unless `skip_missing` is set, in
+/// which case it's left out. That's for auto-detected interfaces, which can disappear between
+/// being listed and being read.
+fn update_ifstats(ifstats: &mut Vec<NetStats>, names: &[String],
+    aggregates: &BTreeMap<String, Vec<String>>, skip_missing: bool) -> Vec<NetStats>
+{
+    let members = |name: &String| aggregates.get(name).map(Vec::as_slice).unwrap_or_default();
+    let mut added = vec![];
+    for name in names {
+        if !ifstats.iter().any(|dev| dev.matches(name, members(name))) {
+            match NetStats::new(name.clone(), members(name)) {
+                Ok(dev) => added.push(dev),
+                Err(e) if skip_missing => eprintln!("skipping {}: {:#}", name, e),
+                Err(e) => {
+                    eprintln!("{}: not there yet, so blank until it appears: {:#}", name, e);
+                    added.push(NetStats::pending(name.clone(), members(name)));
+                }
+            }
+        }
+    }
+    let mut old = std::mem::take(ifstats);
+    for name in names {
+        let dev = match old.iter().position(|dev| dev.matches(name, members(name))) {
+            Some(i) => old.swap_remove(i),
+            None => match added.iter().position(|dev| &dev.name == name) {
+                Some(i) => added.remove(i),
+                None => continue, // skipped
+            },
+        };
+        ifstats.push(dev);
+    }
+    for dev in &old {
+        eprintln!("{}: no longer shown; sent {}, received {}",
+            dev.name, dev.tx_total, dev.rx_total);
+    }
+    old
+}
+
+/// Add saved totals to those of the interfaces they're for, taking them out of `saved`.
+fn restore_totals(ifstats: &mut [NetStats], saved: &mut totals::Totals) {
+    for dev in ifstats {
+        if let Some((sent, received)) = saved.remove(&dev.name) {
+            dev.tx_total.bytes += sent;
+            dev.rx_total.bytes += received;
+        }
+    }
+}
+
+/// Put interfaces' totals into `saved`, to be written out or restored later.
+fn stash_totals(ifstats: &[NetStats], saved: &mut totals::Totals) {
+    for dev in ifstats {
+        saved.insert(dev.name.clone(), (dev.tx_total.bytes, dev.rx_total.bytes));
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_update_ifstats() {
+    let names = |ifstats: &[NetStats]| ifstats.iter().map(|d| d.name.clone()).collect::<Vec<_>>();
+    let mut ifstats = vec![NetStats::new("lo".to_owned(), &[]).unwrap()];
+    ifstats[0].tx_total.add(1, 0);
+
+    // an existing interface keeps its totals, and one that can't be read is left out
+    let list = ["bogus0".to_owned(), "lo".to_owned()];
+    let none = BTreeMap::new();
+    update_ifstats(&mut ifstats, &list, &none, true);
+    assert_eq!(vec!["lo"], names(&ifstats));
+    assert_eq!(1, ifstats[0].tx_total.bytes);
+
+    // or, if it's not auto-detected, waited for
+    update_ifstats(&mut ifstats, &list, &none, false);
+    assert_eq!(vec!["bogus0", "lo"], names(&ifstats));
+    assert!(ifstats[0].missing() && !ifstats[1].missing());
+    assert_eq!(None, ifstats[0].down_for(Instant::now()));
+
+    let removed = update_ifstats(&mut ifstats, &[], &none, false);
+    assert!(ifstats.is_empty());
+    assert_eq!(vec!["bogus0", "lo"], names(&removed));
+
+    // an aggregate is there as soon as some of its members can be read
+    let list = ["lan".to_owned()];
+    let mut aggregates = BTreeMap::new();
+    aggregates.insert("lan".to_owned(), vec!["bogus0".to_owned()]);
+    update_ifstats(&mut ifstats, &list, &aggregates, false);
+    assert!(ifstats[0].missing());
+    aggregates.insert("lan".to_owned(), vec!["lo".to_owned(), "bogus0".to_owned()]);
+    update_ifstats(&mut ifstats, &list, &aggregates, false);
+    assert_eq!(vec!["lan"], names(&ifstats));
+    assert!(!ifstats[0].missing());
+    assert!(ifstats[0].members[1].failing);
+
+    // changing its members makes it a different one
+    aggregates.insert("lan".to_owned(), vec!["lo".to_owned()]);
+    let removed = update_ifstats(&mut ifstats, &list, &aggregates, false);
+    assert_eq!(vec!["lan"], names(&removed));
+    assert_eq!(1, ifstats[0].members.len());
+    let removed = update_ifstats(&mut ifstats, &list, &aggregates, false);
+    assert!(removed.is_empty());
+}
+
+#[cfg(test)]
+#[test]
+fn test_restore_totals() {
+    let mut ifstats = vec![NetStats::new("lo".to_owned(), &[]).unwrap()];
+    ifstats[0].rx_total.add(5, 0);
+    let mut saved: totals::Totals =
+        vec![("lo".to_owned(), (100, 200)), ("wan".to_owned(), (1, 2))].into_iter().collect();
+
+    restore_totals(&mut ifstats, &mut saved);
+    assert_eq!((100, 205), (ifstats[0].tx_total.bytes, ifstats[0].rx_total.bytes));
+    // only restored once
+    assert_eq!(vec!["wan"], saved.keys().collect::<Vec<_>>());
+    restore_totals(&mut ifstats, &mut saved);
+    assert_eq!(100, ifstats[0].tx_total.bytes);
+
+    // ones that aren't shown any more are kept
+    stash_totals(&ifstats, &mut saved);
+    assert_eq!(Some(&(100, 205)), saved.get("lo"));
+    assert_eq!(Some(&(1, 2)), saved.get("wan"));
+}
+
+/// Check that the config is valid and that everything the main loop reads works, printing what
+/// did and didn't, then leave the display as `init_display` left it. For catching problems with a
+/// new setup without having to guess why the display is blank.
+fn check(args: &Args) -> Result<()> {
+    let mut failures = 0;
+    let mut report = |what: &str, result: Result<String>| match result {
+        Ok(detail) => println!("ok      {}{}", what, detail),
+        Err(e) => {
+            println!("FAILED  {}: {:#}", what, e);
+            failures += 1;
+        }
+    };
+
+    let config = match load_config(args) {
+        Ok(config) => config,
+        Err(e) => {
+            report("config", Err(e));
+            bail!("config is invalid");
+        }
+    };
+    report("config", Ok(String::new()));
+
+    for (i, screen) in config.screens().iter().enumerate() {
+        let what = display_name(i);
+        if args.mock {
+            report(&what, Ok(" (mock)".to_owned()));
+            continue;
+        }
+        // Don't disturb the display if another instance is using it.
+        let lock = instance::acquire(&instance::lock_path(
+            std::path::Path::new(instance::LOCK_DIR), screen.bus, screen.addr));
+        match lock {
+            Ok(_lock) => report(&what, open_display(screen)
+                .map(|_| format!(" (I2C bus {}, address {:#x})", screen.bus, screen.addr))
+                .with_context(|| format!("I2C bus {}, address {:#x}", screen.bus, screen.addr))),
+            Err(e) => report(&what, Err(e)),
+        }
+    }
+
+    for name in &config.interfaces {
+        let members = config.aggregates.get(name).map(Vec::as_slice).unwrap_or_default();
+        report(&format!("interface {}", name), NetStats::new(name.clone(), members).map(|dev| {
+            if !members.is_empty() {
+                let failing = dev.members.iter().filter(|member| member.failing).count();
+                return format!(" (sum of {}; {} can't be read)", members.join(", "), failing);
+            }
+            match (link::read_up(name), LinkInfo::read(name)) {
+                (Some(false), _) => " (link down)".to_owned(),
+                (_, Some(link)) => format!(" (link {})", link),
+                (_, None) => String::new(),
+            }
+        }));
+    }
+
+    let diskstats = read_diskstats(&config.disks);
+    for name in &config.disks {
+        report(&format!("disk {}", name), match &diskstats {
+            Ok(text) => match disk::parse(text, name) {
+                Some(_) => Ok(String::new()),
+                None => Err(anyhow::anyhow!("not in {}", disk::DISKSTATS_PATH)),
+            },
+            Err(e) => Err(anyhow::anyhow!("{:#}", e)),
+        });
+    }
+
+    let (signals, _) = read_wireless(&config.wireless);
+    for (name, level) in config.wireless.iter().zip(signals) {
+        let dir = std::path::Path::new(wireless::DEBUGFS_IEEE80211);
+        report(&format!("wireless {}", name), match (level, wireless::stations(dir, name)) {
+            (Some(level), _) => Ok(format!(" ({:.0} dBm)", level)),
+            (None, Some(stations)) => Ok(format!(" (access point, {} clients)", stations)),
+            (None, None) => Err(anyhow::anyhow!("not in {}", wireless::WIRELESS_PATH)),
+        });
+    }
+
+    for path in &config.filesystems {
+        report(&format!("filesystem {}", path), match fs_used(path) {
+            Some(used) => Ok(format!(" ({:.0}% full)", used * 100.)),
+            None => Err(anyhow::anyhow!("can't get its usage")),
+        });
+    }
+
+    report("cpu load", CPUStats::new().and_then(|mut stats| {
+        // it's measured over an interval
+        thread::sleep(Duration::from_millis(100));
+        stats.get_load(config.iowait)
+    }).map(|load| format!(" ({} cores)", load.len())));
+
+    report("memory", mem_info(&System::new()).map(|mem| match mem.swap_total {
+        0 => format!(" ({} of {} MiB available, no swap)", mem.avail, mem.total),
+        _ => format!(" ({} of {} MiB available, {} of {} MiB swap free)", mem.avail, mem.total,
+            mem.swap_free, mem.swap_total),
+    }));
+
+    if config.pings() {
+        report(&format!("ping {}", config.ping_target), match config.ping_target.resolve() {
+            Some(addr) => ping::ping(addr).and_then(|result| match result {
+                ping::Ping::Reply(rtt) => Ok(format!(" ({} replied in {:?})", addr, rtt)),
+                ping::Ping::Timeout => bail!("no reply from {}", addr),
+            }),
+            None => Err(anyhow::anyhow!("no default route in {}", ping::ROUTE_PATH)),
+        });
+    }
+
+    if let Some(name) = &config.address_interface {
+        report(&format!("address of {}", name), match address::ipv4(name) {
+            Some(addr) => Ok(format!(" ({})", addr)),
+            None => Err(anyhow::anyhow!("it has no IPv4 address")),
+        });
+    }
+    if let Some(url) = &config.address_url {
+        report("external address", address::fetch(url).map(|addr| format!(" ({})", addr)));
+    }
+
+    if config.status_rotation.contains(&Segment::Leases) {
+        report(&format!("DHCP leases in {}", config.leases_file.display()),
+            match leases::read(&config.leases_file) {
+                Some(count) => Ok(format!(" ({})", count)),
+                None => Err(anyhow::anyhow!("it can't be read")),
+            });
+    }
+
+    if config.status_rotation.contains(&Segment::Sessions) || config.alerts.sessions.is_some() {
+        report("login sessions", match sessions::count(config.session_source) {
+            Some(count) => Ok(format!(" ({})", count)),
+            None => Err(anyhow::anyhow!("they can't be counted")),
+        });
+    }
+
+    if config.clock_sync || config.alerts.unsynced.is_some() {
+        report("clock sync", match clock::read() {
+            Some(true) => Ok(" (synchronized)".to_owned()),
+            Some(false) => Ok(" (not synchronized)".to_owned()),
+            None => Err(anyhow::anyhow!("the kernel's NTP status can't be read")),
+        });
+    }
+
+    if let Some(server) = &config.ups {
+        report(&format!("UPS at {}:{}", server.host, server.port), ups::query(server)
+            .map(|reading| {
+                let power = if reading.on_battery { "on battery" } else { "on mains power" };
+                match reading.charge {
+                    Some(charge) => format!(" ({}, {:.0}% charged)", power, charge),
+                    None => format!(" ({})", power),
+                }
+            }));
+    }
+
+    if config.status_rotation.contains(&Segment::Top) {
+        let mut top = TopProcess::new(std::path::Path::new(procs::PROC_DIR),
+            config.top_kernel_threads);
+        top.scan(Instant::now());
+        thread::sleep(Duration::from_secs(1));
+        report("busiest process", match top.scan(Instant::now()) {
+            Some(busiest) => Ok(format!(" ({} {:.0}%)", busiest.name, busiest.percent)),
+            None => Err(anyhow::anyhow!("no processes could be read")),
+        });
+    }
+
+    let hwmon_dir = std::path::Path::new(hwmon::HWMON_DIR);
+    let sensors = hwmon::find(&hwmon::list(hwmon_dir, hwmon::Kind::Temp), &config.temperatures);
+    for (spec, sensor) in config.temperatures.iter().zip(sensors) {
+        report(&format!("temperature sensor {}", spec), match sensor {
+            Some(sensor) => match sensor.read() {
+                Some(temp) => Ok(format!(" ({}, {:.0}°C)", sensor, temp)),
+                None => Err(anyhow::anyhow!("{} can't be read", sensor)),
+            },
+            None => Err(anyhow::anyhow!("not in {}", hwmon::HWMON_DIR)),
+        });
+    }
+    let fans = hwmon::find(&hwmon::list(hwmon_dir, hwmon::Kind::Fan), &config.fans);
+    for (spec, fan) in config.fans.iter().zip(fans) {
+        report(&format!("fan {}", spec), match fan {
+            Some(fan) => match fan.read() {
+                Some(rpm) => Ok(format!(" ({}, {:.0} RPM)", fan, rpm)),
+                None => Err(anyhow::anyhow!("{} can't be read", fan)),
+            },
+            None => Err(anyhow::anyhow!("not in {}", hwmon::HWMON_DIR)),
+        });
+    }
+
+    // the first sensor is used instead, if there are any
+    if config.temperatures.is_empty() && config.cpu_temp != CpuTemp::Off {
+        report("temperature", read_cpu_temp(&System::new(), &config.cpu_temp)
+            .map(|temp| format!(" ({:.0}°C)", temp)));
+    }
+
+    if failures > 0 {
+        bail!("{} check(s) failed", failures);
+    }
+    Ok(())
+}
+
+/// Initialize a display on its configured bus, or on the fallback bus if that doesn't work. With
+/// `scan_addresses`, look for it at other addresses on each bus too.
+fn open_display(screen: &Screen) -> Result<Box<dyn DisplayBackend>> {
+    let init_at = |bus, addr| -> Result<Box<dyn DisplayBackend>> {
+        Ok(match screen.driver {
+            Driver::Hd44780 => {
+                Box::new(init_display(bus, addr, screen.glyphs, screen.charset.clone())?)
+            }
+            Driver::Ssd1306 => Box::new(
+                ssd1306::init_display(bus, addr, screen.geometry, screen.glyphs)?),
+        })
+    };
+    let init = |bus| {
+        init_at(bus, screen.addr).or_else(|e| {
+            if !screen.scan_addresses {
+                return Err(e);
+            }
+            match probe::scan(bus, screen.driver.addresses(), screen.addr) {
+                Some(addr) => {
+                    eprintln!("no display at address {:#x} on I2C bus {}, but found something at \
+                        {:#x}", screen.addr, bus, addr);
+                    init_at(bus, addr)
+                }
+                None => Err(e),
+            }
+        })
+    };
+    init(screen.bus)
+        .or_else(|e| match screen.fallback_bus {
+            Some(fallback_bus) if is_bus_fubar_error(&e) => {
+                eprintln!("error on I2C bus {}: {e}", screen.bus);
+                eprintln!("trying I2C bus {fallback_bus} as fallback");
+                match init(fallback_bus) {
                     Err(e2) => {
                         eprintln!("I2C bus fallback also failed: {e2}");
                         Err(e) // return original error
@@ -215,87 +2343,1399 @@ fn main() -> Result<()> {
                         Ok(d)
                     }
                 }
+            }
+            _ => Err(e),
+        })
+}
+
+/// What to call a display in messages: the main one, or one of the extra ones.
+fn display_name(index: usize) -> String {
+    match index {
+        0 => "display".to_owned(),
+        _ => format!("extra display {}", index),
+    }
+}
+
+/// Where the panels go on each display on each page, worked out when the config or the number of
+/// CPU columns changes rather than every frame.
+#[derive(Default)]
+struct Layouts {
+    /// The number of CPU columns and of interfaces they were worked out for.
+    counts: Option<(usize, usize)>,
+    /// By page, then by display: the layout, and how many of the CPU columns it has room for.
+    pages: Vec<Vec<(Layout, usize)>>,
+}
+
+impl Layouts {
+    /// The layouts of a page's displays, worked out again first if the number of CPU columns or of
+    /// interfaces has changed. The config was checked against the number of CPUs at startup, so if
+    /// more have come online since, the CPU panel shows as many as fit rather than failing.
+    fn page(&mut self, config: &Config, cpu_columns: usize, page: usize)
+        -> Result<&[(Layout, usize)]>
+    {
+        let counts = (cpu_columns, config.interfaces.len());
+        if self.counts != Some(counts) {
+            self.pages = (0 ..= config.pages.len())
+                .map(|page| config.page_screens(page).iter().enumerate()
+                    .map(|(i, screen)| {
+                        let mut fit = cpu_columns;
+                        let layout = loop {
+                            match config.layout(screen, fit) {
+                                Ok(layout) => break layout,
+                                Err(e) if fit == 0 => return Err(e),
+                                Err(_) => fit -= 1,
+                            }
+                        };
+                        // the extra displays are the same on every page
+                        if fit < cpu_columns && (i == 0 || page == 0) {
+                            eprintln!("{} only has room for {} of the {} CPU columns{}",
+                                display_name(i), fit, cpu_columns,
+                                if i == 0 && page > 0 {
+                                    format!(" on {}", config.page_name(page))
+                                } else {
+                                    String::new()
+                                });
+                        }
+                        Ok((layout, fit))
+                    })
+                    .collect())
+                .collect::<Result<_>>()?;
+            self.counts = Some(counts);
+        }
+        Ok(self.pages.get(page).unwrap_or(&self.pages[0]))
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_layouts() {
+    let config = Config {
+        interfaces: vec!["lan".to_owned(), "wan".to_owned()],
+        panels: vec![Panel::Cpu, Panel::Net, Panel::Mem],
+        ..Config::default()
+    };
+    let mut layouts = Layouts::default();
+    let page = layouts.page(&config, 4, 0).unwrap();
+    assert_eq!(4, page[0].1);
+    assert_eq!(Some(5), page[0].0.start(Panel::Net));
+    // 20 columns, less 4 for the network, 1 for memory, and 2 separators, leaves room for 13
+    let page = layouts.page(&config, 16, 0).unwrap();
+    assert_eq!(13, page[0].1);
+    assert_eq!(Some(14), page[0].0.start(Panel::Net));
+    // and back down again
+    assert_eq!(2, layouts.page(&config, 2, 0).unwrap()[0].1);
+}
+
+/// A display being drawn on, and what's needed to keep it up to date.
+struct Output {
+    /// None if it couldn't be opened, until `recovery` manages to.
+    display: Option<Box<dyn DisplayBackend>>,
+    framebuffer: Framebuffer,
+    recovery: Recovery,
+    coarse_gauges: Vec<CoarseGauge>,
+    default_status: Vec<StatusItem>,
+    custom_chars: CustomChars,
+}
+
+/// What's uploaded in a display's custom characters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CustomChars {
+    /// The big digits'.
+    BigDigits,
+    /// The bar gauges': as `upload_bar_characters` leaves them, or with some of them swapped for
+    /// the peak-hold markers and combined network columns a frame needs.
+    Gauges(glyphs::Slots),
+}
+
+impl CustomChars {
+    /// Just after `upload_bar_characters`.
+    const BARS: Self = CustomChars::Gauges(glyphs::BAR_SLOTS);
+}
+
+impl Output {
+    fn new(display: Option<Box<dyn DisplayBackend>>, cols: usize, config: &Config)
+        -> Result<Self>
+    {
+        Ok(Self {
+            display,
+            framebuffer: Framebuffer::new(),
+            recovery: Recovery::new(RECOVER_AFTER_FRAMES, RECOVER_BACKOFF),
+            coarse_gauges: vec![],
+            default_status: default_status(cols, config)?,
+            custom_chars: CustomChars::BARS,
+        })
+    }
+
+    /// Draw a frame on the display, unless it's closed or lost. While it's lost, frames keep being
+    /// worked out, so it's up to date when it comes back.
+    fn draw(&mut self, mut frame: Frame, config: &Config, now: Instant) {
+        let display = match &mut self.display {
+            Some(display) if !self.recovery.is_lost() => display,
+            _ => return,
+        };
+        use_big_glyphs(display.as_mut(), &mut self.custom_chars, frame.big);
+        if let CustomChars::Gauges(slots) = &mut self.custom_chars {
+            if !config.peak_hold.is_zero() || config.net_columns == NetColumns::Combined {
+                draw_custom_glyphs(display.as_mut(), slots, &mut frame.lines, &frame.shapes,
+                    &frame.markers);
+            }
+        }
+        self.framebuffer.draw(display.as_mut(), frame.lines);
+        let failed = display.take_error();
+        if failed {
+            // some of that may not have made it to the display
+            self.framebuffer.invalidate();
+        }
+        self.recovery.frame_done(now, failed);
+        display.frame_done();
+    }
+}
+
+/// The status row for a display `cols` wide that doesn't have one configured.
+fn default_status(cols: usize, config: &Config) -> Result<Vec<StatusItem>> {
+    layout::parse_status(&layout::default_status(config.accessible, cols, config.units,
+        config.has_cpu_temp()))
+}
+
+/// Upload the big digits' custom characters in place of the bar gauges', or put the bar gauges'
+/// back, if `uploaded` says they aren't already. Both need all 8.
+fn use_big_glyphs(display: &mut dyn DisplayBackend, uploaded: &mut CustomChars, big: bool) {
+    match (big, *uploaded) {
+        (true, CustomChars::BigDigits) | (false, CustomChars::Gauges(_)) => (),
+        (true, CustomChars::Gauges(_)) => {
+            for (i, &glyph) in bigdigits::GLYPHS.iter().enumerate() {
+                display.upload_character(i as u8, glyph);
+            }
+            *uploaded = CustomChars::BigDigits;
+        }
+        (false, CustomChars::BigDigits) => {
+            display::upload_bar_characters(display);
+            *uploaded = CustomChars::BARS;
+        }
+    }
+}
+
+/// Draw glyphs other than the plain bars, at (row, column), and peak-hold markers, at (row,
+/// column, height within the character), over the gauges in `frame`, fitting the glyphs they need
+/// into the custom characters alongside the plain bars and uploading any that change. A marker on
+/// a cell that isn't a plain bar or empty is left off.
+fn draw_custom_glyphs(display: &mut dyn DisplayBackend, slots: &mut glyphs::Slots,
+    frame: &mut [Vec<u8>], shapes: &[(usize, usize, glyphs::Glyph)],
+    markers: &[(usize, usize, u8)])
+{
+    let mut cells = vec![];
+    let mut wanted = vec![];
+    for (row, line) in frame.iter().enumerate() {
+        for (col, &c) in line.iter().enumerate() {
+            let glyph = shapes.iter()
+                .find(|&&(r, c, _)| (r, c) == (row, col))
+                .map(|&(_, _, glyph)| glyph);
+            if let Some(glyph) = glyph {
+                cells.push((row, col));
+                wanted.push(glyph);
+            } else if let Some(mut glyph) = glyphs::Glyph::from_char(c) {
+                glyph.marker = markers.iter()
+                    .find(|&&(r, c, height)| (r, c) == (row, col) && height > glyph.fill)
+                    .map(|&(_, _, height)| height);
+                cells.push((row, col));
+                wanted.push(glyph);
+            }
+        }
+    }
+    let (new, chars) = glyphs::allocate(&wanted, slots);
+    for ((row, col), c) in cells.into_iter().zip(chars) {
+        frame[row][col] = c;
+    }
+    let style = display.glyphs();
+    for (i, (&old, &glyph)) in slots.iter().zip(&new).enumerate() {
+        if old != glyph {
+            display.upload_character(i as u8, glyph.bitmap(style));
+        }
+    }
+    *slots = new;
+}
+
+/// One frame's readings, sampled by the main loop, for the pages to be drawn from. The gauges'
+/// values are as shown: smoothed, and with their peaks held.
+struct Snapshot<'a> {
+    now: Instant,
+    cpu: &'a [CoreLoad],
+    cpu_separator: u8,
+    cpu_peaks: &'a [f64],
+    ifstats: &'a [NetStats],
+    speeds: &'a [NetSpeeds],
+    net_levels: &'a [f64],
+    net_peaks: &'a [f64],
+    mem: Option<f64>,
+    mem_peak: Option<f64>,
+    swap: Option<f64>,
+    disk_speeds: &'a [NetSpeeds],
+    fs: &'a [Option<f64>],
+    signals: &'a [Option<f64>],
+    connections: Option<(u64, u64)>,
+    temps: &'a [Option<f64>],
+    fan_speeds: &'a [Option<f64>],
+    sparkline: &'a [Option<f64>],
+    ups_charge: Option<f64>,
+    ping: Option<ping::Ping>,
+    status: &'a StatusValues,
+    segment: Segment,
+    segment_values: &'a SegmentValues,
+    /// What the main display's status row shows instead, while it's just switched pages.
+    page_name: Option<String>,
+    /// Whether the main display's status row shows the legend instead.
+    legend: bool,
+    alerting: bool,
+    synced: Option<bool>,
+}
+
+/// What a display shows for a frame.
+enum Page<'a> {
+    /// The gauges of the panels `layout` places, with the status row under them if the display
+    /// has one. Only `cpu_columns` of the CPU columns are shown, which is all of them unless more
+    /// CPUs have come online than fit.
+    Gauges { layout: &'a Layout, cpu_columns: usize },
+    /// The big digits, which take over the whole display.
+    BigDigits { metric: bigdigits::BigMetric, text: &'a [u8] },
+}
+
+/// A page as drawn for one frame: its characters, row by row, and the glyphs other than the plain
+/// bars and the peak-hold markers to draw over them, as for `draw_custom_glyphs`.
+struct Frame {
+    lines: Vec<Vec<u8>>,
+    shapes: Vec<(usize, usize, glyphs::Glyph)>,
+    markers: Vec<(usize, usize, u8)>,
+    big: bool,
+}
+
+impl Page<'_> {
+    /// Draw the page on the output's display, unless it's closed or lost. `main` is whether it's
+    /// the main display, which is the only one that shows page names and the legend.
+    fn render(&self, output: &mut Output, snapshot: &Snapshot, screen: &Screen, config: &Config,
+        main: bool)
+    {
+        let frame = self.frame(output, snapshot, screen, config, main);
+        output.draw(frame, config, snapshot.now);
+    }
+
+    /// The page's frame, for a display laid out like `screen`.
+    fn frame(&self, output: &mut Output, snapshot: &Snapshot, screen: &Screen, config: &Config,
+        main: bool) -> Frame
+    {
+        let (layout, cpu_columns) = match *self {
+            Page::Gauges { layout, cpu_columns } => (layout, cpu_columns),
+            Page::BigDigits { metric, text } => {
+                return Frame {
+                    lines: bigdigits::page(metric, text, screen.geometry.cols,
+                        screen.geometry.rows),
+                    shapes: vec![],
+                    markers: vec![],
+                    big: true,
+                };
+            }
+        };
+        let s = snapshot;
+        let gauge_rows = screen.gauge_rows() as u8;
+        let cpu = &s.cpu[.. cpu_columns];
+        let disk_max = config.disk_max as f64 * 8.;
+        let (net_decades, disk_decades) = (config.net_decades, config.disk_decades);
+        let (temp_min, temp_max) = (config.temp_min as f64, config.temp_max as f64);
+        let fan_max = config.fan_max as f64;
+        let sparkline_max = config.net_max as f64;
+
+        // In accessible mode, each gauge is drawn from a coarse level. Each one keeps its own
+        // state, so they're updated in the same order every frame: CPUs, tx/rx for each interface,
+        // memory, write/read for each disk, filesystems, wireless interfaces, temperature sensors,
+        // fans, the sparkline, the UPS, conntrack, then ping.
+        let accessible = config.accessible.then(|| {
+            let gauges = &mut output.coarse_gauges;
+            let mut used = 0;
+            let mut update = |values: Vec<f64>| -> Vec<u8> {
+                values.into_iter().map(|value| {
+                    if used == gauges.len() {
+                        gauges.push(CoarseGauge::default());
+                    }
+                    used += 1;
+                    gauges[used - 1].update(value, gauge_rows)
+                }).collect()
+            };
+            let levels = CoarseLevels {
+                cpu: update(cpu.iter().map(|core| core.total()).collect()),
+                net: update(s.net_levels.to_vec()),
+                mem: update(vec![s.mem.unwrap_or(0.)])[0],
+                disk: update(s.disk_speeds.iter()
+                    .flat_map(|s| [s.tx.log_scale(disk_max, disk_decades),
+                        s.rx.log_scale(disk_max, disk_decades)])
+                    .collect()),
+                fs: update(s.fs.iter().map(|used| used.unwrap_or(0.)).collect()),
+                wifi: update(s.signals.iter()
+                    .map(|level| level.map_or(0., wireless::signal_fraction))
+                    .collect()),
+                temp: update(s.temps.iter().map(|temp| {
+                    temp.map_or(0., |temp| hwmon::fraction(temp, temp_min, temp_max))
+                }).collect()),
+                fan: update(s.fan_speeds.iter()
+                    .map(|rpm| rpm.map_or(0., |rpm| hwmon::fraction(rpm, 0., fan_max)))
+                    .collect()),
+                sparkline: update(s.sparkline.iter()
+                    .map(|mbps| mbps.map_or(0., |mbps| {
+                        scale::log(mbps, sparkline_max, net_decades)
+                    }))
+                    .collect()),
+                ups: update(vec![s.ups_charge.unwrap_or(0.)])[0],
+                conntrack: update(vec![s.connections.map_or(0., conntrack::used)])[0],
+                ping: update(vec![s.ping.map_or(0., ping::Ping::fraction)])[0],
+            };
+            gauges.truncate(used);
+            levels
+        });
+        let accessible = accessible.as_ref();
+
+        let mut combined = vec![];
+        let mut lines = layout.render(gauge_rows as usize, |panel| match panel {
+            Panel::Cpu => render_cpu(cpu, s.cpu_separator, accessible, gauge_rows),
+            Panel::Net => {
+                let (columns, glyphs) = render_net(s.ifstats, s.speeds, s.net_levels, config,
+                    accessible, s.now, gauge_rows);
+                combined = glyphs;
+                columns
+            }
+            Panel::Mem => render_mem(s.mem, s.swap, accessible, gauge_rows),
+            Panel::Disk => {
+                render_disk(s.disk_speeds, disk_max, disk_decades, accessible, gauge_rows)
+            }
+            Panel::Fs => render_fs(s.fs, accessible, gauge_rows),
+            Panel::Wifi => render_wifi(s.signals, accessible, gauge_rows),
+            Panel::Conntrack => render_conntrack(s.connections, accessible, gauge_rows),
+            Panel::Temp => render_temp(s.temps, temp_min, temp_max, accessible, gauge_rows),
+            Panel::Fan => render_fan(s.fan_speeds, fan_max, accessible, gauge_rows),
+            Panel::Ups => render_ups(s.ups_charge, accessible, gauge_rows),
+            Panel::Sparkline => render_sparkline(s.sparkline, sparkline_max, net_decades,
+                accessible, gauge_rows),
+            Panel::Ping => render_ping(s.ping, accessible, gauge_rows),
+        });
+        let net_start = layout.start(Panel::Net).unwrap_or(0);
+        let shapes = combined.into_iter()
+            .map(|(row, i, glyph)| (row, net_start + i, glyph))
+            .collect();
+
+        // Peak-hold markers, as (row, column, height within the character). They're put on the
+        // frame as it's drawn, since that's when it's known what's uploaded.
+        let mut markers = vec![];
+        if !config.peak_hold.is_zero() && !config.accessible {
+            let mut columns = vec![];
+            if let Some(start) = layout.start(Panel::Cpu) {
+                columns.extend(cpu.iter().zip(s.cpu_peaks).enumerate()
+                    .map(|(i, (core, &peak))| (start + i, 0, gauge_rows, core.busy, peak)));
+            }
+            // combined network columns have nowhere to put them
+            let net = layout.start(Panel::Net)
+                .filter(|_| config.net_columns == NetColumns::Split);
+            if let Some(start) = net {
+                columns.extend(s.net_levels.iter().zip(s.net_peaks).enumerate()
+                    .map(|(i, (&level, &peak))| (start + i, 0, gauge_rows, level, peak)));
+            }
+            if let (Some(start), Some(mem), Some(mem_peak)) =
+                (layout.start(Panel::Mem), s.mem, s.mem_peak)
+            {
+                // below swap's row, while it has one
+                let top = (s.swap.is_some() && gauge_rows > 1) as u8;
+                columns.push((start, top, gauge_rows - top, mem, mem_peak));
+            }
+            markers = columns.into_iter()
+                .filter_map(|(col, top, rows, value, peak)| {
+                    let (row, height) = glyphs::marker_cell(value, peak, rows)?;
+                    Some(((top + row) as usize, col, height))
+                })
+                .collect();
+        }
+
+        // Without a status row, the gauges take up the whole display.
+        if screen.status_row {
+            let cols = screen.geometry.cols;
+            let line = match &s.page_name {
+                Some(name) if main => name.clone().into_bytes(),
+                _ if main && s.legend => {
+                    let mut columns = vec![];
+                    if let Some(start) = layout.start(Panel::Net) {
+                        let width = config.net_columns.per_interface();
+                        columns.extend(s.ifstats.iter().enumerate()
+                            .map(|(i, dev)| (start + i * width, dev.name.as_str())));
+                    }
+                    if let Some(start) = layout.start(Panel::Fs) {
+                        columns.extend(config.filesystems.iter().enumerate()
+                            .map(|(i, path)| (start + i, path.as_str())));
+                    }
+                    if let Some(start) = layout.start(Panel::Wifi) {
+                        columns.extend(config.wireless.iter().enumerate()
+                            .map(|(i, name)| (start + i, name.as_str())));
+                    }
+                    legend(&columns, &config.labels, cols).into_bytes()
+                }
+                _ => {
+                    let line = layout::render_status(
+                        screen.status.as_deref().unwrap_or(&output.default_status), s.status);
+                    layout::render_segment(s.segment, &line, s.segment_values, cols)
+                }
+            };
+            let mut line: Vec<u8> =
+                line.into_iter().chain(std::iter::repeat(b' ')).take(cols).collect();
+            if s.alerting {
+                line[cols - 1] = b'!';
+            } else if config.clock_sync && s.synced == Some(false) {
+                line[cols - 1] = b'~';
+            }
+            lines.push(line);
+        }
+        Frame { lines, shapes, markers, big: false }
+    }
+}
+
+/// Expand a golden frame written with `-` for a half block and `#` for a full one, like the
+/// accessible gauges use, and `°` for the degree sign.
+#[cfg(test)]
+fn golden(rows: &[&str]) -> Vec<Vec<u8>> {
+    rows.iter()
+        .map(|row| row.chars().map(|c| match c {
+            '-' => 3,
+            '#' => 7,
+            '°' => charset::DEGREE,
+            c => c as u8,
+        }).collect())
+        .collect()
+}
+
+#[cfg(test)]
+#[test]
+fn test_page_accessible_golden() {
+    let now = Instant::now();
+    let config = Config {
+        accessible: true,
+        interfaces: vec!["lo".to_owned()],
+        panels: vec![Panel::Cpu, Panel::Net, Panel::Mem, Panel::Fs],
+        filesystems: vec!["/".to_owned()],
+        ..Config::default()
+    };
+    let screen = &config.screens()[0];
+    let layout = config.layout(screen, 4).unwrap();
+    let ifstats = vec![NetStats::new("lo".to_owned(), &[]).unwrap()];
+    let speed = |mbps: u64| NetSpeed { bytes: mbps * 125_000, packets: 1, secs: 1. };
+    let speeds = vec![NetSpeeds { tx: speed(30), rx: speed(600) }];
+    let status = StatusValues {
+        temp: Some(47.),
+        busy: 0.42,
+        max_tx_mbps: 30.,
+        max_rx_mbps: 600.,
+        avail_mib: Some(1234),
+        ..StatusValues::default()
+    };
+    let core = |busy| CoreLoad { busy, iowait: 0. };
+    let snapshot = Snapshot {
+        now,
+        cpu: &[core(0.1), core(0.5), core(0.9), core(1.)],
+        cpu_separator: b'|',
+        cpu_peaks: &[],
+        ifstats: &ifstats,
+        speeds: &speeds,
+        net_levels: &[0.5, 0.9],
+        net_peaks: &[],
+        mem: Some(0.6),
+        mem_peak: None,
+        swap: None,
+        disk_speeds: &[],
+        fs: &[None],
+        signals: &[],
+        connections: None,
+        temps: &[],
+        fan_speeds: &[],
+        sparkline: &[],
+        ups_charge: None,
+        ping: None,
+        status: &status,
+        segment: Segment::Status,
+        segment_values: &SegmentValues::default(),
+        page_name: None,
+        legend: false,
+        alerting: false,
+        synced: None,
+    };
+    let page = Page::Gauges { layout: &layout, cpu_columns: 4 };
+    let mut output = Output::new(None, 20, &config).unwrap();
+
+    // The 10% CPU is too little to show a coarse level, and the filesystem that can't be read is
+    // a ? on the right, where the last panel goes.
+    let frame = page.frame(&mut output, &snapshot, screen, &config, true);
+    assert_eq!(golden(&[
+        "  -#| -| |          ",
+        " -##|-#|#|          ",
+        " ###|##|#|         ?",
+        "temp  47°C  busy 42%",
+    ]), frame.lines);
+    assert!(frame.shapes.is_empty() && frame.markers.is_empty() && !frame.big);
+
+    // Just after switching pages with an alert on, the main display shows the page's name, and
+    // the alert at the end. The extra displays keep showing the status.
+    let snapshot = Snapshot { page_name: Some("network".to_owned()), alerting: true, ..snapshot };
+    let frame = page.frame(&mut output, &snapshot, screen, &config, true);
+    assert_eq!(golden(&["network            !"]), frame.lines[3 ..]);
+    let frame = page.frame(&mut output, &snapshot, screen, &config, false);
+    assert_eq!(golden(&["temp  47°C  busy 42!"]), frame.lines[3 ..]);
+    // and the gauges hold their levels, drawn again from the same readings
+    assert_eq!(golden(&[
+        "  -#| -| |          ",
+        " -##|-#|#|          ",
+        " ###|##|#|         ?",
+    ]), frame.lines[.. 3]);
+}
+
+/// The displays that are open.
+fn open_displays(outputs: &mut [Output]) -> impl Iterator<Item = &mut Box<dyn DisplayBackend>> {
+    outputs.iter_mut().filter_map(|output| output.display.as_mut())
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse(std::env::args().skip(1))?;
+    if args.help {
+        print!("{}", args::USAGE);
+        return Ok(());
+    }
+    if args.check {
+        return check(&args);
+    }
+
+    let mut config = load_config(&args)?;
+
+    apply_priority(&config);
+
+    // Make sure nothing else is driving the same displays; two instances interleave their writes
+    // into garbage.
+    let mut _locks = vec![];
+    if !args.mock {
+        for screen in config.screens() {
+            let path = instance::lock_path(
+                std::path::Path::new(instance::LOCK_DIR), screen.bus, screen.addr);
+            _locks.push(if args.takeover {
+                instance::takeover(&path, TAKEOVER_TIMEOUT, instance::terminate)?
             } else {
-                Err(e)
+                instance::acquire(&path)?
+            });
+        }
+    }
+
+    // The main display has to work, but the extra ones can be retried later.
+    let mut outputs = vec![];
+    for (i, screen) in config.screens().iter().enumerate() {
+        let display = if args.mock {
+            // each one is drawn below the last
+            Ok(Box::new(MockDisplay::new(screen.geometry, i == 0)) as Box<dyn DisplayBackend>)
+        } else {
+            open_display(screen)
+        };
+        let mut output = Output::new(None, screen.geometry.cols, &config)?;
+        match display {
+            Ok(mut display) => {
+                show_splash(display.as_mut(), screen.geometry.cols);
+                output.display = Some(display);
             }
-        })?;
+            Err(e) if i > 0 => {
+                let wait = output.recovery.failed(Instant::now());
+                eprintln!("failed to open {}, trying again in {:?}: {:#}",
+                    display_name(i), wait, e);
+            }
+            Err(e) => return Err(e),
+        }
+        outputs.push(output);
+    }
+    let mut backlight = true;
+    let mut scheduled_backlight = true;
+    // what the backlight actually is, which differs from `backlight` while flashing for an alert
+    let mut backlight_lit = true;
+    let mut alerts = Alerts::new(ALERT_HYSTERESIS, ALERT_FLASH_PERIOD, ALERT_FLASHES, ALERT_REPEAT);
 
     let stop = Arc::new(AtomicBool::new(false));
     signal_hook::flag::register(signal_hook::consts::SIGTERM, stop.clone())
         .context("failed to set SIGTERM handler")?;
     signal_hook::flag::register(signal_hook::consts::SIGINT, stop.clone())
         .context("failed to set SIGINT handler")?;
+    let reload = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(signal_hook::consts::SIGHUP, reload.clone())
+        .context("failed to set SIGHUP handler")?;
+    // SIGUSR1 flips the backlight from what the config says, until its next scheduled change.
+    let backlight_toggled = Arc::new(AtomicBool::new(false));
+    {
+        let toggled = backlight_toggled.clone();
+        // Safety: the handler only does an atomic operation, which is async-signal-safe.
+        unsafe {
+            signal_hook::low_level::register(signal_hook::consts::SIGUSR1, move || {
+                toggled.fetch_xor(true, Ordering::SeqCst);
+            })
+        }.context("failed to set SIGUSR1 handler")?;
+    }
+    let redraw = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(signal_hook::consts::SIGUSR2, redraw.clone())
+        .context("failed to set SIGUSR2 handler")?;
 
     let mut ifstats = vec![];
-    for &name in &NET_DEV_NAMES {
-        ifstats.push(NetStats::new(name.to_owned())?);
+    update_ifstats(&mut ifstats, &config.interfaces, &config.aggregates,
+        config.select_interfaces.is_some());
+    if ifstats.is_empty() {
+        bail!("no network interfaces found to show");
     }
+    // Totals from before a restart, including for interfaces that aren't shown now but might be
+    // after a reload.
+    let mut saved_totals = match &config.totals_file {
+        Some(path) => totals::load(path).unwrap_or_else(|e| {
+            eprintln!("starting the totals from zero: {:#}", e);
+            totals::Totals::new()
+        }),
+        None => totals::Totals::new(),
+    };
+    restore_totals(&mut ifstats, &mut saved_totals);
+
+    let mut diskstats = vec![];
+    disk::update(&mut diskstats, &config.disks, &read_diskstats(&config.disks)?);
 
     let mut cpustats = CPUStats::new()?;
+    let system = System::new();
+    let mut memstats = SlowSample::new(config.mem_interval);
+    let mut mem_failing = false;
+    let mut swap_activity = SwapActivity::default();
+    let mut tempstats = SlowSample::new(config.temp_interval);
+    let mut sensors = find_sensors(hwmon::Kind::Temp, &config.temperatures);
+    let mut sensorstats = SlowSample::new(config.temp_interval);
+    let mut fans = find_sensors(hwmon::Kind::Fan, &config.fans);
+    let mut fanstats = SlowSample::new(config.fan_interval);
+    let mut last_temperature = None;
+    let mut fsstats = SlowSample::new(config.fs_interval);
+    let mut conntrack = Conntrack::new(std::path::Path::new(conntrack::SYSCTL_DIR));
+    let mut pinger =
+        config.pings().then(|| Pinger::spawn(config.ping_target, config.ping_interval));
+    let mut addresses = SlowSample::new(ADDRESS_INTERVAL);
+    let spawn_external = |config: &Config| config.address_url.clone().map(|url| {
+        address::External::spawn(url, EXTERNAL_ADDRESS_INTERVAL, EXTERNAL_ADDRESS_RETRY)
+    });
+    let mut external = spawn_external(&config);
+    let mut leasestats = SlowSample::new(LEASES_INTERVAL);
+    let new_top = |config: &Config| {
+        TopProcess::new(std::path::Path::new(procs::PROC_DIR), config.top_kernel_threads)
+    };
+    let mut top = new_top(&config);
+    let mut topstats = SlowSample::new(TOP_INTERVAL);
+    let mut sessionstats = SlowSample::new(SESSIONS_INTERVAL);
+    let mut clockstats = SlowSample::new(CLOCK_INTERVAL);
+    let mut sync_watch = SyncWatch::default();
+    let spawn_ups = |config: &Config| config.ups.clone()
+        .map(|server| ups::Monitor::spawn(server, UPS_INTERVAL, UPS_BACKOFF));
+    let mut ups_monitor = spawn_ups(&config);
+    let mut on_battery_since = None;
+    let new_peak_decay = |config: &Config| config.peak_decay
+        .map(|rate| (PeakDecay::new(rate as f64), PeakDecay::new(rate as f64)));
+    let mut peak_decay = new_peak_decay(&config);
+    let start = Instant::now();
+    let mut throughput = History::new(start);
+    let mut page = 0;
+    let mut page_since = start;
+    let mut last_smoothed = start;
+    let mut cpu_smoothing = Emas::default();
+    let mut net_smoothing = Emas::default();
+    let mut mem_smoothing = Ema::default();
+    let mut cpu_peak_hold = PeakHolds::default();
+    let mut net_peak_hold = PeakHolds::default();
+    let mut mem_peak_hold = PeakHold::default();
+    let mut last_frame = start;
+    let mut last_reinit = Instant::now();
+    let mut last_redraw = Instant::now();
+    let mut last_render = None;
+    let mut last_link_check = None;
+    let mut last_detect = None;
+    // Auto-detected interfaces there's no room for, which are only logged when they first appear.
+    // None until the first look, since those already there at startup were logged then.
+    let mut crowded: Option<Vec<String>> = None;
+    let mut layouts = Layouts::default();
+    let mut last_latency_log = (Instant::now(), latency::I2C_WRITES.counts());
+    let new_burst = |config: &Config| config.burst_trigger.map(|mbps| {
+        Burst::new(mbps as f64, config.interval, config.burst_interval, config.burst_duration)
+    });
+    let mut burst = new_burst(&config);
 
-    while !stop.load(Ordering::SeqCst) {
+    // The config as of the last reload, for the watchdog to leave the shutdown message by.
+    let parting = Arc::new(Mutex::new(config.clone()));
+    let watchdog = Watchdog::spawn(config.watchdog, {
+        let parting = Arc::clone(&parting);
+        let mock = args.mock;
+        move |stall| {
+            watchdog::dump(&stall);
+            // if it's being replaced right now, go without
+            let config = parting.try_lock().map(|config| config.clone());
+            if let Ok(config) = config {
+                let open = move |screen: &Screen, i| if mock {
+                    let display: Box<dyn DisplayBackend> =
+                        Box::new(MockDisplay::new(screen.geometry, i == 0));
+                    Ok(display)
+                } else {
+                    open_display(screen)
+                };
+                if !stop_displays_within(config, open, PARTING_TIMEOUT) {
+                    eprintln!("watchdog: gave up on leaving the shutdown message");
+                }
+            }
+            watchdog::act(stall.action);
+        }
+    });
+    let stage = |stage| watchdog.stage(stage);
 
-        let cpu = cpustats.get_load()?;
+    // Run the main loop such that any way out of it (signal, error, or panic) still gets to
+    // leave the display in the configured state.
+    let result = panic::catch_unwind(AssertUnwindSafe(|| -> Result<()> {
+        while !stop.load(Ordering::SeqCst) {
 
-        let mut speeds = vec![];
-        for dev in ifstats.iter_mut() {
-            speeds.push(dev.get_speeds()?);
-        }
+            if reload.swap(false, Ordering::SeqCst) {
+                // On any error, keep going with the old config rather than blanking the display.
+                match load_config(&args)
+                    .and_then(|new| {
+                        let sizes = |config: &Config| config.screens().iter()
+                            .map(|screen| screen.geometry)
+                            .collect::<Vec<_>>();
+                        if sizes(&new) != sizes(&config) {
+                            bail!("changing the number or size of displays needs a restart");
+                        }
+                        // Everything that can fail is done before anything is changed, so a
+                        // config that's turned down leaves things as they were.
+                        let disk_text = read_diskstats(&new.disks)?;
+                        // the units, or whether there's a temperature, may have changed
+                        let statuses = new.screens().iter()
+                            .map(|screen| default_status(screen.geometry.cols, &new))
+                            .collect::<Result<Vec<_>>>()?;
+                        Ok((new, disk_text, statuses))
+                    })
+                {
+                    Ok((new, disk_text, statuses)) => {
+                        let detected = new.select_interfaces.is_some();
+                        let removed = update_ifstats(&mut ifstats, &new.interfaces,
+                            &new.aggregates, detected);
+                        stash_totals(&removed, &mut saved_totals);
+                        restore_totals(&mut ifstats, &mut saved_totals);
+                        disk::update(&mut diskstats, &new.disks, &disk_text);
+                        // the list of filesystems may have changed
+                        fsstats = SlowSample::new(new.fs_interval);
+                        // and it might have been resized
+                        conntrack = Conntrack::new(std::path::Path::new(conntrack::SYSCTL_DIR));
+                        let hardware = |config: &Config| config.screens().iter()
+                            .map(|s| (s.bus, s.fallback_bus, s.addr, s.scan_addresses, s.driver,
+                                s.glyphs, s.charset.clone()))
+                            .collect::<Vec<_>>();
+                        if hardware(&new) != hardware(&config) {
+                            eprintln!("config reloaded, but display hardware and glyph changes \
+                                need a restart");
+                        } else {
+                            eprintln!("config reloaded");
+                        }
+                        let ping = |config: &Config| {
+                            (config.pings(), config.ping_target, config.ping_interval)
+                        };
+                        let priority = |config: &Config| {
+                            (config.nice, config.sched_policy, config.cpu_affinity.clone())
+                        };
+                        if priority(&new) != priority(&config) {
+                            apply_priority(&new);
+                        }
+                        if ping(&new) != ping(&config) {
+                            pinger = new.pings()
+                                .then(|| Pinger::spawn(new.ping_target, new.ping_interval));
+                        }
+                        // the interface may have changed
+                        addresses = SlowSample::new(ADDRESS_INTERVAL);
+                        // and the sensors and fans
+                        sensors = find_sensors(hwmon::Kind::Temp, &new.temperatures);
+                        sensorstats = SlowSample::new(new.temp_interval);
+                        tempstats = SlowSample::new(new.temp_interval);
+                        fans = find_sensors(hwmon::Kind::Fan, &new.fans);
+                        fanstats = SlowSample::new(new.fan_interval);
+                        // the lease file may have moved
+                        leasestats = SlowSample::new(LEASES_INTERVAL);
+                        // and whether to count kernel threads
+                        top = new_top(&new);
+                        topstats = SlowSample::new(TOP_INTERVAL);
+                        sessionstats = SlowSample::new(SESSIONS_INTERVAL);
+                        if new.address_url != config.address_url {
+                            external = spawn_external(&new);
+                        }
+                        if new.ups != config.ups {
+                            ups_monitor = spawn_ups(&new);
+                            on_battery_since = None;
+                        }
+                        let burst_settings = |c: &Config| {
+                            (c.interval, c.burst_trigger, c.burst_interval, c.burst_duration)
+                        };
+                        if burst_settings(&new) != burst_settings(&config) {
+                            burst = new_burst(&new);
+                        }
+                        if new.peak_decay != config.peak_decay {
+                            peak_decay = new_peak_decay(&new);
+                        }
+                        if new.mem_interval != config.mem_interval {
+                            memstats = SlowSample::new(new.mem_interval);
+                        }
+                        if new.watchdog != config.watchdog {
+                            watchdog.set(new.watchdog);
+                        }
+                        config = new;
+                        layouts = Layouts::default();
+                        if let Ok(mut parting) = parting.lock() {
+                            *parting = config.clone();
+                        }
+                        // don't leave columns of removed interfaces behind
+                        for (output, status) in outputs.iter_mut().zip(statuses) {
+                            output.default_status = status;
+                            if let Some(display) = &mut output.display {
+                                display.clear();
+                            }
+                            output.framebuffer.invalidate();
+                        }
+                        last_render = None;
+                        last_link_check = None;
+                    }
+                    Err(e) => eprintln!("failed to reload config, keeping the old one: {:#}", e),
+                }
+            }
+
+            // Redraw from scratch every so often, or when asked to, in case the display has been
+            // garbled.
+            let requested = redraw.swap(false, Ordering::SeqCst);
+            if requested {
+                eprintln!("redrawing the display");
+            }
+            let due = config.redraw_interval
+                .is_some_and(|interval| last_redraw.elapsed() >= interval);
+            if requested || due {
+                for output in &mut outputs {
+                    if let Some(display) = &mut output.display {
+                        display.redraw();
+                    }
+                    output.custom_chars = CustomChars::BARS;
+                    output.framebuffer.invalidate();
+                }
+                last_redraw = Instant::now();
+                last_render = None;
+            }
+
+            let time = local_hour_minute().map(|(hour, minute)| (hour * 60 + minute) as u32);
+            if config.backlight_at(time) != scheduled_backlight {
+                scheduled_backlight = !scheduled_backlight;
+                // a scheduled change overrides toggling it by hand
+                backlight_toggled.store(false, Ordering::SeqCst);
+            }
+            let wanted = scheduled_backlight != backlight_toggled.load(Ordering::SeqCst);
+            if wanted != backlight {
+                backlight = wanted;
+                eprintln!("turning the backlight {}", if backlight { "on" } else { "off" });
+            }
 
-        let (mem_avail, mem_total) = avail_mem_mib()
-            .context("failed to get available memory")?;
-        let mem = (mem_total - mem_avail) as f64 / mem_total as f64;
+            let now = Instant::now();
+            let render_interval = if config.accessible {
+                config.interval.max(ACCESSIBLE_INTERVAL)
+            } else {
+                config.interval
+            };
 
-        let temperature = System::new().cpu_temp()
-            .context("failed to get CPU temperature")?;
+            stage(Stage::Cpu);
+            let cpu_load = cpustats.get_load(config.iowait)?;
+            let cpu = cores::columns(&cpu_load, config.cpu_mode);
+            let cpu_separator = config.cpu_mode.resolve(cpu_load.len()).separator();
 
-        for row in 0 .. 3 {
-            display.position(0, row);
+            // add any auto-detected interfaces that have appeared since, as long as they fit
+            let detect_due = last_detect.is_none_or(|last| now - last >= DETECT_INTERVAL);
+            if let (Some(selection), true) = (&config.select_interfaces, detect_due) {
+                last_detect = Some(now);
+                let max = config.max_interfaces(cpu.len());
+                let (added, now_crowded) = selection.new_ones(
+                    std::path::Path::new(detect::SYSFS_NET), &config.interfaces, max);
+                if let Some(crowded) = &crowded {
+                    for name in now_crowded.iter().filter(|name| !crowded.contains(name)) {
+                        eprintln!("{}: appeared, but there's no room to show it", name);
+                    }
+                }
+                crowded = Some(now_crowded);
+                if !added.is_empty() {
+                    eprintln!("showing newly appeared network interfaces: {}", added.join(", "));
+                    config.interfaces.extend(added);
+                    update_ifstats(&mut ifstats, &config.interfaces, &config.aggregates, true);
+                    restore_totals(&mut ifstats, &mut saved_totals);
+                    // the columns have moved over to make room
+                    for output in &mut outputs {
+                        if let Some(display) = &mut output.display {
+                            display.clear();
+                        }
+                        output.framebuffer.invalidate();
+                    }
+                    last_render = None;
+                }
+            }
 
-            for &core in &cpu {
-                display.write(display_char(core, row));
+            stage(Stage::Network);
+            let mut speeds = vec![];
+            for dev in ifstats.iter_mut() {
+                speeds.push(dev.get_speeds(config.history_window, config.link_change_mark));
             }
+            let total_mbps: f64 = speeds.iter().map(|s| s.tx.mbps() + s.rx.mbps()).sum();
+            throughput.record(now, total_mbps, config.sparkline_span,
+                config.sparkline_columns);
 
-            display.write(b'|');
+            let fastest = speeds.iter()
+                .map(|s| s.tx.mbps().max(s.rx.mbps()))
+                .fold(0., f64::max);
+            let interval = match &mut burst {
+                Some(burst) => burst.update(now, fastest),
+                None => config.interval,
+            };
+            if let Some(last) = last_render {
+                if now - last < render_interval {
+                    // sampling faster than the display updates
+                    stage(Stage::Sleep);
+                    thread::sleep(interval);
+                    continue;
+                }
+            }
+            last_render = Some(now);
 
-            for NetSpeeds { rx, tx } in &speeds {
-                display.write(display_char(tx.log_display(), row));
-                display.write(display_char(rx.log_display(), row));
+            if last_link_check.is_none_or(|last| now - last >= LINK_INTERVAL) {
+                for dev in ifstats.iter_mut() {
+                    dev.check_link(now, config.link_change_mark);
+                }
+                last_link_check = Some(now);
             }
 
-            display.print("| ");
+            stage(Stage::Memory);
+            // shown as unknown rather than stopping for, since it's only one column
+            let meminfo = match memstats.get(now, || mem_info(&system)) {
+                Ok(meminfo) => {
+                    if mem_failing {
+                        eprintln!("memory can be read again");
+                        mem_failing = false;
+                    }
+                    Some(meminfo)
+                }
+                Err(e) => {
+                    if !mem_failing {
+                        eprintln!("failed to get available memory: {:#}", e);
+                        mem_failing = true;
+                    }
+                    None
+                }
+            };
+            let mem = meminfo.map(|meminfo| meminfo.total.saturating_sub(meminfo.avail) as f64
+                / meminfo.total.max(1) as f64);
+            let swap = meminfo.and_then(|meminfo| swap::used(meminfo.swap_free,
+                meminfo.swap_total));
+            // only needed for its alert; a missing /proc/vmstat just means no swapping
+            let swapping = match config.alerts.swapping {
+                Some(_) => swap_activity.update(now,
+                    &std::fs::read_to_string(swap::VMSTAT_PATH).unwrap_or_default()),
+                None => 0.,
+            };
 
-            display.write(display_char(mem, row));
-        }
+            stage(Stage::Disk);
+            let text = read_diskstats(&config.disks)?;
+            let disk_speeds: Vec<NetSpeeds> = diskstats.iter_mut()
+                .map(|disk| disk.get_speeds(now, &text))
+                .collect();
+            let net_scales: Vec<(Scale, f64)> = ifstats.iter()
+                .map(|dev| {
+                    let max = config.max_mbps(&dev.name, dev.link.speed_mbps());
+                    (config.interface_scale(&dev.name), max)
+                })
+                .collect();
+            let net_decades = config.net_decades;
+            let sparkline = throughput.columns(now, config.sparkline_span,
+                config.sparkline_columns);
+
+            // Only the bars are smoothed; the figures, like the peak speeds, stay raw.
+            let secs = (now - last_smoothed).as_secs_f64();
+            let time_constant = config.smoothing.as_secs_f64();
+            last_smoothed = now;
+            let shown_cpu: Vec<CoreLoad> = cpu_smoothing
+                .update(cpu.iter().flat_map(|core| [core.busy, core.iowait]), secs, time_constant)
+                .chunks(2)
+                .map(|pair| CoreLoad { busy: pair[0], iowait: pair[1] })
+                .collect();
+            let net_levels = net_smoothing.update(speeds.iter().zip(&net_scales)
+                .flat_map(|(s, &(scale, max))| {
+                    [s.tx.mbps(), s.rx.mbps()].map(|mbps| scale.level(mbps, max, net_decades))
+                }),
+                secs, time_constant);
+            let shown_mem = mem.map(|mem| mem_smoothing.update(mem, secs, time_constant));
+            // and the peak-hold markers go by the bars as they're shown
+            let hold = config.peak_hold.as_secs_f64();
+            let busy: Vec<f64> = shown_cpu.iter().map(|core| core.busy).collect();
+            let cpu_peaks = cpu_peak_hold.update(&busy, secs, hold, PEAK_HOLD_FALL);
+            let net_peaks = net_peak_hold.update(&net_levels, secs, hold, PEAK_HOLD_FALL);
+            let mem_peak = shown_mem
+                .map(|shown_mem| mem_peak_hold.update(shown_mem, secs, hold, PEAK_HOLD_FALL));
+
+            stage(Stage::Filesystem);
+            let fs: Vec<Option<f64>> = fsstats
+                .get(now, || Ok(config.filesystems.iter().map(|path| fs_used(path)).collect()))?;
+
+            stage(Stage::Wireless);
+            let (signals, clients) = read_wireless(&config.wireless);
+
+            stage(Stage::Conntrack);
+            let conntrack_shown = config.shows(Panel::Conntrack)
+                || config.status_rotation.contains(&Segment::Conntrack);
+            let connections = if conntrack_shown { conntrack.sample() } else { None };
+            // never waits for a reply; that's on the pinger's thread
+            let ping = pinger.as_ref().and_then(Pinger::latest);
+
+            stage(Stage::Temperature);
+            let sensor_temps: Vec<Option<f64>> = sensorstats.get(now, || Ok(sensors.iter()
+                .map(|sensor| sensor.as_ref().and_then(hwmon::Sensor::read))
+                .collect()))?;
+            let temperature = match sensor_temps.first() {
+                // the first sensor configured stands in for the CPU temperature
+                Some(&Some(temp)) => Some(temp),
+                // some sensors can't be read while their device is asleep
+                Some(None) => last_temperature,
+                None if config.cpu_temp == CpuTemp::Off => None,
+                // one that's gone away for now is shown as unknown until it's back
+                None => tempstats.get(now, || read_cpu_temp(&system, &config.cpu_temp)).ok(),
+            };
+            last_temperature = temperature;
+            let fan_speeds: Vec<Option<f64>> = fanstats.get(now, || Ok(fans.iter()
+                .map(|fan| fan.as_ref().and_then(hwmon::Sensor::read))
+                .collect()))?;
+            let fan_stopped = if fan_speeds.contains(&Some(0.)) {
+                // with no temperature, there's no telling it's cool enough not to matter
+                temperature.unwrap_or(f64::INFINITY)
+            } else {
+                f64::NEG_INFINITY
+            };
+            let synced = if config.clock_sync || config.alerts.unsynced.is_some() {
+                clockstats.get(now, || Ok(clock::read()))?
+            } else {
+                None
+            };
+
+            stage(Stage::Sessions);
+            let sessions = if config.status_rotation.contains(&Segment::Sessions)
+                || config.alerts.sessions.is_some()
+            {
+                sessionstats.get(now, || Ok(sessions::count(config.session_source)))?
+            } else {
+                None
+            };
+            let unsynced = match synced {
+                Some(synced) => sync_watch.update(now, synced)
+                    .map_or(0., |unsynced| unsynced.as_secs_f64() / 60.),
+                None => f64::NAN,
+            };
+            let link_down = ifstats.iter()
+                .filter_map(|dev| dev.down_for(now))
+                .map(|down| down.as_secs_f64() / 60.)
+                .fold(f64::NEG_INFINITY, f64::max);
+            // never waits for an answer either; that's on the UPS monitor's thread
+            let ups_reading = ups_monitor.as_ref().and_then(ups::Monitor::latest);
+            let on_battery = match ups_reading {
+                Some(reading) if reading.on_battery => {
+                    let since = *on_battery_since.get_or_insert(now);
+                    (now - since).as_secs_f64() / 60.
+                }
+                Some(_) => {
+                    on_battery_since = None;
+                    f64::NEG_INFINITY
+                }
+                // not knowing doesn't mean the power's back
+                None => f64::NAN,
+            };
+            let ups_charge = ups_reading.and_then(|reading| reading.charge)
+                .map(|charge| (charge / 100.).clamp(0., 1.));
 
-        display.position(0, 3);
-        display.print("cpu ");
-        write!(&mut display, "{:>2}", temperature.round())?;
-        display.write(0xdf); // degree sign
-        display.print("C ");
+            let alerting = alerts.update(now, &config.alerts, &Readings {
+                temp: temperature.unwrap_or(f64::NAN),
+                mem: mem.map_or(f64::NAN, |mem| mem * 100.),
+                mbps: fastest,
+                swapping,
+                ping: ping.map_or(0., ping::Ping::millis),
+                fan_stopped,
+                sessions: sessions.map_or(f64::NAN, |sessions| sessions as f64),
+                unsynced,
+                on_battery,
+                link_down,
+            });
 
-        let mut max_rx_mbps = 0;
-        let mut max_tx_mbps = 0;
-        for dev in &ifstats {
-            for (_time, NetSpeeds { rx, tx }) in &dev.buckets {
-                max_rx_mbps = max_rx_mbps.max(rx.mbps().ceil() as u16);
-                max_tx_mbps = max_tx_mbps.max(tx.mbps().ceil() as u16);
+            stage(Stage::Display);
+            // an alert flashes the backlight from whatever it would be otherwise
+            let lit = backlight != alerts.flash(now);
+            if lit != backlight_lit {
+                backlight_lit = lit;
+                for display in open_displays(&mut outputs) {
+                    display.set_backlight(lit);
+                }
+            }
+
+            // blank rather than an error if it can't be read
+            let load = System::new().load_average().ok().map(|load| load.one as f64);
+
+            // in case it was unplugged and plugged back in, which leaves it blank, while writes to
+            // it appear to succeed
+            if let Some(interval) = config.reinit_interval {
+                if now - last_reinit >= interval {
+                    eprintln!("periodic display re-initialization");
+                    for output in &mut outputs {
+                        if let Some(display) = &mut output.display {
+                            display.reinit();
+                        }
+                        output.custom_chars = CustomChars::BARS;
+                        output.framebuffer.invalidate();
+                    }
+                    last_reinit = now;
+                }
+            }
+
+            let mut status = StatusValues {
+                temp: temperature,
+                units: config.units,
+                busy: cpu_load.iter().map(|core| core.busy).sum::<f64>()
+                    / cpu_load.len().max(1) as f64,
+                load,
+                load_per_core: load.map(|load| load / cpu_load.len().max(1) as f64),
+                clients,
+                ping,
+                sensors: sensor_temps.clone(),
+                avail_mib: meminfo.map(|meminfo| meminfo.avail),
+                ..StatusValues::default()
+            };
+            let phase = |period: Duration| Duration::from_millis(
+                (now - start).as_millis() as u64 % period.as_millis() as u64);
+            let big_text = config.big_digits
+                .filter(|_| phase(BIG_DIGITS_PERIOD) >= BIG_DIGITS_PERIOD - BIG_DIGITS_DURATION)
+                .map(|metric| {
+                    bigdigits::text(metric, local_hour_minute(), temperature, total_mbps)
+                });
+            // Only the main display has pages. Switching clears it, so nothing of the last page's
+            // panels is left behind.
+            if config.page_at(now - start) != page {
+                page = config.page_at(now - start);
+                page_since = now;
+                let output = &mut outputs[0];
+                if let Some(display) = &mut output.display {
+                    display.redraw();
+                }
+                output.custom_chars = CustomChars::BARS;
+                output.framebuffer.invalidate();
+            }
+            let show_page_name = !config.pages.is_empty() && big_text.is_none()
+                && now - page_since < PAGE_NAME_DURATION;
+            // the legend is only shown on the main display
+            let show_legend = !config.labels.is_empty() && big_text.is_none() && !show_page_name
+                && phase(LEGEND_PERIOD) < LEGEND_DURATION;
+            stage(Stage::Leases);
+            let leases = if config.status_rotation.contains(&Segment::Leases) {
+                leasestats.get(now, || Ok(leases::read(&config.leases_file)))?
+            } else {
+                None
+            };
+            stage(Stage::Top);
+            // scanned whether or not it's being shown, so it's always over the same interval
+            let busiest = if config.status_rotation.contains(&Segment::Top) {
+                topstats.get(now, || Ok(top.scan(now)))?
+            } else {
+                None
+            };
+            stage(Stage::Display);
+            // without a lease file, e.g. while dnsmasq isn't running, its segment is skipped, and
+            // so is the sessions one while nobody's logged in
+            let rotation: Vec<Segment> = config.status_rotation.iter().copied()
+                .filter(|&segment| segment != Segment::Leases || leases.is_some())
+                .filter(|&segment| segment != Segment::Sessions || sessions.unwrap_or(0) > 0)
+                .collect();
+            // the interfaces the rates segment takes turns showing, leaving out idle ones
+            let rate_ifaces: Vec<&NetStats> = ifstats.iter()
+                .filter(|dev| config.rates_interfaces.is_empty()
+                    || config.rates_interfaces.contains(&dev.name))
+                .filter(|dev| {
+                    let (tx, rx) = dev.current_mbps();
+                    tx.max(rx) >= config.rates_floor as f64
+                })
+                .collect();
+            let step = (now - start).as_millis() / config.status_period.as_millis().max(1);
+            let (segment, iface) =
+                layout::segment_at(&rotation, ifstats.len(), rate_ifaces.len(), step as u64);
+            let segment_values = match segment {
+                Segment::Status => SegmentValues::default(),
+                Segment::Hostname => SegmentValues { hostname: hostname(), ..Default::default() },
+                Segment::Uptime => SegmentValues {
+                    uptime: System::new().uptime().ok(),
+                    ..Default::default()
+                },
+                Segment::Conntrack => {
+                    SegmentValues { conntrack: connections, ..Default::default() }
+                }
+                Segment::Address => SegmentValues {
+                    address: config.address_interface.as_ref().map(|name| {
+                        let local = addresses.get(now, || Ok(address::ipv4(name)))
+                            .ok().flatten().map(std::net::IpAddr::V4);
+                        // the external address is only any use while the interface is up
+                        let addr = local.map(|local| external.as_ref()
+                            .and_then(address::External::latest)
+                            .unwrap_or(local));
+                        (name.clone(), addr)
+                    }),
+                    ..Default::default()
+                },
+                Segment::Fan => SegmentValues { fans: fan_speeds.clone(), ..Default::default() },
+                Segment::Leases => SegmentValues { leases, ..Default::default() },
+                Segment::Sessions => SegmentValues { sessions, ..Default::default() },
+                Segment::Ups => SegmentValues { ups: ups_reading, ..Default::default() },
+                Segment::Top => SegmentValues {
+                    top: busiest.map(|busiest| (busiest.name, busiest.percent)),
+                    ..Default::default()
+                },
+                Segment::Errors => SegmentValues {
+                    errors: ifstats.get(iface).map(|dev| {
+                        let counts = (!config.ignore_errors.contains(&dev.name))
+                            .then_some((dev.errors.errors, dev.errors.drops));
+                        (dev.name.clone(), counts)
+                    }),
+                    ..Default::default()
+                },
+                Segment::Totals => SegmentValues {
+                    totals: ifstats.get(iface).map(|dev| {
+                        (dev.name.clone(), dev.tx_total.bytes, dev.rx_total.bytes)
+                    }),
+                    ..Default::default()
+                },
+                Segment::Rates => SegmentValues {
+                    rates: rate_ifaces.get(iface).map(|dev| {
+                        let (tx, rx) = dev.current_mbps();
+                        (dev.name.clone(), tx, rx)
+                    }),
+                    units: config.units,
+                    ..Default::default()
+                },
+            };
+            let screens = config.page_screens(page);
+            let status_shown = screens.iter().enumerate().any(|(i, screen)| {
+                screen.status_row && !(i == 0 && (show_legend || show_page_name))
+            });
+            if status_shown {
+                // the busiest interface's, each way
+                let busiest = |speeds: fn(&NetStats) -> (f64, f64)| {
+                    ifstats.iter().map(speeds).fold((0f64, 0f64), |(max_tx, max_rx), (tx, rx)| {
+                        (max_tx.max(tx), max_rx.max(rx))
+                    })
+                };
+                (status.max_tx_mbps, status.max_rx_mbps) = busiest(NetStats::peak_mbps);
+                if let Some((tx_decay, rx_decay)) = &mut peak_decay {
+                    let secs = (now - last_frame).as_secs_f64();
+                    let (tx, tx_decaying) = tx_decay.update(status.max_tx_mbps, secs);
+                    let (rx, rx_decaying) = rx_decay.update(status.max_rx_mbps, secs);
+                    (status.max_tx_mbps, status.max_rx_mbps) = (tx, rx);
+                    status.decaying = tx_decaying || rx_decaying;
+                }
+                let turn = (now - start).as_millis() / RATE_PERIOD.as_millis();
+                status.rate = layout::Rate::ALL[turn as usize % layout::Rate::ALL.len()];
+                (status.rate_tx_mbps, status.rate_rx_mbps) = match status.rate {
+                    layout::Rate::Current => busiest(NetStats::current_mbps),
+                    layout::Rate::Mean => busiest(NetStats::mean_mbps),
+                    layout::Rate::Peak => (status.max_tx_mbps, status.max_rx_mbps),
+                };
+                last_frame = now;
+            }
+
+            let snapshot = Snapshot {
+                now,
+                cpu: &shown_cpu,
+                cpu_separator,
+                cpu_peaks: &cpu_peaks,
+                ifstats: &ifstats,
+                speeds: &speeds,
+                net_levels: &net_levels,
+                net_peaks: &net_peaks,
+                mem: shown_mem,
+                mem_peak,
+                swap,
+                disk_speeds: &disk_speeds,
+                fs: &fs,
+                signals: &signals,
+                connections,
+                temps: &sensor_temps,
+                fan_speeds: &fan_speeds,
+                sparkline: &sparkline,
+                ups_charge,
+                ping,
+                status: &status,
+                segment,
+                segment_values: &segment_values,
+                page_name: show_page_name.then(|| config.page_name(page)),
+                legend: show_legend,
+                alerting,
+                synced,
+            };
+            let page_layouts = layouts.page(&config, cpu.len(), page)?;
+            for (i, ((output, screen), &(ref layout, cpu_columns))) in
+                outputs.iter_mut().zip(&screens).zip(page_layouts).enumerate()
+            {
+                if output.recovery.is_due(now) {
+                    match open_display(screen) {
+                        Ok(mut display) => {
+                            eprintln!("{} re-opened", display_name(i));
+                            display.set_backlight(backlight_lit);
+                            output.display = Some(display);
+                            output.custom_chars = CustomChars::BARS;
+                            output.framebuffer.invalidate();
+                            output.recovery.recovered();
+                        }
+                        Err(e) => {
+                            let wait = output.recovery.failed(now);
+                            eprintln!("failed to re-open {}, trying again in {:?}: {:#}",
+                                display_name(i), wait, e);
+                        }
+                    }
+                }
+                let shown = match (config.big_digits, &big_text) {
+                    (Some(metric), Some(text)) => Page::BigDigits { metric, text },
+                    _ => Page::Gauges { layout, cpu_columns },
+                };
+                shown.render(output, &snapshot, screen, &config, i == 0);
+            }
+
+            if now - last_latency_log.0 >= LATENCY_LOG_INTERVAL {
+                let counts = latency::I2C_WRITES.counts();
+                if let Some(summary) = latency::summary(&counts, &last_latency_log.1) {
+                    eprintln!("display latency: {}", summary);
+                }
+                last_latency_log = (now, counts);
             }
-        }
-        write!(&mut display, "{:>3}/{:>3}", max_tx_mbps, max_rx_mbps)?;
 
-        display.print(" mem");
+            watchdog.frame_done();
+            stage(Stage::Sleep);
+            thread::sleep(interval);
+        }
+        Ok(())
+    }));
 
-        #[cfg(feature = "mock")]
-        {
-            print!("\x1b[2J");
-            println!("____________________");
-            display.dump();
-            println!("____________________");
+    for dev in &ifstats {
+        eprintln!("{}: sent {}", dev.name, dev.tx_total);
+        eprintln!("{}: received {}", dev.name, dev.rx_total);
+    }
+    if let Some(path) = &config.totals_file {
+        stash_totals(&ifstats, &mut saved_totals);
+        if let Err(e) = totals::save(path, &saved_totals) {
+            eprintln!("failed to save the totals: {:#}", e);
         }
-        thread::sleep(Duration::from_millis(500));
     }
 
-    stop_display(display);
-    Ok(())
+    let crashed = !matches!(result, Ok(Ok(())));
+    for (output, screen) in outputs.into_iter().zip(config.screens()) {
+        let display = match output.display {
+            Some(display) => display,
+            None => continue,
+        };
+        stop_display(display, &config, screen.geometry.cols, crashed);
+    }
+
+    match result {
+        Ok(result) => result,
+        Err(panic) => panic::resume_unwind(panic),
+    }
 }
@@ -1,71 +1,134 @@
-use anyhow::Result;
-
-pub fn init_display(_bus: u8, _addr: u16) -> Result<MockDisplay> {
-    Ok(MockDisplay::new())
-}
-
-pub fn stop_display(_: MockDisplay) {}
+use crate::charset;
+use crate::display::DisplayBackend;
+use crate::layout::Geometry;
 
+/// Draws the display's contents in the terminal instead.
 pub struct MockDisplay {
+    geometry: Geometry,
+    /// Whether to clear the terminal before each frame. Off for displays drawn below another one.
+    clear_terminal: bool,
     lines: Vec<Vec<char>>,
     pos: (usize, usize),
+    /// Custom characters 0 to 7, as uploaded.
+    custom: [[u8; 8]; 8],
+    backlight: bool,
 }
 
 impl MockDisplay {
-    pub fn new() -> Self {
+    pub fn new(geometry: Geometry, clear_terminal: bool) -> Self {
         Self {
-            lines: vec![vec![' '; 20]; 4],
+            geometry,
+            clear_terminal,
+            lines: vec![vec![' '; geometry.cols]; geometry.rows],
             pos: (0, 0),
+            custom: [[0; 8]; 8],
+            backlight: true,
         }
     }
 
-    pub fn position(&mut self, col: u8, row: u8) {
-        self.pos = (row.min(3) as usize, col.min(19) as usize);
+    pub fn dump(&self) {
+        let rule = "_".repeat(self.geometry.cols);
+        println!("{}", rule);
+        for line in &self.lines {
+            for c in line.iter() {
+                print!("{}", c);
+            }
+            println!();
+        }
+        println!("{}", rule);
+        if !self.backlight {
+            println!("[backlight off]");
+        }
     }
+}
 
-    pub fn print(&mut self, s: &str) {
-        for &byte in s.as_bytes() {
-            self.write(byte);
-        }
+impl DisplayBackend for MockDisplay {
+    fn clear(&mut self) {
+        self.lines = vec![vec![' '; self.geometry.cols]; self.geometry.rows];
+        self.pos = (0, 0);
+    }
+
+    fn position(&mut self, col: u8, row: u8) {
+        self.pos = (
+            (row as usize).min(self.geometry.rows - 1),
+            (col as usize).min(self.geometry.cols - 1),
+        );
     }
 
-    pub fn write(&mut self, byte: u8) {
+    fn write(&mut self, byte: u8) {
         let c = match byte {
-            0 ..= 7 =>
-                // U+2581 thru U+2588 are blocks of increasing heights
-                std::char::from_u32(0x2581 + byte as u32).unwrap(),
-            0xdf => '°',
-            _ => byte as char,
+            0 ..= 7 => custom_char(&self.custom[byte as usize]),
+            _ => charset::unicode(byte).unwrap_or(byte as char),
         };
 
         self.lines[self.pos.0][self.pos.1] = c;
         self.pos.1 += 1;
-        if self.pos.1 == 20 {
+        if self.pos.1 == self.geometry.cols {
             self.pos.0 += 1;
             self.pos.1 = 0;
         }
-        if self.pos.0 == 4 {
+        if self.pos.0 == self.geometry.rows {
             self.pos.0 = 0;
         }
     }
 
-    pub fn dump(&self) {
-        for line in &self.lines {
-            for c in line.iter() {
-                print!("{}", c);
-            }
-            println!();
+    fn upload_character(&mut self, location: u8, map: [u8; 8]) {
+        self.custom[location as usize & 7] = map;
+    }
+
+    fn reinit(&mut self) {}
+
+    fn set_backlight(&mut self, on: bool) {
+        self.backlight = on;
+    }
+
+    fn frame_done(&mut self) {
+        if self.clear_terminal {
+            print!("\x1b[2J");
         }
+        self.dump();
+    }
+
+    fn stop(mut self: Box<Self>, message: Option<&str>, backlight: bool) {
+        self.clear();
+        if let Some(message) = message {
+            self.position(0, 0);
+            self.print(message);
+        }
+        self.set_backlight(backlight);
+        self.frame_done();
     }
 }
 
-impl std::fmt::Write for MockDisplay {
-    fn write_str(&mut self, s: &str) -> std::fmt::Result {
-        self.print(s);
-        Ok(())
+/// The closest Unicode character to a custom character's bitmap.
+fn custom_char(map: &[u8; 8]) -> char {
+    let lit: Vec<bool> = map.iter().map(|&row| row != 0).collect();
+    let height = lit.iter().rev().take_while(|&&lit| lit).count();
+    if height > 0 && lit[.. 8 - height].iter().all(|&lit| !lit) {
+        // a bar: U+2581 thru U+2588 are blocks of increasing heights
+        return std::char::from_u32(0x2580 + height as u32).unwrap();
+    }
+    let top = lit[.. 4].contains(&true);
+    let bottom = lit[4 ..].contains(&true);
+    match (top, bottom) {
+        (false, false) => ' ',
+        // narrower than the character, like a dot
+        _ if map.iter().all(|&row| row & 0b10001 == 0) => '·',
+        (true, true) => '═',
+        (true, false) => '▀',
+        (false, true) => '▄',
     }
 }
 
-pub fn is_bus_fubar_error(_: &anyhow::Error) -> bool {
-    false
+#[cfg(test)]
+#[test]
+fn test_custom_char() {
+    use crate::display::{bar_glyph, GlyphStyle};
+    assert_eq!('▁', custom_char(&bar_glyph(GlyphStyle::Solid, 1)));
+    assert_eq!('█', custom_char(&bar_glyph(GlyphStyle::Ticked, 8)));
+    assert_eq!('▃', custom_char(&[0, 0, 0, 0, 0, 0x1f, 0x1f, 0x1f]));
+    assert_eq!('═', custom_char(&[0x1f, 0x1f, 0x1f, 0, 0, 0x1f, 0x1f, 0x1f]));
+    assert_eq!('▀', custom_char(&[0x1f, 0x1f, 0x1f, 0, 0, 0, 0, 0]));
+    assert_eq!('·', custom_char(&[0, 0x0e, 0x0e, 0, 0, 0, 0, 0]));
+    assert_eq!(' ', custom_char(&[0; 8]));
 }
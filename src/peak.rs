@@ -0,0 +1,120 @@
+/// Smooths the drop of the windowed peak speed readout: instead of holding a burst's figure until
+/// it falls out of the window and then dropping abruptly, the shown figure falls towards the
+/// current peak at a fixed rate. Only affects what's displayed; the history isn't touched.
+pub struct PeakDecay {
+    /// Mbps per second.
+    rate: f64,
+    shown: f64,
+}
+
+impl PeakDecay {
+    pub fn new(rate: f64) -> Self {
+        Self { rate, shown: 0. }
+    }
+
+    /// Feed in the true windowed peak and the time since the last update. Returns the figure to
+    /// show, and whether it's decaying (i.e. higher than the true peak).
+    pub fn update(&mut self, peak: f64, secs: f64) -> (f64, bool) {
+        if peak >= self.shown {
+            self.shown = peak;
+        } else {
+            self.shown = (self.shown - self.rate * secs).max(peak);
+        }
+        (self.shown, self.shown > peak)
+    }
+}
+
+/// The highest level a bar has reached lately, for its peak-hold marker: it stays there for a while
+/// after the bar drops, then falls at a fixed rate until it meets the bar again.
+#[derive(Debug, Clone, Default)]
+pub struct PeakHold {
+    peak: f64,
+    /// Seconds since it was last pushed up.
+    age: f64,
+}
+
+impl PeakHold {
+    /// Feed in the bar's level (0 to 1) and the time since the last update. Returns where the
+    /// marker is: held for `hold` seconds, then falling `fall` of the bar's height per second.
+    pub fn update(&mut self, level: f64, secs: f64, hold: f64, fall: f64) -> f64 {
+        let level = if level.is_nan() { 0. } else { level };
+        let secs = secs.max(0.);
+        if level >= self.peak {
+            self.peak = level;
+            self.age = 0.;
+        } else {
+            let falling = (self.age + secs - hold).clamp(0., secs);
+            self.age += secs;
+            self.peak = (self.peak - fall * falling).max(level);
+        }
+        self.peak
+    }
+}
+
+/// A [`PeakHold`] for each of a list of bars, which can change length, like when interfaces come
+/// and go.
+#[derive(Debug, Default)]
+pub struct PeakHolds(Vec<PeakHold>);
+
+impl PeakHolds {
+    pub fn update(&mut self, levels: &[f64], secs: f64, hold: f64, fall: f64) -> Vec<f64> {
+        self.0.resize(levels.len(), PeakHold::default());
+        levels.iter().zip(&mut self.0)
+            .map(|(&level, peak)| peak.update(level, secs, hold, fall))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_peak_decay() {
+    let mut decay = PeakDecay::new(100.);
+    assert_eq!((50., false), decay.update(50., 0.5));
+    assert_eq!((900., false), decay.update(900., 0.5));
+    assert_eq!((900., false), decay.update(900., 0.5));
+
+    // burst ends: the true peak drops to 20, shown figure falls 50 per half-second
+    assert_eq!((850., true), decay.update(20., 0.5));
+    assert_eq!((800., true), decay.update(20., 0.5));
+    assert_eq!((700., true), decay.update(20., 1.));
+
+    // a new peak above the decaying figure takes over immediately
+    assert_eq!((750., false), decay.update(750., 0.5));
+
+    // never falls below the true peak
+    assert_eq!((700., true), decay.update(10., 0.5));
+    assert_eq!((10., false), decay.update(10., 100.));
+    assert_eq!((10., false), decay.update(10., 0.5));
+
+    // true peak rising while still decaying
+    decay.update(500., 0.5);
+    assert_eq!((450., true), decay.update(400., 0.5));
+    assert_eq!((440., false), decay.update(440., 0.5));
+}
+
+#[cfg(test)]
+#[test]
+fn test_peak_hold() {
+    let mut peak = PeakHold::default();
+    assert_eq!(0.5, peak.update(0.5, 0.5, 2., 0.25));
+    assert_eq!(0.75, peak.update(0.75, 0.5, 2., 0.25));
+
+    // held for 2 seconds after the bar drops
+    assert_eq!(0.75, peak.update(0.1, 1., 2., 0.25));
+    assert_eq!(0.75, peak.update(0.1, 1., 2., 0.25));
+    // then falls, counting only the time past the hold
+    assert_eq!(0.625, peak.update(0.1, 0.5, 2., 0.25));
+    assert_eq!(0.375, peak.update(0.1, 1., 2., 0.25));
+    // but not through the bar, which it then follows down
+    assert_eq!(0.3, peak.update(0.3, 1., 2., 0.25));
+    assert_eq!(0.2, peak.update(0.2, 0.5, 2., 0.25));
+
+    // a new high starts the hold over
+    assert_eq!(0.9, peak.update(0.9, 0.5, 2., 0.25));
+    assert_eq!(0.9, peak.update(0., 1.5, 2., 0.25));
+    assert_eq!(0., peak.update(f64::NAN, 100., 2., 0.25));
+
+    let mut peaks = PeakHolds::default();
+    assert_eq!(vec![0.5, 0.2], peaks.update(&[0.5, 0.2], 0.5, 2., 0.25));
+    assert_eq!(vec![0.5, 0.2, 0.7], peaks.update(&[0., 0.1, 0.7], 0.5, 0., 0.));
+}
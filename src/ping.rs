@@ -0,0 +1,213 @@
+//! Pinging the default gateway, or some other address, on a thread of its own, for a quick sign of
+//! whether the network beyond this machine is working.
+
+use anyhow::{bail, Result};
+use std::fmt;
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+pub const ROUTE_PATH: &str = "/proc/net/route";
+
+/// How long to wait for a reply.
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// What to ping.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    /// Whatever the default route goes through, looked up each time.
+    Gateway,
+    Addr(IpAddr),
+}
+
+impl Target {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "gateway" => Ok(Target::Gateway),
+            _ => match s.parse() {
+                Ok(addr) => Ok(Target::Addr(addr)),
+                Err(_) => bail!("{:?} should be \"gateway\" or an IP address", s),
+            },
+        }
+    }
+
+    /// The address to ping right now, or None if there's no default route.
+    pub fn resolve(self) -> Option<IpAddr> {
+        match self {
+            Target::Gateway => {
+                default_gateway(&fs::read_to_string(ROUTE_PATH).ok()?).map(IpAddr::V4)
+            }
+            Target::Addr(addr) => Some(addr),
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Target::Gateway => f.write_str("gateway"),
+            Target::Addr(addr) => addr.fmt(f),
+        }
+    }
+}
+
+/// The result of one ping.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Ping {
+    Reply(Duration),
+    Timeout,
+}
+
+impl Ping {
+    /// Where this falls on the gauge: a log scale from 1ms to 1s, with no reply at the top.
+    pub fn fraction(self) -> f64 {
+        match self {
+            Ping::Reply(rtt) => (rtt.as_secs_f64() * 1000.).log10() / 3.,
+            Ping::Timeout => 1.,
+        }
+        .clamp(0., 1.)
+    }
+
+    /// Round trip time in milliseconds, for checking against an alert threshold. No reply is
+    /// worse than any threshold.
+    pub fn millis(self) -> f64 {
+        match self {
+            Ping::Reply(rtt) => rtt.as_secs_f64() * 1000.,
+            Ping::Timeout => f64::INFINITY,
+        }
+    }
+}
+
+/// The gateway of the default route with the lowest metric, from the contents of
+/// `/proc/net/route`.
+pub fn default_gateway(route: &str) -> Option<Ipv4Addr> {
+    route.lines()
+        // header
+        .skip(1)
+        .filter_map(|line| {
+            // interface, destination, gateway, flags, refcnt, use, metric, mask, ...
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let hex = |i: usize| u32::from_str_radix(fields.get(i)?, 16).ok();
+            const RTF_GATEWAY: u32 = 0x2;
+            if hex(1)? != 0 || hex(7)? != 0 || hex(3)? & RTF_GATEWAY == 0 {
+                return None;
+            }
+            let metric: u32 = fields.get(6)?.parse().ok()?;
+            // the address is in network byte order, printed as a native integer
+            Some((metric, Ipv4Addr::from(hex(2)?.to_ne_bytes())))
+        })
+        .min_by_key(|&(metric, _)| metric)
+        .map(|(_, gateway)| gateway)
+}
+
+/// Ping an address once, using the system's `ping` command.
+pub fn ping(addr: IpAddr) -> Result<Ping> {
+    let output = Command::new("ping")
+        .args(["-n", "-c", "1", "-W"])
+        .arg(TIMEOUT.as_secs().to_string())
+        .arg(addr.to_string())
+        .output()?;
+    Ok(parse(&String::from_utf8_lossy(&output.stdout)).map_or(Ping::Timeout, Ping::Reply))
+}
+
+/// The round trip time from `ping`'s output, like `... icmp_seq=1 ttl=64 time=0.318 ms`.
+fn parse(output: &str) -> Option<Duration> {
+    let (_, rest) = output.split_once("time=")?;
+    let ms: f64 = rest.split_whitespace().next()?.parse().ok()?;
+    Some(Duration::from_secs_f64(ms / 1000.))
+}
+
+/// Pings a target every so often on a background thread, so waiting for a reply never holds up
+/// the display. The thread stops once this is dropped.
+pub struct Pinger {
+    latest: Arc<Mutex<Option<Ping>>>,
+}
+
+impl Pinger {
+    pub fn spawn(target: Target, interval: Duration) -> Self {
+        let latest = Arc::new(Mutex::new(None));
+        let shared = Arc::downgrade(&latest);
+        thread::Builder::new()
+            .name("ping".to_owned())
+            .spawn(move || {
+                let mut warned = false;
+                loop {
+                    let result = match target.resolve().map(ping) {
+                        Some(Ok(result)) => result,
+                        Some(Err(e)) => {
+                            if !warned {
+                                eprintln!("failed to run ping: {}", e);
+                                warned = true;
+                            }
+                            Ping::Timeout
+                        }
+                        // no route, so nothing would get through
+                        None => Ping::Timeout,
+                    };
+                    match shared.upgrade() {
+                        Some(latest) => *latest.lock().unwrap() = Some(result),
+                        None => return,
+                    }
+                    thread::sleep(interval);
+                }
+            })
+            .expect("failed to spawn ping thread");
+        Self { latest }
+    }
+
+    /// The last result, or None if there hasn't been one yet.
+    pub fn latest(&self) -> Option<Ping> {
+        *self.latest.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_default_gateway() {
+    let route = "\
+Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT
+lan0\t0001A8C0\t00000000\t0001\t0\t0\t0\tFFFFFFFF\t0\t0\t0
+wan1\t00000000\t0100000A\t0003\t0\t0\t200\t00000000\t0\t0\t0
+wan0\t00000000\t0101A8C0\t0003\t0\t0\t100\t00000000\t0\t0\t0
+";
+    assert_eq!(Some(Ipv4Addr::new(192, 168, 1, 1)), default_gateway(route));
+    // only routes through a gateway count
+    assert_eq!(None, default_gateway(
+        "Iface\tDestination\tGateway\tFlags\tRefCnt\tUse\tMetric\tMask\n\
+        wg0\t00000000\t00000000\t0001\t0\t0\t0\t00000000\n"));
+    assert_eq!(None, default_gateway(""));
+}
+
+#[cfg(test)]
+#[test]
+fn test_parse() {
+    let output = "\
+PING 192.168.1.1 (192.168.1.1) 56(84) bytes of data.
+64 bytes from 192.168.1.1: icmp_seq=1 ttl=64 time=9.12 ms
+
+--- 192.168.1.1 ping statistics ---
+1 packets transmitted, 1 received, 0% packet loss, time 0ms
+";
+    assert_eq!(Some(Duration::from_micros(9120)), parse(output));
+    assert_eq!(None, parse("1 packets transmitted, 0 received, 100% packet loss, time 0ms\n"));
+
+    assert_eq!(Target::Gateway, Target::parse("gateway").unwrap());
+    assert_eq!(Target::Addr("1.1.1.1".parse().unwrap()), Target::parse("1.1.1.1").unwrap());
+    assert_eq!("\"example.com\" should be \"gateway\" or an IP address",
+        Target::parse("example.com").unwrap_err().to_string());
+}
+
+#[cfg(test)]
+#[test]
+fn test_fraction() {
+    let ms = |ms| Ping::Reply(Duration::from_millis(ms));
+    assert_eq!(0., ms(0).fraction());
+    assert_eq!(0., ms(1).fraction());
+    assert!((ms(10).fraction() - 1. / 3.).abs() < 1e-9);
+    assert_eq!(1., ms(1500).fraction());
+    assert_eq!(1., Ping::Timeout.fraction());
+    assert_eq!(f64::INFINITY, Ping::Timeout.millis());
+}
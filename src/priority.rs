@@ -0,0 +1,128 @@
+use anyhow::{bail, Context, Result};
+
+/// Nice levels setpriority takes.
+pub const NICE_RANGE: std::ops::RangeInclusive<i32> = -20 ..= 19;
+
+/// Scheduling policy to request for the daemon, in addition to its nice level.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SchedPolicy {
+    /// Only run when nothing else wants the CPU.
+    Idle,
+    /// Treat as a non-interactive, CPU-bound task.
+    Batch,
+}
+
+impl SchedPolicy {
+    /// Parse a policy, or `"normal"` for none.
+    pub fn parse(s: &str) -> Result<Option<Self>> {
+        Ok(match s {
+            "normal" => None,
+            "idle" => Some(SchedPolicy::Idle),
+            "batch" => Some(SchedPolicy::Batch),
+            _ => bail!("should be \"normal\", \"idle\", or \"batch\", not {:?}", s),
+        })
+    }
+}
+
+/// Parse a CPU list like `0,2-3` (the same syntax as `taskset -c` and cpuset files) into a sorted
+/// list of CPU numbers.
+pub fn parse_cpu_list(s: &str) -> Result<Vec<usize>> {
+    let mut cpus = vec![];
+    for part in s.split(',') {
+        let part = part.trim();
+        if part.is_empty() {
+            bail!("empty entry in CPU list {:?}", s);
+        }
+        let parse = |n: &str| n.trim().parse::<usize>()
+            .with_context(|| format!("invalid CPU number {:?} in CPU list {:?}", n, s));
+        if let Some((start, end)) = part.split_once('-') {
+            let (start, end) = (parse(start)?, parse(end)?);
+            if start > end {
+                bail!("backwards range {:?} in CPU list {:?}", part, s);
+            }
+            cpus.extend(start ..= end);
+        } else {
+            cpus.push(parse(part)?);
+        }
+    }
+    for &cpu in &cpus {
+        if cpu >= libc::CPU_SETSIZE as usize {
+            bail!("CPU {} in CPU list {:?} is out of range", cpu, s);
+        }
+    }
+    cpus.sort_unstable();
+    cpus.dedup();
+    Ok(cpus)
+}
+
+#[cfg(test)]
+#[test]
+fn test_parse_cpu_list() {
+    assert_eq!(vec![0], parse_cpu_list("0").unwrap());
+    assert_eq!(vec![0, 2, 3], parse_cpu_list("0,2-3").unwrap());
+    assert_eq!(vec![1, 2, 3, 5], parse_cpu_list("5, 1-3, 2").unwrap());
+    assert_eq!(vec![4], parse_cpu_list("4-4").unwrap());
+    assert!(parse_cpu_list("").is_err());
+    assert!(parse_cpu_list("0,,1").is_err());
+    assert!(parse_cpu_list("3-1").is_err());
+    assert!(parse_cpu_list("a").is_err());
+    assert!(parse_cpu_list("1-").is_err());
+    assert!(parse_cpu_list("-1").is_err());
+    assert!(parse_cpu_list("100000").is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn test_parse_sched_policy() {
+    assert_eq!(None, SchedPolicy::parse("normal").unwrap());
+    assert_eq!(Some(SchedPolicy::Idle), SchedPolicy::parse("idle").unwrap());
+    assert_eq!(Some(SchedPolicy::Batch), SchedPolicy::parse("batch").unwrap());
+    assert_eq!("should be \"normal\", \"idle\", or \"batch\", not \"fifo\"",
+        SchedPolicy::parse("fifo").unwrap_err().to_string());
+}
+
+/// Apply the given priority settings to the current process. Threads spawned afterwards inherit
+/// them. Failures (typically from lacking privileges) are logged and otherwise ignored.
+pub fn apply(nice: Option<i32>, policy: Option<SchedPolicy>, affinity: Option<&[usize]>) {
+    if let Some(nice) = nice {
+        // Safety: plain syscall with no pointer arguments.
+        if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, nice) } == 0 {
+            eprintln!("set nice level to {}", nice);
+        } else {
+            eprintln!("failed to set nice level to {}: {}", nice, std::io::Error::last_os_error());
+        }
+    }
+
+    if let Some(policy) = policy {
+        let raw = match policy {
+            SchedPolicy::Idle => libc::SCHED_IDLE,
+            SchedPolicy::Batch => libc::SCHED_BATCH,
+        };
+        let param = libc::sched_param { sched_priority: 0 };
+        // Safety: param is a valid sched_param that outlives the call.
+        if unsafe { libc::sched_setscheduler(0, raw, &param) } == 0 {
+            eprintln!("set scheduling policy to {:?}", policy);
+        } else {
+            eprintln!("failed to set scheduling policy to {:?}: {}",
+                policy, std::io::Error::last_os_error());
+        }
+    }
+
+    if let Some(cpus) = affinity {
+        // Safety: cpu_set_t is plain data, and parse_cpu_list guarantees every CPU is within
+        // CPU_SETSIZE.
+        let result = unsafe {
+            let mut set: libc::cpu_set_t = std::mem::zeroed();
+            for &cpu in cpus {
+                libc::CPU_SET(cpu, &mut set);
+            }
+            libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set)
+        };
+        if result == 0 {
+            eprintln!("set CPU affinity to {:?}", cpus);
+        } else {
+            eprintln!("failed to set CPU affinity to {:?}: {}",
+                cpus, std::io::Error::last_os_error());
+        }
+    }
+}
@@ -0,0 +1,21 @@
+//! Looking for a display whose I2C address isn't known.
+
+use i2cdev::core::I2CDevice;
+use i2cdev::linux::LinuxI2CDevice;
+
+/// Whether anything acknowledges its address on the given bus. This does an SMBus "quick write",
+/// which carries no data, the same as `i2cdetect` does for these addresses.
+pub fn responds(bus: u8, addr: u16) -> bool {
+    LinuxI2CDevice::new(format!("/dev/i2c-{}", bus), addr)
+        .and_then(|mut dev| dev.smbus_write_quick(false))
+        .is_ok()
+}
+
+/// The first of `candidates` (other than `skip`, which was already tried) that something responds
+/// at.
+pub fn scan(bus: u8, candidates: &[u16], skip: u16) -> Option<u16> {
+    candidates.iter()
+        .copied()
+        .filter(|&addr| addr != skip)
+        .find(|&addr| responds(bus, addr))
+}
@@ -0,0 +1,172 @@
+//! Which process is using the most CPU, for seeing what's behind a spike in the CPU columns
+//! without having to log in and run `top`.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+pub const PROC_DIR: &str = "/proc";
+
+/// Set in a kernel thread's flags, in its `stat`.
+const PF_KTHREAD: u64 = 0x0020_0000;
+
+/// What one process's `stat` says.
+#[derive(Debug, Clone, PartialEq)]
+struct Stat {
+    name: String,
+    /// User plus system time, in clock ticks.
+    cputime: u64,
+    kernel_thread: bool,
+}
+
+/// Parse the contents of `/proc/<pid>/stat`, like
+/// `1234 (nginx) S 1 1234 1234 0 -1 4194560 ... 17 42 ...`. The name is in parentheses and can
+/// have spaces and parentheses of its own, so the fields after it are found from the last `)`.
+fn parse_stat(stat: &str) -> Option<Stat> {
+    let open = stat.find('(')?;
+    let close = stat.rfind(')')?;
+    let name = stat.get(open + 1 .. close)?.to_owned();
+    // starting from the third field, the state
+    let fields: Vec<&str> = stat[close + 1 ..].split_whitespace().collect();
+    let field = |n: usize| fields.get(n - 3)?.parse::<u64>().ok();
+    Some(Stat {
+        name,
+        cputime: field(14)? + field(15)?,
+        kernel_thread: field(9)? & PF_KTHREAD != 0,
+    })
+}
+
+/// The busiest process over the time between two scans.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Busiest {
+    pub name: String,
+    /// Percent of one core, so it can be more than 100 for a multi-threaded process.
+    pub percent: f64,
+}
+
+/// Scans the processes in a proc directory, remembering each one's CPU time for the next scan.
+pub struct TopProcess {
+    dir: PathBuf,
+    kernel_threads: bool,
+    /// Clock ticks per second, which CPU times are counted in.
+    ticks: f64,
+    /// When the last scan was, and each process's CPU time as of then, by pid. Processes that
+    /// have exited are dropped at each scan.
+    last: Option<(Instant, HashMap<u32, u64>)>,
+}
+
+impl TopProcess {
+    /// Scan the processes in `dir` (normally PROC_DIR), leaving out kernel threads unless
+    /// `kernel_threads` is set.
+    pub fn new(dir: &Path, kernel_threads: bool) -> Self {
+        let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+        Self {
+            dir: dir.to_owned(),
+            kernel_threads,
+            ticks: if ticks > 0 { ticks as f64 } else { 100. },
+            last: None,
+        }
+    }
+
+    /// The process that used the most CPU time since the last scan, or None on the first one, or
+    /// if nothing ran.
+    pub fn scan(&mut self, now: Instant) -> Option<Busiest> {
+        let mut cputimes = HashMap::new();
+        let mut busiest: Option<(String, u64)> = None;
+        for entry in fs::read_dir(&self.dir).ok()?.flatten() {
+            let pid = match entry.file_name().to_str().and_then(|name| name.parse::<u32>().ok()) {
+                Some(pid) => pid,
+                None => continue,
+            };
+            // it may have exited since the directory was listed
+            let stat = match fs::read_to_string(entry.path().join("stat")).ok()
+                .as_deref().and_then(parse_stat)
+            {
+                Some(stat) => stat,
+                None => continue,
+            };
+            if stat.kernel_thread && !self.kernel_threads {
+                continue;
+            }
+            cputimes.insert(pid, stat.cputime);
+            let last = self.last.as_ref().and_then(|(_, last)| last.get(&pid)).copied();
+            // one that started since the last scan (or reused a pid) used all its time since
+            let used = match last {
+                Some(last) if last <= stat.cputime => stat.cputime - last,
+                _ => stat.cputime,
+            };
+            if used > 0 && busiest.as_ref().is_none_or(|(_, most)| used > *most) {
+                busiest = Some((stat.name, used));
+            }
+        }
+        let (then, _) = self.last.replace((now, cputimes))?;
+        let secs = (now - then).as_secs_f64();
+        if secs <= 0. {
+            return None;
+        }
+        let percent = |used: u64| used as f64 / self.ticks / secs * 100.;
+        busiest.map(|(name, used)| Busiest { name, percent: percent(used) })
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_top_process() {
+    let dir = std::env::temp_dir().join(format!("h2-net-lcd-test-procs-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let stat = |pid: u32, name: &str, flags: u64, utime: u64, stime: u64| {
+        fs::create_dir_all(dir.join(pid.to_string())).unwrap();
+        fs::write(dir.join(pid.to_string()).join("stat"), format!(
+            "{} ({}) S 1 {} {} 0 -1 {} 120 0 0 0 {} {} 0 0 20 0 1 0 100 0 0\n",
+            pid, name, pid, pid, flags, utime, stime)).unwrap();
+    };
+    stat(1, "init", 0x400100, 50, 50);
+    stat(42, "nginx: worker (1)", 0x400140, 1000, 200);
+    stat(7, "kworker/0:1", 0x4208060, 0, 30);
+    fs::create_dir_all(dir.join("self")).unwrap();
+    fs::write(dir.join("uptime"), "12345.67 23456.78\n").unwrap();
+
+    let start = Instant::now();
+    let secs = |secs| start + std::time::Duration::from_secs(secs);
+    let mut top = TopProcess::new(&dir, false);
+    top.ticks = 100.;
+    assert_eq!(None, top.scan(start));
+
+    // 150 ticks in 2 seconds is 75% of a core
+    stat(42, "nginx: worker (1)", 0x400140, 1100, 250);
+    stat(7, "kworker/0:1", 0x4208060, 0, 400);
+    assert_eq!(Some(Busiest { name: "nginx: worker (1)".to_owned(), percent: 75. }),
+        top.scan(secs(2)));
+
+    // a new process used all of its time since the last scan, and exited ones are dropped
+    fs::remove_dir_all(dir.join("42")).unwrap();
+    stat(99, "make", 0x400000, 300, 100);
+    assert_eq!(Some(Busiest { name: "make".to_owned(), percent: 100. }), top.scan(secs(6)));
+    let mut pids: Vec<u32> = top.last.as_ref().unwrap().1.keys().copied().collect();
+    pids.sort();
+    assert_eq!(vec![1, 99], pids);
+
+    // nothing ran
+    assert_eq!(None, top.scan(secs(8)));
+
+    // kernel threads count when asked for
+    let mut top = TopProcess::new(&dir, true);
+    top.ticks = 100.;
+    top.scan(start);
+    stat(7, "kworker/0:1", 0x4208060, 0, 500);
+    assert_eq!(Some(Busiest { name: "kworker/0:1".to_owned(), percent: 100. }),
+        top.scan(secs(1)));
+
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(None, top.scan(secs(2)));
+}
+
+#[cfg(test)]
+#[test]
+fn test_parse_stat() {
+    assert_eq!(Some(Stat { name: "a) (b".to_owned(), cputime: 30, kernel_thread: true }),
+        parse_stat("5 (a) (b) S 2 0 0 0 -1 2129984 0 0 0 0 10 20 0 0 20 0 1 0 3 0 0"));
+    assert_eq!(None, parse_stat("5 (truncated) S 2 0 0 0 -1 2129984 0 0"));
+    assert_eq!(None, parse_stat(""));
+}
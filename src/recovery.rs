@@ -0,0 +1,98 @@
+use std::time::{Duration, Instant};
+
+/// Decides when to give up on a display that keeps failing (e.g. its cable got bumped) and open
+/// it again from scratch, backing off if that doesn't work either.
+pub struct Recovery {
+    /// Consecutive frames with write errors before the display counts as lost.
+    threshold: u32,
+    /// How long to wait after each failed attempt to re-open it; the last one repeats.
+    backoff: &'static [Duration],
+    failed_frames: u32,
+    failed_attempts: usize,
+    /// When to next try re-opening the display, if it's lost.
+    next_attempt: Option<Instant>,
+}
+
+impl Recovery {
+    pub fn new(threshold: u32, backoff: &'static [Duration]) -> Self {
+        Self { threshold, backoff, failed_frames: 0, failed_attempts: 0, next_attempt: None }
+    }
+
+    /// Note whether drawing a frame had errors.
+    pub fn frame_done(&mut self, now: Instant, failed: bool) {
+        if !failed {
+            self.failed_frames = 0;
+            return;
+        }
+        self.failed_frames += 1;
+        if self.failed_frames >= self.threshold && self.next_attempt.is_none() {
+            eprintln!("{} frames in a row had display errors", self.failed_frames);
+            self.next_attempt = Some(now);
+        }
+    }
+
+    /// Whether the display is lost, so there's no point drawing on it.
+    pub fn is_lost(&self) -> bool {
+        self.next_attempt.is_some()
+    }
+
+    /// Whether it's time to try re-opening the display.
+    pub fn is_due(&self, now: Instant) -> bool {
+        matches!(self.next_attempt, Some(at) if now >= at)
+    }
+
+    /// Re-opening the display failed; returns how long until the next try.
+    pub fn failed(&mut self, now: Instant) -> Duration {
+        let wait = self.backoff[self.failed_attempts.min(self.backoff.len() - 1)];
+        self.failed_attempts += 1;
+        self.next_attempt = Some(now + wait);
+        wait
+    }
+
+    /// Re-opening the display worked.
+    pub fn recovered(&mut self) {
+        self.failed_frames = 0;
+        self.failed_attempts = 0;
+        self.next_attempt = None;
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_recovery() {
+    const BACKOFF: &[Duration] = &[Duration::from_secs(1), Duration::from_secs(5)];
+    let start = Instant::now();
+    let secs = |s| start + Duration::from_secs(s);
+    let mut recovery = Recovery::new(3, BACKOFF);
+
+    // occasional errors are fine
+    recovery.frame_done(secs(0), true);
+    recovery.frame_done(secs(1), true);
+    recovery.frame_done(secs(2), false);
+    recovery.frame_done(secs(3), true);
+    assert!(!recovery.is_lost());
+
+    // three in a row, and it's time to re-open it right away
+    recovery.frame_done(secs(4), true);
+    recovery.frame_done(secs(5), true);
+    assert!(recovery.is_lost());
+    assert!(recovery.is_due(secs(5)));
+
+    // backing off, with the last delay repeating
+    assert_eq!(Duration::from_secs(1), recovery.failed(secs(5)));
+    assert!(!recovery.is_due(secs(5)));
+    assert!(recovery.is_due(secs(6)));
+    assert_eq!(Duration::from_secs(5), recovery.failed(secs(6)));
+    assert!(!recovery.is_due(secs(10)));
+    assert!(recovery.is_due(secs(11)));
+    assert_eq!(Duration::from_secs(5), recovery.failed(secs(11)));
+
+    recovery.recovered();
+    assert!(!recovery.is_lost());
+    // starts over from the first delay next time
+    for s in 20 .. 23 {
+        recovery.frame_done(secs(s), true);
+    }
+    assert!(recovery.is_due(secs(22)));
+    assert_eq!(Duration::from_secs(1), recovery.failed(secs(22)));
+}
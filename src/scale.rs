@@ -0,0 +1,84 @@
+use anyhow::{bail, Result};
+
+/// How an interface's speed maps onto the height of its gauges.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scale {
+    /// Logarithmic, so a trickle shows as well as a flood.
+    Log,
+    /// Linear, so half the column means half the bandwidth.
+    Linear,
+}
+
+impl Scale {
+    pub fn parse(s: &str) -> Result<Self> {
+        Ok(match s {
+            "log" => Scale::Log,
+            "linear" => Scale::Linear,
+            _ => bail!("should be \"log\" or \"linear\", not {:?}", s),
+        })
+    }
+
+    /// Where `mbps` falls on this scale, from 0 to 1, with `max_mbps` at the top. A log scale
+    /// covers `decades` decades below that.
+    pub fn level(self, mbps: f64, max_mbps: f64, decades: u32) -> f64 {
+        match self {
+            Scale::Log => log(mbps, max_mbps, decades),
+            Scale::Linear => linear(mbps, max_mbps),
+        }
+    }
+}
+
+/// Where `mbps` falls on a log scale covering `decades` decades up to `max_mbps`: 0 at or below
+/// the bottom (including for nothing at all, whose log is minus infinity), and 1 at or above the
+/// top.
+pub fn log(mbps: f64, max_mbps: f64, decades: u32) -> f64 {
+    let level = 1. + (mbps / max_mbps).log10() / decades as f64;
+    // NaN too, which a negative speed would give
+    if level > 0. { level.min(1.) } else { 0. }
+}
+
+/// Where `mbps` falls on a linear scale from 0 to `max_mbps`.
+pub fn linear(mbps: f64, max_mbps: f64) -> f64 {
+    let level = mbps / max_mbps;
+    if level > 0. { level.min(1.) } else { 0. }
+}
+
+#[cfg(test)]
+#[test]
+fn test_scale_parse() {
+    assert_eq!(Scale::Linear, Scale::parse("linear").unwrap());
+    assert_eq!("should be \"log\" or \"linear\", not \"lin\"",
+        Scale::parse("lin").unwrap_err().to_string());
+}
+
+#[cfg(test)]
+#[test]
+fn test_log() {
+    assert!((log(100., 1000., 3) - 2. / 3.).abs() < 1e-9);
+    assert_eq!(1., log(1000., 1000., 3));
+    // clamped at both ends
+    assert_eq!(0., log(0., 1000., 3));
+    assert_eq!(0., log(0.5, 1000., 3));
+    assert_eq!(1., log(2000., 1000., 3));
+    assert_eq!(0., log(-1., 1000., 3));
+    assert_eq!(0., log(f64::NAN, 1000., 3));
+    // 3 decades below the top
+    assert_eq!(0., log(8., 8000., 3));
+    assert!((log(800., 8000., 3) - 2. / 3.).abs() < 1e-9);
+    assert_eq!(0.5, log(10., 1000., 4));
+}
+
+#[cfg(test)]
+#[test]
+fn test_linear() {
+    assert_eq!(0.5, linear(150., 300.));
+    assert_eq!(1., linear(300., 300.));
+    // clamped at both ends
+    assert_eq!(0., linear(0., 300.));
+    assert_eq!(1., linear(900., 300.));
+    assert_eq!(0., linear(-5., 300.));
+    assert_eq!(0., linear(f64::NAN, 300.));
+
+    assert_eq!(0.25, Scale::Linear.level(75., 300., 3));
+    assert_eq!(log(75., 300., 3), Scale::Log.level(75., 300., 3));
+}
@@ -0,0 +1,137 @@
+//! How many people are logged in, for noticing someone on a shared machine.
+
+use anyhow::{bail, Result};
+use std::fs;
+use std::path::Path;
+
+pub const UTMP_PATH: &str = "/var/run/utmp";
+pub const PROC_DIR: &str = "/proc";
+
+/// Where to count sessions from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    /// Login records in utmp, like `who` shows.
+    Utmp,
+    /// sshd's per-session processes, for systems that don't keep utmp, or to leave out local
+    /// logins.
+    Sshd,
+}
+
+impl Source {
+    pub fn parse(s: &str) -> Result<Self> {
+        Ok(match s {
+            "utmp" => Source::Utmp,
+            "sshd" => Source::Sshd,
+            _ => bail!("should be \"utmp\" or \"sshd\", not {:?}", s),
+        })
+    }
+}
+
+/// Size of a glibc `struct utmp`, which is the same on 32- and 64-bit Linux.
+const UTMP_RECORD: usize = 384;
+/// `ut_type` of a logged-in user's record. The others are for boot time, runlevel changes, login
+/// prompts, and dead processes, which don't count.
+const USER_PROCESS: i16 = 7;
+/// Where `ut_user` is in a record, and its size.
+const UT_USER: std::ops::Range<usize> = 44 .. 76;
+
+/// Logged-in users' records in the contents of a utmp file. A partial record at the end, e.g.
+/// from one being written, is ignored.
+pub fn count_utmp(utmp: &[u8]) -> usize {
+    utmp.chunks_exact(UTMP_RECORD)
+        .filter(|record| {
+            let ut_type = i16::from_ne_bytes([record[0], record[1]]);
+            ut_type == USER_PROCESS && record[UT_USER][0] != 0
+        })
+        .count()
+}
+
+/// sshd's per-session processes in a proc directory, which are named after the session, like
+/// `sshd: alice@pts/0`, or `sshd-session: alice@notty` in newer versions. The privileged half of
+/// each session, `sshd: alice [priv]`, isn't counted, so each one is counted once.
+pub fn count_sshd(proc_dir: &Path) -> Option<usize> {
+    let count = fs::read_dir(proc_dir).ok()?.flatten()
+        .filter(|entry| entry.file_name().to_str().is_some_and(|name| name.parse::<u32>().is_ok()))
+        .filter_map(|entry| fs::read(entry.path().join("cmdline")).ok())
+        .filter(|cmdline| {
+            let title = String::from_utf8_lossy(cmdline.split(|&b| b == 0).next().unwrap_or(&[]))
+                .into_owned();
+            match title.strip_prefix("sshd: ").or_else(|| title.strip_prefix("sshd-session: ")) {
+                Some(session) => session.split_whitespace().next().is_some_and(|s| s.contains('@')),
+                None => false,
+            }
+        })
+        .count();
+    Some(count)
+}
+
+/// Sessions going by `source`, or None if they can't be counted.
+pub fn count(source: Source) -> Option<usize> {
+    match source {
+        Source::Utmp => fs::read(UTMP_PATH).ok().map(|utmp| count_utmp(&utmp)),
+        Source::Sshd => count_sshd(Path::new(PROC_DIR)),
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_count_utmp() {
+    let record = |ut_type: i16, user: &str| {
+        let mut record = vec![0; UTMP_RECORD];
+        record[.. 2].copy_from_slice(&ut_type.to_ne_bytes());
+        record[UT_USER][.. user.len()].copy_from_slice(user.as_bytes());
+        record
+    };
+    let dir = std::env::temp_dir().join(format!("h2-net-lcd-test-utmp-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("utmp");
+    let records = [
+        record(2, "reboot"),                // BOOT_TIME
+        record(1, "runlevel"),              // RUN_LVL
+        record(6, "LOGIN"),                 // LOGIN_PROCESS, a getty
+        record(USER_PROCESS, "alice"),
+        record(8, ""),                      // DEAD_PROCESS, someone who logged out
+        record(USER_PROCESS, "bob"),
+        record(USER_PROCESS, ""),
+    ];
+    fs::write(&path, records.concat()).unwrap();
+    let utmp = fs::read(&path).unwrap();
+    assert_eq!(2, count_utmp(&utmp));
+    // half-written
+    assert_eq!(2, count_utmp(&utmp[.. utmp.len() - 10]));
+    assert_eq!(0, count_utmp(&[]));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(test)]
+#[test]
+fn test_count_sshd() {
+    let dir = std::env::temp_dir().join(format!("h2-net-lcd-test-sshd-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    assert_eq!(None, count_sshd(&dir));
+    let process = |pid: u32, cmdline: &[u8]| {
+        fs::create_dir_all(dir.join(pid.to_string())).unwrap();
+        fs::write(dir.join(pid.to_string()).join("cmdline"), cmdline).unwrap();
+    };
+    process(1, b"/sbin/init\0splash\0");
+    process(500, b"sshd: /usr/sbin/sshd -D [listener] 0 of 10-100 startups");
+    process(600, b"sshd: alice [priv]");
+    process(601, b"sshd: alice@pts/0");
+    process(700, b"sshd-session: bob [priv]");
+    process(701, b"sshd-session: bob@notty");
+    process(800, b"-bash\0");
+    // kernel threads have an empty one
+    process(2, b"");
+    fs::create_dir_all(dir.join("self")).unwrap();
+    assert_eq!(Some(2), count_sshd(&dir));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(test)]
+#[test]
+fn test_parse() {
+    assert_eq!(Source::Sshd, Source::parse("sshd").unwrap());
+    assert_eq!("should be \"utmp\" or \"sshd\", not \"who\"",
+        Source::parse("who").unwrap_err().to_string());
+}
@@ -0,0 +1,89 @@
+/// Exponential moving average, for smoothing the bars so a burst that lasts one sample doesn't
+/// slam a column to the top for one frame. How quickly it follows is set by a time constant
+/// rather than a per-sample weight, so it looks the same whatever the interval.
+#[derive(Debug, Clone, Default)]
+pub struct Ema {
+    value: Option<f64>,
+}
+
+impl Ema {
+    /// Feed in a value and the time since the last one, in seconds. Returns the average, which
+    /// covers about 63% of a step after `time_constant` seconds. With a time constant of 0, or
+    /// for the first value, that's just the value.
+    pub fn update(&mut self, value: f64, secs: f64, time_constant: f64) -> f64 {
+        let smoothed = match self.value {
+            Some(last) if time_constant > 0. && value.is_finite() && last.is_finite() => {
+                let alpha = 1. - (-secs.max(0.) / time_constant).exp();
+                last + (value - last) * alpha
+            }
+            _ => value,
+        };
+        self.value = Some(smoothed);
+        smoothed
+    }
+}
+
+/// An [`Ema`] for each of a list of values, which can change length, like when interfaces come
+/// and go. New ones start from their first value.
+#[derive(Debug, Default)]
+pub struct Emas(Vec<Ema>);
+
+impl Emas {
+    pub fn update(&mut self, values: impl IntoIterator<Item = f64>, secs: f64, time_constant: f64)
+        -> Vec<f64>
+    {
+        let values: Vec<f64> = values.into_iter().collect();
+        self.0.resize(values.len(), Ema::default());
+        values.into_iter().zip(&mut self.0)
+            .map(|(value, ema)| ema.update(value, secs, time_constant))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_ema() {
+    // a step from 0 to 1 covers 1 - 1/e of the way in one time constant
+    let mut ema = Ema::default();
+    assert_eq!(0., ema.update(0., 0.5, 2.));
+    let after = ema.update(1., 2., 2.);
+    assert!((after - (1. - (-1f64).exp())).abs() < 1e-9);
+
+    // the same whether that time is taken in one sample or many
+    let mut fine = Ema::default();
+    fine.update(0., 0.1, 2.);
+    let mut value = 0.;
+    for _ in 0 .. 20 {
+        value = fine.update(1., 0.1, 2.);
+    }
+    assert!((value - after).abs() < 1e-9);
+
+    // and converges on a steady input
+    for _ in 0 .. 100 {
+        value = fine.update(1., 0.5, 2.);
+    }
+    assert!((value - 1.).abs() < 1e-9);
+
+    // a one-sample burst only moves it part of the way
+    let burst = fine.update(0., 0.5, 2.);
+    assert!(burst > 0.75 && burst < 0.8, "{}", burst);
+
+    // no time constant, no smoothing
+    let mut off = Ema::default();
+    off.update(0., 0.5, 0.);
+    assert_eq!(1., off.update(1., 0.5, 0.));
+    // and nothing sticks at NaN
+    assert!(off.update(f64::NAN, 0.5, 2.).is_nan());
+    assert_eq!(0.5, off.update(0.5, 0.5, 2.));
+}
+
+#[cfg(test)]
+#[test]
+fn test_emas() {
+    let mut emas = Emas::default();
+    assert_eq!(vec![1., 2.], emas.update(vec![1., 2.], 0.5, 0.));
+    let values = emas.update(vec![3., 2., 5.], 1., 1.);
+    assert!((values[0] - (1. + 2. * (1. - (-1f64).exp()))).abs() < 1e-9);
+    assert_eq!(&[2., 5.], &values[1 ..]);
+    assert_eq!(vec![4.], emas.update(vec![4.], 1., 0.));
+}
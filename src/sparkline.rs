@@ -0,0 +1,121 @@
+//! Total network throughput over the last several minutes, for the sparkline panel, kept apart
+//! from the interfaces' own buckets so it can go back further than `history_window`.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How wide each of `columns` columns covering `span` is.
+fn column_width(span: Duration, columns: usize) -> Duration {
+    span / columns.max(1) as u32
+}
+
+/// The end of the column `now` falls in. Columns are laid out from `origin`, rather than from
+/// `now`, so they only move along once a whole column's time has passed, instead of every sample
+/// shifting between columns, and the whole sparkline being redrawn, on every frame.
+fn window_end(origin: Instant, now: Instant, width: Duration) -> Instant {
+    let width = width.as_nanos().max(1);
+    let n = now.saturating_duration_since(origin).as_nanos() / width + 1;
+    origin + Duration::from_nanos((n * width) as u64)
+}
+
+/// Sort samples into `columns` columns of `width` each, the last one ending at `end`, oldest
+/// first. Each column gets its busiest sample, so a short burst isn't averaged away, or None if
+/// it has none, like before there's been that much history, or across a gap in it.
+pub fn resample<'a>(samples: impl IntoIterator<Item = &'a (Instant, f64)>, end: Instant,
+    width: Duration, columns: usize) -> Vec<Option<f64>>
+{
+    let mut values = vec![None; columns];
+    let width = width.as_nanos().max(1);
+    for &(time, mbps) in samples {
+        if time >= end {
+            continue;
+        }
+        let back = ((end - time).as_nanos() - 1) / width;
+        if back >= columns as u128 {
+            continue;
+        }
+        let value: &mut Option<f64> = &mut values[columns - 1 - back as usize];
+        *value = Some(value.map_or(mbps, |v| v.max(mbps)));
+    }
+    values
+}
+
+/// Samples of total throughput, in Mbps.
+pub struct History {
+    origin: Instant,
+    samples: VecDeque<(Instant, f64)>,
+}
+
+impl History {
+    pub fn new(origin: Instant) -> Self {
+        Self { origin, samples: VecDeque::new() }
+    }
+
+    /// Add a sample, dropping any that are too old to be in the sparkline any more.
+    pub fn record(&mut self, time: Instant, mbps: f64, span: Duration, columns: usize) {
+        let width = column_width(span, columns);
+        let end = window_end(self.origin, time, width);
+        while let Some(&(oldest, _)) = self.samples.front() {
+            if end - oldest <= width * columns as u32 {
+                break;
+            }
+            self.samples.pop_front();
+        }
+        self.samples.push_back((time, mbps));
+    }
+
+    /// The value for each of `columns` columns covering the last `span`, oldest first.
+    pub fn columns(&self, now: Instant, span: Duration, columns: usize) -> Vec<Option<f64>> {
+        let width = column_width(span, columns);
+        resample(&self.samples, window_end(self.origin, now, width), width, columns)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_resample() {
+    let end = Instant::now() + Duration::from_secs(1000);
+    let secs = |s: u64| end - Duration::from_secs(s);
+    let minute = Duration::from_secs(60);
+
+    // bursty: one busy sample among idle ones sets its column
+    let samples = [(secs(170), 1.), (secs(110), 900.), (secs(100), 2.), (secs(50), 3.)];
+    assert_eq!(vec![Some(1.), Some(900.), Some(3.)], resample(&samples, end, minute, 3));
+
+    // sparse: nothing in the middle, and nothing yet at the start
+    let samples = [(secs(150), 5.), (secs(10), 7.)];
+    assert_eq!(vec![None, Some(5.), None, Some(7.)], resample(&samples, end, minute, 4));
+
+    // columns are half-open, and anything outside them is left out
+    let samples = [(secs(181), 1.), (secs(180), 2.), (secs(60), 3.), (secs(0), 4.)];
+    assert_eq!(vec![Some(2.), None, Some(3.)], resample(&samples, end, minute, 3));
+    assert_eq!(Vec::<Option<f64>>::new(), resample(&samples, end, minute, 0));
+}
+
+#[cfg(test)]
+#[test]
+fn test_history() {
+    let start = Instant::now();
+    let secs = |s: u64| start + Duration::from_secs(s);
+    let span = Duration::from_secs(180);
+
+    let mut history = History::new(start);
+    assert_eq!(vec![None, None, None], history.columns(start, span, 3));
+    for s in (0 .. 120).step_by(10) {
+        history.record(secs(s), s as f64, span, 3);
+    }
+    assert_eq!(vec![None, Some(50.), Some(110.)], history.columns(secs(119), span, 3));
+
+    // within the same minute, only the newest column changes
+    history.record(secs(125), 500., span, 3);
+    assert_eq!(vec![Some(50.), Some(110.), Some(500.)], history.columns(secs(125), span, 3));
+    assert_eq!(vec![Some(50.), Some(110.), Some(500.)], history.columns(secs(179), span, 3));
+
+    // and then everything moves along a whole column
+    assert_eq!(vec![Some(110.), Some(500.), None], history.columns(secs(180), span, 3));
+
+    // old samples are dropped once they're out of every column
+    history.record(secs(240), 1., span, 3);
+    assert_eq!(vec![(secs(125), 500.), (secs(240), 1.)],
+        history.samples.iter().copied().collect::<Vec<_>>());
+}
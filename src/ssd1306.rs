@@ -0,0 +1,399 @@
+//! A 128x64 SSD1306 OLED, drawn as if it were a character display: each character is a 6x8 cell
+//! (a 5x7 glyph plus spacing), stretched vertically to fill the panel's height, so 20x4 characters
+//! take up 120x64 pixels.
+
+use anyhow::{Context, Result};
+use crate::charset;
+use crate::display::{self, DisplayBackend, GlyphStyle};
+use crate::latency;
+use crate::layout::Geometry;
+use i2cdev::core::I2CDevice;
+use i2cdev::linux::LinuxI2CDevice;
+use std::time::Instant;
+
+/// Width and height of the panel, in pixels.
+const WIDTH: usize = 128;
+const PAGES: usize = 8; // 8 pixels tall each
+
+/// Width of a character cell, in pixels.
+const CELL_WIDTH: usize = 6;
+
+/// Widest display that fits on the panel, in characters.
+pub const MAX_COLS: usize = WIDTH / CELL_WIDTH;
+
+/// Control bytes that start each I2C write, saying whether the rest is commands or pixel data.
+const COMMANDS: u8 = 0x00;
+const DATA: u8 = 0x40;
+
+/// Data bytes to send per I2C write. Small enough for any I2C adapter.
+const CHUNK: usize = 16;
+
+const DISPLAY_OFF: u8 = 0xae;
+const DISPLAY_ON: u8 = 0xaf;
+
+/// Set up the controller for a 128x64 panel with the charge pump on, and horizontal addressing,
+/// so the whole screen can be written in one go.
+const INIT: &[u8] = &[
+    DISPLAY_OFF,
+    0xd5, 0x80, // clock divide ratio and oscillator frequency
+    0xa8, 0x3f, // multiplex ratio: 64 rows
+    0xd3, 0x00, // no display offset
+    0x40,       // start at line 0
+    0x8d, 0x14, // charge pump on
+    0x20, 0x00, // horizontal addressing
+    0xa1,       // column 127 is segment 0 (i.e. not mirrored)
+    0xc8,       // scan rows from the bottom up (i.e. not upside down)
+    0xda, 0x12, // alternative COM pin configuration
+    0x81, 0xcf, // contrast
+    0xd9, 0xf1, // pre-charge period
+    0xdb, 0x40, // VCOMH deselect level
+    0xa4,       // show RAM contents
+    0xa6,       // not inverted
+    DISPLAY_ON,
+];
+
+pub struct Ssd1306 {
+    dev: LinuxI2CDevice,
+    geometry: Geometry,
+    glyphs: GlyphStyle,
+    lines: Vec<Vec<u8>>,
+    pos: (usize, usize),
+    /// Custom characters 0 to 7, as uploaded: a row of 5 pixels per byte, top first.
+    custom: [[u8; 8]; 8],
+    /// What the panel currently shows, if it's known.
+    sent: Option<Vec<u8>>,
+    /// Set when a write fails after initialization.
+    failed: bool,
+}
+
+pub fn init_display(bus: u8, addr: u16, geometry: Geometry, glyphs: GlyphStyle)
+    -> Result<Ssd1306>
+{
+    let dev = LinuxI2CDevice::new(format!("/dev/i2c-{}", bus), addr)
+        .context("failed to open I2C device")?;
+    let mut display = Ssd1306 {
+        dev,
+        geometry,
+        glyphs,
+        lines: vec![vec![b' '; geometry.cols]; geometry.rows],
+        pos: (0, 0),
+        custom: [[0; 8]; 8],
+        sent: None,
+        failed: false,
+    };
+    // Unlike the PCF8574, the first write is enough to tell if anything's there.
+    display.dev.write(&[COMMANDS, DISPLAY_OFF])?;
+    display.reinit();
+    if display.failed {
+        anyhow::bail!("failed to initialize the display");
+    }
+    Ok(display)
+}
+
+impl Ssd1306 {
+    fn send(&mut self, control: u8, bytes: &[u8]) {
+        for chunk in bytes.chunks(CHUNK) {
+            let mut buf = Vec::with_capacity(chunk.len() + 1);
+            buf.push(control);
+            buf.extend_from_slice(chunk);
+            let start = Instant::now();
+            let result = self.dev.write(&buf);
+            latency::I2C_WRITES.record(start.elapsed());
+            if let Err(e) = result {
+                eprintln!("I/O error: {}", e);
+                self.failed = true;
+                // don't know what made it to the panel now
+                self.sent = None;
+                return;
+            }
+        }
+    }
+
+    /// Send whichever pages of the panel changed since last time.
+    fn flush(&mut self) {
+        let pixels = render(&self.lines, &self.custom);
+        for page in 0 .. PAGES {
+            let new = &pixels[page * WIDTH .. (page + 1) * WIDTH];
+            if let Some(sent) = &self.sent {
+                if &sent[page * WIDTH .. (page + 1) * WIDTH] == new {
+                    continue;
+                }
+            }
+            // columns 0 to 127 of just this page
+            self.send(COMMANDS, &[0x21, 0, (WIDTH - 1) as u8, 0x22, page as u8, page as u8]);
+            self.send(DATA, new);
+        }
+        if !self.failed {
+            self.sent = Some(pixels);
+        }
+    }
+}
+
+impl DisplayBackend for Ssd1306 {
+    fn position(&mut self, col: u8, row: u8) {
+        self.pos = (
+            (row as usize).min(self.geometry.rows - 1),
+            (col as usize).min(self.geometry.cols - 1),
+        );
+    }
+
+    fn write(&mut self, c: u8) {
+        self.lines[self.pos.0][self.pos.1] = c;
+        self.pos.1 += 1;
+        if self.pos.1 == self.geometry.cols {
+            self.pos.0 = (self.pos.0 + 1) % self.geometry.rows;
+            self.pos.1 = 0;
+        }
+    }
+
+    fn clear(&mut self) {
+        self.lines = vec![vec![b' '; self.geometry.cols]; self.geometry.rows];
+        self.pos = (0, 0);
+    }
+
+    /// Also send the whole panel with the next frame, not just what changed.
+    fn redraw(&mut self) {
+        self.clear();
+        display::upload_bar_characters(self);
+        self.sent = None;
+    }
+
+    fn upload_character(&mut self, location: u8, map: [u8; 8]) {
+        self.custom[location as usize & 7] = map;
+    }
+
+    fn glyphs(&self) -> GlyphStyle {
+        self.glyphs
+    }
+
+    fn reinit(&mut self) {
+        self.send(COMMANDS, INIT);
+        self.sent = None;
+        display::upload_bar_characters(self);
+    }
+
+    /// There's no backlight, so this turns the whole panel off, which keeps its contents.
+    fn set_backlight(&mut self, on: bool) {
+        self.send(COMMANDS, &[if on { DISPLAY_ON } else { DISPLAY_OFF }]);
+    }
+
+    fn take_error(&mut self) -> bool {
+        std::mem::replace(&mut self.failed, false)
+    }
+
+    fn frame_done(&mut self) {
+        self.flush();
+    }
+
+    fn stop(mut self: Box<Self>, message: Option<&str>, backlight: bool) {
+        match message {
+            Some(message) => {
+                self.clear();
+                self.print(message);
+                self.flush();
+                self.set_backlight(backlight);
+            }
+            None => self.set_backlight(false),
+        }
+    }
+}
+
+/// Draw lines of characters into the panel's memory layout: a byte for each column of 8 pixels
+/// (least significant bit at the top), 128 columns per page, pages from the top down.
+fn render(lines: &[Vec<u8>], custom: &[[u8; 8]; 8]) -> Vec<u8> {
+    let mut pixels = vec![0; WIDTH * PAGES];
+    // each character row is this many pages tall
+    let scale = PAGES / lines.len().max(1);
+    for (row, line) in lines.iter().enumerate() {
+        for (col, &c) in line.iter().enumerate() {
+            let columns = match c {
+                0 ..= 7 => custom_columns(&custom[c as usize]),
+                c => glyph(c),
+            };
+            for (x, &bits) in columns.iter().enumerate() {
+                let stretched = stretch(bits, scale);
+                for page in 0 .. scale {
+                    let offset = (row * scale + page) * WIDTH + col * CELL_WIDTH + x;
+                    pixels[offset] = (stretched >> (page * 8)) as u8;
+                }
+            }
+        }
+    }
+    pixels
+}
+
+/// Stretch a column of 8 pixels to `scale` times the height, repeating each pixel.
+fn stretch(bits: u8, scale: usize) -> u32 {
+    let mut out = 0u32;
+    for i in 0 .. 8 {
+        if bits & (1 << i) != 0 {
+            for j in 0 .. scale {
+                out |= 1 << (i * scale + j);
+            }
+        }
+    }
+    out
+}
+
+/// Turn a custom character (rows of 5 pixels, the leftmost in bit 4) into columns.
+fn custom_columns(rows: &[u8; 8]) -> [u8; CELL_WIDTH] {
+    let mut columns = [0; CELL_WIDTH];
+    for (x, column) in columns.iter_mut().take(5).enumerate() {
+        for (y, row) in rows.iter().enumerate() {
+            if row & (0x10 >> x) != 0 {
+                *column |= 1 << y;
+            }
+        }
+    }
+    columns
+}
+
+/// Columns of pixels for a character in a frame, with symbols as their `charset` codes.
+fn glyph(c: u8) -> [u8; CELL_WIDTH] {
+    let columns = match c {
+        charset::DEGREE => &DEGREE,
+        charset::MICRO => &MICRO,
+        charset::ARROW => &ARROW,
+        0x20 ..= 0x7e => &FONT[c as usize - 0x20],
+        _ => &FONT[b'?' as usize - 0x20],
+    };
+    let mut cell = [0; CELL_WIDTH];
+    cell[.. 5].copy_from_slice(columns);
+    cell
+}
+
+const DEGREE: [u8; 5] = [0x00, 0x06, 0x09, 0x09, 0x06];
+const MICRO: [u8; 5] = [0x7c, 0x20, 0x20, 0x10, 0x3c];
+const ARROW: [u8; 5] = [0x08, 0x08, 0x2a, 0x1c, 0x08];
+
+/// A 5x7 font for printable ASCII, from space to `~`, a byte per column.
+const FONT: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], // space
+    [0x00, 0x00, 0x5f, 0x00, 0x00], // !
+    [0x00, 0x07, 0x00, 0x07, 0x00], // "
+    [0x14, 0x7f, 0x14, 0x7f, 0x14], // #
+    [0x24, 0x2a, 0x7f, 0x2a, 0x12], // $
+    [0x23, 0x13, 0x08, 0x64, 0x62], // %
+    [0x36, 0x49, 0x55, 0x22, 0x50], // &
+    [0x00, 0x05, 0x03, 0x00, 0x00], // '
+    [0x00, 0x1c, 0x22, 0x41, 0x00], // (
+    [0x00, 0x41, 0x22, 0x1c, 0x00], // )
+    [0x14, 0x08, 0x3e, 0x08, 0x14], // *
+    [0x08, 0x08, 0x3e, 0x08, 0x08], // +
+    [0x00, 0x50, 0x30, 0x00, 0x00], // ,
+    [0x08, 0x08, 0x08, 0x08, 0x08], // -
+    [0x00, 0x60, 0x60, 0x00, 0x00], // .
+    [0x20, 0x10, 0x08, 0x04, 0x02], // /
+    [0x3e, 0x51, 0x49, 0x45, 0x3e], // 0
+    [0x00, 0x42, 0x7f, 0x40, 0x00], // 1
+    [0x42, 0x61, 0x51, 0x49, 0x46], // 2
+    [0x21, 0x41, 0x45, 0x4b, 0x31], // 3
+    [0x18, 0x14, 0x12, 0x7f, 0x10], // 4
+    [0x27, 0x45, 0x45, 0x45, 0x39], // 5
+    [0x3c, 0x4a, 0x49, 0x49, 0x30], // 6
+    [0x01, 0x71, 0x09, 0x05, 0x03], // 7
+    [0x36, 0x49, 0x49, 0x49, 0x36], // 8
+    [0x06, 0x49, 0x49, 0x29, 0x1e], // 9
+    [0x00, 0x36, 0x36, 0x00, 0x00], // :
+    [0x00, 0x56, 0x36, 0x00, 0x00], // ;
+    [0x08, 0x14, 0x22, 0x41, 0x00], // <
+    [0x14, 0x14, 0x14, 0x14, 0x14], // =
+    [0x00, 0x41, 0x22, 0x14, 0x08], // >
+    [0x02, 0x01, 0x51, 0x09, 0x06], // ?
+    [0x32, 0x49, 0x79, 0x41, 0x3e], // @
+    [0x7e, 0x11, 0x11, 0x11, 0x7e], // A
+    [0x7f, 0x49, 0x49, 0x49, 0x36], // B
+    [0x3e, 0x41, 0x41, 0x41, 0x22], // C
+    [0x7f, 0x41, 0x41, 0x22, 0x1c], // D
+    [0x7f, 0x49, 0x49, 0x49, 0x41], // E
+    [0x7f, 0x09, 0x09, 0x09, 0x01], // F
+    [0x3e, 0x41, 0x49, 0x49, 0x7a], // G
+    [0x7f, 0x08, 0x08, 0x08, 0x7f], // H
+    [0x00, 0x41, 0x7f, 0x41, 0x00], // I
+    [0x20, 0x40, 0x41, 0x3f, 0x01], // J
+    [0x7f, 0x08, 0x14, 0x22, 0x41], // K
+    [0x7f, 0x40, 0x40, 0x40, 0x40], // L
+    [0x7f, 0x02, 0x0c, 0x02, 0x7f], // M
+    [0x7f, 0x04, 0x08, 0x10, 0x7f], // N
+    [0x3e, 0x41, 0x41, 0x41, 0x3e], // O
+    [0x7f, 0x09, 0x09, 0x09, 0x06], // P
+    [0x3e, 0x41, 0x51, 0x21, 0x5e], // Q
+    [0x7f, 0x09, 0x19, 0x29, 0x46], // R
+    [0x46, 0x49, 0x49, 0x49, 0x31], // S
+    [0x01, 0x01, 0x7f, 0x01, 0x01], // T
+    [0x3f, 0x40, 0x40, 0x40, 0x3f], // U
+    [0x1f, 0x20, 0x40, 0x20, 0x1f], // V
+    [0x3f, 0x40, 0x38, 0x40, 0x3f], // W
+    [0x63, 0x14, 0x08, 0x14, 0x63], // X
+    [0x07, 0x08, 0x70, 0x08, 0x07], // Y
+    [0x61, 0x51, 0x49, 0x45, 0x43], // Z
+    [0x00, 0x7f, 0x41, 0x41, 0x00], // [
+    [0x02, 0x04, 0x08, 0x10, 0x20], // backslash
+    [0x00, 0x41, 0x41, 0x7f, 0x00], // ]
+    [0x04, 0x02, 0x01, 0x02, 0x04], // ^
+    [0x40, 0x40, 0x40, 0x40, 0x40], // _
+    [0x00, 0x01, 0x02, 0x04, 0x00], // `
+    [0x20, 0x54, 0x54, 0x54, 0x78], // a
+    [0x7f, 0x48, 0x44, 0x44, 0x38], // b
+    [0x38, 0x44, 0x44, 0x44, 0x20], // c
+    [0x38, 0x44, 0x44, 0x48, 0x7f], // d
+    [0x38, 0x54, 0x54, 0x54, 0x18], // e
+    [0x08, 0x7e, 0x09, 0x01, 0x02], // f
+    [0x0c, 0x52, 0x52, 0x52, 0x3e], // g
+    [0x7f, 0x08, 0x04, 0x04, 0x78], // h
+    [0x00, 0x44, 0x7d, 0x40, 0x00], // i
+    [0x20, 0x40, 0x44, 0x3d, 0x00], // j
+    [0x7f, 0x10, 0x28, 0x44, 0x00], // k
+    [0x00, 0x41, 0x7f, 0x40, 0x00], // l
+    [0x7c, 0x04, 0x18, 0x04, 0x78], // m
+    [0x7c, 0x08, 0x04, 0x04, 0x78], // n
+    [0x38, 0x44, 0x44, 0x44, 0x38], // o
+    [0x7c, 0x14, 0x14, 0x14, 0x08], // p
+    [0x08, 0x14, 0x14, 0x18, 0x7c], // q
+    [0x7c, 0x08, 0x04, 0x04, 0x08], // r
+    [0x48, 0x54, 0x54, 0x54, 0x20], // s
+    [0x04, 0x3f, 0x44, 0x40, 0x20], // t
+    [0x3c, 0x40, 0x40, 0x20, 0x7c], // u
+    [0x1c, 0x20, 0x40, 0x20, 0x1c], // v
+    [0x3c, 0x40, 0x30, 0x40, 0x3c], // w
+    [0x44, 0x28, 0x10, 0x28, 0x44], // x
+    [0x0c, 0x50, 0x50, 0x50, 0x3c], // y
+    [0x44, 0x64, 0x54, 0x4c, 0x44], // z
+    [0x00, 0x08, 0x36, 0x41, 0x00], // {
+    [0x00, 0x00, 0x7f, 0x00, 0x00], // |
+    [0x00, 0x41, 0x36, 0x08, 0x00], // }
+    [0x08, 0x04, 0x08, 0x10, 0x08], // ~
+];
+
+#[cfg(test)]
+#[test]
+fn test_stretch() {
+    assert_eq!(0b1100_0011, stretch(0b1001, 2));
+    assert_eq!(0xf0f, stretch(0b101, 4));
+    assert_eq!(0b1001, stretch(0b1001, 1));
+}
+
+#[cfg(test)]
+#[test]
+fn test_render() {
+    // the 2-pixel bar gauge block: bottom two rows lit
+    let mut custom = [[0; 8]; 8];
+    custom[1] = [0, 0, 0, 0, 0, 0, 0b11111, 0b11111];
+    assert_eq!([0xc0, 0xc0, 0xc0, 0xc0, 0xc0, 0], custom_columns(&custom[1]));
+
+    // 4 rows: each character row is 2 pages tall
+    let lines = vec![b"|\x01".to_vec(), vec![], vec![], vec![charset::DEGREE]];
+    let pixels = render(&lines, &custom);
+    assert_eq!(WIDTH * PAGES, pixels.len());
+    // `|` is all 7 rows of the middle column, which stretches to 14 rows over 2 pages
+    assert_eq!(&[0, 0, 0xff, 0, 0, 0], &pixels[.. 6]);
+    assert_eq!(&[0, 0, 0x3f, 0, 0, 0], &pixels[WIDTH .. WIDTH + 6]);
+    // the block's bottom 2 rows stretch to the bottom 4 rows of the second page
+    assert_eq!(&[0; 5], &pixels[6 .. 11]);
+    assert_eq!(&[0xf0; 5], &pixels[WIDTH + 6 .. WIDTH + 11]);
+    // the degree sign, in the bottom row
+    assert_eq!(&[0, 0x3c, 0xc3, 0xc3, 0x3c], &pixels[6 * WIDTH .. 6 * WIDTH + 5]);
+
+    // nothing past the characters
+    assert!(pixels[12 .. WIDTH].iter().all(|&b| b == 0));
+}
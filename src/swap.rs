@@ -0,0 +1,73 @@
+//! Swap: how much is in use, from `/proc/meminfo`, and how fast pages are moving in and out of it,
+//! from `/proc/vmstat`.
+
+use std::time::Instant;
+
+pub const VMSTAT_PATH: &str = "/proc/vmstat";
+
+/// Fraction of swap in use, or None if there's no swap, or none of it is used.
+pub fn used(swap_free: u64, swap_total: u64) -> Option<f64> {
+    let used = swap_total.saturating_sub(swap_free);
+    if used == 0 {
+        return None;
+    }
+    Some(used as f64 / swap_total as f64)
+}
+
+/// Pages swapped in and out so far, from the contents of `/proc/vmstat`.
+pub fn parse(vmstat: &str) -> Option<u64> {
+    let counter = |name: &str| vmstat.lines().find_map(|line| {
+        let (key, value) = line.split_once(' ')?;
+        if key == name { value.trim().parse::<u64>().ok() } else { None }
+    });
+    Some(counter("pswpin")? + counter("pswpout")?)
+}
+
+/// How fast the system is swapping.
+#[derive(Default)]
+pub struct SwapActivity {
+    last: Option<(Instant, u64)>,
+}
+
+impl SwapActivity {
+    /// Take a sample from the contents of `/proc/vmstat`, returning the pages swapped in or out
+    /// per second since the last one. Zero if it's the first sample, or the counters can't be
+    /// read.
+    pub fn update(&mut self, now: Instant, vmstat: &str) -> f64 {
+        let pages = parse(vmstat);
+        let rate = match (pages, self.last) {
+            (Some(pages), Some((time, last))) if now > time => {
+                pages.saturating_sub(last) as f64 / (now - time).as_secs_f64()
+            }
+            _ => 0.,
+        };
+        self.last = pages.map(|pages| (now, pages));
+        rate
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_used() {
+    assert_eq!(None, used(0, 0));
+    assert_eq!(None, used(2048, 2048));
+    assert_eq!(Some(0.25), used(1536, 2048));
+}
+
+#[cfg(test)]
+#[test]
+fn test_swap_activity() {
+    let start = Instant::now();
+    let secs = |n| start + std::time::Duration::from_secs(n);
+    let vmstat = |pages_in: u64, pages_out: u64| {
+        format!("pgpgin 1000\npswpin {}\npswpout {}\npgfault 5\n", pages_in, pages_out)
+    };
+    assert_eq!(Some(30), parse(&vmstat(10, 20)));
+    assert_eq!(None, parse("pgpgin 1000\n"));
+
+    let mut activity = SwapActivity::default();
+    assert_eq!(0., activity.update(start, &vmstat(10, 20)));
+    assert_eq!(50., activity.update(secs(2), &vmstat(60, 70)));
+    assert_eq!(0., activity.update(secs(3), ""));
+    assert_eq!(0., activity.update(secs(4), &vmstat(60, 70)));
+}
@@ -0,0 +1,454 @@
+//! A small parser for the subset of TOML used by the config file: tables (including dotted and
+//! array-of-tables headers), dotted keys, basic and literal strings, integers (including hex),
+//! floats, booleans, arrays, and inline tables. No dates/times or multi-line strings.
+
+use anyhow::{bail, Result};
+use std::collections::BTreeMap;
+
+pub type Table = BTreeMap<String, Value>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Array(Vec<Value>),
+    Table(Table),
+}
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "a string",
+            Value::Integer(_) => "an integer",
+            Value::Float(_) => "a float",
+            Value::Boolean(_) => "a boolean",
+            Value::Array(_) => "an array",
+            Value::Table(_) => "a table",
+        }
+    }
+}
+
+pub fn parse(text: &str) -> Result<Table> {
+    let mut parser = Parser { chars: text.chars().collect(), pos: 0, line: 1 };
+    parser.document().map_err(|e| e.context(format!("line {}", parser.line)))
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<()> {
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => bail!("expected {:?}, found {:?}", expected, c),
+            None => bail!("expected {:?}, found end of file", expected),
+        }
+    }
+
+    /// Skip spaces and tabs.
+    fn skip_ws(&mut self) {
+        while let Some(' ' | '\t') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    /// Skip whitespace, newlines, and comments.
+    fn skip_ws_lines(&mut self) {
+        loop {
+            self.skip_ws();
+            match self.peek() {
+                Some('\n' | '\r') => {
+                    self.next();
+                }
+                Some('#') => self.skip_comment(),
+                _ => return,
+            }
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        while !matches!(self.peek(), None | Some('\n')) {
+            self.pos += 1;
+        }
+    }
+
+    /// After a header or key/value pair, only a comment is allowed before the end of the line.
+    fn end_of_line(&mut self) -> Result<()> {
+        self.skip_ws();
+        if self.peek() == Some('#') {
+            self.skip_comment();
+        }
+        match self.peek() {
+            None => Ok(()),
+            Some('\n') => {
+                self.next();
+                Ok(())
+            }
+            Some('\r') if self.peek_at(1) == Some('\n') => {
+                self.next();
+                self.next();
+                Ok(())
+            }
+            Some(c) => bail!("unexpected {:?} after value", c),
+        }
+    }
+
+    fn document(&mut self) -> Result<Table> {
+        let mut root = Table::new();
+        let mut current: Vec<String> = vec![];
+        loop {
+            self.skip_ws_lines();
+            match self.peek() {
+                None => return Ok(root),
+                Some('[') => {
+                    self.next();
+                    let array = self.peek() == Some('[');
+                    if array {
+                        self.next();
+                    }
+                    self.skip_ws();
+                    let path = self.key()?;
+                    self.skip_ws();
+                    self.expect(']')?;
+                    if array {
+                        self.expect(']')?;
+                        let (last, parents) = path.split_last().unwrap();
+                        let parent = table_at(&mut root, parents)?;
+                        match parent.entry(last.clone())
+                            .or_insert_with(|| Value::Array(vec![]))
+                        {
+                            Value::Array(items) => items.push(Value::Table(Table::new())),
+                            _ => bail!("{:?} is already defined and isn't an array of tables",
+                                path.join(".")),
+                        }
+                    } else {
+                        table_at(&mut root, &path)?;
+                    }
+                    self.end_of_line()?;
+                    current = path;
+                }
+                Some(_) => {
+                    let path = self.key()?;
+                    self.skip_ws();
+                    self.expect('=')?;
+                    self.skip_ws();
+                    let value = self.value()?;
+                    let (last, parents) = path.split_last().unwrap();
+                    let table = table_at(&mut root, &current)?;
+                    let table = table_at(table, parents)?;
+                    if table.insert(last.clone(), value).is_some() {
+                        bail!("duplicate key {:?}", path.join("."));
+                    }
+                    self.end_of_line()?;
+                }
+            }
+        }
+    }
+
+    /// A possibly-dotted key.
+    fn key(&mut self) -> Result<Vec<String>> {
+        let mut parts = vec![self.simple_key()?];
+        loop {
+            self.skip_ws();
+            if self.peek() != Some('.') {
+                return Ok(parts);
+            }
+            self.next();
+            self.skip_ws();
+            parts.push(self.simple_key()?);
+        }
+    }
+
+    fn simple_key(&mut self) -> Result<String> {
+        match self.peek() {
+            Some('"') => self.basic_string(),
+            Some('\'') => self.literal_string(),
+            _ => {
+                let mut key = String::new();
+                while let Some(c) = self.peek() {
+                    if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                        key.push(c);
+                        self.pos += 1;
+                    } else {
+                        break;
+                    }
+                }
+                if key.is_empty() {
+                    match self.peek() {
+                        Some(c) => bail!("expected a key, found {:?}", c),
+                        None => bail!("expected a key, found end of file"),
+                    }
+                }
+                Ok(key)
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        match self.peek() {
+            Some('"') => Ok(Value::String(self.basic_string()?)),
+            Some('\'') => Ok(Value::String(self.literal_string()?)),
+            Some('[') => self.array(),
+            Some('{') => self.inline_table(),
+            Some(_) => self.scalar(),
+            None => bail!("expected a value, found end of file"),
+        }
+    }
+
+    fn basic_string(&mut self) -> Result<String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            if self.peek() == Some('\n') {
+                bail!("unterminated string");
+            }
+            match self.next() {
+                None => bail!("unterminated string"),
+                Some('"') => return Ok(s),
+                Some('\\') => {
+                    let c = match self.next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('u') => {
+                            let hex: String = (0 .. 4).filter_map(|_| self.next()).collect();
+                            u32::from_str_radix(&hex, 16).ok()
+                                .and_then(std::char::from_u32)
+                                .ok_or_else(|| anyhow::anyhow!("invalid escape \\u{}", hex))?
+                        }
+                        Some(c) => bail!("invalid escape \\{}", c),
+                        None => bail!("unterminated string"),
+                    };
+                    s.push(c);
+                }
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    fn literal_string(&mut self) -> Result<String> {
+        self.expect('\'')?;
+        let mut s = String::new();
+        loop {
+            if self.peek() == Some('\n') {
+                bail!("unterminated string");
+            }
+            match self.next() {
+                None => bail!("unterminated string"),
+                Some('\'') => return Ok(s),
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value> {
+        self.expect('[')?;
+        let mut items = vec![];
+        loop {
+            self.skip_ws_lines();
+            if self.peek() == Some(']') {
+                self.next();
+                return Ok(Value::Array(items));
+            }
+            items.push(self.value()?);
+            self.skip_ws_lines();
+            match self.next() {
+                Some(',') => (),
+                Some(']') => return Ok(Value::Array(items)),
+                Some(c) => bail!("expected ',' or ']' in array, found {:?}", c),
+                None => bail!("unterminated array"),
+            }
+        }
+    }
+
+    fn inline_table(&mut self) -> Result<Value> {
+        self.expect('{')?;
+        let mut table = Table::new();
+        self.skip_ws();
+        if self.peek() == Some('}') {
+            self.next();
+            return Ok(Value::Table(table));
+        }
+        loop {
+            self.skip_ws();
+            let path = self.key()?;
+            self.skip_ws();
+            self.expect('=')?;
+            self.skip_ws();
+            let value = self.value()?;
+            let (last, parents) = path.split_last().unwrap();
+            if table_at(&mut table, parents)?.insert(last.clone(), value).is_some() {
+                bail!("duplicate key {:?}", path.join("."));
+            }
+            self.skip_ws();
+            match self.next() {
+                Some(',') => (),
+                Some('}') => return Ok(Value::Table(table)),
+                Some(c) => bail!("expected ',' or '}}' in inline table, found {:?}", c),
+                None => bail!("unterminated inline table"),
+            }
+        }
+    }
+
+    /// Booleans and numbers.
+    fn scalar(&mut self) -> Result<Value> {
+        let mut word = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '+' | '.') {
+                word.push(c);
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        match word.as_str() {
+            "" => bail!("expected a value, found {:?}", self.peek().unwrap()),
+            "true" => return Ok(Value::Boolean(true)),
+            "false" => return Ok(Value::Boolean(false)),
+            _ => (),
+        }
+        let digits = word.replace('_', "");
+        let (sign, unsigned) = match digits.strip_prefix('-') {
+            Some(rest) => (-1, rest),
+            None => (1, digits.strip_prefix('+').unwrap_or(&digits)),
+        };
+        for (prefix, radix) in [("0x", 16), ("0o", 8), ("0b", 2)] {
+            if let Some(rest) = unsigned.strip_prefix(prefix) {
+                return i64::from_str_radix(rest, radix)
+                    .map(|n| Value::Integer(sign * n))
+                    .map_err(|_| anyhow::anyhow!("invalid number {:?}", word));
+            }
+        }
+        if let Ok(n) = digits.parse::<i64>() {
+            return Ok(Value::Integer(n));
+        }
+        match digits.parse::<f64>() {
+            Ok(n) if unsigned.starts_with(|c: char| c.is_ascii_digit()) => Ok(Value::Float(n)),
+            _ => bail!("invalid value {:?} (strings need quotes)", word),
+        }
+    }
+}
+
+/// Get (creating if necessary) the table at the given path below `root`. Paths through arrays of
+/// tables go into the last table in the array.
+fn table_at<'a>(root: &'a mut Table, path: &[String]) -> Result<&'a mut Table> {
+    let mut table = root;
+    for (i, key) in path.iter().enumerate() {
+        let value = table.entry(key.clone()).or_insert_with(|| Value::Table(Table::new()));
+        table = match value {
+            Value::Table(t) => t,
+            Value::Array(items) => match items.last_mut() {
+                Some(Value::Table(t)) => t,
+                _ => bail!("{:?} is not a table", path[..= i].join(".")),
+            },
+            _ => bail!("{:?} is not a table", path[..= i].join(".")),
+        };
+    }
+    Ok(table)
+}
+
+#[cfg(test)]
+#[test]
+fn test_parse() {
+    let doc = parse(r#"
+# comment
+top = "hello" # trailing comment
+lit = 'C:\path'
+escaped = "a\"b\\c\u00b0"
+hex = 0x3f
+neg = -12
+big = 1_000
+float = 2.5
+exp = 1e3
+yes = true
+list = [ "a", 'b',
+    "c", # comment in array
+]
+empty = []
+nested = [[1, 2], [3]]
+inline = { a = 1, b.c = "x" }
+dotted.key = 7
+
+[section]
+x = 1
+
+[section.sub]
+y = 2
+
+[[multi]]
+n = 1
+
+[[multi]]
+n = 2
+"#).unwrap();
+
+    let s = |v: &str| Value::String(v.to_owned());
+    let table = |items: Vec<(&str, Value)>| Value::Table(
+        items.into_iter().map(|(k, v)| (k.to_owned(), v)).collect());
+    assert_eq!(s("hello"), doc["top"]);
+    assert_eq!(s("C:\\path"), doc["lit"]);
+    assert_eq!(s("a\"b\\c°"), doc["escaped"]);
+    assert_eq!(Value::Integer(0x3f), doc["hex"]);
+    assert_eq!(Value::Integer(-12), doc["neg"]);
+    assert_eq!(Value::Integer(1000), doc["big"]);
+    assert_eq!(Value::Float(2.5), doc["float"]);
+    assert_eq!(Value::Float(1000.), doc["exp"]);
+    assert_eq!(Value::Boolean(true), doc["yes"]);
+    assert_eq!(Value::Array(vec![s("a"), s("b"), s("c")]), doc["list"]);
+    assert_eq!(Value::Array(vec![]), doc["empty"]);
+    assert_eq!(Value::Array(vec![
+        Value::Array(vec![Value::Integer(1), Value::Integer(2)]),
+        Value::Array(vec![Value::Integer(3)]),
+    ]), doc["nested"]);
+    assert_eq!(table(vec![("a", Value::Integer(1)), ("b", table(vec![("c", s("x"))]))]),
+        doc["inline"]);
+    assert_eq!(table(vec![("key", Value::Integer(7))]), doc["dotted"]);
+    assert_eq!(table(vec![
+        ("x", Value::Integer(1)),
+        ("sub", table(vec![("y", Value::Integer(2))])),
+    ]), doc["section"]);
+    assert_eq!(Value::Array(vec![
+        table(vec![("n", Value::Integer(1))]),
+        table(vec![("n", Value::Integer(2))]),
+    ]), doc["multi"]);
+}
+
+#[cfg(test)]
+#[test]
+fn test_parse_errors() {
+    let err = |text: &str| format!("{:#}", parse(text).unwrap_err());
+    assert_eq!("line 2: duplicate key \"a\"", err("a = 1\na = 2\n"));
+    assert_eq!("line 1: invalid value \"lan0\" (strings need quotes)", err("a = lan0"));
+    assert_eq!("line 1: unterminated string", err("a = \"lan0"));
+    assert_eq!("line 2: unterminated string", err("a = 1\nb = 'lan0\nc = 2"));
+    assert_eq!("line 1: unexpected 'b' after value", err("a = 1 b = 2"));
+    assert_eq!("line 3: expected ',' or ']' in array, found '\"'", err("a = [\n\"x\"\n\"y\"]"));
+    assert_eq!("line 2: \"a\" is not a table", err("a = 1\n[a]"));
+    assert_eq!("line 1: expected a value, found end of file", err("a ="));
+    assert_eq!("line 1: invalid number \"0xzz\"", err("a = 0xzz"));
+    assert_eq!("line 1: expected '=', found end of file", err("a"));
+}
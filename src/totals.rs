@@ -0,0 +1,203 @@
+//! Bytes each interface has sent and received in total, kept in a file across restarts.
+
+use anyhow::{bail, Context, Result};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_PATH: &str = "/var/lib/h2-net-lcd/totals";
+
+/// Each interface's totals: bytes sent, then received.
+pub type Totals = BTreeMap<String, (u128, u128)>;
+
+/// A byte count in decimal units, like `512B`, `1.5K`, or `142.0G`. That's 2 to 6 characters up to
+/// 999.9E, which is more than a 64-bit counter can hold; only beyond that does it get longer.
+pub fn human_bytes(bytes: u128) -> String {
+    const UNITS: &[char] = &['K', 'M', 'G', 'T', 'P', 'E'];
+    if bytes < 1000 {
+        return format!("{}B", bytes);
+    }
+    let mut value = bytes as f64;
+    for (i, &unit) in UNITS.iter().enumerate() {
+        value /= 1000.;
+        // round as it'll be shown, so 999.96K is 1.0M rather than 1000.0K
+        if (value * 10.).round() < 10_000. || i == UNITS.len() - 1 {
+            return format!("{:.1}{}", value, unit);
+        }
+    }
+    unreachable!()
+}
+
+/// Read totals saved by `save`, falling back to the previous file it kept if this one is missing
+/// or can't be parsed. Without either, there are none yet.
+pub fn load(path: &Path) -> Result<Totals> {
+    let backup = backup_path(path);
+    match read(path) {
+        Ok(Some(totals)) => Ok(totals),
+        // a save was cut short between moving the old file aside and putting the new one in place
+        Ok(None) => Ok(read(&backup)?.unwrap_or_default()),
+        Err(e) => match read(&backup) {
+            Ok(Some(totals)) => {
+                eprintln!("{:#}; using {:?} instead", e, backup);
+                Ok(totals)
+            }
+            _ => Err(e),
+        },
+    }
+}
+
+/// Write totals to a file, replacing it all at once, and only once it's safely on disk, so a crash
+/// or power cut part way through can't leave it half written. The file it replaces is kept as a
+/// backup for `load` to fall back to. The directory it goes in is created if need be.
+pub fn save(path: &Path, totals: &Totals) -> Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if dir != Path::new("") => dir,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(dir).with_context(|| format!("failed to create {:?}", dir))?;
+    let tmp = with_suffix(path, ".tmp");
+    File::create(&tmp)
+        .and_then(|mut file| {
+            file.write_all(format(totals).as_bytes())?;
+            file.sync_all()
+        })
+        .with_context(|| format!("failed to write {:?}", tmp))?;
+    let backup = backup_path(path);
+    match fs::rename(path, &backup) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            return Err(e).with_context(|| format!("failed to move {:?} to {:?}", path, backup));
+        }
+        _ => (),
+    }
+    fs::rename(&tmp, path).with_context(|| format!("failed to replace {:?}", path))?;
+    // and the renames themselves need to be on disk too
+    File::open(dir).and_then(|dir| dir.sync_all())
+        .with_context(|| format!("failed to sync {:?}", dir))
+}
+
+fn backup_path(path: &Path) -> PathBuf {
+    with_suffix(path, ".bak")
+}
+
+/// The path with something added on the end, rather than replacing its extension, so that a file
+/// already named like `x.tmp` or `x.bak` isn't its own temporary file or backup.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Totals from one file, or None if it doesn't exist.
+fn read(path: &Path) -> Result<Option<Totals>> {
+    match fs::read_to_string(path) {
+        Ok(text) => parse(&text).with_context(|| format!("in {:?}", path)).map(Some),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("failed to read {:?}", path)),
+    }
+}
+
+/// One line per interface: its name, bytes sent, and bytes received.
+fn parse(text: &str) -> Result<Totals> {
+    let mut totals = Totals::new();
+    for (i, line) in text.lines().enumerate() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let (name, sent, received) = match fields[..] {
+            [] => continue,
+            [name, sent, received] => (name, sent, received),
+            _ => bail!("line {}: expected an interface name and two numbers", i + 1),
+        };
+        let number = |s: &str| s.parse::<u128>()
+            .with_context(|| format!("line {}: {:?} isn't a number", i + 1, s));
+        totals.insert(name.to_owned(), (number(sent)?, number(received)?));
+    }
+    Ok(totals)
+}
+
+fn format(totals: &Totals) -> String {
+    totals.iter()
+        .map(|(name, (sent, received))| format!("{} {} {}\n", name, sent, received))
+        .collect()
+}
+
+#[cfg(test)]
+#[test]
+fn test_human_bytes() {
+    assert_eq!("0B", human_bytes(0));
+    assert_eq!("999B", human_bytes(999));
+    assert_eq!("1.0K", human_bytes(1000));
+    assert_eq!("1.5K", human_bytes(1500));
+    assert_eq!("999.9K", human_bytes(999_949));
+    assert_eq!("1.0M", human_bytes(999_950));
+    assert_eq!("142.0G", human_bytes(142_000_000_000));
+    assert_eq!("38.4T", human_bytes(38_400_000_000_000));
+    assert_eq!("12.0E", human_bytes(12 * 10u128.pow(18)));
+    assert_eq!("18.4E", human_bytes(u64::MAX as u128));
+    assert_eq!("12000.0E", human_bytes(12 * 10u128.pow(21)));
+}
+
+#[cfg(test)]
+#[test]
+fn test_totals_file() {
+    let totals: Totals = vec![
+        ("lan0".to_owned(), (1, 2)),
+        ("wan".to_owned(), (u64::MAX as u128 * 3, 0)),
+    ].into_iter().collect();
+    let text = format(&totals);
+    assert_eq!("lan0 1 2\nwan 55340232221128654845 0\n", text);
+    assert_eq!(totals, parse(&text).unwrap());
+    assert_eq!(Totals::new(), parse("\n").unwrap());
+
+    assert_eq!("line 2: expected an interface name and two numbers",
+        parse("lan0 1 2\nwan 5\n").unwrap_err().to_string());
+    assert_eq!("line 1: \"-1\" isn't a number", parse("wan -1 0").unwrap_err().to_string());
+
+    assert_eq!(Totals::new(), load(Path::new("/nonexistent/h2lcd-totals")).unwrap());
+}
+
+#[cfg(test)]
+#[test]
+fn test_save_and_load() {
+    let dir = std::env::temp_dir()
+        .join(format!("h2-net-lcd-test-totals-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("totals");
+    let first: Totals = vec![("wan".to_owned(), (1, 2))].into_iter().collect();
+    let second: Totals = vec![("wan".to_owned(), (3, 4))].into_iter().collect();
+
+    save(&path, &first).unwrap();
+    assert_eq!(first, load(&path).unwrap());
+    assert!(!dir.join("totals.bak").exists());
+    save(&path, &second).unwrap();
+    assert_eq!(second, load(&path).unwrap());
+    assert_eq!("wan 1 2\n", fs::read_to_string(dir.join("totals.bak")).unwrap());
+    assert!(!dir.join("totals.tmp").exists());
+
+    // a corrupted file, or one a save didn't get to put in place, falls back to the backup
+    fs::write(&path, "wan 3").unwrap();
+    assert_eq!(first, load(&path).unwrap());
+    fs::remove_file(&path).unwrap();
+    assert_eq!(first, load(&path).unwrap());
+    // but if that's no good either, the error is about the file itself
+    fs::write(&path, "wan 3").unwrap();
+    fs::write(dir.join("totals.bak"), "garbage").unwrap();
+    assert!(load(&path).unwrap_err().to_string().starts_with("in "));
+
+    // names that already look like the temporary file or the backup get their own
+    let tmp = dir.join("x.tmp");
+    let bak = dir.join("x.bak");
+    save(&tmp, &first).unwrap();
+    save(&bak, &second).unwrap();
+    save(&tmp, &second).unwrap();
+    assert_eq!((second.clone(), second.clone()), (load(&tmp).unwrap(), load(&bak).unwrap()));
+    assert_eq!("wan 1 2\n", fs::read_to_string(dir.join("x.tmp.bak")).unwrap());
+    assert!(!dir.join("x.tmp.tmp").exists());
+    assert!(!dir.join("x.bak.bak").exists());
+
+    // like the default path on a fresh install
+    let path = dir.join("new").join("totals");
+    save(&path, &first).unwrap();
+    assert_eq!(first, load(&path).unwrap());
+    fs::remove_dir_all(&dir).unwrap();
+}
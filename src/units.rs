@@ -0,0 +1,133 @@
+use anyhow::{bail, Result};
+
+/// What the status row's speeds are in.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Units {
+    /// Megabits per second, like the gauges.
+    #[default]
+    Mbps,
+    /// Megabytes per second.
+    MBps,
+    /// Bits per second, with a k, M, or G suffix.
+    Auto,
+}
+
+impl Units {
+    pub fn parse(s: &str) -> Result<Self> {
+        Ok(match s {
+            "mbps" => Units::Mbps,
+            "MBps" => Units::MBps,
+            "auto" => Units::Auto,
+            _ => bail!("should be \"mbps\", \"MBps\", or \"auto\", not {:?}", s),
+        })
+    }
+
+    /// Columns each speed takes on the status row: one more for the suffix in auto mode.
+    pub fn width(self) -> usize {
+        match self {
+            Units::Mbps | Units::MBps => 3,
+            Units::Auto => 4,
+        }
+    }
+
+    /// What goes after speeds that don't have a suffix of their own.
+    pub fn label(self) -> &'static str {
+        match self {
+            Units::Mbps => " Mb",
+            Units::MBps => " MB",
+            Units::Auto => "",
+        }
+    }
+}
+
+/// Suffixes, and the power of 1000 each stands for.
+const PREFIXES: [(char, i32); 5] = [('k', 1), ('M', 2), ('G', 3), ('T', 4), ('P', 5)];
+
+/// A speed in `units`, right-aligned in exactly `width` columns, and rounded up so any traffic at
+/// all shows as more than nothing. Whole Mbps (or MB/s) too big for the width switch to Gbps (or
+/// GB/s) with a G suffix, rather than pushing everything after them along.
+pub fn format_rate(bytes_per_sec: f64, units: Units, width: usize) -> String {
+    // NaN too
+    let bytes_per_sec = if bytes_per_sec > 0. { bytes_per_sec } else { 0. };
+    let text = match units {
+        Units::Mbps | Units::MBps => {
+            let per_sec = if units == Units::Mbps { bytes_per_sec * 8. } else { bytes_per_sec };
+            let mega = (per_sec / 1e6).ceil();
+            if mega < 10f64.powi(width as i32) {
+                mega.to_string()
+            } else {
+                with_prefix(per_sec, width, 2)
+            }
+        }
+        Units::Auto => with_prefix(bytes_per_sec * 8., width, 0),
+    };
+    let mut text = format!("{:>1$}", text, width);
+    text.truncate(width);
+    text
+}
+
+/// `value` with the smallest of `PREFIXES[from ..]` that fits it in `width` columns, with a
+/// decimal place if there's room for one, and rounded up. Just 0 for nothing at all.
+fn with_prefix(value: f64, width: usize, from: usize) -> String {
+    if value == 0. {
+        return "0".to_owned();
+    }
+    let mut text = String::new();
+    for &(prefix, power) in &PREFIXES[from ..] {
+        let scaled = value / 1000f64.powi(power);
+        text = format!("{:.1}{}", (scaled * 10.).ceil() / 10., prefix);
+        if text.len() <= width {
+            break;
+        }
+        text = format!("{}{}", scaled.ceil(), prefix);
+        if text.len() <= width {
+            break;
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+#[test]
+fn test_units_parse() {
+    assert_eq!(Units::MBps, Units::parse("MBps").unwrap());
+    assert_eq!("should be \"mbps\", \"MBps\", or \"auto\", not \"Mbps\"",
+        Units::parse("Mbps").unwrap_err().to_string());
+}
+
+#[cfg(test)]
+#[test]
+fn test_format_rate() {
+    let mbps = |m: f64| m * 1e6 / 8.;
+
+    assert_eq!("  0", format_rate(0., Units::Mbps, 3));
+    assert_eq!("  1", format_rate(1., Units::Mbps, 3));
+    assert_eq!("940", format_rate(mbps(940.), Units::Mbps, 3));
+    assert_eq!("999", format_rate(mbps(999.), Units::Mbps, 3));
+    // past 999 Mbps it'd take 4 columns, so it goes to Gbps
+    assert_eq!(" 2G", format_rate(mbps(1000.5), Units::Mbps, 3));
+    assert_eq!(" 3G", format_rate(mbps(2500.), Units::Mbps, 3));
+    assert_eq!("1000", format_rate(mbps(1000.), Units::Mbps, 4));
+    assert_eq!(" 10G", format_rate(mbps(10_000.), Units::Mbps, 4));
+    assert_eq!("  0", format_rate(f64::NAN, Units::Mbps, 3));
+    assert_eq!("  0", format_rate(-5., Units::Mbps, 3));
+
+    assert_eq!("118", format_rate(mbps(940.), Units::MBps, 3));
+    assert_eq!(" 2G", format_rate(1.2e9, Units::MBps, 3));
+
+    // the smallest suffix it fits with, with a decimal place if there's room
+    assert_eq!("   0", format_rate(0., Units::Auto, 4));
+    assert_eq!("0.1k", format_rate(1., Units::Auto, 4));
+    assert_eq!("999k", format_rate(mbps(0.999), Units::Auto, 4));
+    assert_eq!("1.0M", format_rate(mbps(0.9995), Units::Auto, 4));
+    assert_eq!("9.9M", format_rate(mbps(9.9), Units::Auto, 4));
+    assert_eq!(" 10M", format_rate(mbps(9.95), Units::Auto, 4));
+    assert_eq!(" 13M", format_rate(mbps(12.5), Units::Auto, 4));
+    assert_eq!("854M", format_rate(mbps(854.), Units::Auto, 4));
+    assert_eq!("999M", format_rate(mbps(999.), Units::Auto, 4));
+    assert_eq!("1.0G", format_rate(mbps(999.5), Units::Auto, 4));
+    assert_eq!("1.2G", format_rate(mbps(1200.), Units::Auto, 4));
+    assert_eq!(" 10G", format_rate(mbps(10_000.), Units::Auto, 4));
+    assert_eq!("9.9k", format_rate(1237.5, Units::Auto, 4));
+    assert_eq!("10k", format_rate(1240., Units::Auto, 3));
+}
@@ -0,0 +1,307 @@
+//! A UPS's battery, from apcupsd or NUT's upsd, asked over the network on a thread of its own.
+//! During a power cut, how long the battery will last is the one thing worth knowing.
+
+use anyhow::{bail, Context, Result};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// How long to wait to connect, and for each read and write after that.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The most of an answer to read. A full status is a couple of kilobytes.
+const MAX_RESPONSE: u64 = 64 * 1024;
+
+/// What's watching the UPS.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Protocol {
+    /// apcupsd's Network Information Server.
+    Apcupsd,
+    /// Network UPS Tools' upsd.
+    Nut,
+}
+
+impl Protocol {
+    pub fn parse(s: &str) -> Result<Self> {
+        Ok(match s {
+            "apcupsd" => Protocol::Apcupsd,
+            "nut" => Protocol::Nut,
+            _ => bail!("should be \"apcupsd\" or \"nut\", not {:?}", s),
+        })
+    }
+
+    pub fn default_port(self) -> u16 {
+        match self {
+            Protocol::Apcupsd => 3551,
+            Protocol::Nut => 3493,
+        }
+    }
+}
+
+/// Where to ask about the UPS.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Server {
+    pub protocol: Protocol,
+    pub host: String,
+    pub port: u16,
+    /// The UPS's name, for NUT, which can watch several.
+    pub name: String,
+}
+
+/// How the UPS is doing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reading {
+    pub on_battery: bool,
+    /// Battery charge, in percent.
+    pub charge: Option<f64>,
+    /// How long the battery would last at the current load.
+    pub runtime: Option<Duration>,
+}
+
+/// The number at the start of a value like `34.5 Minutes`.
+fn leading_number(value: &str) -> Option<f64> {
+    value.split_whitespace().next()?.parse().ok()
+}
+
+/// Parse apcupsd's status, which is lines like `STATUS   : ONBATT`.
+fn parse_apcupsd(status: &str) -> Result<Reading> {
+    let field = |name: &str| status.lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(key, _)| key.trim() == name)
+        .map(|(_, value)| value.trim());
+    let flags = match field("STATUS") {
+        Some(flags) => flags,
+        None => bail!("no STATUS in apcupsd's answer"),
+    };
+    Ok(Reading {
+        on_battery: flags.split_whitespace().any(|flag| flag == "ONBATT"),
+        charge: field("BCHARGE").and_then(leading_number),
+        runtime: field("TIMELEFT").and_then(leading_number)
+            .map(|minutes| Duration::from_secs_f64(minutes.max(0.) * 60.)),
+    })
+}
+
+/// Parse the answer to NUT's `LIST VAR`, which is lines like `VAR myups ups.status "OB DISCHRG"`.
+fn parse_nut(list: &str, name: &str) -> Result<Reading> {
+    if let Some(err) = list.lines().find_map(|line| line.strip_prefix("ERR ")) {
+        bail!("upsd said {}", err);
+    }
+    let prefix = format!("VAR {} ", name);
+    let var = |var: &str| list.lines()
+        .filter_map(|line| line.strip_prefix(&prefix))
+        .filter_map(|line| line.split_once(' '))
+        .find(|(key, _)| *key == var)
+        .map(|(_, value)| value.trim().trim_matches('"'));
+    let flags = match var("ups.status") {
+        Some(flags) => flags,
+        None => bail!("no ups.status for {:?} in upsd's answer", name),
+    };
+    Ok(Reading {
+        on_battery: flags.split_whitespace().any(|flag| flag == "OB"),
+        charge: var("battery.charge").and_then(leading_number),
+        runtime: var("battery.runtime").and_then(leading_number)
+            .map(|secs| Duration::from_secs_f64(secs.max(0.))),
+    })
+}
+
+fn connect(server: &Server) -> Result<TcpStream> {
+    let addr = (server.host.as_str(), server.port).to_socket_addrs()
+        .with_context(|| format!("failed to look up {}", server.host))?
+        .next()
+        .with_context(|| format!("{} has no addresses", server.host))?;
+    let stream = TcpStream::connect_timeout(&addr, TIMEOUT)
+        .with_context(|| format!("failed to connect to {}:{}", server.host, server.port))?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    Ok(stream)
+}
+
+/// Ask apcupsd for its status. Both ways, each message is a 2-byte big-endian length and then
+/// that much text, and the answer is one message per line, ending with an empty one.
+fn query_apcupsd(stream: TcpStream) -> Result<String> {
+    let mut stream = stream;
+    stream.write_all(&[0, 6])?;
+    stream.write_all(b"status")?;
+    let mut reader = stream.take(MAX_RESPONSE);
+    let mut status = String::new();
+    loop {
+        let mut len = [0; 2];
+        reader.read_exact(&mut len).context("apcupsd's answer ended early")?;
+        let len = u16::from_be_bytes(len);
+        if len == 0 {
+            return Ok(status);
+        }
+        let mut line = vec![0; len as usize];
+        reader.read_exact(&mut line).context("apcupsd's answer ended early")?;
+        status.push_str(&String::from_utf8_lossy(&line));
+    }
+}
+
+/// Ask upsd for all of a UPS's variables.
+fn query_nut(stream: TcpStream, name: &str) -> Result<String> {
+    let mut stream = stream;
+    writeln!(stream, "LIST VAR {}", name)?;
+    let mut reader = BufReader::new(stream.take(MAX_RESPONSE));
+    let mut list = String::new();
+    loop {
+        let before = list.len();
+        if reader.read_line(&mut list)? == 0 {
+            bail!("upsd's answer ended early");
+        }
+        let line = &list[before ..];
+        if line.starts_with("END LIST") || line.starts_with("ERR ") {
+            return Ok(list);
+        }
+    }
+}
+
+/// Ask the server how the UPS is doing.
+pub fn query(server: &Server) -> Result<Reading> {
+    let stream = connect(server)?;
+    match server.protocol {
+        Protocol::Apcupsd => parse_apcupsd(&query_apcupsd(stream)?),
+        Protocol::Nut => parse_nut(&query_nut(stream, &server.name)?, &server.name),
+    }
+}
+
+/// Asks about the UPS every so often on a background thread, so a server that's slow or gone
+/// doesn't hold up the display. The thread stops once this is dropped.
+pub struct Monitor {
+    latest: Arc<Mutex<Option<Reading>>>,
+}
+
+impl Monitor {
+    /// Start asking `server` every `interval`. After a failure, wait each of `backoff` in turn
+    /// before trying again, the last one repeating.
+    pub fn spawn(server: Server, interval: Duration, backoff: &'static [Duration]) -> Self {
+        let latest = Arc::new(Mutex::new(None));
+        let shared = Arc::downgrade(&latest);
+        thread::Builder::new()
+            .name("ups".to_owned())
+            .spawn(move || {
+                let mut failures = 0;
+                loop {
+                    let result = query(&server);
+                    let latest = match shared.upgrade() {
+                        Some(latest) => latest,
+                        None => return,
+                    };
+                    let wait = match result {
+                        Ok(reading) => {
+                            *latest.lock().unwrap() = Some(reading);
+                            failures = 0;
+                            interval
+                        }
+                        Err(e) => {
+                            // an old reading could be badly wrong by now, mid power cut
+                            *latest.lock().unwrap() = None;
+                            if failures == 0 {
+                                eprintln!("failed to get the UPS's status: {:#}", e);
+                            }
+                            failures += 1;
+                            backoff[(failures - 1).min(backoff.len() - 1)]
+                        }
+                    };
+                    drop(latest);
+                    thread::sleep(wait);
+                }
+            })
+            .expect("failed to spawn UPS thread");
+        Self { latest }
+    }
+
+    /// The last reading, or None if there hasn't been one yet, or the last try failed.
+    pub fn latest(&self) -> Option<Reading> {
+        *self.latest.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_parse_apcupsd() {
+    let status = "\
+APC      : 001,036,0874
+DATE     : 2024-06-10 21:14:02 +0000
+UPSNAME  : router
+STATUS   : ONBATT
+LINEV    : 0.0 Volts
+LOADPCT  : 18.0 Percent
+BCHARGE  : 87.0 Percent
+TIMELEFT : 34.5 Minutes
+END APC  : 2024-06-10 21:14:05 +0000
+";
+    assert_eq!(Reading {
+        on_battery: true,
+        charge: Some(87.),
+        runtime: Some(Duration::from_secs(34 * 60 + 30)),
+    }, parse_apcupsd(status).unwrap());
+    let reading = parse_apcupsd("STATUS   : ONLINE\nBCHARGE  : 100.0 Percent\n").unwrap();
+    assert_eq!(Reading { on_battery: false, charge: Some(100.), runtime: None }, reading);
+    assert_eq!("no STATUS in apcupsd's answer",
+        parse_apcupsd("BCHARGE  : 100.0 Percent\n").unwrap_err().to_string());
+}
+
+#[cfg(test)]
+#[test]
+fn test_parse_nut() {
+    let list = "\
+BEGIN LIST VAR myups
+VAR myups battery.charge \"64\"
+VAR myups battery.runtime \"1260\"
+VAR myups device.mfr \"EATON\"
+VAR myups ups.status \"OB DISCHRG\"
+END LIST VAR myups
+";
+    assert_eq!(Reading {
+        on_battery: true,
+        charge: Some(64.),
+        runtime: Some(Duration::from_secs(21 * 60)),
+    }, parse_nut(list, "myups").unwrap());
+    assert!(!parse_nut("VAR ups ups.status \"OL CHRG\"\n", "ups").unwrap().on_battery);
+    assert_eq!("no ups.status for \"other\" in upsd's answer",
+        parse_nut(list, "other").unwrap_err().to_string());
+    assert_eq!("upsd said UNKNOWN-UPS",
+        parse_nut("ERR UNKNOWN-UPS\n", "ups").unwrap_err().to_string());
+}
+
+#[cfg(test)]
+#[test]
+fn test_query() {
+    use std::net::TcpListener;
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = thread::spawn(move || {
+        // apcupsd
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = [0; 8];
+        stream.read_exact(&mut request).unwrap();
+        assert_eq!(b"\0\x06status", &request);
+        for line in ["STATUS   : ONBATT\n", "TIMELEFT : 12.0 Minutes\n", ""] {
+            stream.write_all(&(line.len() as u16).to_be_bytes()).unwrap();
+            stream.write_all(line.as_bytes()).unwrap();
+        }
+        // NUT
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut request = String::new();
+        reader.read_line(&mut request).unwrap();
+        assert_eq!("LIST VAR ups\n", request);
+        reader.get_mut().write_all(b"BEGIN LIST VAR ups\nVAR ups ups.status \"OL\"\n\
+            VAR ups battery.charge \"100\"\nEND LIST VAR ups\n").unwrap();
+    });
+    let server_at = |protocol| {
+        Server { protocol, host: "127.0.0.1".to_owned(), port, name: "ups".to_owned() }
+    };
+    assert_eq!(Reading {
+        on_battery: true,
+        charge: None,
+        runtime: Some(Duration::from_secs(12 * 60)),
+    }, query(&server_at(Protocol::Apcupsd)).unwrap());
+    assert_eq!(Reading { on_battery: false, charge: Some(100.), runtime: None },
+        query(&server_at(Protocol::Nut)).unwrap());
+    server.join().unwrap();
+    // nothing listening any more
+    assert!(query(&server_at(Protocol::Nut)).is_err());
+}
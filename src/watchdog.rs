@@ -0,0 +1,280 @@
+use anyhow::{bail, Result};
+use std::os::unix::process::CommandExt;
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long the render loop can go without finishing a frame, unless the config says otherwise.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How often the supervisor checks on the render loop while it's turned off, to see whether it's
+/// been turned on.
+const IDLE_POLL: Duration = Duration::from_secs(1);
+
+/// What to do once the render loop is stuck.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    /// Abort, so the service manager restarts the program.
+    Abort,
+    /// Run the program again in place, for when nothing would restart it.
+    Reset,
+}
+
+impl Action {
+    pub fn parse(s: &str) -> Result<Self> {
+        Ok(match s {
+            "abort" => Action::Abort,
+            "reset" => Action::Reset,
+            _ => bail!("should be \"abort\" or \"reset\", not {:?}", s),
+        })
+    }
+}
+
+/// The `[watchdog]` settings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Settings {
+    /// How long the render loop can go without finishing a frame, or None to not watch it.
+    pub timeout: Option<Duration>,
+    pub action: Action,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            timeout: Some(DEFAULT_TIMEOUT),
+            action: Action::Abort,
+        }
+    }
+}
+
+/// What the render loop was doing most recently, so a stall can be attributed to something.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stage {
+    Cpu,
+    Network,
+    Memory,
+    Disk,
+    Filesystem,
+    Wireless,
+    Conntrack,
+    Temperature,
+    Sessions,
+    Display,
+    Leases,
+    Top,
+    Sleep,
+}
+
+impl Stage {
+    /// In declaration order, for turning what's stored back into a stage.
+    const ALL: [Stage; 13] = [
+        Stage::Cpu, Stage::Network, Stage::Memory, Stage::Disk, Stage::Filesystem,
+        Stage::Wireless, Stage::Conntrack, Stage::Temperature, Stage::Sessions, Stage::Display,
+        Stage::Leases, Stage::Top, Stage::Sleep,
+    ];
+}
+
+/// Shared between the render loop, which reports progress into it, and the supervisor thread,
+/// which watches it. Only atomics, so the supervisor can never block on the thing it's watching.
+pub struct Watchdog {
+    start: Instant,
+    last_frame_ms: AtomicU64,
+    frames: AtomicU64,
+    stage: AtomicU8,
+    stage_since_ms: AtomicU64,
+    /// In milliseconds, with 0 for off, so it can be changed by a config reload.
+    timeout_ms: AtomicU64,
+    action: AtomicU8,
+}
+
+/// What the supervisor knows when it decides the render loop is stuck.
+#[derive(Debug)]
+pub struct Stall {
+    pub since_last_frame: Duration,
+    pub frames: u64,
+    pub stage: Stage,
+    /// How long it's been at `stage`.
+    pub in_stage: Duration,
+    pub action: Action,
+}
+
+impl Watchdog {
+    /// Start a supervisor thread that calls `on_stall` (once) if no frame is completed for longer
+    /// than the timeout in `settings`.
+    pub fn spawn(
+        settings: Settings,
+        on_stall: impl FnOnce(Stall) + Send + 'static,
+    ) -> Arc<Self> {
+        let dog = Arc::new(Self {
+            start: Instant::now(),
+            last_frame_ms: AtomicU64::new(0),
+            frames: AtomicU64::new(0),
+            stage: AtomicU8::new(Stage::Sleep as u8),
+            stage_since_ms: AtomicU64::new(0),
+            timeout_ms: AtomicU64::new(0),
+            action: AtomicU8::new(Action::Abort as u8),
+        });
+        dog.set(settings);
+        thread::Builder::new()
+            .name("watchdog".to_owned())
+            .spawn({
+                let dog = Arc::clone(&dog);
+                move || loop {
+                    let timeout = match dog.timeout_ms.load(Ordering::Relaxed) {
+                        0 => {
+                            thread::sleep(IDLE_POLL);
+                            continue;
+                        }
+                        ms => Duration::from_millis(ms),
+                    };
+                    thread::sleep((timeout / 4).max(Duration::from_millis(1)));
+                    let since_last_frame = dog.since_last_frame();
+                    // checked again, in case it was turned off or lengthened meanwhile
+                    if since_last_frame > timeout
+                        && dog.timeout_ms.load(Ordering::Relaxed) == timeout.as_millis() as u64
+                    {
+                        let stage = Stage::ALL[dog.stage.load(Ordering::Relaxed) as usize];
+                        let stage_since = dog.stage_since_ms.load(Ordering::Relaxed);
+                        on_stall(Stall {
+                            since_last_frame,
+                            frames: dog.frames.load(Ordering::Relaxed),
+                            stage,
+                            in_stage: dog.elapsed_since(stage_since),
+                            action: match dog.action.load(Ordering::Relaxed) {
+                                0 => Action::Abort,
+                                _ => Action::Reset,
+                            },
+                        });
+                        return;
+                    }
+                }
+            })
+            .expect("failed to spawn watchdog thread");
+        dog
+    }
+
+    /// Change the settings, e.g. after a config reload. The time since the last frame starts over.
+    pub fn set(&self, settings: Settings) {
+        self.frame_started();
+        self.action.store(settings.action as u8, Ordering::Relaxed);
+        let ms = settings.timeout.map_or(0, |timeout| timeout.as_millis().max(1) as u64);
+        self.timeout_ms.store(ms, Ordering::Relaxed);
+    }
+
+    /// Record what the render loop is about to do.
+    pub fn stage(&self, stage: Stage) {
+        self.stage_since_ms.store(self.start.elapsed().as_millis() as u64, Ordering::Relaxed);
+        self.stage.store(stage as u8, Ordering::Relaxed);
+    }
+
+    /// Record that a frame was completed.
+    pub fn frame_done(&self) {
+        self.frame_started();
+        self.frames.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn frame_started(&self) {
+        self.last_frame_ms.store(self.start.elapsed().as_millis() as u64, Ordering::Relaxed);
+    }
+
+    fn since_last_frame(&self) -> Duration {
+        self.elapsed_since(self.last_frame_ms.load(Ordering::Relaxed))
+    }
+
+    fn elapsed_since(&self, ms: u64) -> Duration {
+        self.start.elapsed().saturating_sub(Duration::from_millis(ms))
+    }
+}
+
+/// Log what's known about a stall.
+pub fn dump(stall: &Stall) {
+    eprintln!("watchdog: no frame completed in {:?} (after {} frames), stuck at {:?} for {:?}",
+        stall.since_last_frame, stall.frames, stall.stage, stall.in_stage);
+}
+
+/// Abort so the service manager restarts us, or run the program again in place.
+pub fn act(action: Action) -> ! {
+    if action == Action::Reset {
+        eprintln!("watchdog: starting over");
+        // only returns if it couldn't
+        let e = Command::new("/proc/self/exe").args(std::env::args_os().skip(1)).exec();
+        eprintln!("watchdog: failed to start over: {}", e);
+    }
+    eprintln!("watchdog: aborting");
+    std::process::abort();
+}
+
+#[cfg(test)]
+#[test]
+fn test_watchdog_stall() {
+    use std::sync::mpsc;
+
+    let (tx, rx) = mpsc::channel();
+    let settings = Settings { timeout: Some(Duration::from_millis(50)), action: Action::Reset };
+    let dog = Watchdog::spawn(settings, move |stall| tx.send(stall).unwrap());
+    dog.frame_done();
+    dog.stage(Stage::Network);
+
+    // simulate a sampler blocked forever
+    let stall = rx.recv_timeout(Duration::from_secs(5)).expect("watchdog didn't fire");
+    assert_eq!(Stage::Network, stall.stage);
+    assert_eq!(1, stall.frames);
+    assert!(stall.since_last_frame > Duration::from_millis(50));
+    assert!(stall.in_stage > Duration::from_millis(50));
+    assert_eq!(Action::Reset, stall.action);
+}
+
+#[cfg(test)]
+#[test]
+fn test_watchdog_off() {
+    use std::sync::mpsc;
+
+    let (tx, rx) = mpsc::channel();
+    let settings = Settings { timeout: None, action: Action::Abort };
+    let dog = Watchdog::spawn(settings, move |stall| tx.send(stall).unwrap());
+    dog.stage(Stage::Disk);
+    thread::sleep(Duration::from_millis(100));
+    assert!(rx.try_recv().is_err());
+
+    // turned on by a reload, it only counts from then
+    dog.set(Settings { timeout: Some(Duration::from_millis(50)), ..settings });
+    let stall = rx.recv_timeout(Duration::from_secs(5)).expect("watchdog didn't fire");
+    assert_eq!((Stage::Disk, 0), (stall.stage, stall.frames));
+    assert!(stall.since_last_frame < Duration::from_secs(1));
+}
+
+#[cfg(test)]
+#[test]
+fn test_parse_action() {
+    assert_eq!(Action::Abort, Action::parse("abort").unwrap());
+    assert_eq!(Action::Reset, Action::parse("reset").unwrap());
+    assert_eq!("should be \"abort\" or \"reset\", not \"restart\"",
+        Action::parse("restart").unwrap_err().to_string());
+}
+
+#[cfg(test)]
+#[test]
+fn test_watchdog_healthy() {
+    use std::sync::mpsc;
+
+    let (tx, rx) = mpsc::channel();
+    let settings = Settings { timeout: Some(Duration::from_millis(100)), ..Settings::default() };
+    let dog = Watchdog::spawn(settings, move |stall| tx.send(stall).unwrap());
+    for _ in 0 .. 20 {
+        dog.stage(Stage::Cpu);
+        thread::sleep(Duration::from_millis(10));
+        dog.frame_done();
+    }
+    assert!(rx.try_recv().is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn test_stage_all() {
+    // a stage missing from ALL, or out of order, would be reported as another one
+    for (i, &stage) in Stage::ALL.iter().enumerate() {
+        assert_eq!(i, stage as usize, "{:?}", stage);
+    }
+}
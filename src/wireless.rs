@@ -0,0 +1,96 @@
+//! Wi-Fi: signal level from `/proc/net/wireless`, and how many clients an access point has, from
+//! mac80211's debugfs.
+
+use std::fs;
+use std::path::Path;
+
+pub const WIRELESS_PATH: &str = "/proc/net/wireless";
+
+/// Where mac80211 lists each radio's interfaces and the stations connected to them. Only root
+/// can read it, and only with debugfs mounted.
+pub const DEBUGFS_IEEE80211: &str = "/sys/kernel/debug/ieee80211";
+
+/// Signal levels at the bottom and top of the gauge, in dBm. Below -90 is barely usable, and
+/// above -30 is as good as it gets.
+const LEVEL_MIN: f64 = -90.;
+const LEVEL_MAX: f64 = -30.;
+
+/// An interface's signal level in dBm, from the contents of `/proc/net/wireless`, or None if it's
+/// not listed there, i.e. it isn't wireless.
+pub fn parse(wireless: &str, iface: &str) -> Option<f64> {
+    wireless.lines()
+        // two header lines
+        .skip(2)
+        .find_map(|line| {
+            let (name, rest) = line.split_once(':')?;
+            if name.trim() != iface {
+                return None;
+            }
+            // status, link quality, level, noise, ...; the values can end in '.', which means
+            // they were updated since the last read
+            let level = rest.split_whitespace().nth(2)?.trim_end_matches('.');
+            let level: f64 = level.parse().ok()?;
+            // old drivers report levels as unsigned bytes, e.g. 216 for -40
+            Some(if level > 63. { level - 256. } else { level })
+        })
+}
+
+/// A signal level as a fraction of the gauge.
+pub fn signal_fraction(level: f64) -> f64 {
+    ((level - LEVEL_MIN) / (LEVEL_MAX - LEVEL_MIN)).clamp(0., 1.)
+}
+
+/// How many stations are connected to an interface, looking in `dir` (normally DEBUGFS_IEEE80211),
+/// or None if it can't be told.
+pub fn stations(dir: &Path, iface: &str) -> Option<usize> {
+    fs::read_dir(dir).ok()?
+        .filter_map(|phy| phy.ok())
+        .find_map(|phy| fs::read_dir(phy.path().join(format!("netdev:{}", iface)).join("stations"))
+            .ok())
+        .map(|stations| stations.count())
+}
+
+#[cfg(test)]
+#[test]
+fn test_parse() {
+    let header = "\
+Inter-| sta-|   Quality        |   Discarded packets               | Missed | WE
+ face | tus | link level noise |  nwid  crypt   frag  retry   misc | beacon | 22
+";
+    let wireless = header.to_owned() + "\
+ wlan0: 0000   54.  -56.  -256        0      0      0      0     12        0
+wlp2s0: 0000   70   216   0           0      0      0      0      0        0
+";
+    assert_eq!(Some(-56.), parse(&wireless, "wlan0"));
+    assert_eq!(Some(-40.), parse(&wireless, "wlp2s0"));
+    assert_eq!(None, parse(&wireless, "eth0"));
+    // too short
+    assert_eq!(None, parse(&(header.to_owned() + " wlan1: 0000   54.\n"), "wlan1"));
+}
+
+#[cfg(test)]
+#[test]
+fn test_signal_fraction() {
+    assert_eq!(0., signal_fraction(-95.));
+    assert_eq!(0.5, signal_fraction(-60.));
+    assert_eq!(1., signal_fraction(-20.));
+}
+
+#[cfg(test)]
+#[test]
+fn test_stations() {
+    let dir = std::env::temp_dir().join(format!("h2-net-lcd-test-wifi-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let ap = dir.join("phy0").join("netdev:wlan0").join("stations");
+    fs::create_dir_all(dir.join("phy1").join("netdev:wlan1")).unwrap();
+    for mac in ["aa:bb:cc:dd:ee:01", "aa:bb:cc:dd:ee:02"] {
+        fs::create_dir_all(ap.join(mac)).unwrap();
+    }
+
+    assert_eq!(Some(2), stations(&dir, "wlan0"));
+    // not an access point
+    assert_eq!(None, stations(&dir, "wlan1"));
+    assert_eq!(None, stations(&dir, "eth0"));
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(None, stations(&dir, "wlan0"));
+}